@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use nats3_client::Client;
+use nats3_types::{DuplicateChunksQuery, VerifyChunksQuery};
+
+use crate::{config::OutputFormat, output};
+
+#[derive(Subcommand, Clone)]
+pub enum ChunkCommand {
+    /// Verify chunk content against its hash and signature
+    Verify {
+        /// Verify a single chunk by sequence number
+        #[arg(long, conflicts_with_all = ["stream", "consumer", "subject", "bucket", "prefix"])]
+        sequence_number: Option<i64>,
+
+        #[arg(long, required_unless_present = "sequence_number")]
+        stream: Option<String>,
+
+        #[arg(long)]
+        consumer: Option<String>,
+
+        #[arg(long, required_unless_present = "sequence_number")]
+        subject: Option<String>,
+
+        #[arg(long, required_unless_present = "sequence_number")]
+        bucket: Option<String>,
+
+        #[arg(long)]
+        prefix: Option<String>,
+    },
+    /// List chunks of the same stream and subject holding the same messages
+    Duplicates {
+        #[arg(long)]
+        stream: String,
+
+        #[arg(long)]
+        subject: Option<String>,
+
+        #[arg(long)]
+        bucket: Option<String>,
+    },
+}
+
+impl ChunkCommand {
+    pub async fn execute(self, client: &Client, output_format: &OutputFormat) -> Result<()> {
+        match self {
+            ChunkCommand::Verify {
+                sequence_number,
+                stream,
+                consumer,
+                subject,
+                bucket,
+                prefix,
+            } => {
+                let verifications = match sequence_number {
+                    Some(sequence_number) => vec![client
+                        .verify_chunk(sequence_number)
+                        .await
+                        .context("Fail verify chunk")?],
+                    None => client
+                        .verify_chunks(VerifyChunksQuery {
+                            stream: stream.unwrap(),
+                            consumer,
+                            subject: subject.unwrap(),
+                            bucket: bucket.unwrap(),
+                            prefix,
+                        })
+                        .await
+                        .context("Fail verify chunks")?,
+                };
+                output::print_chunk_verifications(verifications, output_format)?;
+            }
+            ChunkCommand::Duplicates {
+                stream,
+                subject,
+                bucket,
+            } => {
+                let overlaps = client
+                    .duplicate_chunks(DuplicateChunksQuery {
+                        stream,
+                        subject,
+                        bucket,
+                    })
+                    .await
+                    .context("Fail list duplicate chunks")?;
+                output::print_chunk_overlaps(overlaps, output_format)?;
+            }
+        }
+        Ok(())
+    }
+}
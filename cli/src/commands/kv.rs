@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::Colorize;
+use nats3_client::Client;
+use nats3_types::{Codec, Compression, Encoding, KvArchiveCreate, KvRestoreCreate};
+use std::time::Duration;
+
+use super::load::retry_override;
+use crate::{config::OutputFormat, interactive, output};
+
+#[derive(Subcommand, Clone)]
+pub enum KvCommand {
+    List,
+    Get {
+        #[arg(short, long)]
+        interactive: bool,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        job_id: Option<String>,
+    },
+    /// Snapshot a key-value bucket to S3
+    Archive {
+        #[arg(short, long)]
+        interactive: bool,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        name: Option<String>,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        kv_bucket: Option<String>,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        bucket: Option<String>,
+
+        #[arg(long)]
+        prefix: Option<String>,
+
+        /// Archive every revision of each key instead of only its latest value
+        #[arg(long)]
+        history: bool,
+
+        #[arg(long, value_parser = clap::value_parser!(Codec))]
+        codec: Option<Codec>,
+
+        #[arg(long, value_parser = clap::value_parser!(Compression))]
+        compression: Option<Compression>,
+
+        #[arg(long)]
+        compression_level: Option<i32>,
+
+        #[arg(long)]
+        encrypt: bool,
+
+        /// Tries per S3, NATS or Postgres operation, overrides the server retry policy
+        #[arg(long)]
+        retry_max_attempts: Option<u32>,
+
+        /// Wait before the first retry, doubled for each retry after it (e.g. 200ms)
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_backoff: Option<Duration>,
+
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_max_backoff: Option<Duration>,
+
+        /// Wait the whole backoff between retries instead of a random part of it
+        #[arg(long)]
+        retry_no_jitter: bool,
+    },
+    /// Write an archive back into a key-value bucket
+    Restore {
+        #[arg(short, long)]
+        interactive: bool,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        name: Option<String>,
+
+        /// Id of a successful archive job
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        archive_id: Option<String>,
+
+        /// Target bucket, defaults to the archived bucket
+        #[arg(long)]
+        kv_bucket: Option<String>,
+
+        /// Tries per S3, NATS or Postgres operation, overrides the server retry policy
+        #[arg(long)]
+        retry_max_attempts: Option<u32>,
+
+        /// Wait before the first retry, doubled for each retry after it (e.g. 200ms)
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_backoff: Option<Duration>,
+
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_max_backoff: Option<Duration>,
+
+        /// Wait the whole backoff between retries instead of a random part of it
+        #[arg(long)]
+        retry_no_jitter: bool,
+    },
+    Delete {
+        #[arg(short, long)]
+        interactive: bool,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        job_id: Option<String>,
+    },
+}
+
+impl KvCommand {
+    pub async fn execute(self, client: &Client, output_format: &OutputFormat) -> Result<()> {
+        match self {
+            KvCommand::List => {
+                let jobs = client.get_kv_jobs().await.context("Fail fetch kv jobs")?;
+                output::print_kv_jobs(jobs, output_format)?;
+            }
+            KvCommand::Get {
+                interactive,
+                mut job_id,
+            } => {
+                if interactive {
+                    job_id = Some(interactive::prompt_job_id()?);
+                };
+                if job_id.is_none() {
+                    println!("{}", "Must provide job id to get kv job".red());
+                    return Ok(());
+                }
+
+                let job = client
+                    .get_kv_job(job_id.expect("job id is set"))
+                    .await
+                    .context("Fail fetch kv job")?;
+                output::print_kv_jobs(vec![job], output_format)?;
+            }
+            KvCommand::Archive {
+                interactive,
+                name,
+                kv_bucket,
+                bucket,
+                prefix,
+                history,
+                codec,
+                compression,
+                compression_level,
+                encrypt,
+                retry_max_attempts,
+                retry_backoff,
+                retry_max_backoff,
+                retry_no_jitter,
+            } => {
+                let job = if interactive {
+                    interactive::prompt_create_kv_archive()?
+                } else {
+                    let defaults = Encoding::default();
+                    let encoding = Encoding {
+                        codec: codec.unwrap_or(defaults.codec),
+                        compression: compression.unwrap_or(defaults.compression),
+                        compression_level,
+                        encrypt,
+                    };
+
+                    KvArchiveCreate {
+                        name: name.unwrap(),
+                        kv_bucket: kv_bucket.unwrap(),
+                        bucket: bucket.unwrap(),
+                        prefix,
+                        history,
+                        encoding,
+                        retry: retry_override(
+                            retry_max_attempts,
+                            retry_backoff,
+                            retry_max_backoff,
+                            retry_no_jitter,
+                        ),
+                    }
+                };
+
+                let created = client
+                    .create_kv_archive(job)
+                    .await
+                    .context("Fail create kv archive")?;
+                output::print_kv_job(created, output_format)?;
+            }
+            KvCommand::Restore {
+                interactive,
+                name,
+                archive_id,
+                kv_bucket,
+                retry_max_attempts,
+                retry_backoff,
+                retry_max_backoff,
+                retry_no_jitter,
+            } => {
+                let job = if interactive {
+                    interactive::prompt_create_kv_restore()?
+                } else {
+                    KvRestoreCreate {
+                        name: name.unwrap(),
+                        archive_id: archive_id.unwrap(),
+                        kv_bucket,
+                        retry: retry_override(
+                            retry_max_attempts,
+                            retry_backoff,
+                            retry_max_backoff,
+                            retry_no_jitter,
+                        ),
+                    }
+                };
+
+                let created = client
+                    .create_kv_restore(job)
+                    .await
+                    .context("Fail create kv restore")?;
+                output::print_kv_job(created, output_format)?;
+            }
+            KvCommand::Delete {
+                interactive,
+                mut job_id,
+            } => {
+                if interactive {
+                    job_id = Some(interactive::prompt_job_id()?);
+                };
+                if job_id.is_none() {
+                    println!("{}", "Must provide job id to delete kv job".red());
+                    return Ok(());
+                }
+
+                client
+                    .delete_kv_job(job_id.expect("job id is set"))
+                    .await
+                    .context("Fail delete kv job")?;
+
+                println!("{}", "Kv job deleted successfully!".green());
+            }
+        }
+        Ok(())
+    }
+}
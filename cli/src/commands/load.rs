@@ -3,7 +3,7 @@ use chrono::{DateTime, Utc};
 use clap::Subcommand;
 use colored::Colorize;
 use nats3_client::Client;
-use nats3_types::LoadJobCreate;
+use nats3_types::{LoadJobCreate, Retry};
 use std::{path::PathBuf, time};
 
 use crate::{config::OutputFormat, interactive, output};
@@ -32,13 +32,13 @@ pub enum LoadCommand {
         #[arg(long, required_unless_present_any = ["interactive", "from_json"])]
         read_stream: Option<String>,
 
-        #[arg(long)]
+        #[arg(long, conflicts_with = "backup_id")]
         read_consumer: Option<String>,
 
-        #[arg(long, required_unless_present_any = ["interactive", "from_json"])]
+        #[arg(long, required_unless_present_any = ["interactive", "from_json", "backup_id"])]
         read_subject: Option<String>,
 
-        #[arg(long, required_unless_present_any = ["interactive", "from_json"])]
+        #[arg(long, required_unless_present_any = ["interactive", "from_json", "backup_id"])]
         write_subject: Option<String>,
 
         #[arg(long, value_parser = humantime::parse_duration)]
@@ -47,11 +47,37 @@ pub enum LoadCommand {
         #[arg(long)]
         delete_chunks: bool,
 
+        #[arg(long)]
+        partial_recovery: bool,
+
         #[arg(long, value_parser = parse_datetime)]
         from_time: Option<DateTime<Utc>>,
 
         #[arg(long, value_parser = parse_datetime)]
         to_time: Option<DateTime<Utc>>,
+
+        /// Backup store job to recreate the read stream from, replaying every message to its own subject
+        #[arg(long, conflicts_with_all = ["read_subject", "write_subject"])]
+        backup_id: Option<String>,
+
+        /// Name of the restored stream, defaults to the read stream
+        #[arg(long, requires = "backup_id")]
+        restore_stream: Option<String>,
+
+        /// Tries per S3, NATS or Postgres operation, overrides the server retry policy
+        #[arg(long)]
+        retry_max_attempts: Option<u32>,
+
+        /// Wait before the first retry, doubled for each retry after it (e.g. 200ms)
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_backoff: Option<time::Duration>,
+
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_max_backoff: Option<time::Duration>,
+
+        /// Wait the whole backoff between retries instead of a random part of it
+        #[arg(long)]
+        retry_no_jitter: bool,
     },
     Pause {
         #[arg(short, long)]
@@ -98,8 +124,15 @@ impl LoadCommand {
                 write_subject,
                 poll_interval,
                 delete_chunks,
+                partial_recovery,
                 from_time,
                 to_time,
+                backup_id,
+                restore_stream,
+                retry_max_attempts,
+                retry_backoff,
+                retry_max_backoff,
+                retry_no_jitter,
             } => {
                 let job = if interactive {
                     interactive::prompt_create_load_job()?
@@ -112,12 +145,21 @@ impl LoadCommand {
                         prefix,
                         read_stream: read_stream.unwrap(),
                         read_consumer,
-                        read_subject: read_subject.unwrap(),
-                        write_subject: write_subject.unwrap(),
+                        read_subject: read_subject.unwrap_or_default(),
+                        write_subject: write_subject.unwrap_or_default(),
                         poll_interval,
                         delete_chunks,
+                        partial_recovery,
                         from_time,
                         to_time,
+                        backup_id,
+                        restore_stream,
+                        retry: retry_override(
+                            retry_max_attempts,
+                            retry_backoff,
+                            retry_max_backoff,
+                            retry_no_jitter,
+                        ),
                     }
                 };
 
@@ -198,7 +240,7 @@ fn load_from_json(path: &PathBuf) -> Result<LoadJobCreate> {
     serde_json::from_str(&content).context("Fail parse json")
 }
 
-fn parse_datetime(s: &str) -> Result<DateTime<Utc>, String> {
+pub(crate) fn parse_datetime(s: &str) -> Result<DateTime<Utc>, String> {
     DateTime::parse_from_rfc3339(s)
         .map(|dt| dt.with_timezone(&Utc))
         .map_err(|e| {
@@ -208,3 +250,23 @@ fn parse_datetime(s: &str) -> Result<DateTime<Utc>, String> {
             )
         })
 }
+
+// a retry policy override if any retry flag is set, unset flags take the
+// policy defaults rather than the server's
+pub(crate) fn retry_override(
+    max_attempts: Option<u32>,
+    backoff: Option<time::Duration>,
+    max_backoff: Option<time::Duration>,
+    no_jitter: bool,
+) -> Option<Retry> {
+    if max_attempts.is_none() && backoff.is_none() && max_backoff.is_none() && !no_jitter {
+        return None;
+    }
+    let defaults = Retry::default();
+    Some(Retry {
+        max_attempts: max_attempts.unwrap_or(defaults.max_attempts),
+        backoff: backoff.unwrap_or(defaults.backoff),
+        max_backoff: max_backoff.unwrap_or(defaults.max_backoff),
+        jitter: !no_jitter,
+    })
+}
@@ -1,3 +1,6 @@
+pub mod chunk;
 pub mod config;
+pub mod kv;
 pub mod load;
+pub mod object;
 pub mod store;
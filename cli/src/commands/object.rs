@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::Colorize;
+use nats3_client::Client;
+use nats3_types::{ObjectArchiveCreate, ObjectRestoreCreate};
+use std::time::Duration;
+
+use super::load::retry_override;
+use crate::{config::OutputFormat, interactive, output};
+
+#[derive(Subcommand, Clone)]
+pub enum ObjectCommand {
+    List,
+    Get {
+        #[arg(short, long)]
+        interactive: bool,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        job_id: Option<String>,
+    },
+    /// Copy the objects of an object store bucket to S3
+    Archive {
+        #[arg(short, long)]
+        interactive: bool,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        name: Option<String>,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        object_bucket: Option<String>,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        bucket: Option<String>,
+
+        #[arg(long)]
+        prefix: Option<String>,
+
+        /// Tries per S3, NATS or Postgres operation, overrides the server retry policy
+        #[arg(long)]
+        retry_max_attempts: Option<u32>,
+
+        /// Wait before the first retry, doubled for each retry after it (e.g. 200ms)
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_backoff: Option<Duration>,
+
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_max_backoff: Option<Duration>,
+
+        /// Wait the whole backoff between retries instead of a random part of it
+        #[arg(long)]
+        retry_no_jitter: bool,
+    },
+    /// Write an archive back into an object store bucket
+    Restore {
+        #[arg(short, long)]
+        interactive: bool,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        name: Option<String>,
+
+        /// Id of a successful archive job
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        archive_id: Option<String>,
+
+        /// Target bucket, defaults to the archived bucket
+        #[arg(long)]
+        object_bucket: Option<String>,
+
+        /// Tries per S3, NATS or Postgres operation, overrides the server retry policy
+        #[arg(long)]
+        retry_max_attempts: Option<u32>,
+
+        /// Wait before the first retry, doubled for each retry after it (e.g. 200ms)
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_backoff: Option<Duration>,
+
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_max_backoff: Option<Duration>,
+
+        /// Wait the whole backoff between retries instead of a random part of it
+        #[arg(long)]
+        retry_no_jitter: bool,
+    },
+    Delete {
+        #[arg(short, long)]
+        interactive: bool,
+
+        #[arg(long, required_unless_present_any = ["interactive"])]
+        job_id: Option<String>,
+    },
+}
+
+impl ObjectCommand {
+    pub async fn execute(self, client: &Client, output_format: &OutputFormat) -> Result<()> {
+        match self {
+            ObjectCommand::List => {
+                let jobs = client
+                    .get_object_jobs()
+                    .await
+                    .context("Fail fetch object jobs")?;
+                output::print_object_jobs(jobs, output_format)?;
+            }
+            ObjectCommand::Get {
+                interactive,
+                mut job_id,
+            } => {
+                if interactive {
+                    job_id = Some(interactive::prompt_job_id()?);
+                };
+                if job_id.is_none() {
+                    println!("{}", "Must provide job id to get object job".red());
+                    return Ok(());
+                }
+
+                let job = client
+                    .get_object_job(job_id.expect("job id is set"))
+                    .await
+                    .context("Fail fetch object job")?;
+                output::print_object_jobs(vec![job], output_format)?;
+            }
+            ObjectCommand::Archive {
+                interactive,
+                name,
+                object_bucket,
+                bucket,
+                prefix,
+                retry_max_attempts,
+                retry_backoff,
+                retry_max_backoff,
+                retry_no_jitter,
+            } => {
+                let job = if interactive {
+                    interactive::prompt_create_object_archive()?
+                } else {
+                    ObjectArchiveCreate {
+                        name: name.unwrap(),
+                        object_bucket: object_bucket.unwrap(),
+                        bucket: bucket.unwrap(),
+                        prefix,
+                        retry: retry_override(
+                            retry_max_attempts,
+                            retry_backoff,
+                            retry_max_backoff,
+                            retry_no_jitter,
+                        ),
+                    }
+                };
+
+                let created = client
+                    .create_object_archive(job)
+                    .await
+                    .context("Fail create object archive")?;
+                output::print_object_job(created, output_format)?;
+            }
+            ObjectCommand::Restore {
+                interactive,
+                name,
+                archive_id,
+                object_bucket,
+                retry_max_attempts,
+                retry_backoff,
+                retry_max_backoff,
+                retry_no_jitter,
+            } => {
+                let job = if interactive {
+                    interactive::prompt_create_object_restore()?
+                } else {
+                    ObjectRestoreCreate {
+                        name: name.unwrap(),
+                        archive_id: archive_id.unwrap(),
+                        object_bucket,
+                        retry: retry_override(
+                            retry_max_attempts,
+                            retry_backoff,
+                            retry_max_backoff,
+                            retry_no_jitter,
+                        ),
+                    }
+                };
+
+                let created = client
+                    .create_object_restore(job)
+                    .await
+                    .context("Fail create object restore")?;
+                output::print_object_job(created, output_format)?;
+            }
+            ObjectCommand::Delete {
+                interactive,
+                mut job_id,
+            } => {
+                if interactive {
+                    job_id = Some(interactive::prompt_job_id()?);
+                };
+                if job_id.is_none() {
+                    println!("{}", "Must provide job id to delete object job".red());
+                    return Ok(());
+                }
+
+                client
+                    .delete_object_job(job_id.expect("job id is set"))
+                    .await
+                    .context("Fail delete object job")?;
+
+                println!("{}", "Object job deleted successfully!".green());
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,14 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use clap::Subcommand;
 use colored::Colorize;
 use nats3_client::Client;
-use nats3_types::{Batch, Codec, Encoding, StoreJobCreate};
-use std::path::PathBuf;
+use nats3_types::{
+    AckPolicy, Batch, Codec, Compression, Deliver, DeliverPolicy, Encoding, StoreJobCreate,
+};
+use std::{path::PathBuf, time::Duration};
 
+use super::load::{parse_datetime, retry_override};
 use crate::{config::OutputFormat, interactive, output};
 
 #[derive(Subcommand, Clone)]
@@ -28,9 +32,13 @@ pub enum StoreCommand {
         #[arg(long)]
         consumer: Option<String>,
 
-        #[arg(long, required_unless_present_any = ["interactive", "from_json"])]
+        #[arg(long, required_unless_present_any = ["interactive", "from_json", "backup"])]
         subject: Option<String>,
 
+        /// More filter subjects to consume, comma separated
+        #[arg(long, value_delimiter = ',')]
+        subjects: Vec<String>,
+
         #[arg(long, required_unless_present_any = ["interactive", "from_json"])]
         bucket: Option<String>,
 
@@ -43,8 +51,74 @@ pub enum StoreCommand {
         #[arg(long)]
         batch_max_count: Option<i64>,
 
+        /// Flush a batch after its oldest message waited this long (e.g. 500ms, 1min)
+        #[arg(long, value_parser = humantime::parse_duration)]
+        batch_max_age: Option<Duration>,
+
         #[arg(long, value_parser = clap::value_parser!(Codec))]
         codec: Option<Codec>,
+
+        #[arg(long, value_parser = clap::value_parser!(Compression))]
+        compression: Option<Compression>,
+
+        #[arg(long)]
+        compression_level: Option<i32>,
+
+        #[arg(long)]
+        encrypt: bool,
+
+        /// Object key layout, e.g. '{stream}/year={YYYY}/month={MM}/{ulid}.{ext}'
+        #[arg(long)]
+        key_template: Option<String>,
+
+        /// Where the consumer starts: all, new, last, by_start_sequence, by_start_time
+        #[arg(long, value_parser = clap::value_parser!(DeliverPolicy))]
+        deliver_policy: Option<DeliverPolicy>,
+
+        #[arg(long)]
+        start_sequence: Option<u64>,
+
+        #[arg(long, value_parser = parse_datetime)]
+        start_time: Option<DateTime<Utc>>,
+
+        /// Complete the job after this stream sequence
+        #[arg(long)]
+        end_sequence: Option<u64>,
+
+        /// Complete the job after messages up to this time
+        #[arg(long, value_parser = parse_datetime)]
+        end_time: Option<DateTime<Utc>>,
+
+        /// Write a separate chunk per concrete subject in each batch
+        #[arg(long)]
+        split_by_subject: bool,
+
+        /// Group split chunks by this many leading subject tokens
+        #[arg(long, requires = "split_by_subject")]
+        subject_depth: Option<u32>,
+
+        /// How the job acks messages: explicit (each message) or all (one ack per batch)
+        #[arg(long, value_parser = clap::value_parser!(AckPolicy))]
+        ack_policy: Option<AckPolicy>,
+
+        /// Archive every subject of the stream and record its config for a restore
+        #[arg(long, conflicts_with_all = ["subject", "subjects", "consumer", "split_by_subject"])]
+        backup: bool,
+
+        /// Tries per S3, NATS or Postgres operation, overrides the server retry policy
+        #[arg(long)]
+        retry_max_attempts: Option<u32>,
+
+        /// Wait before the first retry, doubled for each retry after it (e.g. 200ms)
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_backoff: Option<Duration>,
+
+        #[arg(long, value_parser = humantime::parse_duration)]
+        retry_max_backoff: Option<Duration>,
+
+        /// Wait the whole backoff between retries instead of a random part of it
+        #[arg(long)]
+        retry_no_jitter: bool,
     },
     Pause {
         #[arg(short, long)]
@@ -66,6 +140,10 @@ pub enum StoreCommand {
 
         #[arg(long, required_unless_present_any = ["interactive"])]
         job_id: Option<String>,
+
+        /// Keep the NATS consumer the job created
+        #[arg(long)]
+        keep_consumer: bool,
     },
 }
 
@@ -86,35 +164,79 @@ impl StoreCommand {
                 stream,
                 consumer,
                 subject,
+                subjects,
                 bucket,
                 prefix,
                 batch_max_bytes,
                 batch_max_count,
+                batch_max_age,
                 codec,
+                compression,
+                compression_level,
+                encrypt,
+                key_template,
+                deliver_policy,
+                start_sequence,
+                start_time,
+                end_sequence,
+                end_time,
+                split_by_subject,
+                subject_depth,
+                ack_policy,
+                backup,
+                retry_max_attempts,
+                retry_backoff,
+                retry_max_backoff,
+                retry_no_jitter,
             } => {
                 let job = if interactive {
                     interactive::prompt_create_store_job()?
                 } else if let Some(path) = from_json {
                     load_from_json(&path)?
                 } else {
-                    let batch = match (batch_max_bytes, batch_max_count) {
-                        (None, None) => Batch::default(),
-                        _ => Batch {
-                            max_bytes: batch_max_bytes.unwrap_or(1_000_000),
-                            max_count: batch_max_count.unwrap_or(1000),
-                        },
+                    let defaults = Batch::default();
+                    let batch = Batch {
+                        max_bytes: batch_max_bytes.unwrap_or(defaults.max_bytes),
+                        max_count: batch_max_count.unwrap_or(defaults.max_count),
+                        max_age: batch_max_age.unwrap_or(defaults.max_age),
+                    };
+                    let defaults = Encoding::default();
+                    let encoding = Encoding {
+                        codec: codec.unwrap_or(defaults.codec),
+                        compression: compression.unwrap_or(defaults.compression),
+                        compression_level,
+                        encrypt,
+                    };
+                    let deliver = Deliver {
+                        policy: deliver_policy.unwrap_or_default(),
+                        start_sequence,
+                        start_time,
+                        end_sequence,
+                        end_time,
                     };
-                    let encoding = codec.map_or(Encoding::default(), |c| Encoding { codec: c });
 
                     StoreJobCreate {
                         name: name.unwrap(),
                         stream: stream.unwrap(),
                         consumer,
-                        subject: subject.unwrap(),
+                        subject: subject.unwrap_or_default(),
+                        subjects,
                         bucket: bucket.unwrap(),
                         prefix,
                         batch,
                         encoding,
+                        key_template,
+                        deliver,
+                        split_by_subject,
+                        subject_depth,
+                        ack_policy: ack_policy.unwrap_or_default(),
+                        backup,
+                        retry: retry_override(
+                            retry_max_attempts,
+                            retry_backoff,
+                            retry_max_backoff,
+                            retry_no_jitter,
+                        ),
                     }
                 };
 
@@ -165,6 +287,7 @@ impl StoreCommand {
             StoreCommand::Delete {
                 interactive,
                 mut job_id,
+                keep_consumer,
             } => {
                 if interactive {
                     job_id = Some(interactive::prompt_job_id()?);
@@ -175,7 +298,7 @@ impl StoreCommand {
                 }
 
                 client
-                    .delete_store_job(job_id.expect("job id is set"))
+                    .delete_store_job(job_id.expect("job id is set"), keep_consumer)
                     .await
                     .context("Fail delete store job")?;
 
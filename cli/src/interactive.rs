@@ -1,7 +1,11 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use inquire::{Confirm, Text};
-use nats3_types::{Batch, Codec, Encoding, LoadJobCreate, StoreJobCreate};
+use nats3_types::{
+    AckPolicy, Batch, Codec, Compression, Deliver, DeliverPolicy, Encoding, KvArchiveCreate,
+    KvRestoreCreate, LoadJobCreate, ObjectArchiveCreate, ObjectRestoreCreate, Retry,
+    StoreJobCreate,
+};
 
 pub fn prompt_create_load_job() -> Result<LoadJobCreate> {
     let name = Text::new("Job name:").prompt()?;
@@ -12,19 +16,47 @@ pub fn prompt_create_load_job() -> Result<LoadJobCreate> {
         .prompt_skippable()?;
 
     let read_stream = Text::new("Read stream:").prompt()?;
-    let read_consumer = Text::new("Read consumer (optional):")
-        .with_help_message("Press Enter to skip")
-        .prompt_skippable()?;
-    let read_subject = Text::new("Read subject:").prompt()?;
-    let write_subject = Text::new("Write subject:").prompt()?;
-
-    let poll_interval = Text::new("Poll interval (optional)?")
-        .with_help_message("Duration to keep trying load (e.g. 5sec, 1min). Press Enter to skip")
+    let backup_id = Text::new("Backup job ID to restore the stream from (optional):")
+        .with_help_message(
+            "Recreate the stream and replay every message to its own subject. Press Enter to skip",
+        )
         .prompt_skippable()?
-        .map(|s| humantime::parse_duration(&s))
-        .transpose()?;
+        .filter(|s| !s.is_empty());
+    let restore = backup_id.is_some();
+
+    let (read_consumer, read_subject, write_subject, restore_stream) = if restore {
+        let restore_stream = Text::new("Restored stream name (optional):")
+            .with_help_message("Defaults to the read stream. Press Enter to skip")
+            .prompt_skippable()?
+            .filter(|s| !s.is_empty());
+        (None, String::new(), String::new(), restore_stream)
+    } else {
+        let read_consumer = Text::new("Read consumer (optional):")
+            .with_help_message("Press Enter to skip")
+            .prompt_skippable()?;
+        let read_subject = Text::new("Read subject:").prompt()?;
+        let write_subject = Text::new("Write subject:").prompt()?;
+        (read_consumer, read_subject, write_subject, None)
+    };
 
-    let delete_chunks = Confirm::new("Delete chunks after load?")
+    let poll_interval = if restore {
+        None
+    } else {
+        Text::new("Poll interval (optional)?")
+            .with_help_message(
+                "Duration to keep trying load (e.g. 5sec, 1min). Press Enter to skip",
+            )
+            .prompt_skippable()?
+            .map(|s| humantime::parse_duration(&s))
+            .transpose()?
+    };
+
+    let delete_chunks = !restore
+        && Confirm::new("Delete chunks after load?")
+            .with_default(false)
+            .prompt()?;
+
+    let partial_recovery = Confirm::new("Recover intact messages from damaged chunks?")
         .with_default(false)
         .prompt()?;
 
@@ -50,18 +82,42 @@ pub fn prompt_create_load_job() -> Result<LoadJobCreate> {
         write_subject,
         poll_interval,
         delete_chunks,
+        partial_recovery,
         from_time,
         to_time,
+        backup_id,
+        restore_stream,
+        retry: prompt_retry()?,
     })
 }
 
 pub fn prompt_create_store_job() -> Result<StoreJobCreate> {
     let name = Text::new("Job name:").prompt()?;
     let stream = Text::new("Stream:").prompt()?;
-    let consumer = Text::new("Consumer (optional):")
-        .with_help_message("Press Enter to skip")
-        .prompt_skippable()?;
-    let subject = Text::new("Subject:").prompt()?;
+    let backup = Confirm::new("Back up the whole stream?")
+        .with_help_message("Archive every subject and record the stream config for a restore")
+        .with_default(false)
+        .prompt()?;
+
+    let (consumer, subject, subjects) = if backup {
+        (None, String::new(), Vec::new())
+    } else {
+        let consumer = Text::new("Consumer (optional):")
+            .with_help_message("Press Enter to skip")
+            .prompt_skippable()?;
+        let subject = Text::new("Subject:").prompt()?;
+        let subjects = Text::new("More subjects (optional):")
+            .with_help_message("Comma separated. Press Enter to skip")
+            .prompt_skippable()?
+            .map(|s| {
+                s.split(',')
+                    .map(|subject| subject.trim().to_string())
+                    .filter(|subject| !subject.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        (consumer, subject, subjects)
+    };
     let bucket = Text::new("Bucket:").prompt()?;
 
     let prefix = Text::new("Key prefix (optional):")
@@ -83,9 +139,19 @@ pub fn prompt_create_store_job() -> Result<StoreJobCreate> {
             .prompt()?
             .parse()?;
 
+        let max_age = humantime::parse_duration(
+            &Text::new("Max batch age:")
+                .with_help_message(
+                    "Flush after the oldest message waited this long (e.g. 500ms, 1min)",
+                )
+                .with_default("10s")
+                .prompt()?,
+        )?;
+
         Batch {
             max_bytes,
             max_count,
+            max_age,
         }
     } else {
         Batch::default()
@@ -96,29 +162,283 @@ pub fn prompt_create_store_job() -> Result<StoreJobCreate> {
         .prompt()?;
 
     let encoding = if configure_encoding {
-        let codec_str = Text::new("Codec (json/binary):")
+        let codec_str = Text::new("Codec (json/binary/framed/parquet/jsonl):")
             .with_default("binary")
             .prompt()?;
 
         let codec = codec_str.parse::<Codec>()?;
 
-        Encoding { codec }
+        let compression_str = Text::new("Compression (none/zstd/gzip/lz4):")
+            .with_default("none")
+            .prompt()?;
+
+        let compression = compression_str.parse::<Compression>()?;
+
+        let compression_level = Text::new("Compression level (optional):")
+            .with_help_message("Press Enter to use the algorithm default")
+            .prompt_skippable()?
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse())
+            .transpose()?;
+
+        let encrypt = Confirm::new("Encrypt chunks?")
+            .with_default(false)
+            .prompt()?;
+
+        Encoding {
+            codec,
+            compression,
+            compression_level,
+            encrypt,
+        }
     } else {
         Encoding::default()
     };
 
+    let key_template = Text::new("Key template (optional):")
+        .with_help_message("e.g. {stream}/year={YYYY}/month={MM}/{ulid}.{ext}. Press Enter to skip")
+        .prompt_skippable()?
+        .filter(|s| !s.is_empty());
+
+    let configure_deliver = Confirm::new("Configure deliver policy?")
+        .with_help_message("Backfill from a sequence or time, or stop at an end bound")
+        .with_default(false)
+        .prompt()?;
+
+    let deliver = if configure_deliver {
+        let policy = Text::new("Deliver policy (all/new/last/by_start_sequence/by_start_time):")
+            .with_default("all")
+            .prompt()?
+            .parse::<DeliverPolicy>()?;
+
+        let start_sequence = match policy {
+            DeliverPolicy::ByStartSequence => Some(Text::new("Start sequence:").prompt()?.parse()?),
+            _ => None,
+        };
+
+        let start_time = match policy {
+            DeliverPolicy::ByStartTime => Some(
+                DateTime::parse_from_rfc3339(
+                    &Text::new("Start time:")
+                        .with_help_message("RFC3339 format (e.g. 2024-12-14T18:00:00Z)")
+                        .prompt()?,
+                )?
+                .with_timezone(&Utc),
+            ),
+            _ => None,
+        };
+
+        let end_sequence = Text::new("End sequence (optional):")
+            .with_help_message("Press Enter to skip")
+            .prompt_skippable()?
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse())
+            .transpose()?;
+
+        let end_time = Text::new("End time (optional):")
+            .with_help_message("RFC3339 format (e.g. 2024-12-14T18:00:00Z). Press Enter to skip")
+            .prompt_skippable()?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc));
+
+        Deliver {
+            policy,
+            start_sequence,
+            start_time,
+            end_sequence,
+            end_time,
+        }
+    } else {
+        Deliver::default()
+    };
+
+    let split_by_subject = !backup
+        && Confirm::new("Split batches by subject?")
+            .with_help_message("Write a separate chunk per subject in each batch")
+            .with_default(false)
+            .prompt()?;
+
+    let subject_depth = if split_by_subject {
+        Text::new("Subject depth (optional):")
+            .with_help_message(
+                "Group by this many leading subject tokens. Press Enter to split by full subject",
+            )
+            .prompt_skippable()?
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse())
+            .transpose()?
+    } else {
+        None
+    };
+
+    let ack_policy = Text::new("Ack policy (explicit/all):")
+        .with_help_message("all acks each batch with a single ack of its last message")
+        .with_default("explicit")
+        .prompt()?
+        .parse::<AckPolicy>()?;
+
     Ok(StoreJobCreate {
         name,
         stream,
         consumer,
         subject,
+        subjects,
         bucket,
         prefix,
         batch,
         encoding,
+        key_template,
+        deliver,
+        split_by_subject,
+        subject_depth,
+        ack_policy,
+        backup,
+        retry: prompt_retry()?,
+    })
+}
+
+pub fn prompt_create_kv_archive() -> Result<KvArchiveCreate> {
+    let name = Text::new("Job name:").prompt()?;
+    let kv_bucket = Text::new("Key-value bucket:").prompt()?;
+    let bucket = Text::new("Bucket:").prompt()?;
+
+    let prefix = Text::new("Key prefix (optional):")
+        .with_help_message("Press Enter to skip")
+        .prompt_skippable()?;
+
+    let history = Confirm::new("Archive every revision of each key?")
+        .with_help_message("Otherwise only the latest value of each key is archived")
+        .with_default(false)
+        .prompt()?;
+
+    let configure_encoding = Confirm::new("Configure encoding?")
+        .with_default(false)
+        .prompt()?;
+
+    let encoding = if configure_encoding {
+        let codec = Text::new("Codec (json/binary/framed/parquet/jsonl):")
+            .with_default("binary")
+            .prompt()?
+            .parse::<Codec>()?;
+
+        let compression = Text::new("Compression (none/zstd/gzip/lz4):")
+            .with_default("none")
+            .prompt()?
+            .parse::<Compression>()?;
+
+        let encrypt = Confirm::new("Encrypt chunks?")
+            .with_default(false)
+            .prompt()?;
+
+        Encoding {
+            codec,
+            compression,
+            compression_level: None,
+            encrypt,
+        }
+    } else {
+        Encoding::default()
+    };
+
+    Ok(KvArchiveCreate {
+        name,
+        kv_bucket,
+        bucket,
+        prefix,
+        history,
+        encoding,
+        retry: prompt_retry()?,
+    })
+}
+
+pub fn prompt_create_kv_restore() -> Result<KvRestoreCreate> {
+    let name = Text::new("Job name:").prompt()?;
+    let archive_id = Text::new("Archive job id:").prompt()?;
+
+    let kv_bucket = Text::new("Target key-value bucket (optional):")
+        .with_help_message("Press Enter to restore into the archived bucket")
+        .prompt_skippable()?
+        .filter(|s| !s.is_empty());
+
+    Ok(KvRestoreCreate {
+        name,
+        archive_id,
+        kv_bucket,
+        retry: prompt_retry()?,
+    })
+}
+
+pub fn prompt_create_object_archive() -> Result<ObjectArchiveCreate> {
+    let name = Text::new("Job name:").prompt()?;
+    let object_bucket = Text::new("Object store bucket:").prompt()?;
+    let bucket = Text::new("Bucket:").prompt()?;
+
+    let prefix = Text::new("Key prefix (optional):")
+        .with_help_message("Press Enter to skip")
+        .prompt_skippable()?;
+
+    Ok(ObjectArchiveCreate {
+        name,
+        object_bucket,
+        bucket,
+        prefix,
+        retry: prompt_retry()?,
+    })
+}
+
+pub fn prompt_create_object_restore() -> Result<ObjectRestoreCreate> {
+    let name = Text::new("Job name:").prompt()?;
+    let archive_id = Text::new("Archive job id:").prompt()?;
+
+    let object_bucket = Text::new("Target object store bucket (optional):")
+        .with_help_message("Press Enter to restore into the archived bucket")
+        .prompt_skippable()?
+        .filter(|s| !s.is_empty());
+
+    Ok(ObjectRestoreCreate {
+        name,
+        archive_id,
+        object_bucket,
+        retry: prompt_retry()?,
     })
 }
 
+fn prompt_retry() -> Result<Option<Retry>> {
+    let configure_retry = Confirm::new("Override server retry policy?")
+        .with_help_message("Retries of transient S3, NATS and Postgres errors")
+        .with_default(false)
+        .prompt()?;
+    if !configure_retry {
+        return Ok(None);
+    }
+
+    let max_attempts = Text::new("Max attempts:")
+        .with_default("3")
+        .prompt()?
+        .parse()?;
+
+    let backoff = humantime::parse_duration(
+        &Text::new("Backoff:")
+            .with_help_message("Wait before the first retry, doubled for each retry after it")
+            .with_default("200ms")
+            .prompt()?,
+    )?;
+
+    let max_backoff =
+        humantime::parse_duration(&Text::new("Max backoff:").with_default("10s").prompt()?)?;
+
+    let jitter = Confirm::new("Randomize backoff?")
+        .with_default(true)
+        .prompt()?;
+
+    Ok(Some(Retry {
+        max_attempts,
+        backoff,
+        max_backoff,
+        jitter,
+    }))
+}
+
 pub fn prompt_job_id() -> Result<String> {
     let job_id = Text::new("Job id:").prompt()?;
     Ok(job_id)
@@ -6,7 +6,10 @@ mod output;
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use colored::Colorize;
-use commands::{config::ConfigCommand, load::LoadCommand, store::StoreCommand};
+use commands::{
+    chunk::ChunkCommand, config::ConfigCommand, kv::KvCommand, load::LoadCommand,
+    object::ObjectCommand, store::StoreCommand,
+};
 use nats3_client::Client;
 
 #[derive(Parser)]
@@ -35,6 +38,18 @@ enum Command {
         #[command(subcommand)]
         command: StoreCommand,
     },
+    Chunk {
+        #[command(subcommand)]
+        command: ChunkCommand,
+    },
+    Kv {
+        #[command(subcommand)]
+        command: KvCommand,
+    },
+    Object {
+        #[command(subcommand)]
+        command: ObjectCommand,
+    },
     Config {
         #[command(subcommand)]
         command: ConfigCommand,
@@ -54,12 +69,19 @@ async fn run() -> Result<()> {
 
     match &cli.command {
         Command::Config { command } => command.clone().execute(),
-        Command::Load { .. } | Command::Store { .. } => {
+        Command::Load { .. }
+        | Command::Store { .. }
+        | Command::Chunk { .. }
+        | Command::Kv { .. }
+        | Command::Object { .. } => {
             let (client, output_format) = setup_client_and_format(&cli)?;
 
             match cli.command {
                 Command::Load { command } => command.execute(&client, &output_format).await,
                 Command::Store { command } => command.execute(&client, &output_format).await,
+                Command::Chunk { command } => command.execute(&client, &output_format).await,
+                Command::Kv { command } => command.execute(&client, &output_format).await,
+                Command::Object { command } => command.execute(&client, &output_format).await,
                 _ => unreachable!(),
             }
         }
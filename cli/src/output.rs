@@ -5,7 +5,10 @@ use comfy_table::{
     presets::UTF8_FULL,
     Cell, Color, Table,
 };
-use nats3_types::{LoadJob, LoadJobStatus, StoreJob, StoreJobStatus};
+use nats3_types::{
+    ChunkOverlap, ChunkVerification, KvJob, KvJobStatus, LoadJob, LoadJobStatus, ObjectJob,
+    ObjectJobStatus, StoreJob, StoreJobStatus, VerificationStatus,
+};
 use serde::Serialize;
 
 use crate::config::OutputFormat;
@@ -44,6 +47,57 @@ pub fn print_store_job(job: StoreJob, format: &OutputFormat) -> Result<()> {
     }
 }
 
+pub fn print_kv_jobs(jobs: Vec<KvJob>, format: &OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => print_kv_jobs_table(jobs),
+        OutputFormat::Json => print_json(&jobs),
+    }
+}
+
+pub fn print_kv_job(job: KvJob, format: &OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => {
+            println!("{}", "Kv job created successfully!".green());
+            print_kv_jobs_table(vec![job])
+        }
+        OutputFormat::Json => print_json(&job),
+    }
+}
+
+pub fn print_object_jobs(jobs: Vec<ObjectJob>, format: &OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => print_object_jobs_table(jobs),
+        OutputFormat::Json => print_json(&jobs),
+    }
+}
+
+pub fn print_object_job(job: ObjectJob, format: &OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => {
+            println!("{}", "Object job created successfully!".green());
+            print_object_jobs_table(vec![job])
+        }
+        OutputFormat::Json => print_json(&job),
+    }
+}
+
+pub fn print_chunk_verifications(
+    verifications: Vec<ChunkVerification>,
+    format: &OutputFormat,
+) -> Result<()> {
+    match format {
+        OutputFormat::Table => print_chunk_verifications_table(verifications),
+        OutputFormat::Json => print_json(&verifications),
+    }
+}
+
+pub fn print_chunk_overlaps(overlaps: Vec<ChunkOverlap>, format: &OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Table => print_chunk_overlaps_table(overlaps),
+        OutputFormat::Json => print_json(&overlaps),
+    }
+}
+
 fn print_load_jobs_table(jobs: Vec<LoadJob>) -> Result<()> {
     let mut table = Table::new();
     table
@@ -59,6 +113,7 @@ fn print_load_jobs_table(jobs: Vec<LoadJob>) -> Result<()> {
             Cell::new("read consumer").fg(Color::Blue),
             Cell::new("read subject").fg(Color::Blue),
             Cell::new("write subject").fg(Color::Blue),
+            Cell::new("error").fg(Color::Blue),
         ]);
 
     for job in jobs {
@@ -79,6 +134,7 @@ fn print_load_jobs_table(jobs: Vec<LoadJob>) -> Result<()> {
             Cell::new(job.read_consumer.unwrap_or("".to_string())),
             Cell::new(&job.read_subject),
             Cell::new(&job.write_subject),
+            Cell::new(job.error.unwrap_or("".to_string())).fg(Color::Red),
         ]);
     }
 
@@ -128,6 +184,166 @@ fn print_store_jobs_table(jobs: Vec<StoreJob>) -> Result<()> {
     Ok(())
 }
 
+fn print_kv_jobs_table(jobs: Vec<KvJob>) -> Result<()> {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            Cell::new("id").fg(Color::Blue),
+            Cell::new("name").fg(Color::Blue),
+            Cell::new("kind").fg(Color::Blue),
+            Cell::new("status").fg(Color::Blue),
+            Cell::new("kv bucket").fg(Color::Blue),
+            Cell::new("bucket").fg(Color::Blue),
+            Cell::new("prefix").fg(Color::Blue),
+            Cell::new("archive").fg(Color::Blue),
+            Cell::new("error").fg(Color::Blue),
+        ]);
+
+    for job in jobs {
+        let status_cell = match job.status {
+            KvJobStatus::Created => Cell::new(job.status.to_string()).fg(Color::Grey),
+            KvJobStatus::Running => Cell::new(job.status.to_string()).fg(Color::Yellow),
+            KvJobStatus::Success => Cell::new(job.status.to_string()).fg(Color::Green),
+            KvJobStatus::Failure => Cell::new(job.status.to_string()).fg(Color::Red),
+        };
+
+        table.add_row(vec![
+            Cell::new(&job.id),
+            Cell::new(&job.name),
+            Cell::new(job.kind.to_string()),
+            status_cell,
+            Cell::new(&job.kv_bucket),
+            Cell::new(&job.bucket),
+            Cell::new(job.prefix.unwrap_or("".to_string())),
+            Cell::new(job.archive_id.unwrap_or("".to_string())),
+            Cell::new(job.error.unwrap_or("".to_string())).fg(Color::Red),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
+fn print_object_jobs_table(jobs: Vec<ObjectJob>) -> Result<()> {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            Cell::new("id").fg(Color::Blue),
+            Cell::new("name").fg(Color::Blue),
+            Cell::new("kind").fg(Color::Blue),
+            Cell::new("status").fg(Color::Blue),
+            Cell::new("object bucket").fg(Color::Blue),
+            Cell::new("bucket").fg(Color::Blue),
+            Cell::new("prefix").fg(Color::Blue),
+            Cell::new("archive").fg(Color::Blue),
+            Cell::new("error").fg(Color::Blue),
+        ]);
+
+    for job in jobs {
+        let status_cell = match job.status {
+            ObjectJobStatus::Created => Cell::new(job.status.to_string()).fg(Color::Grey),
+            ObjectJobStatus::Running => Cell::new(job.status.to_string()).fg(Color::Yellow),
+            ObjectJobStatus::Success => Cell::new(job.status.to_string()).fg(Color::Green),
+            ObjectJobStatus::Failure => Cell::new(job.status.to_string()).fg(Color::Red),
+        };
+
+        table.add_row(vec![
+            Cell::new(&job.id),
+            Cell::new(&job.name),
+            Cell::new(job.kind.to_string()),
+            status_cell,
+            Cell::new(&job.object_bucket),
+            Cell::new(&job.bucket),
+            Cell::new(job.prefix.unwrap_or("".to_string())),
+            Cell::new(job.archive_id.unwrap_or("".to_string())),
+            Cell::new(job.error.unwrap_or("".to_string())).fg(Color::Red),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
+fn print_chunk_verifications_table(verifications: Vec<ChunkVerification>) -> Result<()> {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            Cell::new("sequence").fg(Color::Blue),
+            Cell::new("status").fg(Color::Blue),
+            Cell::new("bucket").fg(Color::Blue),
+            Cell::new("key").fg(Color::Blue),
+            Cell::new("reason").fg(Color::Blue),
+        ]);
+
+    for verification in verifications {
+        let status_cell = match verification.status {
+            VerificationStatus::Verified => {
+                Cell::new(verification.status.to_string()).fg(Color::Green)
+            }
+            VerificationStatus::Unsigned => {
+                Cell::new(verification.status.to_string()).fg(Color::Yellow)
+            }
+            VerificationStatus::Invalid => {
+                Cell::new(verification.status.to_string()).fg(Color::Red)
+            }
+        };
+
+        table.add_row(vec![
+            Cell::new(verification.sequence_number),
+            status_cell,
+            Cell::new(&verification.bucket),
+            Cell::new(&verification.key),
+            Cell::new(verification.reason.unwrap_or("".to_string())).fg(Color::Red),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
+fn print_chunk_overlaps_table(overlaps: Vec<ChunkOverlap>) -> Result<()> {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            Cell::new("subject").fg(Color::Blue),
+            Cell::new("first").fg(Color::Blue),
+            Cell::new("first key").fg(Color::Blue),
+            Cell::new("second").fg(Color::Blue),
+            Cell::new("second key").fg(Color::Blue),
+            Cell::new("overlap").fg(Color::Blue),
+        ]);
+
+    for overlap in overlaps {
+        table.add_row(vec![
+            Cell::new(&overlap.subject),
+            Cell::new(overlap.first_sequence_number),
+            Cell::new(&overlap.first_key),
+            Cell::new(overlap.second_sequence_number),
+            Cell::new(&overlap.second_key),
+            Cell::new(format!(
+                "{}-{}",
+                overlap.stream_sequence_start, overlap.stream_sequence_end
+            ))
+            .fg(Color::Yellow),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
+
 fn print_json<T: Serialize>(data: &T) -> Result<()> {
     println!("{}", serde_json::to_string_pretty(data)?);
     Ok(())
@@ -1,7 +1,11 @@
 mod error;
 
 pub use error::{ClientError, Result};
-use nats3_types::{LoadJob, LoadJobCreate, StoreJob, StoreJobCreate};
+use nats3_types::{
+    ChunkOverlap, ChunkVerification, DuplicateChunksQuery, KvArchiveCreate, KvJob, KvRestoreCreate,
+    LoadJob, LoadJobCreate, ObjectArchiveCreate, ObjectJob, ObjectRestoreCreate, StoreJob,
+    StoreJobCreate, VerifyChunksQuery,
+};
 
 const API_PREFIX: &str = "/api/v1";
 
@@ -181,12 +185,13 @@ impl Client {
             .map_err(|e| ClientError::Deserialization(e.to_string()))
     }
 
-    pub async fn delete_store_job(&self, id: String) -> Result<()> {
+    // keep_consumer leaves the consumer the job created on the NATS server
+    pub async fn delete_store_job(&self, id: String, keep_consumer: bool) -> Result<()> {
         let url = format!("{}{}/store/job", self.base_url, API_PREFIX);
         let response = self
             .http
             .delete(&url)
-            .query(&[("job_id", id)])
+            .query(&[("job_id", id), ("keep_consumer", keep_consumer.to_string())])
             .send()
             .await?;
 
@@ -215,6 +220,234 @@ impl Client {
             .await
             .map_err(|e| ClientError::Deserialization(e.to_string()))
     }
+
+    pub async fn verify_chunk(&self, sequence_number: i64) -> Result<ChunkVerification> {
+        let url = format!("{}{}/chunk/verify", self.base_url, API_PREFIX);
+        let response = self
+            .http
+            .get(&url)
+            .query(&[("sequence_number", sequence_number)])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn verify_chunks(&self, query: VerifyChunksQuery) -> Result<Vec<ChunkVerification>> {
+        let url = format!("{}{}/chunks/verify", self.base_url, API_PREFIX);
+        let response = self.http.get(&url).query(&query).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn duplicate_chunks(&self, query: DuplicateChunksQuery) -> Result<Vec<ChunkOverlap>> {
+        let url = format!("{}{}/chunks/duplicates", self.base_url, API_PREFIX);
+        let response = self.http.get(&url).query(&query).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn get_kv_jobs(&self) -> Result<Vec<KvJob>> {
+        let url = format!("{}{}/kv/jobs", self.base_url, API_PREFIX);
+        let response = self.http.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn get_kv_job(&self, id: String) -> Result<KvJob> {
+        let url = format!("{}{}/kv/job", self.base_url, API_PREFIX);
+        let response = self.http.get(&url).query(&[("job_id", id)]).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn delete_kv_job(&self, id: String) -> Result<()> {
+        let url = format!("{}{}/kv/job", self.base_url, API_PREFIX);
+        let response = self
+            .http
+            .delete(&url)
+            .query(&[("job_id", id)])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+
+    pub async fn create_kv_archive(&self, job: KvArchiveCreate) -> Result<KvJob> {
+        let url = format!("{}{}/kv/archive", self.base_url, API_PREFIX);
+        let response = self.http.post(&url).json(&job).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn create_kv_restore(&self, job: KvRestoreCreate) -> Result<KvJob> {
+        let url = format!("{}{}/kv/restore", self.base_url, API_PREFIX);
+        let response = self.http.post(&url).json(&job).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn get_object_jobs(&self) -> Result<Vec<ObjectJob>> {
+        let url = format!("{}{}/object/jobs", self.base_url, API_PREFIX);
+        let response = self.http.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn get_object_job(&self, id: String) -> Result<ObjectJob> {
+        let url = format!("{}{}/object/job", self.base_url, API_PREFIX);
+        let response = self.http.get(&url).query(&[("job_id", id)]).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn delete_object_job(&self, id: String) -> Result<()> {
+        let url = format!("{}{}/object/job", self.base_url, API_PREFIX);
+        let response = self
+            .http
+            .delete(&url)
+            .query(&[("job_id", id)])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+
+    pub async fn create_object_archive(&self, job: ObjectArchiveCreate) -> Result<ObjectJob> {
+        let url = format!("{}{}/object/archive", self.base_url, API_PREFIX);
+        let response = self.http.post(&url).json(&job).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
+
+    pub async fn create_object_restore(&self, job: ObjectRestoreCreate) -> Result<ObjectJob> {
+        let url = format!("{}{}/object/restore", self.base_url, API_PREFIX);
+        let response = self.http.post(&url).json(&job).send().await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::Http {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Deserialization(e.to_string()))
+    }
 }
 
 #[cfg(test)]
@@ -1,8 +1,11 @@
 use crate::{Client, ClientError};
 use chrono::Utc;
 use nats3_types::{
-    Batch, Encoding, LoadJob, LoadJobCreate, LoadJobStatus, StoreJob, StoreJobCreate,
-    StoreJobStatus,
+    AckPolicy, Batch, ChunkOverlap, ChunkVerification, Deliver, DuplicateChunksQuery, Encoding,
+    KvArchiveCreate, KvJob, KvJobKind, KvJobStatus, KvRestoreCreate, LoadJob, LoadJobCreate,
+    LoadJobStatus, ObjectArchiveCreate, ObjectJob, ObjectJobKind, ObjectJobStatus,
+    ObjectRestoreCreate, StoreJob, StoreJobCreate, StoreJobStatus, VerificationStatus,
+    VerifyChunksQuery,
 };
 
 #[cfg(test)]
@@ -19,8 +22,13 @@ fn new_load_job() -> LoadJob {
         write_subject: "write.subject".to_string(),
         poll_interval: None,
         delete_chunks: false,
+        partial_recovery: false,
         from_time: None,
         to_time: None,
+        backup_id: None,
+        restore_stream: None,
+        retry: None,
+        error: None,
         created: Utc::now(),
         updated: Utc::now(),
     }
@@ -35,10 +43,18 @@ fn new_store_job() -> StoreJob {
         stream: "test-stream".to_string(),
         consumer: None,
         subject: "test-subject".to_string(),
+        subjects: Vec::new(),
         bucket: "test-bucket".to_string(),
         prefix: None,
         batch: Batch::default(),
         encoding: Encoding::default(),
+        key_template: None,
+        deliver: Deliver::default(),
+        split_by_subject: false,
+        subject_depth: None,
+        ack_policy: AckPolicy::default(),
+        backup: false,
+        retry: None,
         created: Utc::now(),
         updated: Utc::now(),
     }
@@ -56,8 +72,12 @@ fn new_load_job_create() -> LoadJobCreate {
         write_subject: "write.subject".to_string(),
         poll_interval: None,
         delete_chunks: false,
+        partial_recovery: false,
         from_time: None,
         to_time: None,
+        backup_id: None,
+        restore_stream: None,
+        retry: None,
     }
 }
 
@@ -70,8 +90,16 @@ fn new_store_job_create() -> StoreJobCreate {
         stream: "test-stream".to_string(),
         consumer: None,
         subject: "test-subject".to_string(),
+        subjects: Vec::new(),
         batch: Batch::default(),
         encoding: Encoding::default(),
+        key_template: None,
+        deliver: Deliver::default(),
+        split_by_subject: false,
+        subject_depth: None,
+        ack_policy: AckPolicy::default(),
+        backup: false,
+        retry: None,
     }
 }
 
@@ -343,10 +371,10 @@ async fn test_delete_store_job_success() {
     let job = new_store_job();
     let mock = server
         .mock("DELETE", "/api/v1/store/job")
-        .match_query(mockito::Matcher::UrlEncoded(
-            "job_id".into(),
-            "job-to-delete".into(),
-        ))
+        .match_query(mockito::Matcher::AllOf(vec![
+            mockito::Matcher::UrlEncoded("job_id".into(), "job-to-delete".into()),
+            mockito::Matcher::UrlEncoded("keep_consumer".into(), "true".into()),
+        ]))
         .with_status(200)
         .with_header("content-type", "application/json")
         .with_body(serde_json::to_string(&job).unwrap())
@@ -354,7 +382,7 @@ async fn test_delete_store_job_success() {
 
     let client = Client::new(server.url());
     client
-        .delete_store_job("job-to-delete".to_string())
+        .delete_store_job("job-to-delete".to_string(), true)
         .await
         .unwrap();
 
@@ -375,7 +403,7 @@ async fn test_delete_store_job_http_error() {
         .create();
 
     let client = Client::new(server.url());
-    let result = client.delete_store_job("job-fail".to_string()).await;
+    let result = client.delete_store_job("job-fail".to_string(), false).await;
 
     assert!(result.is_err());
     match result.unwrap_err() {
@@ -428,3 +456,375 @@ async fn test_resume_store_job_success() {
     assert_eq!(result.name, "test");
     mock.assert();
 }
+
+#[tokio::test]
+async fn test_verify_chunks_success() {
+    let mut server = mockito::Server::new_async().await;
+    let verifications = vec![ChunkVerification {
+        sequence_number: 7,
+        bucket: "test-bucket".to_string(),
+        key: "test-stream/test-subject/1-10.bin".to_string(),
+        status: VerificationStatus::Invalid,
+        reason: Some("chunk content does not match its hash".to_string()),
+    }];
+    let mock = server
+        .mock("GET", "/api/v1/chunks/verify")
+        .match_query(mockito::Matcher::AllOf(vec![
+            mockito::Matcher::UrlEncoded("stream".into(), "test-stream".into()),
+            mockito::Matcher::UrlEncoded("subject".into(), "test-subject".into()),
+            mockito::Matcher::UrlEncoded("bucket".into(), "test-bucket".into()),
+        ]))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::to_string(&verifications).unwrap())
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client
+        .verify_chunks(VerifyChunksQuery {
+            stream: "test-stream".to_string(),
+            subject: "test-subject".to_string(),
+            bucket: "test-bucket".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].status, VerificationStatus::Invalid);
+    mock.assert();
+}
+
+#[tokio::test]
+async fn test_duplicate_chunks_success() {
+    let mut server = mockito::Server::new_async().await;
+    let overlaps = vec![ChunkOverlap {
+        stream: "test-stream".to_string(),
+        subject: "test-subject".to_string(),
+        first_sequence_number: 3,
+        first_key: "test-stream/test-subject/1-10.bin".to_string(),
+        second_sequence_number: 4,
+        second_key: "test-stream/test-subject/8-12.bin".to_string(),
+        stream_sequence_start: 8,
+        stream_sequence_end: 10,
+    }];
+    let mock = server
+        .mock("GET", "/api/v1/chunks/duplicates")
+        .match_query(mockito::Matcher::UrlEncoded(
+            "stream".into(),
+            "test-stream".into(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::to_string(&overlaps).unwrap())
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client
+        .duplicate_chunks(DuplicateChunksQuery {
+            stream: "test-stream".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(result, overlaps);
+    mock.assert();
+}
+
+#[cfg(test)]
+fn new_kv_job(kind: KvJobKind) -> KvJob {
+    KvJob {
+        id: "test-id".to_string(),
+        name: "test".to_string(),
+        kind,
+        status: KvJobStatus::Running,
+        kv_bucket: "config".to_string(),
+        bucket: "test-bucket".to_string(),
+        prefix: None,
+        history: false,
+        encoding: Encoding::default(),
+        archive_id: None,
+        retry: None,
+        error: None,
+        created: Utc::now(),
+        updated: Utc::now(),
+    }
+}
+
+#[tokio::test]
+async fn test_create_stream_backup_and_restore() {
+    let mut server = mockito::Server::new_async().await;
+    let store_job = StoreJob {
+        subject: "orders.>".to_string(),
+        backup: true,
+        ..new_store_job()
+    };
+    let load_job = LoadJob {
+        read_subject: String::new(),
+        write_subject: String::new(),
+        backup_id: Some(store_job.id.clone()),
+        restore_stream: Some("orders-restored".to_string()),
+        ..new_load_job()
+    };
+
+    let store_mock = server
+        .mock("POST", "/api/v1/store/job")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"subject": "", "backup": true}"#.to_string(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::to_string(&store_job).unwrap())
+        .create();
+    let load_mock = server
+        .mock("POST", "/api/v1/load/job")
+        .match_body(mockito::Matcher::PartialJsonString(format!(
+            r#"{{"backup_id": "{}", "restore_stream": "orders-restored"}}"#,
+            store_job.id
+        )))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::to_string(&load_job).unwrap())
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client
+        .create_store_job(StoreJobCreate {
+            subject: String::new(),
+            backup: true,
+            ..new_store_job_create()
+        })
+        .await
+        .unwrap();
+    assert!(result.backup);
+    assert_eq!(result.subject, "orders.>");
+
+    let result = client
+        .create_load_job(LoadJobCreate {
+            read_subject: String::new(),
+            write_subject: String::new(),
+            backup_id: Some(store_job.id.clone()),
+            restore_stream: Some("orders-restored".to_string()),
+            ..new_load_job_create()
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.backup_id.as_ref(), Some(&store_job.id));
+    assert_eq!(result.restore_stream.as_deref(), Some("orders-restored"));
+    store_mock.assert();
+    load_mock.assert();
+}
+
+#[tokio::test]
+async fn test_create_kv_archive_success() {
+    let mut server = mockito::Server::new_async().await;
+    let job = new_kv_job(KvJobKind::Archive);
+
+    let mock = server
+        .mock("POST", "/api/v1/kv/archive")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"kv_bucket": "config", "history": true}"#.to_string(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::to_string(&job).unwrap())
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client
+        .create_kv_archive(KvArchiveCreate {
+            name: "test".to_string(),
+            kv_bucket: "config".to_string(),
+            bucket: "test-bucket".to_string(),
+            prefix: None,
+            history: true,
+            encoding: Encoding::default(),
+            retry: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(result.kind, KvJobKind::Archive);
+    assert_eq!(result.kv_bucket, "config");
+    mock.assert();
+}
+
+#[tokio::test]
+async fn test_create_kv_restore_http_error() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/api/v1/kv/restore")
+        .with_status(400)
+        .with_body(r#"{"error":"invalid key-value archive: archive test-id has status Running"}"#)
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client
+        .create_kv_restore(KvRestoreCreate {
+            name: "test".to_string(),
+            archive_id: "test-id".to_string(),
+            kv_bucket: None,
+            retry: None,
+        })
+        .await;
+
+    match result {
+        Err(ClientError::Http { status, message }) => {
+            assert_eq!(status, 400);
+            assert!(message.contains("has status Running"));
+        }
+        _ => panic!("Expected Http error"),
+    }
+    mock.assert();
+}
+
+#[tokio::test]
+async fn test_get_kv_jobs_success() {
+    let mut server = mockito::Server::new_async().await;
+    let jobs = vec![
+        new_kv_job(KvJobKind::Archive),
+        new_kv_job(KvJobKind::Restore),
+    ];
+    let mock = server
+        .mock("GET", "/api/v1/kv/jobs")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::to_string(&jobs).unwrap())
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client.get_kv_jobs().await.unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1].kind, KvJobKind::Restore);
+    mock.assert();
+}
+
+#[tokio::test]
+async fn test_delete_kv_job_success() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("DELETE", "/api/v1/kv/job")
+        .match_query(mockito::Matcher::UrlEncoded(
+            "job_id".into(),
+            "test-job-456".into(),
+        ))
+        .with_status(200)
+        .with_body(r#"null"#)
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client.delete_kv_job("test-job-456".to_string()).await;
+
+    assert!(result.is_ok());
+    mock.assert();
+}
+
+#[cfg(test)]
+fn new_object_job(kind: ObjectJobKind) -> ObjectJob {
+    ObjectJob {
+        id: "test-id".to_string(),
+        name: "test".to_string(),
+        kind,
+        status: ObjectJobStatus::Running,
+        object_bucket: "artifacts".to_string(),
+        bucket: "test-bucket".to_string(),
+        prefix: None,
+        archive_id: None,
+        retry: None,
+        error: None,
+        created: Utc::now(),
+        updated: Utc::now(),
+    }
+}
+
+#[tokio::test]
+async fn test_create_object_archive_success() {
+    let mut server = mockito::Server::new_async().await;
+    let job = new_object_job(ObjectJobKind::Archive);
+
+    let mock = server
+        .mock("POST", "/api/v1/object/archive")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"object_bucket": "artifacts"}"#.to_string(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::to_string(&job).unwrap())
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client
+        .create_object_archive(ObjectArchiveCreate {
+            name: "test".to_string(),
+            object_bucket: "artifacts".to_string(),
+            bucket: "test-bucket".to_string(),
+            prefix: None,
+            retry: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(result.kind, ObjectJobKind::Archive);
+    assert_eq!(result.object_bucket, "artifacts");
+    mock.assert();
+}
+
+#[tokio::test]
+async fn test_create_object_restore_success() {
+    let mut server = mockito::Server::new_async().await;
+    let job = ObjectJob {
+        archive_id: Some("archive-id".to_string()),
+        object_bucket: "artifacts-copy".to_string(),
+        ..new_object_job(ObjectJobKind::Restore)
+    };
+
+    let mock = server
+        .mock("POST", "/api/v1/object/restore")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"archive_id": "archive-id", "object_bucket": "artifacts-copy"}"#.to_string(),
+        ))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(serde_json::to_string(&job).unwrap())
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client
+        .create_object_restore(ObjectRestoreCreate {
+            name: "test".to_string(),
+            archive_id: "archive-id".to_string(),
+            object_bucket: Some("artifacts-copy".to_string()),
+            retry: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(result.kind, ObjectJobKind::Restore);
+    assert_eq!(result.archive_id.as_deref(), Some("archive-id"));
+    mock.assert();
+}
+
+#[tokio::test]
+async fn test_get_object_job_not_found() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("GET", "/api/v1/object/job")
+        .match_query(mockito::Matcher::UrlEncoded(
+            "job_id".into(),
+            "missing".into(),
+        ))
+        .with_status(404)
+        .with_body(r#"{"error":"job not found"}"#)
+        .create();
+
+    let client = Client::new(server.url());
+    let result = client.get_object_job("missing".to_string()).await;
+
+    match result {
+        Err(ClientError::Http { status, .. }) => assert_eq!(status, 404),
+        _ => panic!("Expected Http error"),
+    }
+    mock.assert();
+}
@@ -1,12 +1,15 @@
 use anyhow::{Context, Result};
-use nats3_types::{ListLoadJobsQuery, ListStoreJobsQuery, LoadJobStatus, StoreJobStatus};
+use nats3_types::{
+    KvJobStatus, ListKvJobsQuery, ListLoadJobsQuery, ListObjectJobsQuery, ListStoreJobsQuery,
+    LoadJobStatus, ObjectJobStatus, StoreJobStatus,
+};
 use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
 use crate::{
-    completer::TaskCompleter, config::Config, coordinator, db, error, io, metrics, nats, registry,
-    s3, server, shutdown::ShutdownCoordinator,
+    completer::TaskCompleter, config::Config, coordinator, db, encryption, error, io, metrics,
+    nats, registry, s3, server, shutdown::ShutdownCoordinator, signing, spool,
 };
 
 #[derive(Debug, Clone)]
@@ -16,6 +19,7 @@ pub struct App {
     pub server: server::Server,
     pub registry: Arc<registry::Registry>,
     pub completer: TaskCompleter,
+    pub spool: Option<spool::Spool>,
 }
 
 // construct a new instance of nats3 application
@@ -33,11 +37,14 @@ pub async fn new(config: Config, shutdown: ShutdownCoordinator) -> Result<App> {
     let job_db: db::DynJobStorer = Arc::new(pg_store.clone());
     let chunk_db: db::DynChunkStorer = Arc::new(pg_store);
 
+    let retry = config.retry.policy().context("invalid retry config")?;
+
     let s3_client = s3::Client::new(
         config.s3.region.clone(),
         config.s3.endpoint.clone(),
         config.s3.access_key.clone(),
         config.s3.secret_key.clone(),
+        config.s3.multipart.clone(),
         metrics.clone(),
     );
 
@@ -45,8 +52,48 @@ pub async fn new(config: Config, shutdown: ShutdownCoordinator) -> Result<App> {
         .await
         .context("fail connect nats")?;
 
+    let keyring = config
+        .encryption
+        .as_ref()
+        .map(encryption::Keyring::from_config)
+        .transpose()
+        .context("fail load encryption keys")?
+        .map(Arc::new);
+
+    let signer = config
+        .signing
+        .as_ref()
+        .map(signing::Signer::from_config)
+        .transpose()
+        .context("fail load signing keys")?
+        .map(Arc::new);
+
+    let spool = match &config.spool {
+        Some(spool_config) => Some(
+            spool::Spool::open(
+                spool_config,
+                retry.clone(),
+                s3_client.clone(),
+                chunk_db.clone(),
+                metrics.clone(),
+            )
+            .await
+            .context("fail open spool")?,
+        ),
+        None => None,
+    };
+
     let registry = Arc::new(registry::Registry::new(shutdown.subscribe()));
-    let io = io::IO::new(metrics.clone(), s3_client, nats_client, chunk_db);
+    let io = io::IO::new(
+        metrics.clone(),
+        s3_client,
+        nats_client,
+        chunk_db,
+        keyring,
+        signer,
+        spool.clone(),
+        retry,
+    );
     let coordinator =
         coordinator::Coordinator::new(registry.clone(), io, job_db.clone(), metrics.clone());
 
@@ -64,6 +111,7 @@ pub async fn new(config: Config, shutdown: ShutdownCoordinator) -> Result<App> {
         db: job_db,
         registry,
         completer,
+        spool,
     };
 
     Ok(app)
@@ -109,7 +157,42 @@ impl App {
         Ok(())
     }
 
+    // key-value jobs don't resume, one cut short by a restart is failed. an
+    // interrupted archive is incomplete and a restore may be partly written.
+    pub async fn fail_interrupted_kv_jobs(&self) -> Result<(), error::AppError> {
+        let query =
+            ListKvJobsQuery::new().with_statuses(vec![KvJobStatus::Running, KvJobStatus::Created]);
+        let kv_jobs = self.db.get_kv_jobs(Some(query)).await?;
+        for job in kv_jobs {
+            info!(job_id = job.id, "fail interrupted kv job");
+            self.db
+                .fail_kv_job(job.id, "interrupted by server shutdown".to_string())
+                .await?;
+        }
+        Ok(())
+    }
+
+    // object store jobs don't resume either, for the same reasons
+    pub async fn fail_interrupted_object_jobs(&self) -> Result<(), error::AppError> {
+        let query = ListObjectJobsQuery::new()
+            .with_statuses(vec![ObjectJobStatus::Running, ObjectJobStatus::Created]);
+        let object_jobs = self.db.get_object_jobs(Some(query)).await?;
+        for job in object_jobs {
+            info!(job_id = job.id, "fail interrupted object job");
+            self.db
+                .fail_object_job(job.id, "interrupted by server shutdown".to_string())
+                .await?;
+        }
+        Ok(())
+    }
+
     pub fn start_task_completer(&self, shutdown_token: CancellationToken) {
         self.completer.clone().start(shutdown_token);
     }
+
+    pub fn start_spool_uploader(&self, shutdown_token: CancellationToken) {
+        if let Some(spool) = &self.spool {
+            spool.clone().start(shutdown_token);
+        }
+    }
 }
@@ -0,0 +1,171 @@
+// Layout of whole stream backups in S3.
+//
+// A backup is a store job over every subject of a stream. Each time it starts
+// it writes the stream config to <stream>/stream.json under its prefix, next
+// to the chunks. A restore creates a stream from that config and publishes
+// each message, in stream order, to its original subject.
+//
+// A restore reads only the chunks of the backup job's consumer. Each restored
+// message carries its sequence in the backed up stream, so a rerun reads it
+// from the last message of the restored stream and skips every sequence up to
+// it. Its Nats-Msg-Id is derived from the backup job and that sequence, the id
+// it was published with is kept in Nats3-Original-Msg-Id.
+
+use async_nats::{header::NATS_MESSAGE_ID, jetstream::stream};
+use std::collections::BTreeMap;
+
+use crate::kv;
+
+const CONFIG: &str = "stream.json";
+const BACKUP_SEQUENCE: &str = "Nats3-Backup-Sequence";
+const ORIGINAL_MESSAGE_ID: &str = "Nats3-Original-Msg-Id";
+
+pub fn config_key(stream: &str) -> String {
+    format!("{}/{}", stream, CONFIG)
+}
+
+// config to create the restored stream with. it takes no messages from other
+// streams and starts at sequence 1, whatever the backed up stream did
+pub fn restore_config(config: stream::Config, name: &str) -> stream::Config {
+    stream::Config {
+        name: name.to_string(),
+        mirror: None,
+        sources: None,
+        sealed: false,
+        first_sequence: None,
+        ..config
+    }
+}
+
+// headers of a backed up message to restore it with, given the backup job
+// and its sequence in the backed up stream
+pub fn restore_headers(
+    headers: Option<BTreeMap<String, Vec<String>>>,
+    backup_id: &str,
+    sequence: u64,
+) -> Option<BTreeMap<String, Vec<String>>> {
+    // headers asserting the backed up stream's state don't hold for the restore
+    let mut headers = kv::restore_headers(headers).unwrap_or_default();
+    // messages published with the same id are distinct in the backup, the
+    // restored stream would drop all but the first
+    if let Some(id) = headers.remove(&NATS_MESSAGE_ID.to_string()) {
+        headers.insert(ORIGINAL_MESSAGE_ID.to_string(), id);
+    }
+    headers.insert(
+        NATS_MESSAGE_ID.to_string(),
+        vec![format!("{}:{}", backup_id, sequence)],
+    );
+    headers.insert(BACKUP_SEQUENCE.to_string(), vec![sequence.to_string()]);
+    Some(headers)
+}
+
+// whether some subject is selected by both filters
+pub fn subjects_overlap(a: &str, b: &str) -> bool {
+    let (mut a, mut b) = (a.split('.'), b.split('.'));
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(">"), Some(_)) | (Some(_), Some(">")) => return true,
+            (Some(a), Some(b)) if a == b || a == "*" || b == "*" => {}
+            _ => return false,
+        }
+    }
+}
+
+// sequence in the backed up stream of a restored message
+pub fn backup_sequence(headers: &async_nats::HeaderMap) -> Option<u64> {
+    headers.get(BACKUP_SEQUENCE)?.as_str().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restore_config() {
+        let config = stream::Config {
+            name: "ORDERS".to_string(),
+            subjects: vec!["orders.>".to_string()],
+            max_messages: 1000,
+            num_replicas: 3,
+            sealed: true,
+            first_sequence: Some(42),
+            sources: Some(vec![stream::Source {
+                name: "ORDERS_EU".to_string(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        // the config survives a round trip through json
+        let saved: stream::Config =
+            serde_json::from_slice(&serde_json::to_vec(&config).unwrap()).unwrap();
+        let restored = restore_config(saved, "ORDERS_RESTORED");
+        assert_eq!(restored.name, "ORDERS_RESTORED");
+        assert_eq!(restored.subjects, ["orders.>"]);
+        assert_eq!(restored.max_messages, 1000);
+        assert_eq!(restored.num_replicas, 3);
+        assert!(!restored.sealed);
+        assert_eq!(restored.first_sequence, None);
+        assert!(restored.sources.is_none());
+    }
+
+    #[test]
+    fn test_restore_headers() {
+        let headers = BTreeMap::from([
+            ("Nats-Msg-Id".to_string(), vec!["order-1".to_string()]),
+            (
+                "Nats-Expected-Last-Subject-Sequence".to_string(),
+                vec!["7".to_string()],
+            ),
+        ]);
+        let restored = restore_headers(Some(headers.clone()), "backup-1", 12).unwrap();
+        assert_eq!(restored.len(), 3);
+        assert_eq!(restored["Nats-Msg-Id"], ["backup-1:12"]);
+        assert_eq!(restored[ORIGINAL_MESSAGE_ID], ["order-1"]);
+        assert_eq!(restored[BACKUP_SEQUENCE], ["12"]);
+
+        // a later message that reused the id is not a duplicate of it
+        let reused = restore_headers(Some(headers), "backup-1", 13).unwrap();
+        assert_ne!(reused["Nats-Msg-Id"], restored["Nats-Msg-Id"]);
+
+        let restored = restore_headers(None, "backup-1", 3).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored["Nats-Msg-Id"], ["backup-1:3"]);
+        assert_eq!(restored[BACKUP_SEQUENCE], ["3"]);
+    }
+
+    #[test]
+    fn test_subjects_overlap() {
+        for (a, b) in [
+            ("orders.created", "orders.created"),
+            ("orders.*", "orders.created"),
+            ("orders.>", "orders.eu.created"),
+            ("orders.*.created", "orders.eu.*"),
+            (">", "orders"),
+        ] {
+            assert!(subjects_overlap(a, b), "{a} {b}");
+            assert!(subjects_overlap(b, a), "{b} {a}");
+        }
+        for (a, b) in [
+            ("orders.created", "orders.deleted"),
+            ("orders.*", "orders.eu.created"),
+            ("orders.>", "orders"),
+            ("orders", "orders.created"),
+            ("orders.*.created", "payments.>"),
+        ] {
+            assert!(!subjects_overlap(a, b), "{a} {b}");
+            assert!(!subjects_overlap(b, a), "{b} {a}");
+        }
+    }
+
+    #[test]
+    fn test_backup_sequence() {
+        let mut headers = async_nats::HeaderMap::new();
+        assert_eq!(backup_sequence(&headers), None);
+        headers.insert(BACKUP_SEQUENCE, "42");
+        assert_eq!(backup_sequence(&headers), Some(42));
+        headers.insert(BACKUP_SEQUENCE, "x");
+        assert_eq!(backup_sequence(&headers), None);
+    }
+}
@@ -0,0 +1,226 @@
+// Object bodies written in one pass and read back in ranges.
+//
+// A BodyWriter keeps what is written in memory up to a limit, then moves it
+// to a temporary file and appends there, so a large chunk is never held in
+// memory whole. The Body it finishes into is uploaded part by part, each part
+// read from the file when it is sent. The temporary file is removed once the
+// last clone of the body is dropped.
+
+use bytes::Bytes;
+use std::{
+    io::{self, BufWriter, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tempfile::TempPath;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWrite};
+
+// bytes a body holds in memory before it moves to a temporary file, no more
+// than the smallest multipart part so a body uploaded in parts is read from
+// its file
+pub const SPILL_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub enum Body {
+    Memory(Bytes),
+    File { file: Arc<BodyFile>, len: u64 },
+}
+
+#[derive(Debug)]
+pub enum BodyFile {
+    // spilled by a writer, removed on drop
+    Temp(TempPath),
+    // a file the body was opened from, left in place
+    Kept(PathBuf),
+}
+
+impl BodyFile {
+    fn path(&self) -> &Path {
+        match self {
+            Self::Temp(path) => path,
+            Self::Kept(path) => path,
+        }
+    }
+}
+
+impl Body {
+    // body of an existing file, read in place
+    pub async fn open(path: PathBuf) -> io::Result<Self> {
+        let len = tokio::fs::metadata(&path).await?.len();
+        Ok(Self::File {
+            file: Arc::new(BodyFile::Kept(path)),
+            len,
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        match self {
+            Self::Memory(data) => data.len() as u64,
+            Self::File { len, .. } => *len,
+        }
+    }
+
+    // copy of a range of the body, the range must lie within it
+    pub async fn read_range(&self, range: Range<u64>) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Memory(data) => Ok(data[range.start as usize..range.end as usize].to_vec()),
+            Self::File { file, .. } => {
+                let mut reader = tokio::fs::File::open(file.path()).await?;
+                reader.seek(io::SeekFrom::Start(range.start)).await?;
+                let mut data = vec![0; (range.end - range.start) as usize];
+                reader.read_exact(&mut data).await?;
+                Ok(data)
+            }
+        }
+    }
+
+    // the whole body in memory, for bodies known to be small
+    pub async fn into_bytes(self) -> io::Result<Bytes> {
+        match self {
+            Self::Memory(data) => Ok(data),
+            Self::File { file, .. } => Ok(Bytes::from(tokio::fs::read(file.path()).await?)),
+        }
+    }
+
+    // blocking reader over the body, for the sync encoders
+    pub fn reader(&self) -> io::Result<Box<dyn Read + Send>> {
+        match self {
+            Self::Memory(data) => Ok(Box::new(io::Cursor::new(data.clone()))),
+            Self::File { file, .. } => Ok(Box::new(std::fs::File::open(file.path())?)),
+        }
+    }
+
+    pub async fn copy_to<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> io::Result<u64> {
+        match self {
+            Self::Memory(data) => tokio::io::copy(&mut data.as_ref(), writer).await,
+            Self::File { file, .. } => {
+                let mut reader = tokio::fs::File::open(file.path()).await?;
+                tokio::io::copy(&mut reader, writer).await
+            }
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(data: Vec<u8>) -> Self {
+        Self::Memory(Bytes::from(data))
+    }
+}
+
+impl From<Bytes> for Body {
+    fn from(data: Bytes) -> Self {
+        Self::Memory(data)
+    }
+}
+
+pub struct BodyWriter {
+    limit: usize,
+    state: WriterState,
+}
+
+enum WriterState {
+    Memory(Vec<u8>),
+    File {
+        writer: BufWriter<std::fs::File>,
+        path: TempPath,
+        len: u64,
+    },
+}
+
+impl BodyWriter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            state: WriterState::Memory(Vec::new()),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<Body> {
+        match self.state {
+            WriterState::Memory(data) => Ok(Body::from(data)),
+            WriterState::File { writer, path, len } => {
+                writer.into_inner().map_err(|e| e.into_error())?;
+                Ok(Body::File {
+                    file: Arc::new(BodyFile::Temp(path)),
+                    len,
+                })
+            }
+        }
+    }
+
+    fn spill(&mut self, data: &[u8]) -> io::Result<()> {
+        let (file, path) = tempfile::NamedTempFile::new()?.into_parts();
+        let mut writer = BufWriter::new(file);
+        writer.write_all(data)?;
+        self.state = WriterState::File {
+            writer,
+            path,
+            len: data.len() as u64,
+        };
+        Ok(())
+    }
+}
+
+impl Write for BodyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.state {
+            WriterState::Memory(data) if data.len() + buf.len() <= self.limit => {
+                data.extend_from_slice(buf);
+            }
+            WriterState::Memory(data) => {
+                let data = std::mem::take(data);
+                self.spill(&data)?;
+                return self.write(buf);
+            }
+            WriterState::File { writer, len, .. } => {
+                writer.write_all(buf)?;
+                *len += buf.len() as u64;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.state {
+            WriterState::Memory(_) => Ok(()),
+            WriterState::File { writer, .. } => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_body_writer_spills() {
+        let mut writer = BodyWriter::new(8);
+        writer.write_all(b"0123").unwrap();
+        let body = writer.finish().unwrap();
+        assert!(matches!(body, Body::Memory(_)));
+        assert_eq!(body.into_bytes().await.unwrap().as_ref(), b"0123");
+
+        let mut writer = BodyWriter::new(8);
+        writer.write_all(b"0123").unwrap();
+        writer.write_all(b"45678").unwrap();
+        writer.write_all(b"9").unwrap();
+        let body = writer.finish().unwrap();
+        let Body::File { file, len } = &body else {
+            panic!("body past the limit is not in a file");
+        };
+        assert_eq!(*len, 10);
+        let path = file.path().to_path_buf();
+        assert_eq!(body.read_range(3..7).await.unwrap(), b"3456");
+        let mut read = vec![];
+        body.reader().unwrap().read_to_end(&mut read).unwrap();
+        assert_eq!(read, b"0123456789");
+
+        // the temporary file goes with the last clone
+        let copy = body.clone();
+        drop(body);
+        assert!(path.exists());
+        drop(copy);
+        assert!(!path.exists());
+    }
+}
@@ -5,7 +5,7 @@ use tokio_util::sync::CancellationToken;
 use tracing::{debug, warn};
 
 use crate::{db, metrics, registry};
-use nats3_types::{LoadJobStatus, StoreJobStatus};
+use nats3_types::{KvJobStatus, LoadJobStatus, ObjectJobStatus, StoreJobStatus};
 
 #[derive(Clone, Debug)]
 pub struct TaskCompleter {
@@ -53,8 +53,14 @@ impl TaskCompleter {
     async fn handle_exit(&self, exit_info: registry::TaskExitInfo) -> Result<()> {
         let job_id = exit_info.job_id.clone();
         let is_store = self.registry.is_store_job_running(&job_id).await;
+        let is_kv = !is_store && self.registry.is_kv_job_running(&job_id).await;
+        let is_object = !is_store && !is_kv && self.registry.is_object_job_running(&job_id).await;
         let job_type = if is_store {
             metrics::JOB_TYPE_STORE
+        } else if is_kv {
+            metrics::JOB_TYPE_KV
+        } else if is_object {
+            metrics::JOB_TYPE_OBJECT
         } else {
             metrics::JOB_TYPE_LOAD
         };
@@ -88,6 +94,14 @@ impl TaskCompleter {
                     self.db
                         .update_store_job(job_id.clone(), StoreJobStatus::Success)
                         .await?;
+                } else if is_kv {
+                    self.db
+                        .update_kv_job(job_id.clone(), KvJobStatus::Success)
+                        .await?;
+                } else if is_object {
+                    self.db
+                        .update_object_job(job_id.clone(), ObjectJobStatus::Success)
+                        .await?;
                 } else {
                     self.db
                         .update_load_job(job_id.clone(), LoadJobStatus::Success)
@@ -115,10 +129,12 @@ impl TaskCompleter {
                     self.db
                         .update_store_job(job_id.clone(), StoreJobStatus::Failure)
                         .await?;
+                } else if is_kv {
+                    self.db.fail_kv_job(job_id.clone(), e.clone()).await?;
+                } else if is_object {
+                    self.db.fail_object_job(job_id.clone(), e.clone()).await?;
                 } else {
-                    self.db
-                        .update_load_job(job_id.clone(), LoadJobStatus::Failure)
-                        .await?;
+                    self.db.fail_load_job(job_id.clone(), e.clone()).await?;
                 }
             }
             registry::TaskExitReason::Paused => {
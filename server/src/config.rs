@@ -1,14 +1,20 @@
-use anyhow::{anyhow, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use figment::{
     providers::{Env, Format, Toml, Yaml},
     Figment,
 };
 use serde::Deserialize;
-use std::{ffi::OsStr, path::PathBuf, string::ToString};
+use std::{ffi::OsStr, fmt, path::PathBuf, string::ToString, time::Duration};
 use tracing_subscriber::filter::LevelFilter;
 
 const DEFAULT_CONFIG_PATH: &str = "/etc/nats3/config.toml";
 const DEFAULT_SERVER_ADDR: &str = "0.0.0.0:8080";
+const DEFAULT_MULTIPART_THRESHOLD: usize = 64 * 1024 * 1024;
+const DEFAULT_MULTIPART_PART_SIZE: usize = 16 * 1024 * 1024;
+const DEFAULT_MULTIPART_CONCURRENCY: usize = 4;
+const DEFAULT_SPOOL_MAX_BYTES: u64 = 1 << 30;
+const DEFAULT_SPOOL_RETRY_SECS: u64 = 5;
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct Config {
@@ -17,6 +23,11 @@ pub struct Config {
     pub postgres: Postgres,
     pub nats: Nats,
     pub s3: S3,
+    pub encryption: Option<Encryption>,
+    pub signing: Option<Signing>,
+    pub spool: Option<Spool>,
+    #[serde(default)]
+    pub retry: Retry,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -48,6 +59,194 @@ pub struct S3 {
     pub secret_key: String,
     #[serde(rename = "access")]
     pub access_key: String,
+    #[serde(default)]
+    pub multipart: Multipart,
+}
+
+// chunks larger than the threshold are uploaded in parts, parts must be at
+// least 5 MiB
+#[derive(Deserialize, Clone, Debug)]
+pub struct Multipart {
+    #[serde(default = "multipart_threshold_default")]
+    pub threshold: usize,
+    #[serde(default = "multipart_part_size_default")]
+    pub part_size: usize,
+    // parts uploaded at the same time
+    #[serde(default = "multipart_concurrency_default")]
+    pub concurrency: usize,
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        Self {
+            threshold: multipart_threshold_default(),
+            part_size: multipart_part_size_default(),
+            concurrency: multipart_concurrency_default(),
+        }
+    }
+}
+
+fn multipart_threshold_default() -> usize {
+    DEFAULT_MULTIPART_THRESHOLD
+}
+
+fn multipart_part_size_default() -> usize {
+    DEFAULT_MULTIPART_PART_SIZE
+}
+
+fn multipart_concurrency_default() -> usize {
+    DEFAULT_MULTIPART_CONCURRENCY
+}
+
+// local disk spool that store jobs write sealed chunks to before upload
+#[derive(Deserialize, Clone, Debug)]
+pub struct Spool {
+    pub dir: PathBuf,
+    // store jobs wait for uploads while spooled chunks exceed this size
+    #[serde(default = "spool_max_bytes_default")]
+    pub max_bytes: u64,
+    // wait between attempts to upload a failing chunk
+    #[serde(default = "spool_retry_secs_default")]
+    pub retry_secs: u64,
+}
+
+fn spool_max_bytes_default() -> u64 {
+    DEFAULT_SPOOL_MAX_BYTES
+}
+
+fn spool_retry_secs_default() -> u64 {
+    DEFAULT_SPOOL_RETRY_SECS
+}
+
+// retry policy for transient S3, NATS and Postgres errors, used by jobs that
+// don't set their own
+#[derive(Deserialize, Clone, Debug)]
+pub struct Retry {
+    #[serde(default = "retry_max_attempts_default")]
+    pub max_attempts: u32,
+    #[serde(default = "retry_backoff_ms_default")]
+    pub backoff_ms: u64,
+    #[serde(default = "retry_max_backoff_ms_default")]
+    pub max_backoff_ms: u64,
+    #[serde(default = "retry_jitter_default")]
+    pub jitter: bool,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            max_attempts: retry_max_attempts_default(),
+            backoff_ms: retry_backoff_ms_default(),
+            max_backoff_ms: retry_max_backoff_ms_default(),
+            jitter: retry_jitter_default(),
+        }
+    }
+}
+
+impl Retry {
+    pub fn policy(&self) -> Result<nats3_types::Retry> {
+        let policy = nats3_types::Retry {
+            max_attempts: self.max_attempts,
+            backoff: Duration::from_millis(self.backoff_ms),
+            max_backoff: Duration::from_millis(self.max_backoff_ms),
+            jitter: self.jitter,
+        };
+        policy.validate()?;
+        Ok(policy)
+    }
+}
+
+fn retry_max_attempts_default() -> u32 {
+    nats3_types::Retry::default().max_attempts
+}
+
+fn retry_backoff_ms_default() -> u64 {
+    nats3_types::Retry::default().backoff.as_millis() as u64
+}
+
+fn retry_max_backoff_ms_default() -> u64 {
+    nats3_types::Retry::default().max_backoff.as_millis() as u64
+}
+
+fn retry_jitter_default() -> bool {
+    nats3_types::Retry::default().jitter
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Encryption {
+    // id of the master key used to encrypt new chunks
+    pub active_key: String,
+    pub keys: Vec<MasterKey>,
+}
+
+// a base64 encoded 256 bit master key, set inline or read from a file
+#[derive(Deserialize, Clone)]
+pub struct MasterKey {
+    pub id: String,
+    pub key: Option<String>,
+    pub key_file: Option<PathBuf>,
+}
+
+// keep key material out of logs
+impl fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MasterKey")
+            .field("id", &self.id)
+            .field("key", &self.key.as_ref().map(|_| "<redacted>"))
+            .field("key_file", &self.key_file)
+            .finish()
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Signing {
+    // id of the key used to sign new chunks
+    pub active_key: String,
+    // refuse to load chunks without a signature
+    #[serde(default)]
+    pub require_signed: bool,
+    pub keys: Vec<SigningKey>,
+}
+
+// a base64 encoded hmac secret or ed25519 private key, set inline or read from a file
+#[derive(Deserialize, Clone)]
+pub struct SigningKey {
+    pub id: String,
+    pub algorithm: SigningAlgorithm,
+    pub key: Option<String>,
+    pub key_file: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SigningAlgorithm {
+    HmacSha256,
+    Ed25519,
+}
+
+// keep key material out of logs
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("id", &self.id)
+            .field("algorithm", &self.algorithm)
+            .field("key", &self.key.as_ref().map(|_| "<redacted>"))
+            .field("key_file", &self.key_file)
+            .finish()
+    }
+}
+
+// decode key material set inline or in a file, exactly one must be given
+pub fn read_key(id: &str, key: Option<&String>, key_file: Option<&PathBuf>) -> Result<Vec<u8>> {
+    let encoded = match (key, key_file) {
+        (Some(encoded), None) => encoded.clone(),
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .with_context(|| format!("read key file {}", path.display()))?,
+        _ => bail!("key '{}' needs exactly one of key or key_file", id),
+    };
+    STANDARD
+        .decode(encoded.trim())
+        .with_context(|| format!("decode key '{}'", id))
 }
 
 impl Config {
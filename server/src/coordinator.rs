@@ -1,12 +1,17 @@
 use anyhow::Result;
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
 use nats3_types::{
-    LoadJob, LoadJobCreate, LoadJobStatus, StoreJob, StoreJobCreate, StoreJobStatus,
+    ChunkOverlap, ChunkVerification, DuplicateChunksQuery, KvArchiveCreate, KvJob, KvJobCreate,
+    KvJobKind, KvJobStatus, KvRestoreCreate, ListStoreJobsQuery, LoadJob, LoadJobCreate,
+    LoadJobStatus, ObjectArchiveCreate, ObjectJob, ObjectJobCreate, ObjectJobKind, ObjectJobStatus,
+    ObjectRestoreCreate, StoreJob, StoreJobCreate, StoreJobStatus, ValidationError,
+    VerifyChunksQuery,
 };
 
-use crate::{db, error, io, metrics, registry};
+use crate::{db, error, io, metrics, nats, registry};
 
 #[derive(Debug, Clone)]
 pub struct Coordinator {
@@ -71,7 +76,7 @@ impl Coordinator {
 
             if let Err(e) = &result {
                 let _ = exit_tx_clone.send(registry::TaskExitInfo {
-                    reason: registry::TaskExitReason::Completed(Err(e.to_string())),
+                    reason: registry::TaskExitReason::Completed(Err(format!("{:#}", e))),
                     job_id,
                 });
             }
@@ -98,8 +103,31 @@ impl Coordinator {
         Ok(job)
     }
 
-    pub async fn start_new_load_job(&self, job: LoadJobCreate) -> Result<LoadJob, error::AppError> {
-        let out = self.db.create_load_job(job.clone()).await?;
+    pub async fn start_new_load_job(
+        &self,
+        mut job: LoadJobCreate,
+    ) -> Result<LoadJob, error::AppError> {
+        if let Some(backup_id) = &job.backup_id {
+            // a restore reads the chunks of the backup's own consumer only
+            let backup = self.db.get_store_job(backup_id.clone()).await?;
+            let invalid = |reason: String| ValidationError::InvalidStreamRestore { reason };
+            if !backup.backup {
+                return Err(invalid(format!("job {} is not a backup", backup.id)).into());
+            }
+            if backup.stream != job.read_stream
+                || backup.bucket != job.bucket
+                || backup.prefix != job.prefix
+            {
+                return Err(invalid(format!(
+                    "backup {} is of stream {} in bucket {} with prefix {:?}",
+                    backup.id, backup.stream, backup.bucket, backup.prefix
+                ))
+                .into());
+            }
+            job.read_consumer = Some(nats::consumer_name(&backup.id));
+            job.read_subject = backup.subject;
+        }
+        let out = self.db.create_load_job(job).await?;
         self.start_load_job(out).await
     }
 
@@ -164,7 +192,7 @@ impl Coordinator {
 
             if let Err(e) = &result {
                 let _ = exit_tx_clone.send(registry::TaskExitInfo {
-                    reason: registry::TaskExitReason::Completed(Err(e.to_string())),
+                    reason: registry::TaskExitReason::Completed(Err(format!("{:#}", e))),
                     job_id,
                 });
             }
@@ -193,8 +221,35 @@ impl Coordinator {
 
     pub async fn start_new_store_job(
         &self,
-        job: StoreJobCreate,
+        mut job: StoreJobCreate,
     ) -> Result<StoreJob, error::AppError> {
+        if job.encoding.encrypt && !self.io.encryption_enabled() {
+            return Err(ValidationError::EncryptionNotConfigured.into());
+        }
+        // a backup consumes the subjects the stream has when it is created
+        if job.backup {
+            let config = self.io.nats_client.stream_config(&job.stream).await?;
+            let mut subjects = config.subjects.into_iter();
+            job.subject = subjects
+                .next()
+                .ok_or_else(|| ValidationError::InvalidStreamBackup {
+                    reason: format!("stream {} has no subjects", job.stream),
+                })?;
+            job.subjects = subjects.collect();
+        }
+        if let Some(consumer) = &job.consumer {
+            self.check_consumer_conflict(&job.stream, consumer).await?;
+            self.io
+                .nats_client
+                .check_consumer(
+                    &job.stream,
+                    consumer,
+                    &job.filter_subjects(),
+                    job.ack_policy,
+                    io::max_ack_pending(job.batch.max_count),
+                )
+                .await?;
+        }
         let out = self.db.create_store_job(job.clone()).await?;
         self.start_store_job(out).await
     }
@@ -220,4 +275,347 @@ impl Coordinator {
     pub async fn stop_store_job(&self, job_id: String) {
         self.registry.cancel_store_job(&job_id).await
     }
+
+    // stop and delete a store job, and the consumer it created unless kept.
+    // consumers the job was bound to are never deleted.
+    pub async fn delete_store_job(
+        &self,
+        job_id: String,
+        keep_consumer: bool,
+    ) -> Result<(), error::AppError> {
+        let job = self.db.get_store_job(job_id.clone()).await?;
+        self.stop_store_job(job_id.clone()).await;
+        if job.consumer.is_none() && !keep_consumer {
+            self.io
+                .nats_client
+                .delete_consumer(&job.stream, &nats::consumer_name(&job.id))
+                .await?;
+        }
+        self.db.delete_store_job(job_id).await?;
+        Ok(())
+    }
+
+    // run an archive, or a restore of the archive it reads from
+    async fn start_kv_job(
+        &self,
+        job: KvJob,
+        archive: Option<KvJob>,
+    ) -> Result<KvJob, error::AppError> {
+        let job_id = job.id.to_string();
+        if self.registry.is_kv_job_running(&job_id).await {
+            return Err(registry::RegistryError::JobAlreadyRunning { job_id }.into());
+        }
+
+        self.metrics
+            .jobs
+            .jobs_current
+            .get_or_create(&metrics::JobTypeLabel {
+                job_type: metrics::JOB_TYPE_KV.to_string(),
+            })
+            .inc();
+
+        let io = self.io.clone();
+        let registry_job = job.clone();
+
+        let cancel_token = self.registry.create_cancel_token();
+        let cancel_token_clone = cancel_token.clone();
+        // an empty bucket archives at once, so the job waits until it is
+        // registered and marked running before it can complete
+        let started = CancellationToken::new();
+        let started_clone = started.clone();
+        let exit_tx = self.registry.create_exit_channel();
+        let exit_tx_clone = exit_tx.clone();
+        let job_id_clone = job_id.clone();
+
+        let handle: tokio::task::JoinHandle<Result<()>> = tokio::spawn(async move {
+            started_clone.cancelled().await;
+            let result = match archive {
+                Some(archive) => {
+                    io.restore_kv(registry_job, archive, cancel_token, exit_tx)
+                        .await
+                }
+                None => io.archive_kv(registry_job, cancel_token, exit_tx).await,
+            };
+
+            if let Err(e) = &result {
+                let _ = exit_tx_clone.send(registry::TaskExitInfo {
+                    reason: registry::TaskExitReason::Completed(Err(format!("{:#}", e))),
+                    job_id,
+                });
+            }
+            result
+        });
+
+        let registered = self
+            .registry
+            .try_register_kv_job(job_id_clone.clone(), handle, cancel_token_clone)
+            .await;
+
+        let status = if registered {
+            KvJobStatus::Running
+        } else {
+            KvJobStatus::Failure
+        };
+
+        let job = self.db.update_kv_job(job_id_clone, status).await;
+        started.cancel();
+        Ok(job?)
+    }
+
+    pub async fn start_new_kv_archive(
+        &self,
+        job: KvArchiveCreate,
+    ) -> Result<KvJob, error::AppError> {
+        if job.encoding.encrypt && !self.io.encryption_enabled() {
+            return Err(ValidationError::EncryptionNotConfigured.into());
+        }
+        self.io.nats_client.check_kv_bucket(&job.kv_bucket).await?;
+        let out = self.db.create_kv_job(job.into()).await?;
+        self.start_kv_job(out, None).await
+    }
+
+    // restore a successful archive, into its own bucket unless another is given
+    pub async fn start_new_kv_restore(
+        &self,
+        job: KvRestoreCreate,
+    ) -> Result<KvJob, error::AppError> {
+        let archive = self.db.get_kv_job(job.archive_id.clone()).await?;
+        if archive.kind != KvJobKind::Archive {
+            return Err(ValidationError::InvalidKvArchive {
+                reason: format!("job {} is not an archive", archive.id),
+            }
+            .into());
+        }
+        if archive.status != KvJobStatus::Success {
+            return Err(ValidationError::InvalidKvArchive {
+                reason: format!("archive {} has status {}", archive.id, archive.status),
+            }
+            .into());
+        }
+        if archive.encoding.encrypt && !self.io.encryption_enabled() {
+            return Err(ValidationError::EncryptionNotConfigured.into());
+        }
+        let out = self
+            .db
+            .create_kv_job(KvJobCreate {
+                name: job.name,
+                kind: KvJobKind::Restore,
+                kv_bucket: job.kv_bucket.unwrap_or_else(|| archive.kv_bucket.clone()),
+                bucket: archive.bucket.clone(),
+                prefix: archive.prefix.clone(),
+                history: archive.history,
+                encoding: archive.encoding.clone(),
+                archive_id: Some(archive.id.clone()),
+                retry: job.retry,
+            })
+            .await?;
+        self.start_kv_job(out, Some(archive)).await
+    }
+
+    pub async fn stop_kv_job(&self, job_id: String) {
+        self.registry.cancel_kv_job(&job_id).await
+    }
+
+    // stop and delete a key-value job, the chunks of an archive are kept
+    pub async fn delete_kv_job(&self, job_id: String) -> Result<(), error::AppError> {
+        self.stop_kv_job(job_id.clone()).await;
+        self.db.delete_kv_job(job_id).await?;
+        Ok(())
+    }
+
+    // run an archive, or a restore of the archive it reads from
+    async fn start_object_job(
+        &self,
+        job: ObjectJob,
+        archive: Option<ObjectJob>,
+    ) -> Result<ObjectJob, error::AppError> {
+        let job_id = job.id.to_string();
+        if self.registry.is_object_job_running(&job_id).await {
+            return Err(registry::RegistryError::JobAlreadyRunning { job_id }.into());
+        }
+
+        self.metrics
+            .jobs
+            .jobs_current
+            .get_or_create(&metrics::JobTypeLabel {
+                job_type: metrics::JOB_TYPE_OBJECT.to_string(),
+            })
+            .inc();
+
+        let io = self.io.clone();
+        let registry_job = job.clone();
+
+        let cancel_token = self.registry.create_cancel_token();
+        let cancel_token_clone = cancel_token.clone();
+        // like key-value jobs, wait until registered and marked running
+        let started = CancellationToken::new();
+        let started_clone = started.clone();
+        let exit_tx = self.registry.create_exit_channel();
+        let exit_tx_clone = exit_tx.clone();
+        let job_id_clone = job_id.clone();
+
+        let handle: tokio::task::JoinHandle<Result<()>> = tokio::spawn(async move {
+            started_clone.cancelled().await;
+            let result = match archive {
+                Some(archive) => {
+                    io.restore_objects(registry_job, archive, cancel_token, exit_tx)
+                        .await
+                }
+                None => {
+                    io.archive_objects(registry_job, cancel_token, exit_tx)
+                        .await
+                }
+            };
+
+            if let Err(e) = &result {
+                let _ = exit_tx_clone.send(registry::TaskExitInfo {
+                    reason: registry::TaskExitReason::Completed(Err(format!("{:#}", e))),
+                    job_id,
+                });
+            }
+            result
+        });
+
+        let registered = self
+            .registry
+            .try_register_object_job(job_id_clone.clone(), handle, cancel_token_clone)
+            .await;
+
+        let status = if registered {
+            ObjectJobStatus::Running
+        } else {
+            ObjectJobStatus::Failure
+        };
+
+        let job = self.db.update_object_job(job_id_clone, status).await;
+        started.cancel();
+        Ok(job?)
+    }
+
+    pub async fn start_new_object_archive(
+        &self,
+        job: ObjectArchiveCreate,
+    ) -> Result<ObjectJob, error::AppError> {
+        self.io
+            .nats_client
+            .check_object_bucket(&job.object_bucket)
+            .await?;
+        let out = self.db.create_object_job(job.into()).await?;
+        self.start_object_job(out, None).await
+    }
+
+    // restore a successful archive, into its own bucket unless another is given
+    pub async fn start_new_object_restore(
+        &self,
+        job: ObjectRestoreCreate,
+    ) -> Result<ObjectJob, error::AppError> {
+        let archive = self.db.get_object_job(job.archive_id.clone()).await?;
+        if archive.kind != ObjectJobKind::Archive {
+            return Err(ValidationError::InvalidObjectArchive {
+                reason: format!("job {} is not an archive", archive.id),
+            }
+            .into());
+        }
+        if archive.status != ObjectJobStatus::Success {
+            return Err(ValidationError::InvalidObjectArchive {
+                reason: format!("archive {} has status {}", archive.id, archive.status),
+            }
+            .into());
+        }
+        let out = self
+            .db
+            .create_object_job(ObjectJobCreate {
+                name: job.name,
+                kind: ObjectJobKind::Restore,
+                object_bucket: job
+                    .object_bucket
+                    .unwrap_or_else(|| archive.object_bucket.clone()),
+                bucket: archive.bucket.clone(),
+                prefix: archive.prefix.clone(),
+                archive_id: Some(archive.id.clone()),
+                retry: job.retry,
+            })
+            .await?;
+        self.start_object_job(out, Some(archive)).await
+    }
+
+    pub async fn stop_object_job(&self, job_id: String) {
+        self.registry.cancel_object_job(&job_id).await
+    }
+
+    // stop and delete an object store job, the objects of an archive are kept
+    pub async fn delete_object_job(&self, job_id: String) -> Result<(), error::AppError> {
+        self.stop_object_job(job_id.clone()).await;
+        self.db.delete_object_job(job_id).await?;
+        Ok(())
+    }
+
+    // two active store jobs reading one consumer would split its messages
+    async fn check_consumer_conflict(
+        &self,
+        stream: &str,
+        consumer: &String,
+    ) -> Result<(), error::AppError> {
+        let query = ListStoreJobsQuery {
+            stream: Some(stream.to_string()),
+            ..ListStoreJobsQuery::new().with_statuses(vec![
+                StoreJobStatus::Created,
+                StoreJobStatus::Running,
+                StoreJobStatus::Paused,
+            ])
+        };
+        let jobs = self.db.get_store_jobs(Some(query)).await?;
+        if let Some(job) = jobs
+            .iter()
+            .find(|job| nats::store_job_consumer(&job.id, job.consumer.as_ref()) == *consumer)
+        {
+            return Err(nats::ConsumerError::InUse {
+                consumer: consumer.clone(),
+                job_id: job.id.clone(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    pub async fn verify_chunk(
+        &self,
+        sequence_number: i64,
+    ) -> Result<ChunkVerification, error::AppError> {
+        let chunk_md = self.io.chunk_db.get_chunk(sequence_number).await?;
+        Ok(self.io.verify_chunk(&chunk_md).await)
+    }
+
+    pub async fn verify_chunks(
+        &self,
+        query: VerifyChunksQuery,
+    ) -> Result<Vec<ChunkVerification>, error::AppError> {
+        let chunks = self
+            .io
+            .chunk_db
+            .list_chunks(db::ListChunksQuery {
+                stream: query.stream,
+                consumer: query.consumer,
+                subject: query.subject,
+                bucket: query.bucket,
+                prefix: query.prefix,
+                timestamp_start: None,
+                timestamp_end: None,
+                limit: None,
+                include_deleted: false,
+            })
+            .await?;
+        let mut out = Vec::with_capacity(chunks.len());
+        for chunk_md in chunks {
+            out.push(self.io.verify_chunk(&chunk_md).await);
+        }
+        Ok(out)
+    }
+
+    pub async fn duplicate_chunks(
+        &self,
+        query: DuplicateChunksQuery,
+    ) -> Result<Vec<ChunkOverlap>, error::AppError> {
+        Ok(self.io.chunk_db.list_overlapping_chunks(query).await?)
+    }
 }
@@ -1,10 +1,11 @@
 use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, sync::Arc};
 use thiserror::Error;
 
-use nats3_types::Codec;
+use nats3_types::{ChunkOverlap, Codec, Compression, DuplicateChunksQuery};
 
 #[derive(Error, Debug)]
 pub enum ChunkMetadataError {
@@ -14,12 +15,18 @@ pub enum ChunkMetadataError {
     #[error("duplicate chunk at location: bucket={bucket}, key={key}")]
     Duplicate { bucket: String, key: String },
 
+    #[error("a different chunk is recorded at location: bucket={bucket}, key={key}")]
+    Conflict { bucket: String, key: String },
+
     #[error("invalid timestamp range: start={start} end={end}")]
     InvalidTimestampRange {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     },
 
+    #[error("invalid job id: {0}")]
+    InvalidJobId(#[from] uuid::Error),
+
     #[error("database error: {0}")]
     Postgres(#[from] crate::db::postgres::PostgresError),
 
@@ -40,15 +47,38 @@ pub struct ChunkMetadata {
     pub timestamp_start: DateTime<Utc>,
     pub timestamp_end: DateTime<Utc>,
     pub message_count: i64,
+    // lowest and highest stream sequence archived, none for older chunks
+    pub stream_sequence_start: Option<i64>,
+    pub stream_sequence_end: Option<i64>,
+    // distinct message subjects in the chunk, empty for older chunks
+    pub subjects: Vec<String>,
     pub size_bytes: i64,
     pub codec: Codec,
+    pub compression: Compression,
+    // master key id the chunk was encrypted with, none if not encrypted
+    pub encryption_key_id: Option<String>,
     pub hash: Bytes,
+    // signature over the hash and the key that made it, none if unsigned
+    pub signature: Option<Bytes>,
+    pub signature_key_id: Option<String>,
     pub version: String,
+    // set once the chunk has been loaded with partial recovery
+    pub recovery: Option<ChunkRecovery>,
     pub created_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Clone, Debug)]
+// outcome of loading a damaged chunk with partial recovery
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkRecovery {
+    pub recovered_count: i64,
+    pub lost_sequences: Vec<i64>,
+    // false if some damaged records could not be tied to a sequence,
+    // or the chunk could not be read to the end
+    pub lost_sequences_complete: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CreateChunkMetadata {
     pub bucket: String,
     pub prefix: Option<String>,
@@ -59,9 +89,20 @@ pub struct CreateChunkMetadata {
     pub timestamp_start: DateTime<Utc>,
     pub timestamp_end: DateTime<Utc>,
     pub message_count: i64,
+    // lowest and highest stream sequence archived, none for older chunks
+    pub stream_sequence_start: Option<i64>,
+    pub stream_sequence_end: Option<i64>,
+    // distinct message subjects in the chunk, empty for older chunks
+    pub subjects: Vec<String>,
     pub size_bytes: i64,
     pub codec: Codec,
+    pub compression: Compression,
+    // master key id the chunk was encrypted with, none if not encrypted
+    pub encryption_key_id: Option<String>,
     pub hash: Bytes,
+    // signature over the hash and the key that made it, none if unsigned
+    pub signature: Option<Bytes>,
+    pub signature_key_id: Option<String>,
     pub version: String,
 }
 
@@ -88,6 +129,14 @@ pub trait ChunkMetadataStorer: Sync + Send + Debug {
 
     async fn get_chunk(&self, sequence_number: i64) -> Result<ChunkMetadata, ChunkMetadataError>;
 
+    /// Chunk recorded at an object location, deleted or not
+    async fn find_chunk(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        key: &str,
+    ) -> Result<Option<ChunkMetadata>, ChunkMetadataError>;
+
     /// List chunks matching query criteria.
     /// Results ordered by: timestamp_start ASC, timestamp_end ASC
     async fn list_chunks(
@@ -101,8 +150,56 @@ pub trait ChunkMetadataStorer: Sync + Send + Debug {
         sequence_number: i64,
     ) -> Result<ChunkMetadata, ChunkMetadataError>;
 
+    /// Record the outcome of a partial recovery. Returns updated metadata.
+    async fn record_chunk_recovery(
+        &self,
+        sequence_number: i64,
+        recovery: ChunkRecovery,
+    ) -> Result<ChunkMetadata, ChunkMetadataError>;
+
     /// Hard delete chunk (removes from database)
     async fn hard_delete_chunk(&self, sequence_number: i64) -> Result<(), ChunkMetadataError>;
+
+    /// Highest stream sequence a store job has archived, none before its first batch
+    async fn get_checkpoint(&self, job_id: &str) -> Result<Option<i64>, ChunkMetadataError>;
+
+    /// Raise the checkpoint of a store job, it never moves backwards
+    async fn set_checkpoint(
+        &self,
+        job_id: &str,
+        stream_sequence: i64,
+    ) -> Result<(), ChunkMetadataError>;
+
+    /// Pairs of live chunks with the same stream and subject whose stream
+    /// sequence ranges overlap. Results ordered by: first, second sequence number
+    async fn list_overlapping_chunks(
+        &self,
+        query: DuplicateChunksQuery,
+    ) -> Result<Vec<ChunkOverlap>, ChunkMetadataError>;
 }
 
 pub type DynChunkStorer = Arc<dyn ChunkMetadataStorer + Send + Sync>;
+
+/// Record a chunk. One already recorded at its location with the same hash is
+/// an earlier attempt of the same upload, any other is a conflict
+pub async fn record_chunk(
+    store: &dyn ChunkMetadataStorer,
+    chunk: CreateChunkMetadata,
+) -> Result<(), ChunkMetadataError> {
+    let (bucket, prefix, key, hash) = (
+        chunk.bucket.clone(),
+        chunk.prefix.clone(),
+        chunk.key.clone(),
+        chunk.hash.clone(),
+    );
+    match store.create_chunk(chunk).await {
+        Ok(_) => Ok(()),
+        Err(ChunkMetadataError::Duplicate { .. }) => {
+            match store.find_chunk(&bucket, prefix.as_deref(), &key).await? {
+                Some(existing) if existing.hash == hash => Ok(()),
+                _ => Err(ChunkMetadataError::Conflict { bucket, key }),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
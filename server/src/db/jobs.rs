@@ -4,8 +4,9 @@ use std::{fmt::Debug, sync::Arc};
 use thiserror::Error;
 
 use nats3_types::{
-    ListLoadJobsQuery, ListStoreJobsQuery, LoadJob, LoadJobCreate, LoadJobStatus, StoreJob,
-    StoreJobCreate, StoreJobStatus,
+    KvJob, KvJobCreate, KvJobStatus, ListKvJobsQuery, ListLoadJobsQuery, ListObjectJobsQuery,
+    ListStoreJobsQuery, LoadJob, LoadJobCreate, LoadJobStatus, ObjectJob, ObjectJobCreate,
+    ObjectJobStatus, StoreJob, StoreJobCreate, StoreJobStatus,
 };
 
 #[derive(Error, Debug)]
@@ -40,6 +41,8 @@ pub trait LoadJobStorer: Sync + Debug {
         id: String,
         status: LoadJobStatus,
     ) -> Result<LoadJob, JobStoreError>;
+    // mark a load job failed and record why
+    async fn fail_load_job(&self, id: String, error: String) -> Result<LoadJob, JobStoreError>;
     async fn delete_load_job(&self, id: String) -> Result<(), JobStoreError>;
 }
 
@@ -60,7 +63,41 @@ pub trait StoreJobStorer: Sync + Debug {
     async fn delete_store_job(&self, id: String) -> Result<(), JobStoreError>;
 }
 
+#[allow(dead_code)]
+#[async_trait]
+pub trait KvJobStorer: Sync + Debug {
+    async fn get_kv_job(&self, id: String) -> Result<KvJob, JobStoreError>;
+    async fn get_kv_jobs(
+        &self,
+        query: Option<ListKvJobsQuery>,
+    ) -> Result<Vec<KvJob>, JobStoreError>;
+    async fn create_kv_job(&self, job: KvJobCreate) -> Result<KvJob, JobStoreError>;
+    async fn update_kv_job(&self, id: String, status: KvJobStatus) -> Result<KvJob, JobStoreError>;
+    // mark a key-value job failed and record why
+    async fn fail_kv_job(&self, id: String, error: String) -> Result<KvJob, JobStoreError>;
+    async fn delete_kv_job(&self, id: String) -> Result<(), JobStoreError>;
+}
+
+#[allow(dead_code)]
+#[async_trait]
+pub trait ObjectJobStorer: Sync + Debug {
+    async fn get_object_job(&self, id: String) -> Result<ObjectJob, JobStoreError>;
+    async fn get_object_jobs(
+        &self,
+        query: Option<ListObjectJobsQuery>,
+    ) -> Result<Vec<ObjectJob>, JobStoreError>;
+    async fn create_object_job(&self, job: ObjectJobCreate) -> Result<ObjectJob, JobStoreError>;
+    async fn update_object_job(
+        &self,
+        id: String,
+        status: ObjectJobStatus,
+    ) -> Result<ObjectJob, JobStoreError>;
+    // mark an object store job failed and record why
+    async fn fail_object_job(&self, id: String, error: String) -> Result<ObjectJob, JobStoreError>;
+    async fn delete_object_job(&self, id: String) -> Result<(), JobStoreError>;
+}
+
 #[async_trait]
-pub trait JobStorer: LoadJobStorer + StoreJobStorer {}
+pub trait JobStorer: LoadJobStorer + StoreJobStorer + KvJobStorer + ObjectJobStorer {}
 
 pub type DynJobStorer = Arc<dyn JobStorer + Send + Sync>;
@@ -3,8 +3,11 @@ pub mod jobs;
 pub mod postgres;
 
 pub use chunks::{
-    ChunkMetadata, ChunkMetadataError, ChunkMetadataStorer, CreateChunkMetadata, DynChunkStorer,
-    ListChunksQuery,
+    record_chunk, ChunkMetadata, ChunkMetadataError, ChunkMetadataStorer, ChunkRecovery,
+    CreateChunkMetadata, DynChunkStorer, ListChunksQuery,
+};
+pub use jobs::{
+    DynJobStorer, JobStoreError, JobStorer, KvJobStorer, LoadJobStorer, ObjectJobStorer,
+    StoreJobStorer,
 };
-pub use jobs::{DynJobStorer, JobStoreError, JobStorer, LoadJobStorer, StoreJobStorer};
 pub use postgres::PostgresStore;
@@ -1,12 +1,15 @@
 use async_trait::async_trait;
+use nats3_types::{ChunkOverlap, DuplicateChunksQuery};
 use tracing::debug;
+use uuid::Uuid;
 
 use super::{
     models::{ChunkMetadataRow, CreateChunkMetadataRow},
     postgres::PostgresStore,
 };
 use crate::db::{
-    ChunkMetadata, ChunkMetadataError, ChunkMetadataStorer, CreateChunkMetadata, ListChunksQuery,
+    ChunkMetadata, ChunkMetadataError, ChunkMetadataStorer, ChunkRecovery, CreateChunkMetadata,
+    ListChunksQuery,
 };
 
 #[async_trait]
@@ -40,11 +43,12 @@ impl ChunkMetadataStorer for PostgresStore {
             .query_one(
                 "INSERT INTO chunks 
                  (bucket, prefix, key, stream, consumer, subject, timestamp_start,
-                 timestamp_end, message_count, size_bytes, codec, hash, version)
-                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                 timestamp_end, message_count, stream_sequence_start, stream_sequence_end, subjects, size_bytes, codec, compression, encryption_key_id, hash, signature, signature_key_id, version)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
                  RETURNING sequence_number, bucket, prefix, key, stream, consumer, subject,
-                           timestamp_start, timestamp_end, message_count, size_bytes,
-                           codec, hash, version, created_at, deleted_at",
+                           timestamp_start, timestamp_end, message_count, stream_sequence_start, stream_sequence_end, subjects, size_bytes,
+                           codec, compression, encryption_key_id, hash, signature, signature_key_id, version, recovered_count,
+                           lost_sequences, lost_sequences_complete, created_at, deleted_at",
                 &[
                     &row.bucket,
                     &row.prefix,
@@ -55,9 +59,16 @@ impl ChunkMetadataStorer for PostgresStore {
                     &row.timestamp_start,
                     &row.timestamp_end,
                     &row.message_count,
+                    &row.stream_sequence_start,
+                    &row.stream_sequence_end,
+                    &row.subjects,
                     &row.size_bytes,
                     &row.codec,
+                    &row.compression,
+                    &row.encryption_key_id,
                     &row.hash,
+                    &row.signature,
+                    &row.signature_key_id,
                     &row.version,
                 ],
             )
@@ -85,8 +96,9 @@ impl ChunkMetadataStorer for PostgresStore {
         let row = client
             .query_one(
                 "SELECT sequence_number, bucket, prefix, key, stream, consumer, subject,
-                        timestamp_start, timestamp_end, message_count, size_bytes,
-                        codec, hash, version, created_at, deleted_at
+                        timestamp_start, timestamp_end, message_count, stream_sequence_start, stream_sequence_end, subjects, size_bytes,
+                        codec, compression, encryption_key_id, hash, signature, signature_key_id, version, recovered_count,
+                        lost_sequences, lost_sequences_complete, created_at, deleted_at
                  FROM chunks
                  WHERE sequence_number = $1",
                 &[&sequence_number],
@@ -101,6 +113,33 @@ impl ChunkMetadataStorer for PostgresStore {
         Ok(chunk_row.into())
     }
 
+    async fn find_chunk(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        key: &str,
+    ) -> Result<Option<ChunkMetadata>, ChunkMetadataError> {
+        debug!(bucket = bucket, prefix = prefix, key = key, "find chunk");
+        let client = self.get_client().await?;
+
+        let row = client
+            .query_opt(
+                "SELECT sequence_number, bucket, prefix, key, stream, consumer, subject,
+                        timestamp_start, timestamp_end, message_count, stream_sequence_start, stream_sequence_end, subjects, size_bytes,
+                        codec, compression, encryption_key_id, hash, signature, signature_key_id, version, recovered_count,
+                        lost_sequences, lost_sequences_complete, created_at, deleted_at
+                 FROM chunks
+                 WHERE bucket = $1 AND COALESCE(prefix, '') = COALESCE($2, '') AND key = $3",
+                &[&bucket, &prefix, &key],
+            )
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(ChunkMetadataRow::from_row(&row)?.into())),
+            None => Ok(None),
+        }
+    }
+
     async fn list_chunks(
         &self,
         query: ListChunksQuery,
@@ -117,15 +156,20 @@ impl ChunkMetadataStorer for PostgresStore {
 
         let mut sql = String::from(
             "SELECT sequence_number, bucket, prefix, key, stream, consumer, subject,
-                    timestamp_start, timestamp_end, message_count, size_bytes,
-                    codec, hash, version, created_at, deleted_at
+                    timestamp_start, timestamp_end, message_count, stream_sequence_start, stream_sequence_end, subjects, size_bytes,
+                    codec, compression, encryption_key_id, hash, signature, signature_key_id, version, recovered_count,
+                    lost_sequences, lost_sequences_complete, created_at, deleted_at
              FROM chunks
-             WHERE stream = $1 AND subject = $2 AND bucket = $3",
+             WHERE stream = $1 AND bucket = $3
+                AND (subject = $2 OR EXISTS (
+                    SELECT 1 FROM unnest(subjects) AS s WHERE s ~ $4
+                ))",
         );
 
+        let pattern = subject_pattern(&query.subject);
         let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> =
-            vec![&query.stream, &query.subject, &query.bucket];
-        let mut param_idx = 4;
+            vec![&query.stream, &query.subject, &query.bucket, &pattern];
+        let mut param_idx = 5;
 
         if let Some(ref prefix) = query.prefix {
             sql.push_str(&format!(" AND prefix = ${}", param_idx));
@@ -182,8 +226,10 @@ impl ChunkMetadataStorer for PostgresStore {
                  SET deleted_at = NOW()
                  WHERE sequence_number = $1
                  RETURNING sequence_number, bucket, prefix, key, stream, consumer,
-                        subject, timestamp_start, timestamp_end, message_count,
-                        size_bytes, codec, hash, version, created_at, deleted_at",
+                        subject, timestamp_start, timestamp_end, message_count, stream_sequence_start, stream_sequence_end,
+                        subjects, size_bytes, codec, compression, encryption_key_id, hash, signature, signature_key_id, version,
+                        recovered_count, lost_sequences, lost_sequences_complete, created_at,
+                        deleted_at",
                 &[&sequence_number],
             )
             .await
@@ -196,6 +242,46 @@ impl ChunkMetadataStorer for PostgresStore {
         Ok(chunk_row.into())
     }
 
+    async fn record_chunk_recovery(
+        &self,
+        sequence_number: i64,
+        recovery: ChunkRecovery,
+    ) -> Result<ChunkMetadata, ChunkMetadataError> {
+        debug!(
+            sequence_number = sequence_number,
+            recovered_count = recovery.recovered_count,
+            lost_count = recovery.lost_sequences.len(),
+            "record chunk recovery"
+        );
+        let client = self.get_client().await?;
+
+        let row = client
+            .query_one(
+                "UPDATE chunks
+                 SET recovered_count = $1, lost_sequences = $2, lost_sequences_complete = $3
+                 WHERE sequence_number = $4
+                 RETURNING sequence_number, bucket, prefix, key, stream, consumer,
+                        subject, timestamp_start, timestamp_end, message_count, stream_sequence_start, stream_sequence_end,
+                        subjects, size_bytes, codec, compression, encryption_key_id, hash, signature, signature_key_id, version,
+                        recovered_count, lost_sequences, lost_sequences_complete, created_at,
+                        deleted_at",
+                &[
+                    &recovery.recovered_count,
+                    &recovery.lost_sequences,
+                    &recovery.lost_sequences_complete,
+                    &sequence_number,
+                ],
+            )
+            .await
+            .map_err(|e| match e.as_db_error() {
+                Some(_) => ChunkMetadataError::Database(e),
+                None => ChunkMetadataError::NotFound { sequence_number },
+            })?;
+
+        let chunk_row = ChunkMetadataRow::from_row(&row)?;
+        Ok(chunk_row.into())
+    }
+
     async fn hard_delete_chunk(&self, sequence_number: i64) -> Result<(), ChunkMetadataError> {
         debug!(sequence_number = sequence_number, "hard delete chunk");
         let client = self.get_client().await?;
@@ -213,4 +299,129 @@ impl ChunkMetadataStorer for PostgresStore {
 
         Ok(())
     }
+
+    async fn get_checkpoint(&self, job_id: &str) -> Result<Option<i64>, ChunkMetadataError> {
+        debug!(job_id = job_id, "get checkpoint");
+        let client = self.get_client().await?;
+        let uuid = Uuid::parse_str(job_id)?;
+
+        let row = client
+            .query_opt(
+                "SELECT stream_sequence FROM store_job_checkpoints WHERE job_id = $1",
+                &[&uuid],
+            )
+            .await?;
+        Ok(row.map(|row| row.get("stream_sequence")))
+    }
+
+    async fn set_checkpoint(
+        &self,
+        job_id: &str,
+        stream_sequence: i64,
+    ) -> Result<(), ChunkMetadataError> {
+        debug!(
+            job_id = job_id,
+            stream_sequence = stream_sequence,
+            "set checkpoint"
+        );
+        let client = self.get_client().await?;
+        let uuid = Uuid::parse_str(job_id)?;
+
+        client
+            .execute(
+                "INSERT INTO store_job_checkpoints (job_id, stream_sequence)
+                 VALUES ($1, $2)
+                 ON CONFLICT (job_id) DO UPDATE
+                 SET stream_sequence = GREATEST(store_job_checkpoints.stream_sequence, EXCLUDED.stream_sequence),
+                     updated_at = NOW()",
+                &[&uuid, &stream_sequence],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn list_overlapping_chunks(
+        &self,
+        query: DuplicateChunksQuery,
+    ) -> Result<Vec<ChunkOverlap>, ChunkMetadataError> {
+        debug!(
+            stream = query.stream,
+            subject = query.subject,
+            bucket = query.bucket,
+            "list overlapping chunks"
+        );
+        let client = self.get_client().await?;
+
+        let mut sql = String::from(
+            "SELECT a.stream, a.subject,
+                    a.sequence_number AS first_sequence_number, a.key AS first_key,
+                    b.sequence_number AS second_sequence_number, b.key AS second_key,
+                    GREATEST(a.stream_sequence_start, b.stream_sequence_start) AS stream_sequence_start,
+                    LEAST(a.stream_sequence_end, b.stream_sequence_end) AS stream_sequence_end
+             FROM chunks a
+             JOIN chunks b ON a.stream = b.stream
+                          AND a.subject = b.subject
+                          AND a.sequence_number < b.sequence_number
+                          AND a.stream_sequence_start <= b.stream_sequence_end
+                          AND b.stream_sequence_start <= a.stream_sequence_end
+             WHERE a.stream = $1 AND a.deleted_at IS NULL AND b.deleted_at IS NULL",
+        );
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = vec![&query.stream];
+        let mut param_idx = 2;
+
+        if let Some(ref subject) = query.subject {
+            sql.push_str(&format!(" AND a.subject = ${}", param_idx));
+            params.push(subject);
+            param_idx += 1;
+        }
+
+        if let Some(ref bucket) = query.bucket {
+            sql.push_str(&format!(
+                " AND a.bucket = ${0} AND b.bucket = ${0}",
+                param_idx
+            ));
+            params.push(bucket);
+        }
+
+        sql.push_str(" ORDER BY a.sequence_number, b.sequence_number");
+
+        let rows = client.query(&sql, &params).await?;
+        Ok(rows
+            .iter()
+            .map(|row| ChunkOverlap {
+                stream: row.get("stream"),
+                subject: row.get("subject"),
+                first_sequence_number: row.get("first_sequence_number"),
+                first_key: row.get("first_key"),
+                second_sequence_number: row.get("second_sequence_number"),
+                second_key: row.get("second_key"),
+                stream_sequence_start: row.get("stream_sequence_start"),
+                stream_sequence_end: row.get("stream_sequence_end"),
+            })
+            .collect())
+    }
+}
+
+// regex matching the concrete subjects a NATS subject filter selects
+pub(crate) fn subject_pattern(filter: &str) -> String {
+    let tokens: Vec<String> = filter
+        .split('.')
+        .map(|token| match token {
+            "*" => "[^.]+".to_string(),
+            ">" => ".+".to_string(),
+            token => regex_escape(token),
+        })
+        .collect();
+    format!("^{}$", tokens.join("\\."))
+}
+
+fn regex_escape(token: &str) -> String {
+    let mut out = String::with_capacity(token.len());
+    for c in token.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
 }
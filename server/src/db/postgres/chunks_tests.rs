@@ -3,11 +3,12 @@ use chrono::{Duration, Utc};
 use testcontainers::{runners::AsyncRunner, ImageExt};
 use testcontainers_modules::postgres::Postgres;
 
+use super::chunks::subject_pattern;
 use crate::db::{
-    postgres::PostgresStore, ChunkMetadataError, ChunkMetadataStorer, CreateChunkMetadata,
-    ListChunksQuery,
+    postgres::PostgresStore, record_chunk, ChunkMetadataError, ChunkMetadataStorer, ChunkRecovery,
+    CreateChunkMetadata, ListChunksQuery,
 };
-use nats3_types::Codec;
+use nats3_types::{Codec, Compression, DuplicateChunksQuery};
 
 struct TestContext {
     _container: testcontainers::ContainerAsync<Postgres>,
@@ -54,9 +55,16 @@ struct ChunkMetadataBuilder {
     timestamp_start: chrono::DateTime<chrono::Utc>,
     timestamp_end: chrono::DateTime<chrono::Utc>,
     message_count: i64,
+    stream_sequence_start: Option<i64>,
+    stream_sequence_end: Option<i64>,
+    subjects: Vec<String>,
     size_bytes: i64,
     codec: Codec,
+    compression: Compression,
+    encryption_key_id: Option<String>,
     hash: Bytes,
+    signature: Option<Bytes>,
+    signature_key_id: Option<String>,
     version: String,
 }
 
@@ -73,9 +81,16 @@ impl Default for ChunkMetadataBuilder {
             timestamp_start: now,
             timestamp_end: now + Duration::minutes(5),
             message_count: 100,
+            stream_sequence_start: Some(1),
+            stream_sequence_end: Some(100),
+            subjects: vec!["test.subject".to_string()],
             size_bytes: 1024,
             codec: Codec::Json,
+            compression: Compression::None,
+            encryption_key_id: None,
             hash: Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]),
+            signature: None,
+            signature_key_id: None,
             version: "1.0.0".to_string(),
         }
     }
@@ -107,6 +122,11 @@ impl ChunkMetadataBuilder {
         self
     }
 
+    fn subjects(mut self, subjects: &[&str]) -> Self {
+        self.subjects = subjects.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
     fn timestamp_start(mut self, ts: chrono::DateTime<chrono::Utc>) -> Self {
         self.timestamp_start = ts;
         self
@@ -117,11 +137,33 @@ impl ChunkMetadataBuilder {
         self
     }
 
+    fn stream_sequences(mut self, start: i64, end: i64) -> Self {
+        self.stream_sequence_start = Some(start);
+        self.stream_sequence_end = Some(end);
+        self
+    }
+
     fn codec(mut self, codec: Codec) -> Self {
         self.codec = codec;
         self
     }
 
+    fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    fn encryption_key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.encryption_key_id = Some(key_id.into());
+        self
+    }
+
+    fn signature(mut self, signature: Bytes, key_id: impl Into<String>) -> Self {
+        self.signature = Some(signature);
+        self.signature_key_id = Some(key_id.into());
+        self
+    }
+
     fn build(self) -> CreateChunkMetadata {
         CreateChunkMetadata {
             bucket: self.bucket,
@@ -133,9 +175,16 @@ impl ChunkMetadataBuilder {
             timestamp_start: self.timestamp_start,
             timestamp_end: self.timestamp_end,
             message_count: self.message_count,
+            stream_sequence_start: self.stream_sequence_start,
+            stream_sequence_end: self.stream_sequence_end,
+            subjects: self.subjects,
             size_bytes: self.size_bytes,
             codec: self.codec,
+            compression: self.compression,
+            encryption_key_id: self.encryption_key_id,
             hash: self.hash,
+            signature: self.signature,
+            signature_key_id: self.signature_key_id,
             version: self.version,
         }
     }
@@ -157,6 +206,8 @@ async fn test_create_and_get_chunk() {
     assert_eq!(created.bucket, "my-bucket");
     assert_eq!(created.key, "my-chunk.dat");
     assert_eq!(created.message_count, 100);
+    assert_eq!(created.stream_sequence_start, Some(1));
+    assert_eq!(created.stream_sequence_end, Some(100));
     assert!(created.deleted_at.is_none());
 
     let retrieved = ctx.store.get_chunk(created.sequence_number).await.unwrap();
@@ -207,6 +258,73 @@ async fn test_duplicate_chunk_location() {
     ));
 }
 
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_duplicate_chunk_location_without_prefix() {
+    let ctx = setup_postgres().await;
+
+    let mut chunk1 = chunk_builder().bucket("bucket-1").key("duplicate.dat");
+    chunk1.prefix = None;
+    ctx.store.create_chunk(chunk1.build()).await.unwrap();
+
+    let mut chunk2 = chunk_builder().bucket("bucket-1").key("duplicate.dat");
+    chunk2.prefix = None;
+    let result = ctx.store.create_chunk(chunk2.build()).await;
+    assert!(matches!(
+        result,
+        Err(ChunkMetadataError::Duplicate { bucket, key })
+        if bucket == "bucket-1" && key == "duplicate.dat"
+    ));
+
+    // an empty prefix is the same location as none
+    let chunk3 = chunk_builder()
+        .bucket("bucket-1")
+        .prefix("")
+        .key("duplicate.dat")
+        .build();
+    let result = ctx.store.create_chunk(chunk3).await;
+    assert!(matches!(result, Err(ChunkMetadataError::Duplicate { .. })));
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_record_chunk_at_taken_location() {
+    let ctx = setup_postgres().await;
+
+    let mut chunk = chunk_builder().bucket("bucket-1").key("chunk.dat");
+    chunk.prefix = None;
+    let chunk = chunk.build();
+    record_chunk(&ctx.store, chunk.clone()).await.unwrap();
+
+    let found = ctx
+        .store
+        .find_chunk("bucket-1", Some(""), "chunk.dat")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.hash, chunk.hash);
+    assert!(ctx
+        .store
+        .find_chunk("bucket-1", None, "other.dat")
+        .await
+        .unwrap()
+        .is_none());
+
+    // the same chunk recorded again is an earlier attempt of the upload
+    record_chunk(&ctx.store, chunk.clone()).await.unwrap();
+
+    let other = CreateChunkMetadata {
+        hash: Bytes::from(vec![0xca, 0xfe]),
+        ..chunk
+    };
+    let result = record_chunk(&ctx.store, other).await;
+    assert!(matches!(
+        result,
+        Err(ChunkMetadataError::Conflict { bucket, key })
+        if bucket == "bucket-1" && key == "chunk.dat"
+    ));
+}
+
 #[tokio::test]
 #[cfg_attr(not(feature = "integration"), ignore)]
 async fn test_list_chunks_basic() {
@@ -259,6 +377,78 @@ async fn test_list_chunks_basic() {
     assert_eq!(chunks[1].stream, "stream-1");
 }
 
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_list_chunks_by_contained_subject() {
+    let ctx = setup_postgres().await;
+
+    let multi = chunk_builder()
+        .subject("orders.created")
+        .subjects(&["orders.created", "orders.updated", "payments.eu.settled"])
+        .key("multi.dat")
+        .build();
+    let legacy = chunk_builder()
+        .subject("orders.created")
+        .subjects(&[])
+        .key("legacy.dat")
+        .build();
+    ctx.store.create_chunk(multi).await.unwrap();
+    ctx.store.create_chunk(legacy).await.unwrap();
+
+    let query = |subject: &str| ListChunksQuery {
+        stream: "test-stream".to_string(),
+        consumer: None,
+        subject: subject.to_string(),
+        bucket: "test-bucket".to_string(),
+        prefix: Some("test-prefix".to_string()),
+        timestamp_start: None,
+        timestamp_end: None,
+        limit: None,
+        include_deleted: false,
+    };
+
+    let chunks = ctx
+        .store
+        .list_chunks(query("orders.created"))
+        .await
+        .unwrap();
+    assert_eq!(chunks.len(), 2);
+
+    let chunks = ctx
+        .store
+        .list_chunks(query("orders.updated"))
+        .await
+        .unwrap();
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].key, "multi.dat");
+    assert_eq!(
+        chunks[0].subjects,
+        vec!["orders.created", "orders.updated", "payments.eu.settled"]
+    );
+
+    let chunks = ctx.store.list_chunks(query("payments.>")).await.unwrap();
+    assert_eq!(chunks.len(), 1);
+    let chunks = ctx
+        .store
+        .list_chunks(query("payments.*.settled"))
+        .await
+        .unwrap();
+    assert_eq!(chunks.len(), 1);
+    let chunks = ctx.store.list_chunks(query("payments.*")).await.unwrap();
+    assert!(chunks.is_empty());
+}
+
+#[test]
+fn test_subject_pattern() {
+    assert_eq!(subject_pattern("orders.created"), "^orders\\.created$");
+    assert_eq!(
+        subject_pattern("orders.*.created"),
+        "^orders\\.[^.]+\\.created$"
+    );
+    assert_eq!(subject_pattern("orders.>"), "^orders\\..+$");
+    assert_eq!(subject_pattern("a+b.c$"), "^a\\+b\\.c\\$$");
+}
+
 #[tokio::test]
 #[cfg_attr(not(feature = "integration"), ignore)]
 async fn test_list_chunks_with_time_range() {
@@ -570,6 +760,102 @@ async fn test_chunk_codec_binary() {
     assert_eq!(retrieved.codec, Codec::Binary);
 }
 
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_chunk_compression_zstd() {
+    let ctx = setup_postgres().await;
+
+    let chunk = chunk_builder()
+        .codec(Codec::Binary)
+        .compression(Compression::Zstd)
+        .key("binary-chunk.bin.zst")
+        .build();
+
+    let created = ctx.store.create_chunk(chunk).await.unwrap();
+
+    assert_eq!(created.compression, Compression::Zstd);
+
+    let retrieved = ctx.store.get_chunk(created.sequence_number).await.unwrap();
+    assert_eq!(retrieved.compression, Compression::Zstd);
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_chunk_encryption_key_id() {
+    let ctx = setup_postgres().await;
+
+    let plain = ctx
+        .store
+        .create_chunk(chunk_builder().build())
+        .await
+        .unwrap();
+    assert_eq!(plain.encryption_key_id, None);
+
+    let chunk = chunk_builder().encryption_key_id("master-2").build();
+    let created = ctx.store.create_chunk(chunk).await.unwrap();
+    let retrieved = ctx.store.get_chunk(created.sequence_number).await.unwrap();
+    assert_eq!(retrieved.encryption_key_id, Some("master-2".to_string()));
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_chunk_signature() {
+    let ctx = setup_postgres().await;
+
+    let unsigned = ctx
+        .store
+        .create_chunk(chunk_builder().build())
+        .await
+        .unwrap();
+    assert_eq!(unsigned.signature, None);
+    assert_eq!(unsigned.signature_key_id, None);
+
+    let signature = Bytes::from(vec![1u8; 64]);
+    let chunk = chunk_builder()
+        .signature(signature.clone(), "sig-1")
+        .build();
+    let created = ctx.store.create_chunk(chunk).await.unwrap();
+    let retrieved = ctx.store.get_chunk(created.sequence_number).await.unwrap();
+    assert_eq!(retrieved.signature, Some(signature));
+    assert_eq!(retrieved.signature_key_id, Some("sig-1".to_string()));
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_record_chunk_recovery() {
+    let ctx = setup_postgres().await;
+
+    let created = ctx
+        .store
+        .create_chunk(chunk_builder().build())
+        .await
+        .unwrap();
+    assert_eq!(created.recovery, None);
+
+    let recovery = ChunkRecovery {
+        recovered_count: 8,
+        lost_sequences: vec![3, 7],
+        lost_sequences_complete: true,
+    };
+    let updated = ctx
+        .store
+        .record_chunk_recovery(created.sequence_number, recovery.clone())
+        .await
+        .unwrap();
+    assert_eq!(updated.recovery, Some(recovery.clone()));
+
+    let retrieved = ctx.store.get_chunk(created.sequence_number).await.unwrap();
+    assert_eq!(retrieved.recovery, Some(recovery.clone()));
+
+    let result = ctx.store.record_chunk_recovery(99999, recovery).await;
+    assert!(matches!(
+        result,
+        Err(ChunkMetadataError::NotFound {
+            sequence_number: 99999
+        })
+    ));
+}
+
 #[tokio::test]
 #[cfg_attr(not(feature = "integration"), ignore)]
 async fn test_chunk_different_prefixes() {
@@ -620,3 +906,64 @@ async fn test_chunk_different_prefixes() {
     assert_eq!(chunks2.len(), 1);
     assert_eq!(chunks2[0].prefix, Some("prefix-b".to_string()));
 }
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_list_overlapping_chunks() {
+    let ctx = setup_postgres().await;
+
+    let first = ctx
+        .store
+        .create_chunk(chunk_builder().stream_sequences(1, 10).build())
+        .await
+        .unwrap();
+    let second = ctx
+        .store
+        .create_chunk(chunk_builder().stream_sequences(8, 12).build())
+        .await
+        .unwrap();
+    // adjacent ranges and other subjects do not overlap
+    ctx.store
+        .create_chunk(chunk_builder().stream_sequences(13, 20).build())
+        .await
+        .unwrap();
+    ctx.store
+        .create_chunk(
+            chunk_builder()
+                .subject("other.subject")
+                .stream_sequences(1, 10)
+                .build(),
+        )
+        .await
+        .unwrap();
+
+    let overlaps = ctx
+        .store
+        .list_overlapping_chunks(DuplicateChunksQuery {
+            stream: "test-stream".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(overlaps.len(), 1);
+    assert_eq!(overlaps[0].first_sequence_number, first.sequence_number);
+    assert_eq!(overlaps[0].second_sequence_number, second.sequence_number);
+    assert_eq!(overlaps[0].stream_sequence_start, 8);
+    assert_eq!(overlaps[0].stream_sequence_end, 10);
+
+    // deleted chunks are no longer reported
+    ctx.store
+        .soft_delete_chunk(second.sequence_number)
+        .await
+        .unwrap();
+    let overlaps = ctx
+        .store
+        .list_overlapping_chunks(DuplicateChunksQuery {
+            stream: "test-stream".to_string(),
+            subject: Some("test.subject".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(overlaps.is_empty());
+}
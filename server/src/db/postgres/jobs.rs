@@ -5,15 +5,19 @@ use uuid::Uuid;
 
 use super::{
     models::{
-        LoadJobCreateRow, LoadJobRow, LoadJobStatusEnum, StoreJobCreateRow, StoreJobRow,
-        StoreJobStatusEnum,
+        KvJobCreateRow, KvJobKindEnum, KvJobRow, KvJobStatusEnum, LoadJobCreateRow, LoadJobRow,
+        LoadJobStatusEnum, ObjectJobCreateRow, ObjectJobKindEnum, ObjectJobRow,
+        ObjectJobStatusEnum, StoreJobCreateRow, StoreJobRow, StoreJobStatusEnum,
     },
     postgres::PostgresStore,
 };
-use crate::db::{JobStoreError, JobStorer, LoadJobStorer, StoreJobStorer};
+use crate::db::{
+    JobStoreError, JobStorer, KvJobStorer, LoadJobStorer, ObjectJobStorer, StoreJobStorer,
+};
 use nats3_types::{
-    ListLoadJobsQuery, ListStoreJobsQuery, LoadJob, LoadJobCreate, LoadJobStatus, StoreJob,
-    StoreJobCreate, StoreJobStatus,
+    KvJob, KvJobCreate, KvJobStatus, ListKvJobsQuery, ListLoadJobsQuery, ListObjectJobsQuery,
+    ListStoreJobsQuery, LoadJob, LoadJobCreate, LoadJobStatus, ObjectJob, ObjectJobCreate,
+    ObjectJobStatus, StoreJob, StoreJobCreate, StoreJobStatus,
 };
 
 #[async_trait]
@@ -27,8 +31,10 @@ impl LoadJobStorer for PostgresStore {
         let row = client
             .query_one(
                 "SELECT id, name, status, bucket, prefix, read_stream, read_consumer,
-                        read_subject, write_subject, poll_interval, delete_chunks, from_time,
-                        to_time, created_at, updated_at
+                        read_subject, write_subject, poll_interval, delete_chunks,
+                        partial_recovery, from_time, to_time, backup_id, restore_stream,
+                        retry_max_attempts, retry_backoff_ms, retry_max_backoff_ms,
+                        retry_jitter, error, created_at, updated_at
                  FROM load_jobs WHERE id = $1",
                 &[&uuid],
             )
@@ -129,17 +135,21 @@ impl LoadJobStorer for PostgresStore {
     async fn create_load_job(&self, job: LoadJobCreate) -> Result<LoadJob, JobStoreError> {
         debug!("create load job");
         let client = self.get_client().await?;
-        let row: LoadJobCreateRow = job.into();
+        let row: LoadJobCreateRow = job.try_into()?;
 
         let db_row = client
             .query_one(
                 "INSERT INTO load_jobs
             (name, status, bucket, prefix, read_stream, read_consumer,
-            read_subject, write_subject, poll_interval, delete_chunks, from_time, to_time)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            read_subject, write_subject, poll_interval, delete_chunks, partial_recovery,
+            from_time, to_time, backup_id, restore_stream, retry_max_attempts, retry_backoff_ms,
+            retry_max_backoff_ms, retry_jitter)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16,
+            $17, $18, $19)
             RETURNING id, name, status, bucket, prefix, read_stream, read_consumer,
-            read_subject, write_subject, poll_interval, delete_chunks, from_time, to_time,
-            created_at, updated_at",
+            read_subject, write_subject, poll_interval, delete_chunks, partial_recovery,
+            from_time, to_time, backup_id, restore_stream, retry_max_attempts, retry_backoff_ms,
+            retry_max_backoff_ms, retry_jitter, error, created_at, updated_at",
                 &[
                     &row.name,
                     &row.status,
@@ -151,8 +161,15 @@ impl LoadJobStorer for PostgresStore {
                     &row.write_subject,
                     &row.poll_interval,
                     &row.delete_chunks,
+                    &row.partial_recovery,
                     &row.from_time,
                     &row.to_time,
+                    &row.backup_id,
+                    &row.restore_stream,
+                    &row.retry_max_attempts,
+                    &row.retry_backoff_ms,
+                    &row.retry_max_backoff_ms,
+                    &row.retry_jitter,
                 ],
             )
             .await?;
@@ -175,7 +192,7 @@ impl LoadJobStorer for PostgresStore {
         let row = client
             .query_one(
                 "UPDATE load_jobs 
-             SET status = $1, updated_at = NOW() 
+             SET status = $1, error = NULL, updated_at = NOW()
              WHERE id = $2
              RETURNING *",
                 &[&status_row, &uuid],
@@ -190,6 +207,31 @@ impl LoadJobStorer for PostgresStore {
         Ok(job_row.into())
     }
 
+    async fn fail_load_job(&self, id: String, error: String) -> Result<LoadJob, JobStoreError> {
+        debug!(job_id = id, "fail load job");
+
+        let client = self.get_client().await?;
+        let status_row: LoadJobStatusEnum = LoadJobStatus::Failure.into();
+        let uuid = Uuid::parse_str(&id)?;
+
+        let row = client
+            .query_one(
+                "UPDATE load_jobs
+             SET status = $1, error = $2, updated_at = NOW()
+             WHERE id = $3
+             RETURNING *",
+                &[&status_row, &error, &uuid],
+            )
+            .await
+            .map_err(|e| match e.as_db_error() {
+                Some(_) => JobStoreError::Database(e),
+                None => JobStoreError::NotFound { id: id.clone() },
+            })?;
+
+        let job_row = LoadJobRow::from_row(&row)?;
+        Ok(job_row.into())
+    }
+
     async fn delete_load_job(&self, id: String) -> Result<(), JobStoreError> {
         debug!(job_id = id, "delete load job");
         let client = self.get_client().await?;
@@ -216,9 +258,13 @@ impl StoreJobStorer for PostgresStore {
         let uuid = Uuid::parse_str(&id)?;
         let row = client
             .query_one(
-                "SELECT id, name, status, stream, consumer, subject,
-                 bucket, prefix, batch_max_bytes, batch_max_count,
-                 encoding_codec, created_at, updated_at
+                "SELECT id, name, status, stream, consumer, subject, subjects,
+                 bucket, prefix, batch_max_bytes, batch_max_count, batch_max_age_ms,
+                 encoding_codec, encoding_compression, encoding_compression_level,
+                 encoding_encrypt, key_template, deliver_policy, deliver_start_sequence,
+                 deliver_start_time, deliver_end_sequence, deliver_end_time,
+                 split_by_subject, subject_depth, ack_policy, backup, retry_max_attempts,
+                 retry_backoff_ms, retry_max_backoff_ms, retry_jitter, created_at, updated_at
                  FROM store_jobs WHERE id = $1",
                 &[&uuid],
             )
@@ -319,22 +365,52 @@ impl StoreJobStorer for PostgresStore {
         let db_row = client
             .query_one(
                 "INSERT INTO store_jobs 
-            (name, status, stream, consumer, subject, bucket,
-            prefix, batch_max_bytes, batch_max_count, encoding_codec)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-            RETURNING id, name, status, stream, consumer, subject, bucket,
-            prefix, batch_max_bytes, batch_max_count, encoding_codec, created_at, updated_at",
+            (name, status, stream, consumer, subject, subjects, bucket,
+            prefix, batch_max_bytes, batch_max_count, batch_max_age_ms, encoding_codec,
+            encoding_compression, encoding_compression_level, encoding_encrypt,
+            key_template, deliver_policy, deliver_start_sequence, deliver_start_time,
+            deliver_end_sequence, deliver_end_time, split_by_subject, subject_depth,
+            ack_policy, backup, retry_max_attempts, retry_backoff_ms, retry_max_backoff_ms,
+            retry_jitter)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14,
+            $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29)
+            RETURNING id, name, status, stream, consumer, subject, subjects, bucket,
+            prefix, batch_max_bytes, batch_max_count, batch_max_age_ms, encoding_codec,
+            encoding_compression, encoding_compression_level, encoding_encrypt,
+            key_template, deliver_policy, deliver_start_sequence, deliver_start_time,
+            deliver_end_sequence, deliver_end_time, split_by_subject, subject_depth,
+            ack_policy, backup, retry_max_attempts, retry_backoff_ms, retry_max_backoff_ms,
+            retry_jitter, created_at, updated_at",
                 &[
                     &row.name,
                     &row.status,
                     &row.stream,
                     &row.consumer,
                     &row.subject,
+                    &row.subjects,
                     &row.bucket,
                     &row.prefix,
                     &row.batch_max_bytes,
                     &row.batch_max_count,
+                    &row.batch_max_age_ms,
                     &row.encoding_codec,
+                    &row.encoding_compression,
+                    &row.encoding_compression_level,
+                    &row.encoding_encrypt,
+                    &row.key_template,
+                    &row.deliver_policy,
+                    &row.deliver_start_sequence,
+                    &row.deliver_start_time,
+                    &row.deliver_end_sequence,
+                    &row.deliver_end_time,
+                    &row.split_by_subject,
+                    &row.subject_depth,
+                    &row.ack_policy,
+                    &row.backup,
+                    &row.retry_max_attempts,
+                    &row.retry_backoff_ms,
+                    &row.retry_max_backoff_ms,
+                    &row.retry_jitter,
                 ],
             )
             .await?;
@@ -388,4 +464,376 @@ impl StoreJobStorer for PostgresStore {
     }
 }
 
+#[async_trait]
+impl KvJobStorer for PostgresStore {
+    async fn get_kv_job(&self, id: String) -> Result<KvJob, JobStoreError> {
+        debug!(job_id = id, "get kv job");
+        let client = self.get_client().await?;
+
+        let uuid = Uuid::parse_str(&id)?;
+
+        let row = client
+            .query_one("SELECT * FROM kv_jobs WHERE id = $1", &[&uuid])
+            .await
+            .map_err(|e| match e.as_db_error() {
+                Some(_) => JobStoreError::Database(e),
+                None => JobStoreError::NotFound { id: id.clone() },
+            })?;
+
+        let job_row = KvJobRow::from_row(&row)?;
+        Ok(job_row.into())
+    }
+
+    async fn get_kv_jobs(
+        &self,
+        query: Option<ListKvJobsQuery>,
+    ) -> Result<Vec<KvJob>, JobStoreError> {
+        debug!("get kv jobs");
+        let client = self.get_client().await?;
+
+        let mut sql = String::from("SELECT * FROM kv_jobs WHERE 1=1");
+
+        let statuses: Option<Vec<KvJobStatusEnum>> = query.as_ref().and_then(|q| {
+            q.statuses
+                .as_ref()
+                .map(|s| s.iter().map(|st| st.clone().into()).collect())
+        });
+        let kind: Option<KvJobKindEnum> = query.as_ref().and_then(|q| q.kind.map(Into::into));
+
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = vec![];
+        let mut param_idx = 1;
+
+        if let Some(ref statuses) = statuses {
+            if !statuses.is_empty() {
+                let placeholders: Vec<String> = (0..statuses.len())
+                    .map(|i| format!("${}", param_idx + i))
+                    .collect();
+                sql.push_str(&format!(" AND status IN ({})", placeholders.join(", ")));
+                for status in statuses {
+                    params.push(status);
+                }
+                param_idx += statuses.len();
+            }
+        }
+
+        if let Some(ref kind) = kind {
+            sql.push_str(&format!(" AND kind = ${}", param_idx));
+            params.push(kind);
+            param_idx += 1;
+        }
+
+        if let Some(ref q) = query {
+            if let Some(ref kv_bucket) = q.kv_bucket {
+                sql.push_str(&format!(" AND kv_bucket = ${}", param_idx));
+                params.push(kv_bucket);
+                param_idx += 1;
+            }
+        }
+
+        sql.push_str(" ORDER BY created_at DESC");
+
+        if let Some(ref q) = query {
+            if let Some(ref limit) = q.limit {
+                sql.push_str(&format!(" LIMIT ${}", param_idx));
+                params.push(limit);
+            }
+        }
+
+        let rows = client.query(&sql, &params).await?;
+
+        rows.iter()
+            .map(|row| KvJobRow::from_row(row).map(Into::into))
+            .collect()
+    }
+
+    async fn create_kv_job(&self, job: KvJobCreate) -> Result<KvJob, JobStoreError> {
+        debug!("create kv job");
+        let client = self.get_client().await?;
+        let row = KvJobCreateRow::try_from(job)?;
+
+        let db_row = client
+            .query_one(
+                "INSERT INTO kv_jobs
+            (name, kind, status, kv_bucket, bucket, prefix, history, encoding_codec,
+            encoding_compression, encoding_compression_level, encoding_encrypt, archive_id,
+            retry_max_attempts, retry_backoff_ms, retry_max_backoff_ms, retry_jitter)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            RETURNING *",
+                &[
+                    &row.name,
+                    &row.kind,
+                    &row.status,
+                    &row.kv_bucket,
+                    &row.bucket,
+                    &row.prefix,
+                    &row.history,
+                    &row.encoding_codec,
+                    &row.encoding_compression,
+                    &row.encoding_compression_level,
+                    &row.encoding_encrypt,
+                    &row.archive_id,
+                    &row.retry_max_attempts,
+                    &row.retry_backoff_ms,
+                    &row.retry_max_backoff_ms,
+                    &row.retry_jitter,
+                ],
+            )
+            .await?;
+
+        let job_row = KvJobRow::from_row(&db_row)?;
+        Ok(job_row.into())
+    }
+
+    async fn update_kv_job(&self, id: String, status: KvJobStatus) -> Result<KvJob, JobStoreError> {
+        debug!(job_id = id, "update kv job");
+
+        let client = self.get_client().await?;
+        let status_row: KvJobStatusEnum = status.into();
+        let uuid = Uuid::parse_str(&id)?;
+
+        let row = client
+            .query_one(
+                "UPDATE kv_jobs
+             SET status = $1, error = NULL, updated_at = NOW()
+             WHERE id = $2
+             RETURNING *",
+                &[&status_row, &uuid],
+            )
+            .await
+            .map_err(|e| match e.as_db_error() {
+                Some(_) => JobStoreError::Database(e),
+                None => JobStoreError::NotFound { id: id.clone() },
+            })?;
+
+        let job_row = KvJobRow::from_row(&row)?;
+        Ok(job_row.into())
+    }
+
+    async fn fail_kv_job(&self, id: String, error: String) -> Result<KvJob, JobStoreError> {
+        debug!(job_id = id, "fail kv job");
+
+        let client = self.get_client().await?;
+        let status_row: KvJobStatusEnum = KvJobStatus::Failure.into();
+        let uuid = Uuid::parse_str(&id)?;
+
+        let row = client
+            .query_one(
+                "UPDATE kv_jobs
+             SET status = $1, error = $2, updated_at = NOW()
+             WHERE id = $3
+             RETURNING *",
+                &[&status_row, &error, &uuid],
+            )
+            .await
+            .map_err(|e| match e.as_db_error() {
+                Some(_) => JobStoreError::Database(e),
+                None => JobStoreError::NotFound { id: id.clone() },
+            })?;
+
+        let job_row = KvJobRow::from_row(&row)?;
+        Ok(job_row.into())
+    }
+
+    async fn delete_kv_job(&self, id: String) -> Result<(), JobStoreError> {
+        debug!(job_id = id, "delete kv job");
+        let client = self.get_client().await?;
+
+        let uuid = Uuid::parse_str(&id)?;
+
+        let rows_affected = client
+            .execute("DELETE FROM kv_jobs WHERE id = $1", &[&uuid])
+            .await?;
+
+        if rows_affected == 0 {
+            return Err(JobStoreError::NotFound { id });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ObjectJobStorer for PostgresStore {
+    async fn get_object_job(&self, id: String) -> Result<ObjectJob, JobStoreError> {
+        debug!(job_id = id, "get object job");
+        let client = self.get_client().await?;
+
+        let uuid = Uuid::parse_str(&id)?;
+
+        let row = client
+            .query_one("SELECT * FROM object_jobs WHERE id = $1", &[&uuid])
+            .await
+            .map_err(|e| match e.as_db_error() {
+                Some(_) => JobStoreError::Database(e),
+                None => JobStoreError::NotFound { id: id.clone() },
+            })?;
+
+        let job_row = ObjectJobRow::from_row(&row)?;
+        Ok(job_row.into())
+    }
+
+    async fn get_object_jobs(
+        &self,
+        query: Option<ListObjectJobsQuery>,
+    ) -> Result<Vec<ObjectJob>, JobStoreError> {
+        debug!("get object jobs");
+        let client = self.get_client().await?;
+
+        let mut sql = String::from("SELECT * FROM object_jobs WHERE 1=1");
+
+        let statuses: Option<Vec<ObjectJobStatusEnum>> = query.as_ref().and_then(|q| {
+            q.statuses
+                .as_ref()
+                .map(|s| s.iter().map(|st| st.clone().into()).collect())
+        });
+        let kind: Option<ObjectJobKindEnum> = query.as_ref().and_then(|q| q.kind.map(Into::into));
+
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = vec![];
+        let mut param_idx = 1;
+
+        if let Some(ref statuses) = statuses {
+            if !statuses.is_empty() {
+                let placeholders: Vec<String> = (0..statuses.len())
+                    .map(|i| format!("${}", param_idx + i))
+                    .collect();
+                sql.push_str(&format!(" AND status IN ({})", placeholders.join(", ")));
+                for status in statuses {
+                    params.push(status);
+                }
+                param_idx += statuses.len();
+            }
+        }
+
+        if let Some(ref kind) = kind {
+            sql.push_str(&format!(" AND kind = ${}", param_idx));
+            params.push(kind);
+            param_idx += 1;
+        }
+
+        if let Some(ref q) = query {
+            if let Some(ref object_bucket) = q.object_bucket {
+                sql.push_str(&format!(" AND object_bucket = ${}", param_idx));
+                params.push(object_bucket);
+                param_idx += 1;
+            }
+        }
+
+        sql.push_str(" ORDER BY created_at DESC");
+
+        if let Some(ref q) = query {
+            if let Some(ref limit) = q.limit {
+                sql.push_str(&format!(" LIMIT ${}", param_idx));
+                params.push(limit);
+            }
+        }
+
+        let rows = client.query(&sql, &params).await?;
+
+        rows.iter()
+            .map(|row| ObjectJobRow::from_row(row).map(Into::into))
+            .collect()
+    }
+
+    async fn create_object_job(&self, job: ObjectJobCreate) -> Result<ObjectJob, JobStoreError> {
+        debug!("create object job");
+        let client = self.get_client().await?;
+        let row = ObjectJobCreateRow::try_from(job)?;
+
+        let db_row = client
+            .query_one(
+                "INSERT INTO object_jobs
+            (name, kind, status, object_bucket, bucket, prefix, archive_id,
+            retry_max_attempts, retry_backoff_ms, retry_max_backoff_ms, retry_jitter)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING *",
+                &[
+                    &row.name,
+                    &row.kind,
+                    &row.status,
+                    &row.object_bucket,
+                    &row.bucket,
+                    &row.prefix,
+                    &row.archive_id,
+                    &row.retry_max_attempts,
+                    &row.retry_backoff_ms,
+                    &row.retry_max_backoff_ms,
+                    &row.retry_jitter,
+                ],
+            )
+            .await?;
+
+        let job_row = ObjectJobRow::from_row(&db_row)?;
+        Ok(job_row.into())
+    }
+
+    async fn update_object_job(
+        &self,
+        id: String,
+        status: ObjectJobStatus,
+    ) -> Result<ObjectJob, JobStoreError> {
+        debug!(job_id = id, "update object job");
+
+        let client = self.get_client().await?;
+        let status_row: ObjectJobStatusEnum = status.into();
+        let uuid = Uuid::parse_str(&id)?;
+
+        let row = client
+            .query_one(
+                "UPDATE object_jobs
+             SET status = $1, error = NULL, updated_at = NOW()
+             WHERE id = $2
+             RETURNING *",
+                &[&status_row, &uuid],
+            )
+            .await
+            .map_err(|e| match e.as_db_error() {
+                Some(_) => JobStoreError::Database(e),
+                None => JobStoreError::NotFound { id: id.clone() },
+            })?;
+
+        let job_row = ObjectJobRow::from_row(&row)?;
+        Ok(job_row.into())
+    }
+
+    async fn fail_object_job(&self, id: String, error: String) -> Result<ObjectJob, JobStoreError> {
+        debug!(job_id = id, "fail object job");
+
+        let client = self.get_client().await?;
+        let status_row: ObjectJobStatusEnum = ObjectJobStatus::Failure.into();
+        let uuid = Uuid::parse_str(&id)?;
+
+        let row = client
+            .query_one(
+                "UPDATE object_jobs
+             SET status = $1, error = $2, updated_at = NOW()
+             WHERE id = $3
+             RETURNING *",
+                &[&status_row, &error, &uuid],
+            )
+            .await
+            .map_err(|e| match e.as_db_error() {
+                Some(_) => JobStoreError::Database(e),
+                None => JobStoreError::NotFound { id: id.clone() },
+            })?;
+
+        let job_row = ObjectJobRow::from_row(&row)?;
+        Ok(job_row.into())
+    }
+
+    async fn delete_object_job(&self, id: String) -> Result<(), JobStoreError> {
+        debug!(job_id = id, "delete object job");
+        let client = self.get_client().await?;
+
+        let uuid = Uuid::parse_str(&id)?;
+
+        let rows_affected = client
+            .execute("DELETE FROM object_jobs WHERE id = $1", &[&uuid])
+            .await?;
+
+        if rows_affected == 0 {
+            return Err(JobStoreError::NotFound { id });
+        }
+        Ok(())
+    }
+}
+
 impl JobStorer for PostgresStore {}
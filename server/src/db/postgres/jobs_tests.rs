@@ -1,8 +1,13 @@
-use crate::db::{postgres::PostgresStore, LoadJobStorer, StoreJobStorer};
+use crate::db::{
+    postgres::PostgresStore, ChunkMetadataStorer, KvJobStorer, LoadJobStorer, ObjectJobStorer,
+    StoreJobStorer,
+};
 use chrono::{DateTime, Utc};
 use nats3_types::{
-    Batch, Codec, Encoding, ListLoadJobsQuery, ListStoreJobsQuery, LoadJobCreate, LoadJobStatus,
-    StoreJobCreate, StoreJobStatus,
+    AckPolicy, Batch, Codec, Compression, Deliver, DeliverPolicy, Encoding, KvArchiveCreate,
+    KvJobCreate, KvJobKind, KvJobStatus, ListKvJobsQuery, ListLoadJobsQuery, ListObjectJobsQuery,
+    ListStoreJobsQuery, LoadJobCreate, LoadJobStatus, ObjectArchiveCreate, ObjectJobCreate,
+    ObjectJobKind, ObjectJobStatus, Retry, StoreJobCreate, StoreJobStatus,
 };
 use std::time;
 use testcontainers::{runners::AsyncRunner, ImageExt};
@@ -54,8 +59,12 @@ struct LoadJobCreateBuilder {
     write_subject: String,
     poll_interval: Option<time::Duration>,
     delete_chunks: bool,
+    partial_recovery: bool,
     from_time: Option<DateTime<Utc>>,
     to_time: Option<DateTime<Utc>>,
+    backup_id: Option<String>,
+    restore_stream: Option<String>,
+    retry: Option<Retry>,
 }
 
 impl Default for LoadJobCreateBuilder {
@@ -70,8 +79,12 @@ impl Default for LoadJobCreateBuilder {
             write_subject: "write.subject".to_string(),
             poll_interval: None,
             delete_chunks: false,
+            partial_recovery: false,
             from_time: None,
             to_time: None,
+            backup_id: None,
+            restore_stream: None,
+            retry: None,
         }
     }
 }
@@ -82,6 +95,24 @@ impl LoadJobCreateBuilder {
         self
     }
 
+    fn partial_recovery(mut self, partial_recovery: bool) -> Self {
+        self.partial_recovery = partial_recovery;
+        self
+    }
+
+    fn restore(mut self, backup_id: &str, stream: Option<&str>) -> Self {
+        self.read_subject = String::new();
+        self.write_subject = String::new();
+        self.backup_id = Some(backup_id.to_string());
+        self.restore_stream = stream.map(|s| s.to_string());
+        self
+    }
+
+    fn retry(mut self, retry: Retry) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
     fn build(self) -> LoadJobCreate {
         LoadJobCreate {
             name: self.name,
@@ -93,8 +124,12 @@ impl LoadJobCreateBuilder {
             write_subject: self.write_subject,
             poll_interval: self.poll_interval,
             delete_chunks: self.delete_chunks,
+            partial_recovery: self.partial_recovery,
             from_time: self.from_time,
             to_time: self.to_time,
+            backup_id: self.backup_id,
+            restore_stream: self.restore_stream,
+            retry: self.retry,
         }
     }
 }
@@ -108,11 +143,20 @@ struct StoreJobCreateBuilder {
     stream: String,
     consumer: Option<String>,
     subject: String,
+    subjects: Vec<String>,
     bucket: String,
     prefix: Option<String>,
     batch_max_bytes: Option<i64>,
     batch_max_count: Option<i64>,
+    batch_max_age: time::Duration,
     encoding_codec: Option<Encoding>,
+    key_template: Option<String>,
+    deliver: Deliver,
+    split_by_subject: bool,
+    subject_depth: Option<u32>,
+    ack_policy: AckPolicy,
+    backup: bool,
+    retry: Option<Retry>,
 }
 
 impl Default for StoreJobCreateBuilder {
@@ -122,11 +166,25 @@ impl Default for StoreJobCreateBuilder {
             stream: "test-stream".to_string(),
             consumer: None,
             subject: "test.subject".to_string(),
+            subjects: Vec::new(),
             bucket: "test-bucket".to_string(),
             prefix: Some("test-prefix".to_string()),
             batch_max_bytes: Some(1024000),
             batch_max_count: Some(100),
-            encoding_codec: Some(Encoding { codec: Codec::Json }),
+            batch_max_age: time::Duration::from_secs(10),
+            encoding_codec: Some(Encoding {
+                codec: Codec::Json,
+                compression: Compression::Gzip,
+                compression_level: Some(6),
+                encrypt: true,
+            }),
+            key_template: None,
+            deliver: Deliver::default(),
+            split_by_subject: false,
+            subject_depth: None,
+            ack_policy: AckPolicy::Explicit,
+            backup: false,
+            retry: None,
         }
     }
 }
@@ -142,19 +200,69 @@ impl StoreJobCreateBuilder {
         self
     }
 
+    fn key_template(mut self, template: impl Into<String>) -> Self {
+        self.key_template = Some(template.into());
+        self
+    }
+
+    fn subjects(mut self, subjects: &[&str]) -> Self {
+        self.subjects = subjects.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    fn deliver(mut self, deliver: Deliver) -> Self {
+        self.deliver = deliver;
+        self
+    }
+
+    fn batch_max_age(mut self, max_age: time::Duration) -> Self {
+        self.batch_max_age = max_age;
+        self
+    }
+
+    fn split_by_subject(mut self, depth: Option<u32>) -> Self {
+        self.split_by_subject = true;
+        self.subject_depth = depth;
+        self
+    }
+
+    fn ack_policy(mut self, policy: AckPolicy) -> Self {
+        self.ack_policy = policy;
+        self
+    }
+
+    fn backup(mut self) -> Self {
+        self.backup = true;
+        self
+    }
+
+    fn retry(mut self, retry: Retry) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
     fn build(self) -> StoreJobCreate {
         StoreJobCreate {
             name: self.name,
             stream: self.stream,
             consumer: self.consumer,
             subject: self.subject,
+            subjects: self.subjects,
             bucket: self.bucket,
             prefix: self.prefix,
             batch: Batch {
                 max_bytes: self.batch_max_bytes.expect("has default max bytes"),
                 max_count: self.batch_max_count.expect("has default max count"),
+                max_age: self.batch_max_age,
             },
             encoding: self.encoding_codec.expect("has default codec"),
+            key_template: self.key_template,
+            deliver: self.deliver,
+            split_by_subject: self.split_by_subject,
+            subject_depth: self.subject_depth,
+            ack_policy: self.ack_policy,
+            backup: self.backup,
+            retry: self.retry,
         }
     }
 }
@@ -164,7 +272,10 @@ impl StoreJobCreateBuilder {
 async fn test_create_and_get_load_job() {
     let ctx = setup_postgres().await;
 
-    let job = load_job_create_builder().bucket("my-bucket").build();
+    let job = load_job_create_builder()
+        .bucket("my-bucket")
+        .partial_recovery(true)
+        .build();
     let out = ctx.store.create_load_job(job.clone()).await.unwrap();
     let retrieved = ctx.store.get_load_job(out.id.clone()).await.unwrap();
 
@@ -172,6 +283,7 @@ async fn test_create_and_get_load_job() {
     assert_eq!(retrieved.bucket, "my-bucket");
     assert_eq!(retrieved.status, LoadJobStatus::Created);
     assert!(!retrieved.delete_chunks);
+    assert!(retrieved.partial_recovery);
 }
 
 #[tokio::test]
@@ -192,6 +304,35 @@ async fn test_update_load_job_status() {
     assert_eq!(updated.id, out.id);
 }
 
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_fail_load_job() {
+    let ctx = setup_postgres().await;
+
+    let job = load_job_create_builder().build();
+    let out = ctx.store.create_load_job(job).await.unwrap();
+    assert!(out.error.is_none());
+
+    let failed = ctx
+        .store
+        .fail_load_job(out.id.clone(), "decode chunk: corrupt".to_string())
+        .await
+        .unwrap();
+    assert_eq!(failed.status, LoadJobStatus::Failure);
+    assert_eq!(failed.error.as_deref(), Some("decode chunk: corrupt"));
+
+    let fetched = ctx.store.get_load_job(out.id.clone()).await.unwrap();
+    assert_eq!(fetched.error.as_deref(), Some("decode chunk: corrupt"));
+
+    // resuming clears the previous failure
+    let resumed = ctx
+        .store
+        .update_load_job(out.id, LoadJobStatus::Running)
+        .await
+        .unwrap();
+    assert!(resumed.error.is_none());
+}
+
 #[tokio::test]
 #[cfg_attr(not(feature = "integration"), ignore)]
 async fn test_get_load_jobs() {
@@ -326,6 +467,8 @@ async fn test_create_and_get_store_job() {
     let job = store_job_create_builder()
         .name("my-store-job")
         .bucket("store-bucket")
+        .key_template("{stream}/dt={YYYY}-{MM}-{DD}/{ulid}.{ext}")
+        .subjects(&["test.other", "payments.>"])
         .build();
     let out = ctx.store.create_store_job(job.clone()).await.unwrap();
     let retrieved = ctx.store.get_store_job(out.id.to_string()).await.unwrap();
@@ -333,6 +476,194 @@ async fn test_create_and_get_store_job() {
     assert_eq!(retrieved.name, "my-store-job");
     assert_eq!(retrieved.bucket, "store-bucket");
     assert_eq!(retrieved.status, StoreJobStatus::Created);
+    assert_eq!(retrieved.encoding.compression, Compression::Gzip);
+    assert_eq!(retrieved.encoding.compression_level, Some(6));
+    assert!(retrieved.encoding.encrypt);
+    assert_eq!(
+        retrieved.key_template.as_deref(),
+        Some("{stream}/dt={YYYY}-{MM}-{DD}/{ulid}.{ext}")
+    );
+    assert_eq!(
+        retrieved.filter_subjects(),
+        vec!["test.subject", "test.other", "payments.>"]
+    );
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_create_store_job_with_deliver_policy() {
+    let ctx = setup_postgres().await;
+
+    let deliver = Deliver {
+        policy: DeliverPolicy::ByStartSequence,
+        start_sequence: Some(1_000_000),
+        start_time: None,
+        end_sequence: Some(2_000_000),
+        end_time: Some("2024-12-14T18:00:00Z".parse::<DateTime<Utc>>().unwrap()),
+    };
+    let job = store_job_create_builder().deliver(deliver.clone()).build();
+    let out = ctx.store.create_store_job(job).await.unwrap();
+    assert_eq!(out.deliver, deliver);
+
+    let retrieved = ctx.store.get_store_job(out.id.to_string()).await.unwrap();
+    assert_eq!(retrieved.deliver.policy, DeliverPolicy::ByStartSequence);
+    assert_eq!(retrieved.deliver.start_sequence, Some(1_000_000));
+    assert_eq!(retrieved.deliver.end_sequence, Some(2_000_000));
+
+    let jobs = ctx.store.get_store_jobs(None).await.unwrap();
+    assert_eq!(jobs[0].deliver.policy, DeliverPolicy::ByStartSequence);
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_create_store_job_batch_max_age() {
+    let ctx = setup_postgres().await;
+
+    let job = store_job_create_builder()
+        .batch_max_age(time::Duration::from_millis(250))
+        .build();
+    let out = ctx.store.create_store_job(job).await.unwrap();
+    assert_eq!(out.batch.max_age, time::Duration::from_millis(250));
+
+    let retrieved = ctx.store.get_store_job(out.id.to_string()).await.unwrap();
+    assert_eq!(retrieved.batch.max_age, time::Duration::from_millis(250));
+
+    let jobs = ctx.store.get_store_jobs(None).await.unwrap();
+    assert_eq!(jobs[0].batch.max_age, time::Duration::from_millis(250));
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_create_store_job_split_by_subject() {
+    let ctx = setup_postgres().await;
+
+    let job = store_job_create_builder().split_by_subject(Some(2)).build();
+    let out = ctx.store.create_store_job(job).await.unwrap();
+    assert!(out.split_by_subject);
+    assert_eq!(out.subject_depth, Some(2));
+
+    let retrieved = ctx.store.get_store_job(out.id.to_string()).await.unwrap();
+    assert!(retrieved.split_by_subject);
+    assert_eq!(retrieved.subject_depth, Some(2));
+
+    let job = store_job_create_builder().name("unsplit").build();
+    let out = ctx.store.create_store_job(job).await.unwrap();
+    assert!(!out.split_by_subject);
+    assert_eq!(out.subject_depth, None);
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_create_stream_backup_and_restore_jobs() {
+    let ctx = setup_postgres().await;
+
+    let job = store_job_create_builder()
+        .subjects(&["test.other"])
+        .backup()
+        .build();
+    let backup = ctx.store.create_store_job(job).await.unwrap();
+    assert!(backup.backup);
+    let retrieved = ctx.store.get_store_job(backup.id.clone()).await.unwrap();
+    assert!(retrieved.backup);
+    assert_eq!(retrieved.filter_subjects(), ["test.subject", "test.other"]);
+
+    let job = store_job_create_builder().name("plain").build();
+    let out = ctx.store.create_store_job(job).await.unwrap();
+    assert!(!out.backup);
+
+    let job = load_job_create_builder()
+        .restore(&backup.id, Some("restored-stream"))
+        .build();
+    let out = ctx.store.create_load_job(job).await.unwrap();
+    assert_eq!(out.backup_id.as_ref(), Some(&backup.id));
+    assert_eq!(out.restore_stream.as_deref(), Some("restored-stream"));
+    let retrieved = ctx.store.get_load_job(out.id.clone()).await.unwrap();
+    assert_eq!(retrieved.backup_id.as_ref(), Some(&backup.id));
+    assert_eq!(retrieved.restore_stream.as_deref(), Some("restored-stream"));
+    assert_eq!(retrieved.read_subject, "");
+
+    let out = ctx
+        .store
+        .create_load_job(load_job_create_builder().build())
+        .await
+        .unwrap();
+    assert_eq!(out.backup_id, None);
+    assert_eq!(out.restore_stream, None);
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_create_store_job_ack_policy() {
+    let ctx = setup_postgres().await;
+
+    let job = store_job_create_builder()
+        .ack_policy(AckPolicy::All)
+        .build();
+    let out = ctx.store.create_store_job(job).await.unwrap();
+    assert_eq!(out.ack_policy, AckPolicy::All);
+
+    let retrieved = ctx.store.get_store_job(out.id.to_string()).await.unwrap();
+    assert_eq!(retrieved.ack_policy, AckPolicy::All);
+
+    let job = store_job_create_builder().name("explicit").build();
+    let out = ctx.store.create_store_job(job).await.unwrap();
+    assert_eq!(out.ack_policy, AckPolicy::Explicit);
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_job_retry_override() {
+    let ctx = setup_postgres().await;
+    let retry = Retry {
+        max_attempts: 5,
+        backoff: time::Duration::from_millis(50),
+        max_backoff: time::Duration::from_secs(2),
+        jitter: false,
+    };
+
+    let job = store_job_create_builder().retry(retry.clone()).build();
+    let out = ctx.store.create_store_job(job).await.unwrap();
+    assert_eq!(out.retry, Some(retry.clone()));
+    let retrieved = ctx.store.get_store_job(out.id.to_string()).await.unwrap();
+    assert_eq!(retrieved.retry, Some(retry.clone()));
+
+    let job = load_job_create_builder().retry(retry.clone()).build();
+    let out = ctx.store.create_load_job(job).await.unwrap();
+    assert_eq!(out.retry, Some(retry));
+    let retrieved = ctx.store.get_load_job(out.id.to_string()).await.unwrap();
+    assert_eq!(retrieved.retry, out.retry);
+
+    // jobs without an override use the server policy
+    let out = ctx
+        .store
+        .create_store_job(store_job_create_builder().name("default").build())
+        .await
+        .unwrap();
+    assert_eq!(out.retry, None);
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_store_job_checkpoint() {
+    let ctx = setup_postgres().await;
+
+    let job = ctx
+        .store
+        .create_store_job(store_job_create_builder().build())
+        .await
+        .unwrap();
+    let job_id = job.id.to_string();
+    assert_eq!(ctx.store.get_checkpoint(&job_id).await.unwrap(), None);
+
+    ctx.store.set_checkpoint(&job_id, 100).await.unwrap();
+    assert_eq!(ctx.store.get_checkpoint(&job_id).await.unwrap(), Some(100));
+
+    // the checkpoint never moves back
+    ctx.store.set_checkpoint(&job_id, 50).await.unwrap();
+    assert_eq!(ctx.store.get_checkpoint(&job_id).await.unwrap(), Some(100));
+
+    ctx.store.delete_store_job(job_id.clone()).await.unwrap();
+    assert_eq!(ctx.store.get_checkpoint(&job_id).await.unwrap(), None);
 }
 
 #[tokio::test]
@@ -536,3 +867,189 @@ async fn test_get_store_job_not_found() {
         Err(crate::db::JobStoreError::NotFound { .. })
     ));
 }
+
+fn kv_archive_create(kv_bucket: &str) -> KvJobCreate {
+    KvArchiveCreate {
+        name: "test-archive".to_string(),
+        kv_bucket: kv_bucket.to_string(),
+        bucket: "test-bucket".to_string(),
+        prefix: Some("kv".to_string()),
+        history: true,
+        encoding: Encoding {
+            codec: Codec::Framed,
+            compression: Compression::Zstd,
+            compression_level: Some(3),
+            encrypt: false,
+        },
+        retry: None,
+    }
+    .into()
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_create_and_get_kv_jobs() {
+    let ctx = setup_postgres().await;
+
+    let archive = ctx
+        .store
+        .create_kv_job(kv_archive_create("config"))
+        .await
+        .unwrap();
+    assert_eq!(archive.kind, KvJobKind::Archive);
+    assert_eq!(archive.status, KvJobStatus::Created);
+    assert_eq!(archive.archive_id, None);
+
+    let retrieved = ctx.store.get_kv_job(archive.id.clone()).await.unwrap();
+    assert_eq!(retrieved.kv_bucket, "config");
+    assert_eq!(retrieved.prefix.as_deref(), Some("kv"));
+    assert!(retrieved.history);
+    assert_eq!(retrieved.encoding.codec, Codec::Framed);
+    assert_eq!(retrieved.encoding.compression, Compression::Zstd);
+    assert_eq!(retrieved.encoding.compression_level, Some(3));
+
+    let restore = ctx
+        .store
+        .create_kv_job(KvJobCreate {
+            name: "test-restore".to_string(),
+            kind: KvJobKind::Restore,
+            kv_bucket: "config-restored".to_string(),
+            archive_id: Some(archive.id.clone()),
+            ..kv_archive_create("config")
+        })
+        .await
+        .unwrap();
+    assert_eq!(restore.kind, KvJobKind::Restore);
+    assert_eq!(restore.archive_id, Some(archive.id.clone()));
+
+    let jobs = ctx.store.get_kv_jobs(None).await.unwrap();
+    assert_eq!(jobs.len(), 2);
+    assert_eq!(jobs[0].id, restore.id);
+
+    let query = ListKvJobsQuery {
+        kind: Some(KvJobKind::Archive),
+        ..ListKvJobsQuery::new().with_statuses(vec![KvJobStatus::Created])
+    };
+    let jobs = ctx.store.get_kv_jobs(Some(query)).await.unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].id, archive.id);
+
+    ctx.store.delete_kv_job(archive.id.clone()).await.unwrap();
+    let result = ctx.store.get_kv_job(archive.id).await;
+    assert!(matches!(
+        result,
+        Err(crate::db::JobStoreError::NotFound { .. })
+    ));
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_update_and_fail_kv_job() {
+    let ctx = setup_postgres().await;
+
+    let job = ctx
+        .store
+        .create_kv_job(kv_archive_create("config"))
+        .await
+        .unwrap();
+
+    let updated = ctx
+        .store
+        .update_kv_job(job.id.clone(), KvJobStatus::Running)
+        .await
+        .unwrap();
+    assert_eq!(updated.status, KvJobStatus::Running);
+
+    let failed = ctx
+        .store
+        .fail_kv_job(job.id.clone(), "interrupted by server shutdown".to_string())
+        .await
+        .unwrap();
+    assert_eq!(failed.status, KvJobStatus::Failure);
+    assert_eq!(
+        failed.error.as_deref(),
+        Some("interrupted by server shutdown")
+    );
+
+    let result = ctx
+        .store
+        .update_kv_job(Uuid::default().to_string(), KvJobStatus::Success)
+        .await;
+    assert!(matches!(
+        result,
+        Err(crate::db::JobStoreError::NotFound { .. })
+    ));
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "integration"), ignore)]
+async fn test_object_job_lifecycle() {
+    let ctx = setup_postgres().await;
+
+    let archive = ctx
+        .store
+        .create_object_job(
+            ObjectArchiveCreate {
+                name: "test-archive".to_string(),
+                object_bucket: "artifacts".to_string(),
+                bucket: "test-bucket".to_string(),
+                prefix: Some("objects".to_string()),
+                retry: None,
+            }
+            .into(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(archive.kind, ObjectJobKind::Archive);
+    assert_eq!(archive.status, ObjectJobStatus::Created);
+    assert_eq!(archive.object_bucket, "artifacts");
+    assert_eq!(archive.prefix.as_deref(), Some("objects"));
+
+    let updated = ctx
+        .store
+        .update_object_job(archive.id.clone(), ObjectJobStatus::Success)
+        .await
+        .unwrap();
+    assert_eq!(updated.status, ObjectJobStatus::Success);
+
+    let restore = ctx
+        .store
+        .create_object_job(ObjectJobCreate {
+            name: "test-restore".to_string(),
+            kind: ObjectJobKind::Restore,
+            object_bucket: "artifacts-restored".to_string(),
+            bucket: archive.bucket.clone(),
+            prefix: archive.prefix.clone(),
+            archive_id: Some(archive.id.clone()),
+            retry: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(restore.archive_id, Some(archive.id.clone()));
+
+    let failed = ctx
+        .store
+        .fail_object_job(restore.id.clone(), "digest mismatch".to_string())
+        .await
+        .unwrap();
+    assert_eq!(failed.status, ObjectJobStatus::Failure);
+    assert_eq!(failed.error.as_deref(), Some("digest mismatch"));
+
+    let query = ListObjectJobsQuery {
+        kind: Some(ObjectJobKind::Restore),
+        ..ListObjectJobsQuery::new().with_statuses(vec![ObjectJobStatus::Failure])
+    };
+    let jobs = ctx.store.get_object_jobs(Some(query)).await.unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].id, restore.id);
+
+    ctx.store
+        .delete_object_job(restore.id.clone())
+        .await
+        .unwrap();
+    let result = ctx.store.get_object_job(restore.id).await;
+    assert!(matches!(
+        result,
+        Err(crate::db::JobStoreError::NotFound { .. })
+    ));
+}
@@ -0,0 +1,4 @@
+-- Signature over the chunk hash and the key that made it, null when unsigned
+ALTER TABLE chunks
+    ADD COLUMN signature BYTEA,
+    ADD COLUMN signature_key_id TEXT;
@@ -0,0 +1,5 @@
+-- First and last stream sequence archived in each chunk, null for chunks
+-- written before delivery metadata was captured
+ALTER TABLE chunks
+    ADD COLUMN stream_sequence_start BIGINT,
+    ADD COLUMN stream_sequence_end BIGINT;
@@ -0,0 +1,10 @@
+-- Object key layout of a store job, null uses the default layout
+ALTER TABLE store_jobs
+    ADD COLUMN key_template TEXT;
+
+-- Chunk keys no longer include the job prefix, which is stored separately
+UPDATE chunks
+SET key = substr(key, length(prefix) + 2)
+WHERE prefix IS NOT NULL
+    AND prefix <> ''
+    AND starts_with(key, prefix || '/');
@@ -0,0 +1,9 @@
+CREATE TYPE deliver_policy AS ENUM ('all', 'new', 'last', 'by_start_sequence', 'by_start_time');
+
+-- Where a store job's created consumer starts and where the job ends
+ALTER TABLE store_jobs
+    ADD COLUMN deliver_policy deliver_policy NOT NULL DEFAULT 'all',
+    ADD COLUMN deliver_start_sequence BIGINT,
+    ADD COLUMN deliver_start_time TIMESTAMPTZ,
+    ADD COLUMN deliver_end_sequence BIGINT,
+    ADD COLUMN deliver_end_time TIMESTAMPTZ;
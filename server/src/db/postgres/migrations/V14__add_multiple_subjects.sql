@@ -0,0 +1,7 @@
+-- Filter subjects consumed by a store job besides its subject
+ALTER TABLE store_jobs
+    ADD COLUMN subjects TEXT[] NOT NULL DEFAULT '{}';
+
+-- Distinct message subjects in a chunk, empty for chunks written before
+ALTER TABLE chunks
+    ADD COLUMN subjects TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,4 @@
+-- Upload one chunk per subject (or subject prefix) of each batch
+ALTER TABLE store_jobs
+    ADD COLUMN split_by_subject BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN subject_depth INTEGER;
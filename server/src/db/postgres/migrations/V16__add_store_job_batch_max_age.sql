@@ -0,0 +1,2 @@
+-- Flush a store job batch after its oldest message waits this long
+ALTER TABLE store_jobs ADD COLUMN batch_max_age_ms BIGINT NOT NULL DEFAULT 10000;
@@ -0,0 +1,9 @@
+-- Highest stream sequence each store job has archived, redelivered messages
+-- at or below it are dropped
+CREATE TABLE store_job_checkpoints (
+    job_id UUID PRIMARY KEY REFERENCES store_jobs(id) ON DELETE CASCADE,
+    stream_sequence BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_chunks_stream_sequences ON chunks(stream, subject, stream_sequence_start);
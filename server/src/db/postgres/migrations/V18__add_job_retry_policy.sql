@@ -0,0 +1,12 @@
+-- Per job override of the server retry policy, null uses the server policy
+ALTER TABLE store_jobs
+    ADD COLUMN retry_max_attempts INTEGER,
+    ADD COLUMN retry_backoff_ms BIGINT,
+    ADD COLUMN retry_max_backoff_ms BIGINT,
+    ADD COLUMN retry_jitter BOOLEAN;
+
+ALTER TABLE load_jobs
+    ADD COLUMN retry_max_attempts INTEGER,
+    ADD COLUMN retry_backoff_ms BIGINT,
+    ADD COLUMN retry_max_backoff_ms BIGINT,
+    ADD COLUMN retry_jitter BOOLEAN;
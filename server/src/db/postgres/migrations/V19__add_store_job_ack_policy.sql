@@ -0,0 +1,4 @@
+CREATE TYPE ack_policy AS ENUM ('explicit', 'all');
+
+-- Ack policy of the consumer a store job creates or binds to
+ALTER TABLE store_jobs ADD COLUMN ack_policy ack_policy NOT NULL DEFAULT 'explicit';
@@ -0,0 +1,30 @@
+CREATE TYPE kv_job_kind AS ENUM ('archive', 'restore');
+
+CREATE TYPE kv_job_status AS ENUM ('created', 'running', 'success', 'failure');
+
+-- Key-value bucket snapshots to S3 and restores of them. A restore copies the
+-- S3 location and encoding of its archive.
+CREATE TABLE kv_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    kind kv_job_kind NOT NULL,
+    status kv_job_status NOT NULL,
+    kv_bucket TEXT NOT NULL,
+    bucket TEXT NOT NULL,
+    prefix TEXT,
+    history BOOLEAN NOT NULL DEFAULT false,
+    encoding_codec encoding_codec NOT NULL,
+    encoding_compression compression_algorithm NOT NULL DEFAULT 'none',
+    encoding_compression_level INTEGER,
+    encoding_encrypt BOOLEAN NOT NULL DEFAULT false,
+    archive_id UUID,
+    retry_max_attempts INTEGER,
+    retry_backoff_ms BIGINT,
+    retry_max_backoff_ms BIGINT,
+    retry_jitter BOOLEAN,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_kv_jobs_status ON kv_jobs(status);
@@ -0,0 +1,25 @@
+CREATE TYPE object_job_kind AS ENUM ('archive', 'restore');
+
+CREATE TYPE object_job_status AS ENUM ('created', 'running', 'success', 'failure');
+
+-- Object store bucket copies to S3 and restores of them. A restore copies the
+-- S3 location of its archive.
+CREATE TABLE object_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    kind object_job_kind NOT NULL,
+    status object_job_status NOT NULL,
+    object_bucket TEXT NOT NULL,
+    bucket TEXT NOT NULL,
+    prefix TEXT,
+    archive_id UUID,
+    retry_max_attempts INTEGER,
+    retry_backoff_ms BIGINT,
+    retry_max_backoff_ms BIGINT,
+    retry_jitter BOOLEAN,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_object_jobs_status ON object_jobs(status);
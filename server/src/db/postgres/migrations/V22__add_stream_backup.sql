@@ -0,0 +1,7 @@
+-- Store jobs archiving a whole stream with its config, and load jobs
+-- recreating a stream from such a backup
+ALTER TABLE store_jobs ADD COLUMN backup BOOLEAN NOT NULL DEFAULT false;
+
+ALTER TABLE load_jobs
+    ADD COLUMN restore BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN restore_stream TEXT;
@@ -0,0 +1,6 @@
+-- A unique constraint treats null prefixes as distinct, so chunks without a
+-- prefix could share a key. Compare a missing prefix as empty instead.
+ALTER TABLE chunks
+    DROP CONSTRAINT chunks_bucket_key_unique;
+
+CREATE UNIQUE INDEX chunks_bucket_key_unique ON chunks(bucket, COALESCE(prefix, ''), key);
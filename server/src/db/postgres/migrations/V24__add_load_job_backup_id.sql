@@ -0,0 +1,4 @@
+-- A restore reads the chunks of one backup store job, named by its id
+ALTER TABLE load_jobs
+    DROP COLUMN restore,
+    ADD COLUMN backup_id UUID;
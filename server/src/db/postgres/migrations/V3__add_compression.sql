@@ -0,0 +1,9 @@
+CREATE TYPE compression_algorithm AS ENUM ('none', 'zstd', 'gzip', 'lz4');
+
+ALTER TABLE store_jobs
+    ADD COLUMN encoding_compression compression_algorithm NOT NULL DEFAULT 'none',
+    ADD COLUMN encoding_compression_level INTEGER;
+
+-- Existing chunks were written uncompressed
+ALTER TABLE chunks
+    ADD COLUMN compression compression_algorithm NOT NULL DEFAULT 'none';
@@ -0,0 +1 @@
+ALTER TYPE encoding_codec ADD VALUE 'jsonl';
@@ -0,0 +1,6 @@
+ALTER TABLE store_jobs
+    ADD COLUMN encoding_encrypt BOOLEAN NOT NULL DEFAULT false;
+
+-- Master key id used to wrap the chunk data key, null when not encrypted
+ALTER TABLE chunks
+    ADD COLUMN encryption_key_id TEXT;
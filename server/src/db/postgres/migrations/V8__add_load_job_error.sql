@@ -0,0 +1,3 @@
+-- Reason a load job failed, e.g. an unreadable chunk
+ALTER TABLE load_jobs
+    ADD COLUMN error TEXT;
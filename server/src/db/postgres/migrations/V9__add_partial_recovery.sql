@@ -0,0 +1,8 @@
+ALTER TABLE load_jobs
+    ADD COLUMN partial_recovery BOOLEAN NOT NULL DEFAULT false;
+
+-- Outcome of loading a damaged chunk with partial recovery, null if never recovered
+ALTER TABLE chunks
+    ADD COLUMN recovered_count BIGINT,
+    ADD COLUMN lost_sequences BIGINT[],
+    ADD COLUMN lost_sequences_complete BOOLEAN;
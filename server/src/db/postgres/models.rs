@@ -6,11 +6,14 @@ use tokio_postgres::Row;
 use uuid::Uuid;
 
 use nats3_types::{
-    Batch, Codec, Encoding, LoadJob, LoadJobCreate, LoadJobStatus, StoreJob, StoreJobCreate,
-    StoreJobStatus,
+    AckPolicy, Batch, Codec, Compression, Deliver, DeliverPolicy, Encoding, KvJob, KvJobCreate,
+    KvJobKind, KvJobStatus, LoadJob, LoadJobCreate, LoadJobStatus, ObjectJob, ObjectJobCreate,
+    ObjectJobKind, ObjectJobStatus, Retry, StoreJob, StoreJobCreate, StoreJobStatus,
 };
 
-use crate::db::{ChunkMetadata, ChunkMetadataError, CreateChunkMetadata, JobStoreError};
+use crate::db::{
+    ChunkMetadata, ChunkMetadataError, ChunkRecovery, CreateChunkMetadata, JobStoreError,
+};
 
 #[derive(Debug, Clone, ToSql, FromSql)]
 #[postgres(name = "load_job_status")]
@@ -62,13 +65,22 @@ pub struct LoadJobCreateRow {
     pub poll_interval: Option<i64>,
     pub write_subject: String,
     pub delete_chunks: bool,
+    pub partial_recovery: bool,
     pub from_time: Option<DateTime<Utc>>,
     pub to_time: Option<DateTime<Utc>>,
+    pub backup_id: Option<Uuid>,
+    pub restore_stream: Option<String>,
+    pub retry_max_attempts: Option<i32>,
+    pub retry_backoff_ms: Option<i64>,
+    pub retry_max_backoff_ms: Option<i64>,
+    pub retry_jitter: Option<bool>,
 }
 
-impl From<LoadJobCreate> for LoadJobCreateRow {
-    fn from(row: LoadJobCreate) -> Self {
-        Self {
+impl TryFrom<LoadJobCreate> for LoadJobCreateRow {
+    type Error = JobStoreError;
+
+    fn try_from(row: LoadJobCreate) -> Result<Self, Self::Error> {
+        Ok(Self {
             name: row.name,
             status: LoadJobStatus::Created.into(),
             bucket: row.bucket,
@@ -79,9 +91,16 @@ impl From<LoadJobCreate> for LoadJobCreateRow {
             write_subject: row.write_subject,
             poll_interval: row.poll_interval.map(|d| d.as_secs() as i64),
             delete_chunks: row.delete_chunks,
+            partial_recovery: row.partial_recovery,
             from_time: row.from_time,
             to_time: row.to_time,
-        }
+            backup_id: row.backup_id.as_deref().map(Uuid::parse_str).transpose()?,
+            restore_stream: row.restore_stream,
+            retry_max_attempts: row.retry.as_ref().map(|r| r.max_attempts as i32),
+            retry_backoff_ms: row.retry.as_ref().map(|r| r.backoff.as_millis() as i64),
+            retry_max_backoff_ms: row.retry.as_ref().map(|r| r.max_backoff.as_millis() as i64),
+            retry_jitter: row.retry.as_ref().map(|r| r.jitter),
+        })
     }
 }
 
@@ -97,8 +116,16 @@ pub struct LoadJobRow {
     pub write_subject: String,
     pub poll_interval: Option<i64>,
     pub delete_chunks: bool,
+    pub partial_recovery: bool,
     pub from_time: Option<DateTime<Utc>>,
     pub to_time: Option<DateTime<Utc>>,
+    pub backup_id: Option<Uuid>,
+    pub restore_stream: Option<String>,
+    pub retry_max_attempts: Option<i32>,
+    pub retry_backoff_ms: Option<i64>,
+    pub retry_max_backoff_ms: Option<i64>,
+    pub retry_jitter: Option<bool>,
+    pub error: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -117,8 +144,16 @@ impl LoadJobRow {
             write_subject: row.try_get("write_subject")?,
             poll_interval: row.try_get("poll_interval")?,
             delete_chunks: row.try_get("delete_chunks")?,
+            partial_recovery: row.try_get("partial_recovery")?,
             from_time: row.try_get("from_time")?,
             to_time: row.try_get("to_time")?,
+            backup_id: row.try_get("backup_id")?,
+            restore_stream: row.try_get("restore_stream")?,
+            retry_max_attempts: row.try_get("retry_max_attempts")?,
+            retry_backoff_ms: row.try_get("retry_backoff_ms")?,
+            retry_max_backoff_ms: row.try_get("retry_max_backoff_ms")?,
+            retry_jitter: row.try_get("retry_jitter")?,
+            error: row.try_get("error")?,
             created_at: row.try_get("created_at")?,
             updated_at: row.try_get("updated_at")?,
         })
@@ -141,8 +176,18 @@ impl From<LoadJobRow> for LoadJob {
                 .poll_interval
                 .map(|d| time::Duration::from_secs(d as u64)),
             delete_chunks: row.delete_chunks,
+            partial_recovery: row.partial_recovery,
             from_time: row.from_time,
             to_time: row.to_time,
+            backup_id: row.backup_id.map(|id| id.to_string()),
+            restore_stream: row.restore_stream,
+            retry: retry_from_columns(
+                row.retry_max_attempts,
+                row.retry_backoff_ms,
+                row.retry_max_backoff_ms,
+                row.retry_jitter,
+            ),
+            error: row.error,
             created: row.created_at,
             updated: row.updated_at,
         }
@@ -164,8 +209,19 @@ impl From<LoadJob> for LoadJobRow {
             write_subject: job.write_subject,
             poll_interval: job.poll_interval.map(|d| d.as_secs() as i64),
             delete_chunks: job.delete_chunks,
+            partial_recovery: job.partial_recovery,
             from_time: job.from_time,
             to_time: job.to_time,
+            backup_id: job
+                .backup_id
+                .as_deref()
+                .and_then(|id| Uuid::parse_str(id).ok()),
+            restore_stream: job.restore_stream,
+            retry_max_attempts: job.retry.as_ref().map(|r| r.max_attempts as i32),
+            retry_backoff_ms: job.retry.as_ref().map(|r| r.backoff.as_millis() as i64),
+            retry_max_backoff_ms: job.retry.as_ref().map(|r| r.max_backoff.as_millis() as i64),
+            retry_jitter: job.retry.as_ref().map(|r| r.jitter),
+            error: job.error,
             created_at: now,
             updated_at: now,
         }
@@ -218,6 +274,12 @@ pub enum EncodingCodec {
     Json,
     #[postgres(name = "binary")]
     Binary,
+    #[postgres(name = "framed")]
+    Framed,
+    #[postgres(name = "parquet")]
+    Parquet,
+    #[postgres(name = "jsonl")]
+    Jsonl,
 }
 
 impl From<Codec> for EncodingCodec {
@@ -225,6 +287,9 @@ impl From<Codec> for EncodingCodec {
         match codec {
             Codec::Json => Self::Json,
             Codec::Binary => Self::Binary,
+            Codec::Framed => Self::Framed,
+            Codec::Parquet => Self::Parquet,
+            Codec::Jsonl => Self::Jsonl,
         }
     }
 }
@@ -234,6 +299,110 @@ impl From<EncodingCodec> for Codec {
         match codec {
             EncodingCodec::Json => Self::Json,
             EncodingCodec::Binary => Self::Binary,
+            EncodingCodec::Framed => Self::Framed,
+            EncodingCodec::Parquet => Self::Parquet,
+            EncodingCodec::Jsonl => Self::Jsonl,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ToSql, FromSql)]
+#[postgres(name = "compression_algorithm")]
+pub enum CompressionAlgorithm {
+    #[postgres(name = "none")]
+    None,
+    #[postgres(name = "zstd")]
+    Zstd,
+    #[postgres(name = "gzip")]
+    Gzip,
+    #[postgres(name = "lz4")]
+    Lz4,
+}
+
+impl From<Compression> for CompressionAlgorithm {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::None => Self::None,
+            Compression::Zstd => Self::Zstd,
+            Compression::Gzip => Self::Gzip,
+            Compression::Lz4 => Self::Lz4,
+        }
+    }
+}
+
+impl From<CompressionAlgorithm> for Compression {
+    fn from(compression: CompressionAlgorithm) -> Self {
+        match compression {
+            CompressionAlgorithm::None => Self::None,
+            CompressionAlgorithm::Zstd => Self::Zstd,
+            CompressionAlgorithm::Gzip => Self::Gzip,
+            CompressionAlgorithm::Lz4 => Self::Lz4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ToSql, FromSql)]
+#[postgres(name = "deliver_policy")]
+pub enum DeliverPolicyEnum {
+    #[postgres(name = "all")]
+    All,
+    #[postgres(name = "new")]
+    New,
+    #[postgres(name = "last")]
+    Last,
+    #[postgres(name = "by_start_sequence")]
+    ByStartSequence,
+    #[postgres(name = "by_start_time")]
+    ByStartTime,
+}
+
+impl From<DeliverPolicy> for DeliverPolicyEnum {
+    fn from(policy: DeliverPolicy) -> Self {
+        match policy {
+            DeliverPolicy::All => Self::All,
+            DeliverPolicy::New => Self::New,
+            DeliverPolicy::Last => Self::Last,
+            DeliverPolicy::ByStartSequence => Self::ByStartSequence,
+            DeliverPolicy::ByStartTime => Self::ByStartTime,
+        }
+    }
+}
+
+impl From<DeliverPolicyEnum> for DeliverPolicy {
+    fn from(policy: DeliverPolicyEnum) -> Self {
+        match policy {
+            DeliverPolicyEnum::All => Self::All,
+            DeliverPolicyEnum::New => Self::New,
+            DeliverPolicyEnum::Last => Self::Last,
+            DeliverPolicyEnum::ByStartSequence => Self::ByStartSequence,
+            DeliverPolicyEnum::ByStartTime => Self::ByStartTime,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ToSql, FromSql)]
+#[postgres(name = "ack_policy")]
+pub enum AckPolicyEnum {
+    #[postgres(name = "explicit")]
+    Explicit,
+    #[postgres(name = "all")]
+    All,
+}
+
+impl From<AckPolicy> for AckPolicyEnum {
+    fn from(policy: AckPolicy) -> Self {
+        match policy {
+            AckPolicy::Explicit => Self::Explicit,
+            AckPolicy::All => Self::All,
+        }
+    }
+}
+
+impl From<AckPolicyEnum> for AckPolicy {
+    fn from(policy: AckPolicyEnum) -> Self {
+        match policy {
+            AckPolicyEnum::Explicit => Self::Explicit,
+            AckPolicyEnum::All => Self::All,
         }
     }
 }
@@ -245,11 +414,30 @@ pub struct StoreJobCreateRow {
     pub stream: String,
     pub consumer: Option<String>,
     pub subject: String,
+    pub subjects: Vec<String>,
     pub bucket: String,
     pub prefix: Option<String>,
     pub batch_max_bytes: i64,
     pub batch_max_count: i64,
+    pub batch_max_age_ms: i64,
     pub encoding_codec: EncodingCodec,
+    pub encoding_compression: CompressionAlgorithm,
+    pub encoding_compression_level: Option<i32>,
+    pub encoding_encrypt: bool,
+    pub key_template: Option<String>,
+    pub deliver_policy: DeliverPolicyEnum,
+    pub deliver_start_sequence: Option<i64>,
+    pub deliver_start_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub deliver_end_sequence: Option<i64>,
+    pub deliver_end_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub split_by_subject: bool,
+    pub subject_depth: Option<i32>,
+    pub ack_policy: AckPolicyEnum,
+    pub backup: bool,
+    pub retry_max_attempts: Option<i32>,
+    pub retry_backoff_ms: Option<i64>,
+    pub retry_max_backoff_ms: Option<i64>,
+    pub retry_jitter: Option<bool>,
 }
 
 pub struct StoreJobRow {
@@ -259,11 +447,30 @@ pub struct StoreJobRow {
     pub stream: String,
     pub consumer: Option<String>,
     pub subject: String,
+    pub subjects: Vec<String>,
     pub bucket: String,
     pub prefix: Option<String>,
     pub batch_max_bytes: i64,
     pub batch_max_count: i64,
+    pub batch_max_age_ms: i64,
     pub encoding_codec: EncodingCodec,
+    pub encoding_compression: CompressionAlgorithm,
+    pub encoding_compression_level: Option<i32>,
+    pub encoding_encrypt: bool,
+    pub key_template: Option<String>,
+    pub deliver_policy: DeliverPolicyEnum,
+    pub deliver_start_sequence: Option<i64>,
+    pub deliver_start_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub deliver_end_sequence: Option<i64>,
+    pub deliver_end_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub split_by_subject: bool,
+    pub subject_depth: Option<i32>,
+    pub ack_policy: AckPolicyEnum,
+    pub backup: bool,
+    pub retry_max_attempts: Option<i32>,
+    pub retry_backoff_ms: Option<i64>,
+    pub retry_max_backoff_ms: Option<i64>,
+    pub retry_jitter: Option<bool>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -277,11 +484,30 @@ impl StoreJobRow {
             stream: row.try_get("stream")?,
             consumer: row.try_get("consumer")?,
             subject: row.try_get("subject")?,
+            subjects: row.try_get("subjects")?,
             bucket: row.try_get("bucket")?,
             prefix: row.try_get("prefix")?,
             batch_max_bytes: row.try_get("batch_max_bytes")?,
             batch_max_count: row.try_get("batch_max_count")?,
+            batch_max_age_ms: row.try_get("batch_max_age_ms")?,
             encoding_codec: row.try_get("encoding_codec")?,
+            encoding_compression: row.try_get("encoding_compression")?,
+            encoding_compression_level: row.try_get("encoding_compression_level")?,
+            encoding_encrypt: row.try_get("encoding_encrypt")?,
+            key_template: row.try_get("key_template")?,
+            deliver_policy: row.try_get("deliver_policy")?,
+            deliver_start_sequence: row.try_get("deliver_start_sequence")?,
+            deliver_start_time: row.try_get("deliver_start_time")?,
+            deliver_end_sequence: row.try_get("deliver_end_sequence")?,
+            deliver_end_time: row.try_get("deliver_end_time")?,
+            split_by_subject: row.try_get("split_by_subject")?,
+            subject_depth: row.try_get("subject_depth")?,
+            ack_policy: row.try_get("ack_policy")?,
+            backup: row.try_get("backup")?,
+            retry_max_attempts: row.try_get("retry_max_attempts")?,
+            retry_backoff_ms: row.try_get("retry_backoff_ms")?,
+            retry_max_backoff_ms: row.try_get("retry_max_backoff_ms")?,
+            retry_jitter: row.try_get("retry_jitter")?,
             created_at: row.try_get("created_at")?,
             updated_at: row.try_get("updated_at")?,
         })
@@ -297,15 +523,38 @@ impl From<StoreJobRow> for StoreJob {
             stream: row.stream,
             consumer: row.consumer,
             subject: row.subject,
+            subjects: row.subjects,
             bucket: row.bucket,
             prefix: row.prefix,
             batch: Batch {
                 max_bytes: row.batch_max_bytes,
                 max_count: row.batch_max_count,
+                max_age: time::Duration::from_millis(row.batch_max_age_ms as u64),
             },
             encoding: Encoding {
                 codec: row.encoding_codec.into(),
+                compression: row.encoding_compression.into(),
+                compression_level: row.encoding_compression_level,
+                encrypt: row.encoding_encrypt,
             },
+            key_template: row.key_template,
+            deliver: Deliver {
+                policy: row.deliver_policy.into(),
+                start_sequence: row.deliver_start_sequence.map(|s| s as u64),
+                start_time: row.deliver_start_time,
+                end_sequence: row.deliver_end_sequence.map(|s| s as u64),
+                end_time: row.deliver_end_time,
+            },
+            split_by_subject: row.split_by_subject,
+            subject_depth: row.subject_depth.map(|d| d as u32),
+            ack_policy: row.ack_policy.into(),
+            backup: row.backup,
+            retry: retry_from_columns(
+                row.retry_max_attempts,
+                row.retry_backoff_ms,
+                row.retry_max_backoff_ms,
+                row.retry_jitter,
+            ),
             created: row.created_at,
             updated: row.updated_at,
         }
@@ -320,15 +569,398 @@ impl From<StoreJobCreate> for StoreJobCreateRow {
             stream: job.stream,
             consumer: job.consumer,
             subject: job.subject,
+            subjects: job.subjects,
             bucket: job.bucket,
             prefix: job.prefix,
             batch_max_bytes: job.batch.max_bytes,
             batch_max_count: job.batch.max_count,
+            batch_max_age_ms: job.batch.max_age.as_millis() as i64,
             encoding_codec: job.encoding.codec.into(),
+            encoding_compression: job.encoding.compression.into(),
+            encoding_compression_level: job.encoding.compression_level,
+            encoding_encrypt: job.encoding.encrypt,
+            key_template: job.key_template,
+            deliver_policy: job.deliver.policy.into(),
+            deliver_start_sequence: job.deliver.start_sequence.map(|s| s as i64),
+            deliver_start_time: job.deliver.start_time,
+            deliver_end_sequence: job.deliver.end_sequence.map(|s| s as i64),
+            deliver_end_time: job.deliver.end_time,
+            split_by_subject: job.split_by_subject,
+            subject_depth: job.subject_depth.map(|d| d as i32),
+            ack_policy: job.ack_policy.into(),
+            backup: job.backup,
+            retry_max_attempts: job.retry.as_ref().map(|r| r.max_attempts as i32),
+            retry_backoff_ms: job.retry.as_ref().map(|r| r.backoff.as_millis() as i64),
+            retry_max_backoff_ms: job.retry.as_ref().map(|r| r.max_backoff.as_millis() as i64),
+            retry_jitter: job.retry.as_ref().map(|r| r.jitter),
+        }
+    }
+}
+
+#[derive(Debug, Clone, ToSql, FromSql)]
+#[postgres(name = "kv_job_kind")]
+pub enum KvJobKindEnum {
+    #[postgres(name = "archive")]
+    Archive,
+    #[postgres(name = "restore")]
+    Restore,
+}
+
+impl From<KvJobKind> for KvJobKindEnum {
+    fn from(kind: KvJobKind) -> Self {
+        match kind {
+            KvJobKind::Archive => Self::Archive,
+            KvJobKind::Restore => Self::Restore,
+        }
+    }
+}
+
+impl From<KvJobKindEnum> for KvJobKind {
+    fn from(kind: KvJobKindEnum) -> Self {
+        match kind {
+            KvJobKindEnum::Archive => Self::Archive,
+            KvJobKindEnum::Restore => Self::Restore,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ToSql, FromSql)]
+#[postgres(name = "kv_job_status")]
+pub enum KvJobStatusEnum {
+    #[postgres(name = "created")]
+    Created,
+    #[postgres(name = "running")]
+    Running,
+    #[postgres(name = "success")]
+    Success,
+    #[postgres(name = "failure")]
+    Failure,
+}
+
+impl From<KvJobStatus> for KvJobStatusEnum {
+    fn from(status: KvJobStatus) -> Self {
+        match status {
+            KvJobStatus::Created => Self::Created,
+            KvJobStatus::Running => Self::Running,
+            KvJobStatus::Success => Self::Success,
+            KvJobStatus::Failure => Self::Failure,
+        }
+    }
+}
+
+impl From<KvJobStatusEnum> for KvJobStatus {
+    fn from(status: KvJobStatusEnum) -> Self {
+        match status {
+            KvJobStatusEnum::Created => Self::Created,
+            KvJobStatusEnum::Running => Self::Running,
+            KvJobStatusEnum::Success => Self::Success,
+            KvJobStatusEnum::Failure => Self::Failure,
         }
     }
 }
 
+// model when creating a new key-value job (doesn't yet have timestamps)
+pub struct KvJobCreateRow {
+    pub name: String,
+    pub kind: KvJobKindEnum,
+    pub status: KvJobStatusEnum,
+    pub kv_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub history: bool,
+    pub encoding_codec: EncodingCodec,
+    pub encoding_compression: CompressionAlgorithm,
+    pub encoding_compression_level: Option<i32>,
+    pub encoding_encrypt: bool,
+    pub archive_id: Option<Uuid>,
+    pub retry_max_attempts: Option<i32>,
+    pub retry_backoff_ms: Option<i64>,
+    pub retry_max_backoff_ms: Option<i64>,
+    pub retry_jitter: Option<bool>,
+}
+
+impl TryFrom<KvJobCreate> for KvJobCreateRow {
+    type Error = JobStoreError;
+
+    fn try_from(job: KvJobCreate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: job.name,
+            kind: job.kind.into(),
+            status: KvJobStatus::Created.into(),
+            kv_bucket: job.kv_bucket,
+            bucket: job.bucket,
+            prefix: job.prefix,
+            history: job.history,
+            encoding_codec: job.encoding.codec.into(),
+            encoding_compression: job.encoding.compression.into(),
+            encoding_compression_level: job.encoding.compression_level,
+            encoding_encrypt: job.encoding.encrypt,
+            archive_id: job.archive_id.as_deref().map(Uuid::parse_str).transpose()?,
+            retry_max_attempts: job.retry.as_ref().map(|r| r.max_attempts as i32),
+            retry_backoff_ms: job.retry.as_ref().map(|r| r.backoff.as_millis() as i64),
+            retry_max_backoff_ms: job.retry.as_ref().map(|r| r.max_backoff.as_millis() as i64),
+            retry_jitter: job.retry.as_ref().map(|r| r.jitter),
+        })
+    }
+}
+
+pub struct KvJobRow {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: KvJobKindEnum,
+    pub status: KvJobStatusEnum,
+    pub kv_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub history: bool,
+    pub encoding_codec: EncodingCodec,
+    pub encoding_compression: CompressionAlgorithm,
+    pub encoding_compression_level: Option<i32>,
+    pub encoding_encrypt: bool,
+    pub archive_id: Option<Uuid>,
+    pub retry_max_attempts: Option<i32>,
+    pub retry_backoff_ms: Option<i64>,
+    pub retry_max_backoff_ms: Option<i64>,
+    pub retry_jitter: Option<bool>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl KvJobRow {
+    pub fn from_row(row: &Row) -> Result<Self, JobStoreError> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            kind: row.try_get("kind")?,
+            status: row.try_get("status")?,
+            kv_bucket: row.try_get("kv_bucket")?,
+            bucket: row.try_get("bucket")?,
+            prefix: row.try_get("prefix")?,
+            history: row.try_get("history")?,
+            encoding_codec: row.try_get("encoding_codec")?,
+            encoding_compression: row.try_get("encoding_compression")?,
+            encoding_compression_level: row.try_get("encoding_compression_level")?,
+            encoding_encrypt: row.try_get("encoding_encrypt")?,
+            archive_id: row.try_get("archive_id")?,
+            retry_max_attempts: row.try_get("retry_max_attempts")?,
+            retry_backoff_ms: row.try_get("retry_backoff_ms")?,
+            retry_max_backoff_ms: row.try_get("retry_max_backoff_ms")?,
+            retry_jitter: row.try_get("retry_jitter")?,
+            error: row.try_get("error")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+impl From<KvJobRow> for KvJob {
+    fn from(row: KvJobRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            name: row.name,
+            kind: row.kind.into(),
+            status: row.status.into(),
+            kv_bucket: row.kv_bucket,
+            bucket: row.bucket,
+            prefix: row.prefix,
+            history: row.history,
+            encoding: Encoding {
+                codec: row.encoding_codec.into(),
+                compression: row.encoding_compression.into(),
+                compression_level: row.encoding_compression_level,
+                encrypt: row.encoding_encrypt,
+            },
+            archive_id: row.archive_id.map(|id| id.to_string()),
+            retry: retry_from_columns(
+                row.retry_max_attempts,
+                row.retry_backoff_ms,
+                row.retry_max_backoff_ms,
+                row.retry_jitter,
+            ),
+            error: row.error,
+            created: row.created_at,
+            updated: row.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ToSql, FromSql)]
+#[postgres(name = "object_job_kind")]
+pub enum ObjectJobKindEnum {
+    #[postgres(name = "archive")]
+    Archive,
+    #[postgres(name = "restore")]
+    Restore,
+}
+
+impl From<ObjectJobKind> for ObjectJobKindEnum {
+    fn from(kind: ObjectJobKind) -> Self {
+        match kind {
+            ObjectJobKind::Archive => Self::Archive,
+            ObjectJobKind::Restore => Self::Restore,
+        }
+    }
+}
+
+impl From<ObjectJobKindEnum> for ObjectJobKind {
+    fn from(kind: ObjectJobKindEnum) -> Self {
+        match kind {
+            ObjectJobKindEnum::Archive => Self::Archive,
+            ObjectJobKindEnum::Restore => Self::Restore,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ToSql, FromSql)]
+#[postgres(name = "object_job_status")]
+pub enum ObjectJobStatusEnum {
+    #[postgres(name = "created")]
+    Created,
+    #[postgres(name = "running")]
+    Running,
+    #[postgres(name = "success")]
+    Success,
+    #[postgres(name = "failure")]
+    Failure,
+}
+
+impl From<ObjectJobStatus> for ObjectJobStatusEnum {
+    fn from(status: ObjectJobStatus) -> Self {
+        match status {
+            ObjectJobStatus::Created => Self::Created,
+            ObjectJobStatus::Running => Self::Running,
+            ObjectJobStatus::Success => Self::Success,
+            ObjectJobStatus::Failure => Self::Failure,
+        }
+    }
+}
+
+impl From<ObjectJobStatusEnum> for ObjectJobStatus {
+    fn from(status: ObjectJobStatusEnum) -> Self {
+        match status {
+            ObjectJobStatusEnum::Created => Self::Created,
+            ObjectJobStatusEnum::Running => Self::Running,
+            ObjectJobStatusEnum::Success => Self::Success,
+            ObjectJobStatusEnum::Failure => Self::Failure,
+        }
+    }
+}
+
+// model when creating a new object store job (doesn't yet have timestamps)
+pub struct ObjectJobCreateRow {
+    pub name: String,
+    pub kind: ObjectJobKindEnum,
+    pub status: ObjectJobStatusEnum,
+    pub object_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub archive_id: Option<Uuid>,
+    pub retry_max_attempts: Option<i32>,
+    pub retry_backoff_ms: Option<i64>,
+    pub retry_max_backoff_ms: Option<i64>,
+    pub retry_jitter: Option<bool>,
+}
+
+impl TryFrom<ObjectJobCreate> for ObjectJobCreateRow {
+    type Error = JobStoreError;
+
+    fn try_from(job: ObjectJobCreate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: job.name,
+            kind: job.kind.into(),
+            status: ObjectJobStatus::Created.into(),
+            object_bucket: job.object_bucket,
+            bucket: job.bucket,
+            prefix: job.prefix,
+            archive_id: job.archive_id.as_deref().map(Uuid::parse_str).transpose()?,
+            retry_max_attempts: job.retry.as_ref().map(|r| r.max_attempts as i32),
+            retry_backoff_ms: job.retry.as_ref().map(|r| r.backoff.as_millis() as i64),
+            retry_max_backoff_ms: job.retry.as_ref().map(|r| r.max_backoff.as_millis() as i64),
+            retry_jitter: job.retry.as_ref().map(|r| r.jitter),
+        })
+    }
+}
+
+pub struct ObjectJobRow {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: ObjectJobKindEnum,
+    pub status: ObjectJobStatusEnum,
+    pub object_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub archive_id: Option<Uuid>,
+    pub retry_max_attempts: Option<i32>,
+    pub retry_backoff_ms: Option<i64>,
+    pub retry_max_backoff_ms: Option<i64>,
+    pub retry_jitter: Option<bool>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ObjectJobRow {
+    pub fn from_row(row: &Row) -> Result<Self, JobStoreError> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            kind: row.try_get("kind")?,
+            status: row.try_get("status")?,
+            object_bucket: row.try_get("object_bucket")?,
+            bucket: row.try_get("bucket")?,
+            prefix: row.try_get("prefix")?,
+            archive_id: row.try_get("archive_id")?,
+            retry_max_attempts: row.try_get("retry_max_attempts")?,
+            retry_backoff_ms: row.try_get("retry_backoff_ms")?,
+            retry_max_backoff_ms: row.try_get("retry_max_backoff_ms")?,
+            retry_jitter: row.try_get("retry_jitter")?,
+            error: row.try_get("error")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+impl From<ObjectJobRow> for ObjectJob {
+    fn from(row: ObjectJobRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            name: row.name,
+            kind: row.kind.into(),
+            status: row.status.into(),
+            object_bucket: row.object_bucket,
+            bucket: row.bucket,
+            prefix: row.prefix,
+            archive_id: row.archive_id.map(|id| id.to_string()),
+            retry: retry_from_columns(
+                row.retry_max_attempts,
+                row.retry_backoff_ms,
+                row.retry_max_backoff_ms,
+                row.retry_jitter,
+            ),
+            error: row.error,
+            created: row.created_at,
+            updated: row.updated_at,
+        }
+    }
+}
+
+// a job's retry override, its columns are all set or all null
+fn retry_from_columns(
+    max_attempts: Option<i32>,
+    backoff_ms: Option<i64>,
+    max_backoff_ms: Option<i64>,
+    jitter: Option<bool>,
+) -> Option<Retry> {
+    Some(Retry {
+        max_attempts: max_attempts? as u32,
+        backoff: time::Duration::from_millis(backoff_ms? as u64),
+        max_backoff: time::Duration::from_millis(max_backoff_ms? as u64),
+        jitter: jitter?,
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct ChunkMetadataRow {
     pub sequence_number: i64,
@@ -341,10 +973,20 @@ pub struct ChunkMetadataRow {
     pub timestamp_start: chrono::DateTime<chrono::Utc>,
     pub timestamp_end: chrono::DateTime<chrono::Utc>,
     pub message_count: i64,
+    pub stream_sequence_start: Option<i64>,
+    pub stream_sequence_end: Option<i64>,
+    pub subjects: Vec<String>,
     pub size_bytes: i64,
     pub codec: EncodingCodec,
+    pub compression: CompressionAlgorithm,
+    pub encryption_key_id: Option<String>,
     pub hash: Vec<u8>,
+    pub signature: Option<Vec<u8>>,
+    pub signature_key_id: Option<String>,
     pub version: String,
+    pub recovered_count: Option<i64>,
+    pub lost_sequences: Option<Vec<i64>>,
+    pub lost_sequences_complete: Option<bool>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
 }
@@ -362,10 +1004,20 @@ impl ChunkMetadataRow {
             timestamp_start: row.try_get("timestamp_start")?,
             timestamp_end: row.try_get("timestamp_end")?,
             message_count: row.try_get("message_count")?,
+            stream_sequence_start: row.try_get("stream_sequence_start")?,
+            stream_sequence_end: row.try_get("stream_sequence_end")?,
+            subjects: row.try_get("subjects")?,
             size_bytes: row.try_get("size_bytes")?,
             codec: row.try_get("codec")?,
+            compression: row.try_get("compression")?,
+            encryption_key_id: row.try_get("encryption_key_id")?,
             hash: row.try_get("hash")?,
+            signature: row.try_get("signature")?,
+            signature_key_id: row.try_get("signature_key_id")?,
             version: row.try_get("version")?,
+            recovered_count: row.try_get("recovered_count")?,
+            lost_sequences: row.try_get("lost_sequences")?,
+            lost_sequences_complete: row.try_get("lost_sequences_complete")?,
             created_at: row.try_get("created_at")?,
             deleted_at: row.try_get("deleted_at")?,
         })
@@ -385,10 +1037,22 @@ impl From<ChunkMetadataRow> for ChunkMetadata {
             timestamp_start: row.timestamp_start,
             timestamp_end: row.timestamp_end,
             message_count: row.message_count,
+            stream_sequence_start: row.stream_sequence_start,
+            stream_sequence_end: row.stream_sequence_end,
+            subjects: row.subjects,
             size_bytes: row.size_bytes,
             codec: row.codec.into(),
+            compression: row.compression.into(),
+            encryption_key_id: row.encryption_key_id,
             hash: Bytes::from(row.hash),
+            signature: row.signature.map(Bytes::from),
+            signature_key_id: row.signature_key_id,
             version: row.version,
+            recovery: row.recovered_count.map(|recovered_count| ChunkRecovery {
+                recovered_count,
+                lost_sequences: row.lost_sequences.unwrap_or_default(),
+                lost_sequences_complete: row.lost_sequences_complete.unwrap_or(false),
+            }),
             created_at: row.created_at,
             deleted_at: row.deleted_at,
         }
@@ -406,9 +1070,16 @@ pub struct CreateChunkMetadataRow {
     pub timestamp_start: chrono::DateTime<chrono::Utc>,
     pub timestamp_end: chrono::DateTime<chrono::Utc>,
     pub message_count: i64,
+    pub stream_sequence_start: Option<i64>,
+    pub stream_sequence_end: Option<i64>,
+    pub subjects: Vec<String>,
     pub size_bytes: i64,
     pub codec: EncodingCodec,
+    pub compression: CompressionAlgorithm,
+    pub encryption_key_id: Option<String>,
     pub hash: Vec<u8>,
+    pub signature: Option<Vec<u8>>,
+    pub signature_key_id: Option<String>,
     pub version: String,
 }
 
@@ -424,9 +1095,16 @@ impl From<CreateChunkMetadata> for CreateChunkMetadataRow {
             timestamp_start: chunk.timestamp_start,
             timestamp_end: chunk.timestamp_end,
             message_count: chunk.message_count,
+            stream_sequence_start: chunk.stream_sequence_start,
+            stream_sequence_end: chunk.stream_sequence_end,
+            subjects: chunk.subjects,
             size_bytes: chunk.size_bytes,
             codec: chunk.codec.into(),
+            compression: chunk.compression.into(),
+            encryption_key_id: chunk.encryption_key_id,
             hash: chunk.hash.to_vec(),
+            signature: chunk.signature.map(|s| s.to_vec()),
+            signature_key_id: chunk.signature_key_id,
             version: chunk.version,
         }
     }
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use flate2::{read::GzDecoder, write::GzEncoder};
+use nats3_types::Compression;
+use std::io::{self, Read, Write};
+
+use crate::body::{Body, BodyWriter, SPILL_BYTES};
+
+// compress serialized chunk bytes, a no-op without compression
+pub fn compress(data: Vec<u8>, compression: &Compression, level: Option<i32>) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data),
+        Compression::Zstd => {
+            let level = level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+            zstd::encode_all(data.as_slice(), level).context("zstd compression")
+        }
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), gzip_level(level));
+            encoder.write_all(&data).context("gzip compression")?;
+            encoder.finish().context("gzip compression")
+        }
+        Compression::Lz4 => Ok(lz4_flex::compress_prepend_size(&data)),
+    }
+}
+
+pub fn decompress(data: &Bytes, compression: &Compression) -> Result<Bytes> {
+    match compression {
+        Compression::None => Ok(data.clone()),
+        Compression::Zstd => zstd::decode_all(data.as_ref())
+            .map(Bytes::from)
+            .context("zstd decompression"),
+        Compression::Gzip => {
+            let mut decoded = Vec::new();
+            GzDecoder::new(data.as_ref())
+                .read_to_end(&mut decoded)
+                .context("gzip decompression")?;
+            Ok(Bytes::from(decoded))
+        }
+        Compression::Lz4 => lz4_flex::decompress_size_prepended(data)
+            .map(Bytes::from)
+            .context("lz4 decompression"),
+    }
+}
+
+fn gzip_level(level: Option<i32>) -> flate2::Compression {
+    level
+        .map(|l| flate2::Compression::new(l as u32))
+        .unwrap_or_default()
+}
+
+// CompressWriter incrementally compresses into a body, which moves to a
+// temporary file once it grows past SPILL_BYTES. Unlike `compress`, lz4 uses
+// the frame format so output can be decompressed as a stream.
+pub enum CompressWriter {
+    None(BodyWriter),
+    Zstd(zstd::Encoder<'static, BodyWriter>),
+    Gzip(GzEncoder<BodyWriter>),
+    Lz4(lz4_flex::frame::FrameEncoder<BodyWriter>),
+}
+
+impl CompressWriter {
+    pub fn new(compression: &Compression, level: Option<i32>) -> Result<Self> {
+        let inner = BodyWriter::new(SPILL_BYTES);
+        let writer = match compression {
+            Compression::None => Self::None(inner),
+            Compression::Zstd => {
+                let level = level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+                Self::Zstd(zstd::Encoder::new(inner, level).context("zstd encoder")?)
+            }
+            Compression::Gzip => Self::Gzip(GzEncoder::new(inner, gzip_level(level))),
+            Compression::Lz4 => Self::Lz4(lz4_flex::frame::FrameEncoder::new(inner)),
+        };
+        Ok(writer)
+    }
+
+    // flush remaining compressed data and return the body
+    pub fn finish(self) -> Result<Body> {
+        let inner = match self {
+            Self::None(inner) => inner,
+            Self::Zstd(encoder) => encoder.finish().context("zstd compression")?,
+            Self::Gzip(encoder) => encoder.finish().context("gzip compression")?,
+            Self::Lz4(encoder) => encoder.finish().context("lz4 compression")?,
+        };
+        inner.finish().context("write chunk body")
+    }
+}
+
+impl Write for CompressWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::None(w) => w.write(buf),
+            Self::Zstd(w) => w.write(buf),
+            Self::Gzip(w) => w.write(buf),
+            Self::Lz4(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::None(w) => w.flush(),
+            Self::Zstd(w) => w.flush(),
+            Self::Gzip(w) => w.flush(),
+            Self::Lz4(w) => w.flush(),
+        }
+    }
+}
+
+// wrap a reader of data written by CompressWriter to decompress on read
+pub fn decompress_reader<'a, R: Read + 'a>(
+    inner: R,
+    compression: &Compression,
+) -> Result<Box<dyn Read + 'a>> {
+    let reader: Box<dyn Read + 'a> = match compression {
+        Compression::None => Box::new(inner),
+        Compression::Zstd => Box::new(zstd::Decoder::new(inner).context("zstd decoder")?),
+        Compression::Gzip => Box::new(GzDecoder::new(inner)),
+        Compression::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(inner)),
+    };
+    Ok(reader)
+}
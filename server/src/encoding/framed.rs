@@ -0,0 +1,266 @@
+// Framed chunk format, written and read one message at a time.
+//
+// header: magic "NATS3" | format version (u8)
+// record: RECORD_TAG (u8) | length (u32 BE) | sequence (u64 BE) | crc32 (u32 BE)
+//         | bincode encoded Message
+// footer: FOOTER_TAG (u8) | length (u32 BE) | bincode encoded Footer
+//
+// The footer hash is a SHA-256 over every encoded message, in order. The
+// per record crc32 covers the encoded message, so a damaged record can be
+// skipped while the rest of the chunk is still read. Version 1 records
+// carry neither the sequence nor the crc32, versions before 3 encode
+// messages and the footer without delivery metadata.
+
+use anyhow::{anyhow, bail, Context, Result};
+use bytes::Bytes;
+use nats3_types::Codec;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+use super::{v1, ChunkError, Decoded, Message, MAGIC_NUMBER};
+
+pub const VERSION: &str = "3.0";
+// versions this reader understands
+pub const VERSIONS: &[&str] = &["1.0", "2.0", VERSION];
+
+const FORMAT_VERSION: u8 = 3;
+const RECORD_HEADER_BYTES: usize = 12;
+const RECORD_TAG: u8 = 1;
+const FOOTER_TAG: u8 = 0;
+
+// upper bound on a single record, guards against allocating on corrupt lengths
+const MAX_RECORD_BYTES: usize = 128 * 1024 * 1024;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Footer {
+    pub message_count: u64,
+    pub bytes_total: u64,
+    pub timestamp_min: chrono::DateTime<chrono::Utc>,
+    pub timestamp_max: chrono::DateTime<chrono::Utc>,
+    pub sequence_min: Option<u64>,
+    pub sequence_max: Option<u64>,
+    pub hash: Bytes,
+}
+
+pub struct FramedWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    message_count: u64,
+    bytes_total: u64,
+    timestamp_min: Option<chrono::DateTime<chrono::Utc>>,
+    timestamp_max: Option<chrono::DateTime<chrono::Utc>>,
+    sequence_min: Option<u64>,
+    sequence_max: Option<u64>,
+}
+
+impl<W: Write> FramedWriter<W> {
+    pub fn new(mut inner: W) -> Result<Self> {
+        inner
+            .write_all(MAGIC_NUMBER.as_bytes())
+            .and_then(|_| inner.write_all(&[FORMAT_VERSION]))
+            .context("write framed header")?;
+        Ok(Self {
+            inner,
+            hasher: Sha256::new(),
+            message_count: 0,
+            bytes_total: 0,
+            timestamp_min: None,
+            timestamp_max: None,
+            sequence_min: None,
+            sequence_max: None,
+        })
+    }
+
+    pub fn write_message(&mut self, message: &Message) -> Result<()> {
+        let config = bincode::config::legacy();
+        let record =
+            bincode::serde::encode_to_vec(message, config).context("encode framed record")?;
+        let mut body = Vec::with_capacity(RECORD_HEADER_BYTES + record.len());
+        body.extend_from_slice(&message.sequence.to_be_bytes());
+        body.extend_from_slice(&crc32fast::hash(&record).to_be_bytes());
+        body.extend_from_slice(&record);
+        write_frame(&mut self.inner, RECORD_TAG, &body)?;
+        self.hasher.update(&record);
+
+        self.message_count += 1;
+        self.bytes_total += message.length as u64;
+        self.timestamp_min = Some(
+            self.timestamp_min
+                .map_or(message.timestamp, |ts| ts.min(message.timestamp)),
+        );
+        self.timestamp_max = Some(
+            self.timestamp_max
+                .map_or(message.timestamp, |ts| ts.max(message.timestamp)),
+        );
+        self.sequence_min = Some(
+            self.sequence_min
+                .map_or(message.sequence, |seq| seq.min(message.sequence)),
+        );
+        self.sequence_max = Some(
+            self.sequence_max
+                .map_or(message.sequence, |seq| seq.max(message.sequence)),
+        );
+        Ok(())
+    }
+
+    // write the footer and hand back the underlying writer
+    pub fn finish(mut self) -> Result<(W, Footer)> {
+        let now = chrono::Utc::now();
+        let footer = Footer {
+            message_count: self.message_count,
+            bytes_total: self.bytes_total,
+            timestamp_min: self.timestamp_min.unwrap_or(now),
+            timestamp_max: self.timestamp_max.unwrap_or(now),
+            sequence_min: self.sequence_min,
+            sequence_max: self.sequence_max,
+            hash: Bytes::from(self.hasher.finalize().to_vec()),
+        };
+        let config = bincode::config::legacy();
+        let encoded =
+            bincode::serde::encode_to_vec(&footer, config).context("encode framed footer")?;
+        write_frame(&mut self.inner, FOOTER_TAG, &encoded)?;
+        Ok((self.inner, footer))
+    }
+}
+
+fn write_frame<W: Write>(writer: &mut W, tag: u8, body: &[u8]) -> Result<()> {
+    let len = u32::try_from(body.len()).context("framed record too large")?;
+    writer
+        .write_all(&[tag])
+        .and_then(|_| writer.write_all(&len.to_be_bytes()))
+        .and_then(|_| writer.write_all(body))
+        .context("write frame")
+}
+
+pub struct FramedReader<R: Read> {
+    inner: R,
+    version: u8,
+    hasher: Sha256,
+    message_count: u64,
+    damaged_count: u64,
+    footer: Option<Footer>,
+}
+
+impl<R: Read> FramedReader<R> {
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut header = [0u8; MAGIC_NUMBER.len() + 1];
+        inner
+            .read_exact(&mut header)
+            .context("read framed header")?;
+        if &header[..MAGIC_NUMBER.len()] != MAGIC_NUMBER.as_bytes() {
+            bail!(ChunkError::MissingMagicNumber);
+        }
+        let version = header[MAGIC_NUMBER.len()];
+        if !(1..=FORMAT_VERSION).contains(&version) {
+            bail!(ChunkError::UnsupportedVersion {
+                codec: Codec::Framed,
+                version: version.to_string(),
+            });
+        }
+        Ok(Self {
+            inner,
+            version,
+            hasher: Sha256::new(),
+            message_count: 0,
+            damaged_count: 0,
+            footer: None,
+        })
+    }
+
+    // read the next record, returns none once the footer has been read
+    // and verified against the records read so far.
+    pub fn next_record(&mut self) -> Result<Option<Decoded>> {
+        if self.footer.is_some() {
+            return Ok(None);
+        }
+
+        let mut prefix = [0u8; 5];
+        self.inner
+            .read_exact(&mut prefix)
+            .context("read frame prefix, chunk truncated")?;
+        let tag = prefix[0];
+        let len = u32::from_be_bytes([prefix[1], prefix[2], prefix[3], prefix[4]]) as usize;
+        if len > MAX_RECORD_BYTES {
+            bail!("frame length {} exceeds limit", len);
+        }
+        let mut body = vec![0u8; len];
+        self.inner
+            .read_exact(&mut body)
+            .context("read frame body")?;
+
+        let config = bincode::config::legacy();
+        match tag {
+            RECORD_TAG if self.version == 1 => {
+                self.hasher.update(&body);
+                self.message_count += 1;
+                Ok(Some(Decoded::Message(self.decode_message(&body)?)))
+            }
+            RECORD_TAG => {
+                if body.len() < RECORD_HEADER_BYTES {
+                    bail!("framed record shorter than its header");
+                }
+                let (header, record) = body.split_at(RECORD_HEADER_BYTES);
+                let sequence = u64::from_be_bytes(header[..8].try_into()?);
+                let crc = u32::from_be_bytes(header[8..].try_into()?);
+                self.hasher.update(record);
+                self.message_count += 1;
+                if crc32fast::hash(record) != crc {
+                    self.damaged_count += 1;
+                    return Ok(Some(Decoded::Damaged {
+                        sequence: Some(sequence),
+                    }));
+                }
+                Ok(Some(Decoded::Message(self.decode_message(record)?)))
+            }
+            FOOTER_TAG => {
+                let footer = if self.version < 3 {
+                    let (footer, _): (v1::Footer, _) =
+                        bincode::serde::decode_from_slice(&body, config)
+                            .context("decode framed footer")?;
+                    footer.into()
+                } else {
+                    let (footer, _): (Footer, _) = bincode::serde::decode_from_slice(&body, config)
+                        .context("decode framed footer")?;
+                    footer
+                };
+                self.verify(&footer)?;
+                self.footer = Some(footer);
+                Ok(None)
+            }
+            tag => Err(anyhow!("unknown frame tag {}", tag)),
+        }
+    }
+
+    fn decode_message(&self, record: &[u8]) -> Result<Message> {
+        let config = bincode::config::legacy();
+        if self.version < 3 {
+            let (message, _): (v1::Message, _) = bincode::serde::decode_from_slice(record, config)
+                .context("decode framed record")?;
+            return Ok(message.into());
+        }
+        let (message, _) =
+            bincode::serde::decode_from_slice(record, config).context("decode framed record")?;
+        Ok(message)
+    }
+
+    fn verify(&self, footer: &Footer) -> Result<()> {
+        if footer.message_count != self.message_count {
+            bail!(
+                "framed chunk message count mismatch, footer={} read={}",
+                footer.message_count,
+                self.message_count
+            );
+        }
+        // damaged records were already caught by their crc32
+        let hash = self.hasher.clone().finalize();
+        if self.damaged_count == 0 && footer.hash.as_ref() != hash.as_slice() {
+            bail!("framed chunk hash mismatch");
+        }
+        Ok(())
+    }
+
+    pub fn footer(&self) -> Option<&Footer> {
+        self.footer.as_ref()
+    }
+}
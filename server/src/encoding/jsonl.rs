@@ -0,0 +1,370 @@
+// JSON Lines chunk format, one JSON object per line.
+//
+// {"type":"header","magic_number":"NATS3","version":"3.0"}
+// {"type":"message","subject":..,"sequence":..,"stream":..,"timestamp":..,"payload":..,"crc":..}
+// {"type":"trailer","message_count":..,"hash":..}
+//
+// Payloads are written as UTF-8 text when valid, base64 otherwise. The
+// trailer hash is a hex SHA-256 over every message line, in order. Each
+// message line carries the message crc32 so a damaged line can be skipped,
+// version 1 lines have no crc. Lines before version 3 have no delivery
+// metadata and their crc covers the message without it.
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use nats3_types::Codec;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Read, Write},
+};
+
+use super::{v1, ChunkError, Decoded, Message, MAGIC_NUMBER};
+
+pub const VERSION: &str = "3.0";
+// versions this reader understands
+pub const VERSIONS: &[&str] = &["1.0", "2.0", VERSION];
+
+// upper bound on a single line, guards against unbounded reads on corrupt data
+const MAX_LINE_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Line {
+    Header(Header),
+    Message(Record),
+    Trailer(Trailer),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    magic_number: String,
+    version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    subject: String,
+    sequence: u64,
+    #[serde(default)]
+    stream: String,
+    #[serde(default)]
+    consumer_sequence: u64,
+    #[serde(default)]
+    delivered: i64,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    headers: Option<BTreeMap<String, Vec<String>>>,
+    length: usize,
+    payload_encoding: PayloadEncoding,
+    payload: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    crc: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PayloadEncoding {
+    Utf8,
+    Base64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Trailer {
+    pub message_count: u64,
+    pub bytes_total: u64,
+    pub timestamp_min: chrono::DateTime<chrono::Utc>,
+    pub timestamp_max: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub sequence_min: Option<u64>,
+    #[serde(default)]
+    pub sequence_max: Option<u64>,
+    pub hash: String,
+}
+
+impl Trailer {
+    pub fn hash_bytes(&self) -> Result<Bytes> {
+        let hash = hex::decode(&self.hash).context("jsonl trailer hash")?;
+        Ok(Bytes::from(hash))
+    }
+}
+
+impl From<&Message> for Record {
+    fn from(message: &Message) -> Self {
+        let (payload_encoding, payload) = match std::str::from_utf8(&message.payload) {
+            Ok(text) => (PayloadEncoding::Utf8, text.to_string()),
+            Err(_) => (PayloadEncoding::Base64, STANDARD.encode(&message.payload)),
+        };
+        Record {
+            subject: message.subject.clone(),
+            sequence: message.sequence,
+            stream: message.stream.clone(),
+            consumer_sequence: message.consumer_sequence,
+            delivered: message.delivered,
+            timestamp: message.timestamp,
+            headers: message.headers.clone(),
+            length: message.length,
+            payload_encoding,
+            payload,
+            crc: Some(message.checksum()),
+        }
+    }
+}
+
+impl TryFrom<Record> for Message {
+    type Error = anyhow::Error;
+
+    fn try_from(record: Record) -> Result<Self> {
+        let payload = match record.payload_encoding {
+            PayloadEncoding::Utf8 => Bytes::from(record.payload),
+            PayloadEncoding::Base64 => Bytes::from(
+                STANDARD
+                    .decode(record.payload)
+                    .context("decode base64 payload")?,
+            ),
+        };
+        Ok(Message {
+            subject: record.subject,
+            payload,
+            headers: record.headers,
+            length: record.length,
+            timestamp: record.timestamp,
+            sequence: record.sequence,
+            stream: record.stream,
+            consumer_sequence: record.consumer_sequence,
+            delivered: record.delivered,
+        })
+    }
+}
+
+pub struct JsonlWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    message_count: u64,
+    bytes_total: u64,
+    timestamp_min: Option<chrono::DateTime<chrono::Utc>>,
+    timestamp_max: Option<chrono::DateTime<chrono::Utc>>,
+    sequence_min: Option<u64>,
+    sequence_max: Option<u64>,
+}
+
+impl<W: Write> JsonlWriter<W> {
+    pub fn new(mut inner: W) -> Result<Self> {
+        let header = Line::Header(Header {
+            magic_number: MAGIC_NUMBER.to_string(),
+            version: VERSION.to_string(),
+        });
+        write_line(&mut inner, &encode_line(&header)?)?;
+        Ok(Self {
+            inner,
+            hasher: Sha256::new(),
+            message_count: 0,
+            bytes_total: 0,
+            timestamp_min: None,
+            timestamp_max: None,
+            sequence_min: None,
+            sequence_max: None,
+        })
+    }
+
+    pub fn write_message(&mut self, message: &Message) -> Result<()> {
+        let line = encode_line(&Line::Message(Record::from(message)))?;
+        write_line(&mut self.inner, &line)?;
+        self.hasher.update(&line);
+
+        self.message_count += 1;
+        self.bytes_total += message.length as u64;
+        self.timestamp_min = Some(
+            self.timestamp_min
+                .map_or(message.timestamp, |ts| ts.min(message.timestamp)),
+        );
+        self.timestamp_max = Some(
+            self.timestamp_max
+                .map_or(message.timestamp, |ts| ts.max(message.timestamp)),
+        );
+        self.sequence_min = Some(
+            self.sequence_min
+                .map_or(message.sequence, |seq| seq.min(message.sequence)),
+        );
+        self.sequence_max = Some(
+            self.sequence_max
+                .map_or(message.sequence, |seq| seq.max(message.sequence)),
+        );
+        Ok(())
+    }
+
+    // write the trailer and hand back the underlying writer
+    pub fn finish(mut self) -> Result<(W, Trailer)> {
+        let now = chrono::Utc::now();
+        let trailer = Trailer {
+            message_count: self.message_count,
+            bytes_total: self.bytes_total,
+            timestamp_min: self.timestamp_min.unwrap_or(now),
+            timestamp_max: self.timestamp_max.unwrap_or(now),
+            sequence_min: self.sequence_min,
+            sequence_max: self.sequence_max,
+            hash: hex::encode(self.hasher.finalize()),
+        };
+        let line = encode_line(&Line::Trailer(trailer.clone()))?;
+        write_line(&mut self.inner, &line)?;
+        Ok((self.inner, trailer))
+    }
+}
+
+fn encode_line(line: &Line) -> Result<Vec<u8>> {
+    serde_json::to_vec(line).context("encode jsonl line")
+}
+
+fn write_line<W: Write>(writer: &mut W, line: &[u8]) -> Result<()> {
+    writer
+        .write_all(line)
+        .and_then(|_| writer.write_all(b"\n"))
+        .context("write jsonl line")
+}
+
+pub struct JsonlReader<R: BufRead> {
+    inner: R,
+    checksummed: bool,
+    // crc computed over the message without delivery metadata
+    legacy_checksum: bool,
+    hasher: Sha256,
+    message_count: u64,
+    damaged_count: u64,
+    trailer: Option<Trailer>,
+}
+
+impl<R: BufRead> JsonlReader<R> {
+    pub fn new(mut inner: R) -> Result<Self> {
+        let line = read_line(&mut inner)?.ok_or_else(|| anyhow!("jsonl chunk is empty"))?;
+        // anything other than a header line is not a nats3 jsonl chunk
+        let header = match serde_json::from_slice(&line) {
+            Ok(Line::Header(header)) => header,
+            _ => bail!(ChunkError::MissingMagicNumber),
+        };
+        if header.magic_number != MAGIC_NUMBER {
+            bail!(ChunkError::MissingMagicNumber);
+        }
+        if !VERSIONS.contains(&header.version.as_str()) {
+            bail!(ChunkError::UnsupportedVersion {
+                codec: Codec::Jsonl,
+                version: header.version,
+            });
+        }
+        Ok(Self {
+            inner,
+            checksummed: header.version != "1.0",
+            legacy_checksum: header.version != VERSION,
+            hasher: Sha256::new(),
+            message_count: 0,
+            damaged_count: 0,
+            trailer: None,
+        })
+    }
+
+    // read the next record, returns none once the trailer has been read
+    // and verified against the lines read so far.
+    pub fn next_record(&mut self) -> Result<Option<Decoded>> {
+        if self.trailer.is_some() {
+            return Ok(None);
+        }
+
+        let line = read_line(&mut self.inner)?
+            .ok_or_else(|| anyhow!("jsonl chunk truncated, missing trailer"))?;
+        let parsed = match serde_json::from_slice(&line) {
+            Ok(parsed) => parsed,
+            // a damaged line may no longer be valid json
+            Err(_) if self.checksummed => return Ok(Some(self.damaged(&line, None))),
+            Err(e) => return Err(e).context("decode jsonl line"),
+        };
+        match parsed {
+            Line::Message(record) => {
+                let (sequence, crc) = (record.sequence, record.crc);
+                let message = match Message::try_from(record) {
+                    Ok(message) => message,
+                    Err(_) if self.checksummed => {
+                        return Ok(Some(self.damaged(&line, Some(sequence))))
+                    }
+                    Err(e) => return Err(e),
+                };
+                let checksum = if self.legacy_checksum {
+                    v1::checksum(&message)
+                } else {
+                    message.checksum()
+                };
+                if self.checksummed && crc != Some(checksum) {
+                    return Ok(Some(self.damaged(&line, Some(sequence))));
+                }
+                self.hasher.update(&line);
+                self.message_count += 1;
+                Ok(Some(Decoded::Message(message)))
+            }
+            Line::Trailer(trailer) => {
+                self.verify(&trailer)?;
+                self.trailer = Some(trailer);
+                Ok(None)
+            }
+            Line::Header(_) => bail!("unexpected jsonl header line"),
+        }
+    }
+
+    fn damaged(&mut self, line: &[u8], sequence: Option<u64>) -> Decoded {
+        self.hasher.update(line);
+        self.message_count += 1;
+        self.damaged_count += 1;
+        Decoded::Damaged {
+            sequence: sequence.or_else(|| scan_sequence(line)),
+        }
+    }
+
+    fn verify(&self, trailer: &Trailer) -> Result<()> {
+        if trailer.message_count != self.message_count {
+            bail!(
+                "jsonl chunk message count mismatch, trailer={} read={}",
+                trailer.message_count,
+                self.message_count
+            );
+        }
+        // damaged lines were already caught by their crc32
+        let hash = self.hasher.clone().finalize();
+        if self.damaged_count == 0 && trailer.hash_bytes()?.as_ref() != hash.as_slice() {
+            bail!("jsonl chunk hash mismatch");
+        }
+        Ok(())
+    }
+
+    pub fn trailer(&self) -> Option<&Trailer> {
+        self.trailer.as_ref()
+    }
+}
+
+// read a line without its newline, none at end of input
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let n = Read::take(reader, MAX_LINE_BYTES)
+        .read_until(b'\n', &mut line)
+        .context("read jsonl line")?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if line.last() != Some(&b'\n') {
+        bail!("jsonl line truncated or exceeds limit");
+    }
+    line.pop();
+    Ok(Some(line))
+}
+
+// best effort recovery of the sequence from a line that is no longer valid json
+fn scan_sequence(line: &[u8]) -> Option<u64> {
+    const FIELD: &[u8] = b"\"sequence\":";
+    let start = line.windows(FIELD.len()).position(|w| w == FIELD)? + FIELD.len();
+    let digits = line[start..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    std::str::from_utf8(&line[start..start + digits])
+        .ok()?
+        .parse()
+        .ok()
+}
@@ -0,0 +1,1347 @@
+use anyhow::{bail, Context, Result};
+use async_nats::{header, jetstream};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use nats3_types::{parse_key_template, Codec, Compression, KeyPart, DEFAULT_KEY_TEMPLATE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::{BufReader, Read},
+};
+use thiserror::Error;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use ulid::Ulid;
+
+use crate::{
+    body::{Body, BodyWriter, SPILL_BYTES},
+    db::CreateChunkMetadata,
+    encryption::Keyring,
+    io::ConsumeConfig,
+    signing::Signer,
+};
+
+mod compression;
+pub mod framed;
+pub mod jsonl;
+mod parquet;
+mod v1;
+
+use compression::decompress_reader;
+use compression::{compress, decompress, CompressWriter};
+
+const MAGIC_NUMBER: &str = "NATS3";
+const VERSION: &str = "2.0";
+
+// s3 object metadata keys, sent as x-amz-meta-*
+pub const HASH_METADATA: &str = "nats3-hash";
+pub const SIGNATURE_METADATA: &str = "nats3-signature";
+pub const SIGNATURE_KEY_METADATA: &str = "nats3-signature-key-id";
+
+type BlockDecoder = fn(&[u8], &Codec) -> Result<Chunk>;
+
+// block chunk decoders, keyed by format version. chunk metadata records the
+// version each chunk was written with, so a new format registers a decoder
+// here and chunks written by older versions stay readable.
+const BLOCK_DECODERS: &[(&str, BlockDecoder)] =
+    &[("1.0", decode_block_v1), (VERSION, decode_block_v2)];
+
+#[derive(Error, Debug)]
+pub enum ChunkError {
+    #[error("not a nats3 chunk, missing magic number")]
+    MissingMagicNumber,
+    #[error("unsupported {codec} chunk version '{version}'")]
+    UnsupportedVersion { codec: Codec, version: String },
+    #[error("corrupt {codec} chunk: {reason}")]
+    Corrupt { codec: Codec, reason: String },
+}
+
+impl ChunkError {
+    fn corrupt(codec: &Codec, err: anyhow::Error) -> Self {
+        match err.downcast::<ChunkError>() {
+            Ok(err) => err,
+            Err(err) => ChunkError::Corrupt {
+                codec: codec.clone(),
+                reason: format!("{:#}", err),
+            },
+        }
+    }
+}
+
+// our repr of a NATS message.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Message {
+    // subject to which message is published to.
+    pub subject: String,
+    // payload of the message. Can be any arbitrary data format.
+    pub payload: Bytes,
+    // optional headers.
+    // must use ordered map for determinstic hashing.
+    pub headers: Option<BTreeMap<String, Vec<String>>>,
+    pub length: usize,
+    // time the message was published to the stream
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    // stream sequence
+    pub sequence: u64,
+    // stream the message was consumed from
+    pub stream: String,
+    pub consumer_sequence: u64,
+    // delivery attempts, including the one it was archived from
+    pub delivered: i64,
+}
+
+impl Message {
+    // crc32 of the encoded message, stored with each record by the
+    // streamed codecs so corruption can be pinned to a single message
+    pub fn checksum(&self) -> u32 {
+        let config = bincode::config::legacy();
+        let encoded: Vec<u8> = bincode::serde::encode_to_vec(self, config).unwrap();
+        crc32fast::hash(&encoded)
+    }
+}
+
+impl From<&jetstream::Message> for Message {
+    fn from(source: &jetstream::Message) -> Message {
+        let headers = source.headers.as_ref().map(|h| {
+            h.iter()
+                .map(|(k, values)| {
+                    (
+                        k.to_string(),
+                        values.iter().map(|v| v.to_string()).collect(),
+                    )
+                })
+                .collect()
+        });
+
+        // consumer deliveries carry their metadata in the ack reply subject
+        if let Ok(info) = source.info() {
+            return Message {
+                subject: source.subject.to_string(),
+                payload: source.payload.clone(),
+                headers,
+                length: source.length,
+                timestamp: to_chrono(info.published).unwrap_or_else(chrono::Utc::now),
+                sequence: info.stream_sequence,
+                stream: info.stream.to_string(),
+                consumer_sequence: info.consumer_sequence,
+                delivered: info.delivered,
+            };
+        }
+
+        // messages fetched directly from a stream only have headers
+        let headers_ref = source.headers.as_ref();
+        let timestamp = headers_ref
+            .and_then(|h| h.get_last(header::NATS_TIME_STAMP))
+            .and_then(|ts| OffsetDateTime::parse(ts.as_str(), &Rfc3339).ok())
+            .and_then(to_chrono)
+            .unwrap_or_else(chrono::Utc::now);
+
+        let sequence = headers_ref
+            .and_then(|h| h.get_last(header::NATS_SEQUENCE))
+            .and_then(|seq| seq.as_str().parse::<u64>().ok())
+            .unwrap_or(0);
+
+        Message {
+            subject: source.subject.to_string(),
+            payload: source.payload.clone(),
+            headers,
+            length: source.length,
+            timestamp,
+            sequence,
+            stream: String::new(),
+            consumer_sequence: 0,
+            delivered: 0,
+        }
+    }
+}
+
+fn to_chrono(odt: OffsetDateTime) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::<chrono::Utc>::from_timestamp(odt.unix_timestamp(), odt.nanosecond())
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MessageBlock {
+    pub messages: Vec<Message>,
+    pub timestamp_min: chrono::DateTime<chrono::Utc>,
+    pub timestamp_max: chrono::DateTime<chrono::Utc>,
+    pub bytes_total: usize,
+}
+
+impl MessageBlock {
+    pub fn hash(&self) -> Bytes {
+        let config = bincode::config::legacy();
+        let payload: Vec<u8> = bincode::serde::encode_to_vec(self, config).unwrap();
+        Bytes::from(Sha256::digest(&payload).to_vec())
+    }
+}
+
+impl From<Vec<Message>> for MessageBlock {
+    fn from(messages: Vec<Message>) -> MessageBlock {
+        let timestamp_min = messages
+            .iter()
+            .min_by_key(|m| m.timestamp)
+            .map(|m| m.timestamp)
+            .unwrap_or_else(chrono::Utc::now);
+
+        let timestamp_max = messages
+            .iter()
+            .max_by_key(|m| m.timestamp)
+            .map(|m| m.timestamp)
+            .unwrap_or_else(chrono::Utc::now);
+
+        let bytes_total = messages.iter().map(|m| m.length).sum();
+
+        MessageBlock {
+            messages,
+            timestamp_min,
+            timestamp_max,
+            bytes_total,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chunk {
+    pub block: MessageBlock,
+    magic_number: String,
+    version: String,
+    pub hash: Bytes,
+}
+
+impl Chunk {
+    pub fn serialize(
+        &self,
+        codec: Codec,
+        compression: &Compression,
+        level: Option<i32>,
+    ) -> Result<Vec<u8>> {
+        let data = match codec {
+            Codec::Json => serde_json::to_vec(&self).context("json serialization")?,
+            Codec::Binary => {
+                let config = bincode::config::legacy();
+                bincode::serde::encode_to_vec(self, config).context("binary serialization")?
+            }
+            Codec::Framed | Codec::Jsonl => {
+                bail!("{} chunks are written with a ChunkWriter", codec)
+            }
+            // parquet compresses columns itself
+            Codec::Parquet => return parquet::serialize(self, compression, level),
+        };
+        compress(data, compression, level)
+    }
+    // decode a block chunk with the decoder registered for its version
+    pub fn deserialize(
+        data: &Bytes,
+        codec: Codec,
+        compression: &Compression,
+        version: &str,
+    ) -> Result<Self, ChunkError> {
+        let (_, decoder) = BLOCK_DECODERS
+            .iter()
+            .find(|(v, _)| *v == version)
+            .ok_or_else(|| ChunkError::UnsupportedVersion {
+                codec: codec.clone(),
+                version: version.to_string(),
+            })?;
+        let chunk = match codec {
+            // parquet compresses columns itself
+            Codec::Parquet => decoder(data, &codec),
+            _ => decompress(data, compression).and_then(|data| decoder(&data, &codec)),
+        }
+        .map_err(|e| ChunkError::corrupt(&codec, e))?;
+
+        if chunk.magic_number != MAGIC_NUMBER {
+            return Err(ChunkError::MissingMagicNumber);
+        }
+        if chunk.version != version {
+            return Err(ChunkError::Corrupt {
+                codec,
+                reason: format!(
+                    "chunk version '{}' does not match metadata version '{}'",
+                    chunk.version, version
+                ),
+            });
+        }
+        Ok(chunk)
+    }
+
+    // recompute the block hash as the chunk's format version computes it
+    pub fn content_hash(&self) -> Bytes {
+        match self.version.as_str() {
+            "1.0" => v1::block_hash(&self.block),
+            _ => self.block.hash(),
+        }
+    }
+}
+
+fn decode_block_v1(data: &[u8], codec: &Codec) -> Result<Chunk> {
+    let chunk: v1::Chunk = match codec {
+        Codec::Json => serde_json::from_slice(data).context("json deserialization")?,
+        Codec::Binary => {
+            let config = bincode::config::legacy();
+            let (chunk, _) = bincode::serde::decode_from_slice(data, config)
+                .context("binary deserialization")?;
+            chunk
+        }
+        // parquet columns added since are read as empty
+        Codec::Parquet => return parquet::deserialize(data),
+        Codec::Framed | Codec::Jsonl => {
+            bail!("{} chunks are read with a MessageReader", codec)
+        }
+    };
+    Ok(chunk.into())
+}
+
+fn decode_block_v2(data: &[u8], codec: &Codec) -> Result<Chunk> {
+    match codec {
+        Codec::Json => serde_json::from_slice(data).context("json deserialization"),
+        Codec::Binary => {
+            let config = bincode::config::legacy();
+            let (chunk, _) = bincode::serde::decode_from_slice(data, config)
+                .context("binary deserialization")?;
+            Ok(chunk)
+        }
+        Codec::Parquet => parquet::deserialize(data),
+        Codec::Framed | Codec::Jsonl => {
+            bail!("{} chunks are read with a MessageReader", codec)
+        }
+    }
+}
+
+impl From<MessageBlock> for Chunk {
+    fn from(block: MessageBlock) -> Self {
+        let hash = block.hash();
+        Chunk {
+            magic_number: MAGIC_NUMBER.to_string(),
+            version: VERSION.to_string(),
+            block,
+            hash,
+        }
+    }
+}
+
+// ChunkWriter accumulates messages into a chunk as they are consumed.
+// Block codecs collect messages and serialize once sealed, the framed
+// and jsonl codecs encode (and compress) each message as it is written.
+pub struct ChunkWriter {
+    encoder: Encoder,
+    // distinct subjects of the messages written
+    subjects: BTreeSet<String>,
+}
+
+enum Encoder {
+    Block {
+        messages: Vec<Message>,
+        codec: Codec,
+        compression: Compression,
+        level: Option<i32>,
+    },
+    Framed(Box<framed::FramedWriter<CompressWriter>>),
+    Jsonl(Box<jsonl::JsonlWriter<CompressWriter>>),
+}
+
+impl ChunkWriter {
+    pub fn new(codec: Codec, compression: &Compression, level: Option<i32>) -> Result<Self> {
+        let encoder = match codec {
+            Codec::Framed => {
+                let inner = CompressWriter::new(compression, level)?;
+                Encoder::Framed(Box::new(framed::FramedWriter::new(inner)?))
+            }
+            Codec::Jsonl => {
+                let inner = CompressWriter::new(compression, level)?;
+                Encoder::Jsonl(Box::new(jsonl::JsonlWriter::new(inner)?))
+            }
+            Codec::Json | Codec::Binary | Codec::Parquet => Encoder::Block {
+                messages: Vec::new(),
+                codec,
+                compression: compression.clone(),
+                level,
+            },
+        };
+        Ok(Self {
+            encoder,
+            subjects: BTreeSet::new(),
+        })
+    }
+
+    pub fn write(&mut self, message: Message) -> Result<()> {
+        if !self.subjects.contains(&message.subject) {
+            self.subjects.insert(message.subject.clone());
+        }
+        match &mut self.encoder {
+            Encoder::Block { messages, .. } => {
+                messages.push(message);
+                Ok(())
+            }
+            Encoder::Framed(writer) => writer.write_message(&message),
+            Encoder::Jsonl(writer) => writer.write_message(&message),
+        }
+    }
+
+    // seal the chunk, producing the bytes to upload
+    pub fn finish(self) -> Result<SealedChunk> {
+        let subjects = self.subjects.into_iter().collect();
+        match self.encoder {
+            Encoder::Block {
+                messages,
+                codec,
+                compression,
+                level,
+            } => {
+                let chunk = Chunk::from(MessageBlock::from(messages));
+                let data = Body::from(chunk.serialize(codec, &compression, level)?);
+                let sequences = chunk.block.messages.iter().map(|m| m.sequence);
+                Ok(SealedChunk {
+                    data,
+                    hash: chunk.hash,
+                    version: chunk.version,
+                    timestamp_min: chunk.block.timestamp_min,
+                    timestamp_max: chunk.block.timestamp_max,
+                    message_count: chunk.block.messages.len(),
+                    sequence_min: sequences.clone().min(),
+                    sequence_max: sequences.max(),
+                    subjects,
+                    key_id: None,
+                    signature: None,
+                })
+            }
+            Encoder::Framed(writer) => {
+                let (inner, footer) = writer.finish()?;
+                Ok(SealedChunk {
+                    data: inner.finish()?,
+                    hash: footer.hash,
+                    version: framed::VERSION.to_string(),
+                    timestamp_min: footer.timestamp_min,
+                    timestamp_max: footer.timestamp_max,
+                    message_count: footer.message_count as usize,
+                    sequence_min: footer.sequence_min,
+                    sequence_max: footer.sequence_max,
+                    subjects,
+                    key_id: None,
+                    signature: None,
+                })
+            }
+            Encoder::Jsonl(writer) => {
+                let (inner, trailer) = writer.finish()?;
+                Ok(SealedChunk {
+                    data: inner.finish()?,
+                    hash: trailer.hash_bytes()?,
+                    version: jsonl::VERSION.to_string(),
+                    timestamp_min: trailer.timestamp_min,
+                    timestamp_max: trailer.timestamp_max,
+                    message_count: trailer.message_count as usize,
+                    sequence_min: trailer.sequence_min,
+                    sequence_max: trailer.sequence_max,
+                    subjects,
+                    key_id: None,
+                    signature: None,
+                })
+            }
+        }
+    }
+}
+
+// MessageReader decodes chunks written one message at a time, verifying
+// the chunk once its footer (or trailer) is reached.
+pub enum MessageReader<'a> {
+    Framed(framed::FramedReader<Box<dyn Read + 'a>>),
+    Jsonl(jsonl::JsonlReader<BufReader<Box<dyn Read + 'a>>>),
+}
+
+impl<'a> MessageReader<'a> {
+    pub fn new<R: Read + 'a>(
+        inner: R,
+        codec: Codec,
+        compression: &Compression,
+        version: &str,
+    ) -> Result<Self, ChunkError> {
+        let open = || -> Result<Self> {
+            let inner = decompress_reader(inner, compression)?;
+            let reader = match (&codec, version) {
+                (Codec::Framed, v) if framed::VERSIONS.contains(&v) => {
+                    Self::Framed(framed::FramedReader::new(inner)?)
+                }
+                (Codec::Jsonl, v) if jsonl::VERSIONS.contains(&v) => {
+                    Self::Jsonl(jsonl::JsonlReader::new(BufReader::new(inner))?)
+                }
+                (Codec::Framed | Codec::Jsonl, _) => bail!(ChunkError::UnsupportedVersion {
+                    codec: codec.clone(),
+                    version: version.to_string(),
+                }),
+                (Codec::Json | Codec::Binary | Codec::Parquet, _) => {
+                    bail!("{} chunks are read with Chunk::deserialize", codec)
+                }
+            };
+            Ok(reader)
+        };
+        open().map_err(|e| ChunkError::corrupt(&codec, e))
+    }
+
+    // read the next record, damaged records are returned rather than
+    // failing the read so the rest of the chunk can be recovered
+    pub fn next_record(&mut self) -> Result<Option<Decoded>, ChunkError> {
+        let (codec, result) = match self {
+            Self::Framed(reader) => (Codec::Framed, reader.next_record()),
+            Self::Jsonl(reader) => (Codec::Jsonl, reader.next_record()),
+        };
+        result.map_err(|e| ChunkError::corrupt(&codec, e))
+    }
+
+    // read the next message, any damaged record fails the read
+    pub fn next_message(&mut self) -> Result<Option<Message>, ChunkError> {
+        match self.next_record()? {
+            Some(Decoded::Message(message)) => Ok(Some(message)),
+            Some(Decoded::Damaged { sequence }) => Err(ChunkError::Corrupt {
+                codec: self.codec(),
+                reason: match sequence {
+                    Some(sequence) => format!("message {} failed its checksum", sequence),
+                    None => "message failed its checksum".to_string(),
+                },
+            }),
+            None => Ok(None),
+        }
+    }
+
+    fn codec(&self) -> Codec {
+        match self {
+            Self::Framed(_) => Codec::Framed,
+            Self::Jsonl(_) => Codec::Jsonl,
+        }
+    }
+
+    // the verified chunk hash, available once every message has been read
+    pub fn hash(&self) -> Result<Option<Bytes>> {
+        match self {
+            Self::Framed(reader) => Ok(reader.footer().map(|f| f.hash.clone())),
+            Self::Jsonl(reader) => reader.trailer().map(|t| t.hash_bytes()).transpose(),
+        }
+    }
+}
+
+// a record read from a streamed chunk
+pub enum Decoded {
+    Message(Message),
+    // the record failed its checksum. the sequence is read from the damaged
+    // record itself, so it is best effort and may be missing
+    Damaged { sequence: Option<u64> },
+}
+
+// a serialized chunk ready for upload
+pub struct SealedChunk {
+    // in a temporary file once it outgrows memory, streamed codecs write it as
+    // messages come in
+    pub data: Body,
+    pub hash: Bytes,
+    pub version: String,
+    pub timestamp_min: chrono::DateTime<chrono::Utc>,
+    pub timestamp_max: chrono::DateTime<chrono::Utc>,
+    pub message_count: usize,
+    // lowest and highest stream sequence in the chunk
+    pub sequence_min: Option<u64>,
+    pub sequence_max: Option<u64>,
+    // distinct message subjects in the chunk, sorted
+    pub subjects: Vec<String>,
+    // master key id, set once the chunk has been encrypted
+    pub key_id: Option<String>,
+    // signature over the hash and the signing key id, set once signed
+    pub signature: Option<(Bytes, String)>,
+}
+
+impl SealedChunk {
+    pub fn encrypt(&mut self, keyring: &Keyring) -> Result<()> {
+        let mut envelope = BodyWriter::new(SPILL_BYTES);
+        let key_id = keyring.encrypt(self.data.reader()?, &mut envelope)?;
+        self.data = envelope.finish().context("write encrypted chunk")?;
+        self.key_id = Some(key_id);
+        Ok(())
+    }
+
+    pub fn sign(&mut self, signer: &Signer) {
+        let (signature, key_id) = signer.sign(&self.hash);
+        self.signature = Some((Bytes::from(signature), key_id));
+    }
+
+    // user metadata stored on the s3 object alongside the chunk
+    pub fn object_metadata(&self) -> Vec<(&'static str, String)> {
+        let mut metadata = vec![(HASH_METADATA, hex::encode(&self.hash))];
+        if let Some((signature, key_id)) = &self.signature {
+            metadata.push((SIGNATURE_METADATA, STANDARD.encode(signature)));
+            metadata.push((SIGNATURE_KEY_METADATA, key_id.clone()));
+        }
+        metadata
+    }
+
+    // subject is the job subject, or the route subject of a split batch
+    pub fn to_chunk_metadata(
+        &self,
+        config: &ConsumeConfig,
+        subject: &str,
+        key: &str,
+    ) -> CreateChunkMetadata {
+        CreateChunkMetadata {
+            bucket: config.bucket.clone(),
+            prefix: config.prefix.clone(),
+            key: key.to_string(),
+            stream: config.stream.clone(),
+            consumer: config.chunk_consumer.clone(),
+            subject: subject.to_string(),
+            timestamp_start: self.timestamp_min,
+            timestamp_end: self.timestamp_max,
+            message_count: self.message_count as i64,
+            stream_sequence_start: self.sequence_min.map(|s| s as i64),
+            stream_sequence_end: self.sequence_max.map(|s| s as i64),
+            subjects: self.subjects.clone(),
+            size_bytes: self.data.len() as i64,
+            codec: config.codec.clone(),
+            compression: config.compression.clone(),
+            encryption_key_id: self.key_id.clone(),
+            hash: self.hash.clone(),
+            signature: self.signature.as_ref().map(|(s, _)| s.clone()),
+            signature_key_id: self.signature.as_ref().map(|(_, id)| id.clone()),
+            version: self.version.clone(),
+        }
+    }
+
+    pub fn key(&self, config: &ConsumeConfig, subject: &str) -> ChunkKey {
+        ChunkKey {
+            stream: config.stream.clone(),
+            subject: subject.to_string(),
+            timestamp: self.timestamp_min,
+            message_count: self.message_count,
+            sequence_min: self.sequence_min,
+            sequence_max: self.sequence_max,
+            codec: config.codec.clone(),
+            compression: config.compression.clone(),
+        }
+    }
+}
+
+pub struct ChunkKey {
+    pub stream: String,
+    pub subject: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub message_count: usize,
+    pub sequence_min: Option<u64>,
+    pub sequence_max: Option<u64>,
+    pub codec: Codec,
+    pub compression: Compression,
+}
+
+impl ChunkKey {
+    // render the object key from a template, templates are validated on job
+    // creation so this only fails on templates stored before validation
+    pub fn render(&self, template: &str) -> Result<String> {
+        let mut key = String::new();
+        for part in parse_key_template(template)? {
+            match part {
+                KeyPart::Literal(text) => key.push_str(text),
+                KeyPart::Placeholder(name) => {
+                    let value = match name {
+                        "stream" => self.stream.clone(),
+                        "subject" => self.subject.clone(),
+                        "timestamp" => self.timestamp.timestamp().to_string(),
+                        "count" => self.message_count.to_string(),
+                        "seq_start" => self.sequence_min.unwrap_or_default().to_string(),
+                        "seq_end" => self.sequence_max.unwrap_or_default().to_string(),
+                        "ulid" => Ulid::from_datetime(self.timestamp.into()).to_string(),
+                        "ext" => key_extension(&self.codec, &self.compression),
+                        "YYYY" => self.timestamp.format("%Y").to_string(),
+                        "MM" => self.timestamp.format("%m").to_string(),
+                        "DD" => self.timestamp.format("%d").to_string(),
+                        "HH" => self.timestamp.format("%H").to_string(),
+                        "mm" => self.timestamp.format("%M").to_string(),
+                        name => bail!("unknown key placeholder '{}'", name),
+                    };
+                    key.push_str(&value);
+                }
+            }
+        }
+        Ok(key)
+    }
+}
+
+impl fmt::Display for ChunkKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = self.render(DEFAULT_KEY_TEMPLATE).map_err(|_| fmt::Error)?;
+        f.write_str(&key)
+    }
+}
+
+// object extension for a codec, followed by the compression if any
+pub fn key_extension(codec: &Codec, compression: &Compression) -> String {
+    let ext = codec.to_extension().to_string();
+    // parquet files are compressed internally and keep a plain extension
+    if *codec == Codec::Parquet {
+        return ext;
+    }
+    match compression.to_extension() {
+        Some(compression) => format!("{}.{}", ext, compression),
+        None => ext,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_chunk() -> Chunk {
+        let now = chrono::Utc::now();
+        let messages: Vec<Message> = (0..10)
+            .map(|i| Message {
+                subject: "test.subject".to_string(),
+                payload: Bytes::from(format!("payload-{i}").repeat(20)),
+                headers: None,
+                length: 140,
+                timestamp: now,
+                sequence: i,
+                stream: "test-stream".to_string(),
+                consumer_sequence: i + 1,
+                delivered: 1,
+            })
+            .collect();
+        Chunk::from(MessageBlock {
+            bytes_total: messages.iter().map(|m| m.length).sum(),
+            messages,
+            timestamp_min: now,
+            timestamp_max: now,
+        })
+    }
+
+    #[test]
+    fn test_compression_roundtrip() {
+        let chunk = test_chunk();
+        for codec in [Codec::Json, Codec::Binary] {
+            for compression in [
+                Compression::None,
+                Compression::Zstd,
+                Compression::Gzip,
+                Compression::Lz4,
+            ] {
+                let data = chunk.serialize(codec.clone(), &compression, None).unwrap();
+                let decoded =
+                    Chunk::deserialize(&Bytes::from(data), codec.clone(), &compression, VERSION)
+                        .unwrap();
+                assert_eq!(decoded.hash, chunk.hash);
+                assert_eq!(decoded.block.hash(), chunk.hash);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compression_shrinks_chunk() {
+        let chunk = test_chunk();
+        let raw = chunk
+            .serialize(Codec::Binary, &Compression::None, None)
+            .unwrap();
+        let compressed = chunk
+            .serialize(Codec::Binary, &Compression::Zstd, Some(19))
+            .unwrap();
+        assert!(compressed.len() < raw.len());
+    }
+
+    fn read_streamed(data: &[u8], codec: Codec, compression: &Compression) -> Result<Vec<Message>> {
+        let mut reader = MessageReader::new(data, codec, compression, VERSION)?;
+        let mut messages = Vec::new();
+        while let Some(message) = reader.next_message()? {
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+
+    fn sealed_data(sealed: &SealedChunk) -> Vec<u8> {
+        let mut data = Vec::new();
+        sealed
+            .data
+            .reader()
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        data
+    }
+
+    fn write_streamed(codec: Codec, compression: &Compression) -> SealedChunk {
+        let mut writer = ChunkWriter::new(codec, compression, None).unwrap();
+        for message in test_chunk().block.messages {
+            writer.write(message).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_framed_roundtrip() {
+        for compression in [
+            Compression::None,
+            Compression::Zstd,
+            Compression::Gzip,
+            Compression::Lz4,
+        ] {
+            let sealed = write_streamed(Codec::Framed, &compression);
+            assert_eq!(sealed.message_count, 10);
+
+            let messages =
+                read_streamed(&sealed_data(&sealed), Codec::Framed, &compression).unwrap();
+            assert_eq!(messages.len(), 10);
+            for (i, message) in messages.iter().enumerate() {
+                assert_eq!(message.sequence, i as u64);
+                assert_eq!(
+                    message.payload,
+                    Bytes::from(format!("payload-{i}").repeat(20))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_framed_spills_to_file() {
+        let mut writer = ChunkWriter::new(Codec::Framed, &Compression::None, None).unwrap();
+        let message = test_chunk().block.messages[0].clone();
+        let payload = Bytes::from(vec![7; 64 * 1024]);
+        let count = SPILL_BYTES / payload.len() + 1;
+        for i in 0..count {
+            writer
+                .write(Message {
+                    payload: payload.clone(),
+                    sequence: i as u64,
+                    ..message.clone()
+                })
+                .unwrap();
+        }
+        let sealed = writer.finish().unwrap();
+        assert!(matches!(sealed.data, Body::File { .. }));
+        assert!(sealed.data.len() > SPILL_BYTES as u64);
+
+        let messages =
+            read_streamed(&sealed_data(&sealed), Codec::Framed, &Compression::None).unwrap();
+        assert_eq!(messages.len(), count);
+        assert_eq!(messages[count - 1].payload, payload);
+    }
+
+    #[test]
+    fn test_framed_truncated() {
+        let sealed = write_streamed(Codec::Framed, &Compression::None);
+        let data = sealed_data(&sealed);
+        let truncated = &data[..data.len() - 10];
+        assert!(read_streamed(truncated, Codec::Framed, &Compression::None).is_err());
+    }
+
+    #[test]
+    fn test_framed_corrupt_record() {
+        let sealed = write_streamed(Codec::Framed, &Compression::None);
+        let mut data = sealed_data(&sealed);
+        // flip a bit in the first record payload
+        let idx = MAGIC_NUMBER.len() + 1 + 5 + 40;
+        data[idx] ^= 0x01;
+        assert!(read_streamed(&data, Codec::Framed, &Compression::None).is_err());
+    }
+
+    #[test]
+    fn test_jsonl_roundtrip() {
+        let binary = Message {
+            payload: Bytes::from_static(&[0xff, 0x00, 0xfe]),
+            ..test_chunk().block.messages[0].clone()
+        };
+        for compression in [Compression::None, Compression::Gzip] {
+            let mut writer = ChunkWriter::new(Codec::Jsonl, &compression, None).unwrap();
+            for message in test_chunk().block.messages {
+                writer.write(message).unwrap();
+            }
+            writer.write(binary.clone()).unwrap();
+            let sealed = writer.finish().unwrap();
+            assert_eq!(sealed.message_count, 11);
+
+            let messages =
+                read_streamed(&sealed_data(&sealed), Codec::Jsonl, &compression).unwrap();
+            assert_eq!(messages.len(), 11);
+            assert_eq!(messages[0].payload, Bytes::from("payload-0".repeat(20)));
+            assert_eq!(messages[10].payload, binary.payload);
+        }
+    }
+
+    #[test]
+    fn test_jsonl_readable_lines() {
+        let sealed = write_streamed(Codec::Jsonl, &Compression::None);
+        let text = String::from_utf8(sealed_data(&sealed)).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 12);
+        assert_eq!(lines[0]["type"], "header");
+        assert_eq!(lines[1]["type"], "message");
+        assert_eq!(lines[1]["payload_encoding"], "utf8");
+        assert_eq!(lines[1]["payload"], "payload-0".repeat(20));
+        assert_eq!(lines[11]["type"], "trailer");
+        assert_eq!(lines[11]["hash"], hex::encode(&sealed.hash));
+    }
+
+    #[test]
+    fn test_jsonl_corrupt_line() {
+        let sealed = write_streamed(Codec::Jsonl, &Compression::None);
+        let text = String::from_utf8(sealed_data(&sealed)).unwrap();
+        let tampered = text.replacen("payload-3", "payload-X", 1);
+        assert!(read_streamed(tampered.as_bytes(), Codec::Jsonl, &Compression::None).is_err());
+
+        let truncated = &text.as_bytes()[..text.len() - 20];
+        assert!(read_streamed(truncated, Codec::Jsonl, &Compression::None).is_err());
+    }
+
+    fn read_records(data: &[u8], codec: Codec) -> Vec<Decoded> {
+        let mut reader = MessageReader::new(data, codec, &Compression::None, VERSION).unwrap();
+        let mut records = Vec::new();
+        while let Some(record) = reader.next_record().unwrap() {
+            records.push(record);
+        }
+        records
+    }
+
+    fn damaged_sequences(records: &[Decoded]) -> Vec<Option<u64>> {
+        records
+            .iter()
+            .filter_map(|r| match r {
+                Decoded::Damaged { sequence } => Some(*sequence),
+                Decoded::Message(_) => None,
+            })
+            .collect()
+    }
+
+    // flip a bit inside the payload of message 3
+    fn damage_message_3(data: &mut [u8]) {
+        let needle = b"payload-3";
+        let idx = data
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .unwrap();
+        data[idx + 8] ^= 0x01;
+    }
+
+    #[test]
+    fn test_framed_partial_recovery() {
+        let sealed = write_streamed(Codec::Framed, &Compression::None);
+        let mut data = sealed_data(&sealed);
+        damage_message_3(&mut data);
+
+        assert!(read_streamed(&data, Codec::Framed, &Compression::None).is_err());
+
+        let records = read_records(&data, Codec::Framed);
+        assert_eq!(records.len(), 10);
+        assert_eq!(damaged_sequences(&records), vec![Some(3)]);
+    }
+
+    #[test]
+    fn test_jsonl_partial_recovery() {
+        let sealed = write_streamed(Codec::Jsonl, &Compression::None);
+        let mut data = sealed_data(&sealed);
+        damage_message_3(&mut data);
+
+        assert!(read_streamed(&data, Codec::Jsonl, &Compression::None).is_err());
+
+        let records = read_records(&data, Codec::Jsonl);
+        assert_eq!(records.len(), 10);
+        assert_eq!(damaged_sequences(&records), vec![Some(3)]);
+
+        // a damaged line that is no longer valid json
+        let text = String::from_utf8(sealed_data(&sealed)).unwrap();
+        let broken = text.replacen("\"subject\":\"test.subject\",\"sequence\":5", "{", 1);
+        assert_ne!(broken, text);
+        let records = read_records(broken.as_bytes(), Codec::Jsonl);
+        assert_eq!(damaged_sequences(&records), vec![None]);
+
+        let broken = text.replacen(
+            "\"subject\":\"test.subject\",\"sequence\":5",
+            "\"sequence\":5,{",
+            1,
+        );
+        let records = read_records(broken.as_bytes(), Codec::Jsonl);
+        assert_eq!(damaged_sequences(&records), vec![Some(5)]);
+    }
+
+    #[test]
+    fn test_parquet_roundtrip() {
+        let mut chunk = test_chunk();
+        chunk.block.messages[0].headers = Some(BTreeMap::from([
+            ("a".to_string(), vec!["1".to_string(), "2".to_string()]),
+            ("b".to_string(), vec![]),
+        ]));
+        let chunk = Chunk::from(chunk.block);
+
+        for compression in [
+            Compression::None,
+            Compression::Zstd,
+            Compression::Gzip,
+            Compression::Lz4,
+        ] {
+            let data = chunk.serialize(Codec::Parquet, &compression, None).unwrap();
+            let decoded =
+                Chunk::deserialize(&Bytes::from(data), Codec::Parquet, &compression, VERSION)
+                    .unwrap();
+            assert_eq!(decoded.hash, chunk.hash);
+            assert_eq!(decoded.block.hash(), chunk.hash);
+            assert_eq!(
+                decoded.block.messages[0].headers,
+                chunk.block.messages[0].headers
+            );
+            assert!(decoded.block.messages[1].headers.is_none());
+        }
+    }
+
+    #[test]
+    fn test_delivery_metadata_roundtrip() {
+        for codec in [
+            Codec::Json,
+            Codec::Binary,
+            Codec::Parquet,
+            Codec::Framed,
+            Codec::Jsonl,
+        ] {
+            let sealed = write_streamed(codec.clone(), &Compression::None);
+            assert_eq!(sealed.sequence_min, Some(0), "{codec}");
+            assert_eq!(sealed.sequence_max, Some(9), "{codec}");
+            assert_eq!(sealed.subjects, vec!["test.subject"], "{codec}");
+
+            let messages = match codec {
+                Codec::Framed | Codec::Jsonl => {
+                    read_streamed(&sealed_data(&sealed), codec.clone(), &Compression::None).unwrap()
+                }
+                _ => {
+                    Chunk::deserialize(
+                        &Bytes::from(sealed_data(&sealed)),
+                        codec.clone(),
+                        &Compression::None,
+                        &sealed.version,
+                    )
+                    .unwrap()
+                    .block
+                    .messages
+                }
+            };
+            for (i, message) in messages.iter().enumerate() {
+                assert_eq!(message.stream, "test-stream", "{codec}");
+                assert_eq!(message.consumer_sequence, i as u64 + 1, "{codec}");
+                assert_eq!(message.delivered, 1, "{codec}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let chunk = test_chunk();
+        let data = Bytes::from(
+            chunk
+                .serialize(Codec::Binary, &Compression::None, None)
+                .unwrap(),
+        );
+        let err = Chunk::deserialize(&data, Codec::Binary, &Compression::None, "9.9").unwrap_err();
+        assert!(matches!(err, ChunkError::UnsupportedVersion { .. }));
+
+        let sealed = write_streamed(Codec::Framed, &Compression::None);
+        let err = MessageReader::new(
+            &sealed_data(&sealed)[..],
+            Codec::Framed,
+            &Compression::None,
+            "9.9",
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, ChunkError::UnsupportedVersion { .. }));
+    }
+
+    #[test]
+    fn test_missing_magic_number() {
+        let mut chunk = test_chunk();
+        chunk.magic_number = "NOPE".to_string();
+        let data = Bytes::from(
+            chunk
+                .serialize(Codec::Json, &Compression::None, None)
+                .unwrap(),
+        );
+        let err = Chunk::deserialize(&data, Codec::Json, &Compression::None, VERSION).unwrap_err();
+        assert!(matches!(err, ChunkError::MissingMagicNumber));
+
+        for codec in [Codec::Framed, Codec::Jsonl] {
+            let err = MessageReader::new(&b"not a chunk\n"[..], codec, &Compression::None, VERSION)
+                .err()
+                .unwrap();
+            assert!(matches!(err, ChunkError::MissingMagicNumber));
+        }
+
+        let err = Chunk::deserialize(
+            &Bytes::from_static(b"garbage"),
+            Codec::Binary,
+            &Compression::None,
+            VERSION,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ChunkError::Corrupt { .. }));
+    }
+
+    // fixed chunks written by each format version. a decoder change that
+    // breaks any of these breaks chunks already sitting in buckets.
+    const COMPAT_CORPUS: &[(&str, Codec, Compression, &[u8])] = &[
+        (
+            "1.0",
+            Codec::Json,
+            Compression::None,
+            include_bytes!("testdata/v1.0/chunk.json"),
+        ),
+        (
+            "1.0",
+            Codec::Binary,
+            Compression::Lz4,
+            include_bytes!("testdata/v1.0/chunk.bin.lz4"),
+        ),
+        (
+            "1.0",
+            Codec::Parquet,
+            Compression::Zstd,
+            include_bytes!("testdata/v1.0/chunk.parquet"),
+        ),
+        (
+            "1.0",
+            Codec::Framed,
+            Compression::Zstd,
+            include_bytes!("testdata/v1.0/chunk.framed.zst"),
+        ),
+        (
+            "1.0",
+            Codec::Jsonl,
+            Compression::Gzip,
+            include_bytes!("testdata/v1.0/chunk.jsonl.gz"),
+        ),
+        (
+            "2.0",
+            Codec::Framed,
+            Compression::Zstd,
+            include_bytes!("testdata/v2.0/chunk.framed.zst"),
+        ),
+        (
+            "2.0",
+            Codec::Jsonl,
+            Compression::Gzip,
+            include_bytes!("testdata/v2.0/chunk.jsonl.gz"),
+        ),
+        (
+            "2.0",
+            Codec::Json,
+            Compression::None,
+            include_bytes!("testdata/v2.0/chunk.json"),
+        ),
+        (
+            "2.0",
+            Codec::Binary,
+            Compression::Lz4,
+            include_bytes!("testdata/v2.0/chunk.bin.lz4"),
+        ),
+        (
+            "2.0",
+            Codec::Parquet,
+            Compression::Zstd,
+            include_bytes!("testdata/v2.0/chunk.parquet"),
+        ),
+        (
+            "3.0",
+            Codec::Framed,
+            Compression::Zstd,
+            include_bytes!("testdata/v3.0/chunk.framed.zst"),
+        ),
+        (
+            "3.0",
+            Codec::Jsonl,
+            Compression::Gzip,
+            include_bytes!("testdata/v3.0/chunk.jsonl.gz"),
+        ),
+    ];
+
+    fn compat_messages() -> Vec<Message> {
+        let start = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        (0..5)
+            .map(|i| Message {
+                subject: format!("compat.{i}"),
+                payload: Bytes::from(format!("compat-payload-{i}")),
+                headers: (i % 2 == 0).then(|| {
+                    BTreeMap::from([("Nats-Msg-Id".to_string(), vec![format!("id-{i}")])])
+                }),
+                length: 32,
+                timestamp: start + chrono::Duration::seconds(i),
+                sequence: 100 + i as u64,
+                stream: "compat".to_string(),
+                consumer_sequence: 1 + i as u64,
+                delivered: 1 + i % 2,
+            })
+            .collect()
+    }
+
+    // writes fixtures for the versions currently written, run with
+    // `cargo test -p nats3-server generate_compat_corpus -- --ignored`
+    // only when adding a new format version
+    #[test]
+    #[ignore]
+    fn generate_compat_corpus() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/encoding/testdata");
+        for (version, codec, compression, _) in COMPAT_CORPUS {
+            let mut writer = ChunkWriter::new(codec.clone(), compression, None).unwrap();
+            for message in compat_messages() {
+                writer.write(message).unwrap();
+            }
+            let sealed = writer.finish().unwrap();
+            if &sealed.version != version {
+                continue;
+            }
+
+            let ext = key_extension(codec, compression);
+            let path = dir.join(format!("v{version}/chunk.{ext}"));
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, sealed_data(&sealed)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_compat_corpus() {
+        let expected = compat_messages();
+        for (version, codec, compression, data) in COMPAT_CORPUS {
+            let messages = match codec {
+                Codec::Framed | Codec::Jsonl => {
+                    let mut reader =
+                        MessageReader::new(*data, codec.clone(), compression, version).unwrap();
+                    let mut messages = Vec::new();
+                    while let Some(message) = reader.next_message().unwrap() {
+                        messages.push(message);
+                    }
+                    assert!(reader.hash().unwrap().is_some());
+                    messages
+                }
+                _ => {
+                    let chunk = Chunk::deserialize(
+                        &Bytes::from_static(data),
+                        codec.clone(),
+                        compression,
+                        version,
+                    )
+                    .unwrap();
+                    assert_eq!(chunk.content_hash(), chunk.hash);
+                    chunk.block.messages
+                }
+            };
+
+            let current = match codec {
+                Codec::Framed => framed::VERSION,
+                Codec::Jsonl => jsonl::VERSION,
+                _ => VERSION,
+            };
+            assert_eq!(messages.len(), expected.len(), "{codec} v{version}");
+            for (got, want) in messages.iter().zip(&expected) {
+                assert_eq!(got.subject, want.subject);
+                assert_eq!(got.payload, want.payload);
+                assert_eq!(got.headers, want.headers);
+                assert_eq!(got.length, want.length);
+                assert_eq!(got.timestamp, want.timestamp);
+                assert_eq!(got.sequence, want.sequence);
+                // delivery metadata is only carried by the current versions
+                if *version == current {
+                    assert_eq!(got.stream, want.stream);
+                    assert_eq!(got.consumer_sequence, want.consumer_sequence);
+                    assert_eq!(got.delivered, want.delivered);
+                } else {
+                    assert!(got.stream.is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunk_key_extension() {
+        let key = ChunkKey {
+            stream: "orders".to_string(),
+            subject: "orders.created".to_string(),
+            timestamp: chrono::DateTime::from_timestamp(1700000000, 0).unwrap(),
+            message_count: 10,
+            sequence_min: Some(42),
+            sequence_max: Some(51),
+            codec: Codec::Binary,
+            compression: Compression::Zstd,
+        };
+        let template = "{stream}/{subject}/{timestamp}-{seq_start}.{ext}";
+        assert_eq!(
+            key.render(template).unwrap(),
+            "orders/orders.created/1700000000-42.bin.zst"
+        );
+
+        let key = ChunkKey {
+            compression: Compression::None,
+            ..key
+        };
+        assert_eq!(
+            key.render(template).unwrap(),
+            "orders/orders.created/1700000000-42.bin"
+        );
+
+        let key = ChunkKey {
+            codec: Codec::Parquet,
+            compression: Compression::Zstd,
+            ..key
+        };
+        assert_eq!(
+            key.render(template).unwrap(),
+            "orders/orders.created/1700000000-42.parquet"
+        );
+
+        // the default template keys every chunk apart
+        let rendered = key.to_string();
+        let file = rendered
+            .strip_prefix("orders/orders.created/1700000000-")
+            .unwrap();
+        let (id, ext) = file.split_once('.').unwrap();
+        assert_eq!(ext, "parquet");
+        Ulid::from_string(id).unwrap();
+        assert_ne!(key.to_string(), rendered);
+    }
+
+    #[test]
+    fn test_chunk_key_template() {
+        let key = ChunkKey {
+            stream: "orders".to_string(),
+            subject: "orders.created".to_string(),
+            // 2023-11-14T22:13:20Z
+            timestamp: chrono::DateTime::from_timestamp(1700000000, 0).unwrap(),
+            message_count: 10,
+            sequence_min: Some(42),
+            sequence_max: Some(51),
+            codec: Codec::Jsonl,
+            compression: Compression::Gzip,
+        };
+        let template = "{stream}/year={YYYY}/month={MM}/day={DD}/hour={HH}/{ulid}.{ext}";
+        nats3_types::validate_key_template(template).unwrap();
+        let rendered = key.render(template).unwrap();
+        let (partition, file) = rendered.rsplit_once('/').unwrap();
+        assert_eq!(partition, "orders/year=2023/month=11/day=14/hour=22");
+        let (id, ext) = file.split_once('.').unwrap();
+        assert_eq!(ext, "jsonl.gz");
+        let id = Ulid::from_string(id).unwrap();
+        assert_eq!(id.timestamp_ms(), 1_700_000_000_000);
+
+        assert!(nats3_types::key_template_is_unique(template));
+
+        let template = "{stream}/{subject}/{seq_start}-{seq_end}.{ext}";
+        nats3_types::validate_key_template(template).unwrap();
+        assert!(!nats3_types::key_template_is_unique(template));
+        assert_eq!(
+            key.render(template).unwrap(),
+            "orders/orders.created/42-51.jsonl.gz"
+        );
+
+        for invalid in [
+            "{stream}/{subject}/{count}.{ext}",
+            "{subject}/{seq_start}.{ext}",
+            "{stream}/{subject}/{timestamp}-{count}.{ext}",
+            "{stream}/{unknown}/{ulid}",
+            "{stream}/{ulid",
+            "/{stream}/{ulid}",
+            "{stream}//{ulid}",
+        ] {
+            assert!(
+                nats3_types::validate_key_template(invalid).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,242 @@
+// Parquet chunk codec. Each message is a row, chunk level fields are stored
+// as key/value metadata in the file footer. Compression is applied by parquet
+// per column so the object stays a plain parquet file for query engines.
+
+use ::parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    basic::{Compression as ParquetCompression, GzipLevel, ZstdLevel},
+    file::{metadata::KeyValue, properties::WriterProperties},
+};
+use anyhow::{anyhow, Context, Result};
+use arrow_array::{
+    builder::{
+        BinaryBuilder, Int64Builder, ListBuilder, MapBuilder, StringBuilder,
+        TimestampNanosecondBuilder, UInt64Builder,
+    },
+    cast::AsArray,
+    types::{Int64Type, TimestampNanosecondType, UInt64Type},
+    Array, ArrayRef, RecordBatch,
+};
+use bytes::Bytes;
+use nats3_types::Compression;
+use std::{collections::BTreeMap, sync::Arc};
+
+use super::{Chunk, Message, MessageBlock};
+
+const META_MAGIC_NUMBER: &str = "nats3.magic_number";
+const META_VERSION: &str = "nats3.version";
+const META_HASH: &str = "nats3.hash";
+const META_MESSAGE_COUNT: &str = "nats3.message_count";
+const META_TIMESTAMP_MIN: &str = "nats3.timestamp_min";
+const META_TIMESTAMP_MAX: &str = "nats3.timestamp_max";
+const META_BYTES_TOTAL: &str = "nats3.bytes_total";
+
+pub fn serialize(chunk: &Chunk, compression: &Compression, level: Option<i32>) -> Result<Vec<u8>> {
+    let batch = to_record_batch(&chunk.block.messages)?;
+
+    let metadata = vec![
+        KeyValue::new(META_MAGIC_NUMBER.to_string(), chunk.magic_number.clone()),
+        KeyValue::new(META_VERSION.to_string(), chunk.version.clone()),
+        KeyValue::new(META_HASH.to_string(), hex::encode(&chunk.hash)),
+        KeyValue::new(
+            META_MESSAGE_COUNT.to_string(),
+            chunk.block.messages.len().to_string(),
+        ),
+        KeyValue::new(
+            META_TIMESTAMP_MIN.to_string(),
+            chunk.block.timestamp_min.to_rfc3339(),
+        ),
+        KeyValue::new(
+            META_TIMESTAMP_MAX.to_string(),
+            chunk.block.timestamp_max.to_rfc3339(),
+        ),
+        KeyValue::new(
+            META_BYTES_TOTAL.to_string(),
+            chunk.block.bytes_total.to_string(),
+        ),
+    ];
+    let props = WriterProperties::builder()
+        .set_compression(parquet_compression(compression, level)?)
+        .set_key_value_metadata(Some(metadata))
+        .build();
+
+    let mut writer =
+        ArrowWriter::try_new(Vec::new(), batch.schema(), Some(props)).context("parquet writer")?;
+    writer.write(&batch).context("parquet write")?;
+    writer.into_inner().context("parquet serialization")
+}
+
+pub fn deserialize(data: &[u8]) -> Result<Chunk> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(Bytes::copy_from_slice(data))
+        .context("parquet reader")?;
+
+    let metadata: BTreeMap<String, String> = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .map(|kvs| {
+            kvs.iter()
+                .filter_map(|kv| kv.value.clone().map(|v| (kv.key.clone(), v)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let get = |key: &str| {
+        metadata
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow!("parquet chunk missing metadata '{}'", key))
+    };
+
+    let mut messages = Vec::new();
+    for batch in builder.build().context("parquet reader")? {
+        let batch = batch.context("parquet read batch")?;
+        messages.extend(from_record_batch(&batch)?);
+    }
+
+    Ok(Chunk {
+        block: MessageBlock::from(messages),
+        magic_number: get(META_MAGIC_NUMBER)?,
+        version: get(META_VERSION)?,
+        hash: Bytes::from(hex::decode(get(META_HASH)?).context("parquet chunk hash")?),
+    })
+}
+
+fn parquet_compression(
+    compression: &Compression,
+    level: Option<i32>,
+) -> Result<ParquetCompression> {
+    let out = match compression {
+        Compression::None => ParquetCompression::UNCOMPRESSED,
+        Compression::Zstd => ParquetCompression::ZSTD(match level {
+            Some(level) => ZstdLevel::try_new(level)?,
+            None => ZstdLevel::default(),
+        }),
+        Compression::Gzip => ParquetCompression::GZIP(match level {
+            Some(level) => GzipLevel::try_new(level as u32)?,
+            None => GzipLevel::default(),
+        }),
+        Compression::Lz4 => ParquetCompression::LZ4_RAW,
+    };
+    Ok(out)
+}
+
+fn to_record_batch(messages: &[Message]) -> Result<RecordBatch> {
+    let mut subject = StringBuilder::new();
+    let mut sequence = UInt64Builder::new();
+    let mut timestamp = TimestampNanosecondBuilder::new();
+    let mut headers = MapBuilder::new(
+        None,
+        StringBuilder::new(),
+        ListBuilder::new(StringBuilder::new()),
+    );
+    let mut payload = BinaryBuilder::new();
+    let mut length = UInt64Builder::new();
+    let mut stream = StringBuilder::new();
+    let mut consumer_sequence = UInt64Builder::new();
+    let mut delivered = Int64Builder::new();
+
+    for message in messages {
+        subject.append_value(&message.subject);
+        sequence.append_value(message.sequence);
+        timestamp.append_value(
+            message
+                .timestamp
+                .timestamp_nanos_opt()
+                .context("message timestamp out of range")?,
+        );
+        match &message.headers {
+            Some(map) => {
+                for (key, values) in map {
+                    headers.keys().append_value(key);
+                    for value in values {
+                        headers.values().values().append_value(value);
+                    }
+                    headers.values().append(true);
+                }
+                headers.append(true)?;
+            }
+            None => headers.append(false)?,
+        }
+        payload.append_value(&message.payload);
+        length.append_value(message.length as u64);
+        stream.append_value(&message.stream);
+        consumer_sequence.append_value(message.consumer_sequence);
+        delivered.append_value(message.delivered);
+    }
+
+    let batch = RecordBatch::try_from_iter_with_nullable(vec![
+        ("subject", Arc::new(subject.finish()) as ArrayRef, false),
+        ("sequence", Arc::new(sequence.finish()) as ArrayRef, false),
+        (
+            "timestamp",
+            Arc::new(timestamp.finish().with_timezone("UTC")) as ArrayRef,
+            false,
+        ),
+        ("headers", Arc::new(headers.finish()) as ArrayRef, true),
+        ("payload", Arc::new(payload.finish()) as ArrayRef, false),
+        ("length", Arc::new(length.finish()) as ArrayRef, false),
+        ("stream", Arc::new(stream.finish()) as ArrayRef, false),
+        (
+            "consumer_sequence",
+            Arc::new(consumer_sequence.finish()) as ArrayRef,
+            false,
+        ),
+        ("delivered", Arc::new(delivered.finish()) as ArrayRef, false),
+    ])?;
+    Ok(batch)
+}
+
+fn from_record_batch(batch: &RecordBatch) -> Result<Vec<Message>> {
+    let column = |name: &str| {
+        batch
+            .column_by_name(name)
+            .ok_or_else(|| anyhow!("parquet chunk missing column '{}'", name))
+    };
+    let subject = column("subject")?.as_string::<i32>();
+    let sequence = column("sequence")?.as_primitive::<UInt64Type>();
+    let timestamp = column("timestamp")?.as_primitive::<TimestampNanosecondType>();
+    let headers = column("headers")?.as_map();
+    let payload = column("payload")?.as_binary::<i32>();
+    let length = column("length")?.as_primitive::<UInt64Type>();
+    // delivery metadata columns are missing from version 1.0 chunks
+    let stream = batch.column_by_name("stream").map(|c| c.as_string::<i32>());
+    let consumer_sequence = batch
+        .column_by_name("consumer_sequence")
+        .map(|c| c.as_primitive::<UInt64Type>());
+    let delivered = batch
+        .column_by_name("delivered")
+        .map(|c| c.as_primitive::<Int64Type>());
+
+    let mut messages = Vec::with_capacity(batch.num_rows());
+    for i in 0..batch.num_rows() {
+        let message_headers = if headers.is_null(i) {
+            None
+        } else {
+            let entries = headers.value(i);
+            let keys = entries.column(0).as_string::<i32>();
+            let values = entries.column(1).as_list::<i32>();
+            let map = (0..entries.len())
+                .map(|j| {
+                    let list = values.value(j);
+                    let list = list.as_string::<i32>();
+                    let values = list.iter().flatten().map(str::to_string).collect();
+                    (keys.value(j).to_string(), values)
+                })
+                .collect();
+            Some(map)
+        };
+
+        messages.push(Message {
+            subject: subject.value(i).to_string(),
+            payload: Bytes::copy_from_slice(payload.value(i)),
+            headers: message_headers,
+            length: length.value(i) as usize,
+            timestamp: chrono::DateTime::from_timestamp_nanos(timestamp.value(i)),
+            sequence: sequence.value(i),
+            stream: stream.map(|c| c.value(i).to_string()).unwrap_or_default(),
+            consumer_sequence: consumer_sequence.map(|c| c.value(i)).unwrap_or_default(),
+            delivered: delivered.map(|c| c.value(i)).unwrap_or_default(),
+        });
+    }
+    Ok(messages)
+}
@@ -0,0 +1,125 @@
+// Message layout of chunks written before delivery metadata was captured,
+// block chunks at version 1.0 and framed or jsonl chunks before 3.0.
+//
+// Old chunks are decoded with these types and converted, leaving the stream,
+// consumer sequence and delivery count empty. Hashes and checksums of old
+// chunks were computed over this layout, so they are recomputed with it too.
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Message {
+    pub subject: String,
+    pub payload: Bytes,
+    pub headers: Option<BTreeMap<String, Vec<String>>>,
+    pub length: usize,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub sequence: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MessageBlock {
+    pub messages: Vec<Message>,
+    pub timestamp_min: chrono::DateTime<chrono::Utc>,
+    pub timestamp_max: chrono::DateTime<chrono::Utc>,
+    pub bytes_total: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Chunk {
+    pub block: MessageBlock,
+    pub magic_number: String,
+    pub version: String,
+    pub hash: Bytes,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Footer {
+    pub message_count: u64,
+    pub bytes_total: u64,
+    pub timestamp_min: chrono::DateTime<chrono::Utc>,
+    pub timestamp_max: chrono::DateTime<chrono::Utc>,
+    pub hash: Bytes,
+}
+
+impl From<Message> for super::Message {
+    fn from(message: Message) -> Self {
+        super::Message {
+            subject: message.subject,
+            payload: message.payload,
+            headers: message.headers,
+            length: message.length,
+            timestamp: message.timestamp,
+            sequence: message.sequence,
+            stream: String::new(),
+            consumer_sequence: 0,
+            delivered: 0,
+        }
+    }
+}
+
+impl From<&super::Message> for Message {
+    fn from(message: &super::Message) -> Self {
+        Message {
+            subject: message.subject.clone(),
+            payload: message.payload.clone(),
+            headers: message.headers.clone(),
+            length: message.length,
+            timestamp: message.timestamp,
+            sequence: message.sequence,
+        }
+    }
+}
+
+impl From<Chunk> for super::Chunk {
+    fn from(chunk: Chunk) -> Self {
+        super::Chunk {
+            block: super::MessageBlock {
+                messages: chunk.block.messages.into_iter().map(Into::into).collect(),
+                timestamp_min: chunk.block.timestamp_min,
+                timestamp_max: chunk.block.timestamp_max,
+                bytes_total: chunk.block.bytes_total,
+            },
+            magic_number: chunk.magic_number,
+            version: chunk.version,
+            hash: chunk.hash,
+        }
+    }
+}
+
+impl From<Footer> for super::framed::Footer {
+    fn from(footer: Footer) -> Self {
+        super::framed::Footer {
+            message_count: footer.message_count,
+            bytes_total: footer.bytes_total,
+            timestamp_min: footer.timestamp_min,
+            timestamp_max: footer.timestamp_max,
+            sequence_min: None,
+            sequence_max: None,
+            hash: footer.hash,
+        }
+    }
+}
+
+// block hash as computed by version 1.0 block chunks
+pub fn block_hash(block: &super::MessageBlock) -> Bytes {
+    let block = MessageBlock {
+        messages: block.messages.iter().map(Into::into).collect(),
+        timestamp_min: block.timestamp_min,
+        timestamp_max: block.timestamp_max,
+        bytes_total: block.bytes_total,
+    };
+    let config = bincode::config::legacy();
+    let payload: Vec<u8> = bincode::serde::encode_to_vec(&block, config).unwrap();
+    Bytes::from(Sha256::digest(&payload).to_vec())
+}
+
+// message crc32 as computed by version 2.0 jsonl chunks
+pub fn checksum(message: &super::Message) -> u32 {
+    let config = bincode::config::legacy();
+    let encoded: Vec<u8> = bincode::serde::encode_to_vec(Message::from(message), config).unwrap();
+    crc32fast::hash(&encoded)
+}
@@ -0,0 +1,327 @@
+// Envelope encryption of serialized chunks.
+//
+// Each chunk is encrypted with a fresh AES-256-GCM data key, which is itself
+// encrypted (wrapped) by a master key from the server config. Old master keys
+// stay in the keyring to decrypt chunks written before a rotation.
+//
+// envelope: magic "NATS3E" | version (u8) | key id len (u8) | key id
+//           | key nonce (12) | wrapped data key (48) | nonce prefix (7) | segments
+//
+// The chunk is encrypted in segments of 64 KiB so it is never held in memory
+// whole. Each segment is sealed on its own with the nonce prefix, its index
+// (u32 BE) and a last segment flag (u8), so segments can't be reordered and a
+// chunk cut short at a segment boundary fails to decrypt. Version 1 envelopes
+// hold a data nonce (12) and the chunk sealed in one piece.
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use crate::config;
+
+const ENVELOPE_MAGIC: &[u8] = b"NATS3E";
+const ENVELOPE_VERSION: u8 = 2;
+const KEY_BYTES: usize = 32;
+const NONCE_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+const WRAPPED_KEY_BYTES: usize = KEY_BYTES + TAG_BYTES;
+const NONCE_PREFIX_BYTES: usize = 7;
+const SEGMENT_BYTES: usize = 64 * 1024;
+
+pub struct Keyring {
+    active: String,
+    keys: HashMap<String, Aes256Gcm>,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyring")
+            .field("active", &self.active)
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Keyring {
+    pub fn from_config(config: &config::Encryption) -> Result<Self> {
+        let mut keys = HashMap::new();
+        for key in &config.keys {
+            if key.id.is_empty() || key.id.len() > u8::MAX as usize {
+                bail!("master key id '{}' must be 1-255 bytes", key.id);
+            }
+            let material = config::read_key(&key.id, key.key.as_ref(), key.key_file.as_ref())?;
+            if material.len() != KEY_BYTES {
+                bail!("master key '{}' must be {} bytes", key.id, KEY_BYTES);
+            }
+            let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&material));
+            if keys.insert(key.id.clone(), cipher).is_some() {
+                bail!("duplicate master key id '{}'", key.id);
+            }
+        }
+        if !keys.contains_key(&config.active_key) {
+            bail!("active master key '{}' not configured", config.active_key);
+        }
+        Ok(Self {
+            active: config.active_key.clone(),
+            keys,
+        })
+    }
+
+    // encrypt with the active master key, writing the envelope as the
+    // plaintext is read. returns the key id
+    pub fn encrypt<R: Read, W: Write>(&self, mut plaintext: R, mut out: W) -> Result<String> {
+        let master = &self.keys[&self.active];
+
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let key_nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let wrapped = master
+            .encrypt(&key_nonce, data_key.as_slice())
+            .map_err(|_| anyhow!("wrap data key"))?;
+        let mut nonce_prefix = [0; NONCE_PREFIX_BYTES];
+        nonce_prefix.copy_from_slice(&Aes256Gcm::generate_nonce(&mut OsRng)[..NONCE_PREFIX_BYTES]);
+
+        out.write_all(ENVELOPE_MAGIC)?;
+        out.write_all(&[ENVELOPE_VERSION, self.active.len() as u8])?;
+        out.write_all(self.active.as_bytes())?;
+        out.write_all(&key_nonce)?;
+        out.write_all(&wrapped)?;
+        out.write_all(&nonce_prefix)?;
+
+        // a segment is the last one once the next read comes back empty
+        let cipher = Aes256Gcm::new(&data_key);
+        let mut segment = vec![0; SEGMENT_BYTES];
+        let mut next = vec![0; SEGMENT_BYTES];
+        let mut len = read_full(&mut plaintext, &mut segment)?;
+        for index in 0u32.. {
+            let next_len = if len == SEGMENT_BYTES {
+                read_full(&mut plaintext, &mut next)?
+            } else {
+                0
+            };
+            let last = next_len == 0;
+            let ciphertext = cipher
+                .encrypt(&segment_nonce(&nonce_prefix, index, last), &segment[..len])
+                .map_err(|_| anyhow!("encrypt chunk"))?;
+            out.write_all(&ciphertext)?;
+            if last {
+                return Ok(self.active.clone());
+            }
+            std::mem::swap(&mut segment, &mut next);
+            len = next_len;
+        }
+        bail!("chunk too large to encrypt")
+    }
+
+    // decrypt an envelope with whichever master key it was written with
+    pub fn decrypt(&self, envelope: &[u8]) -> Result<Vec<u8>> {
+        let mut rest = envelope
+            .strip_prefix(ENVELOPE_MAGIC)
+            .ok_or_else(|| anyhow!("chunk is not an encryption envelope"))?;
+        let version = take(&mut rest, 1)?[0];
+        if version != 1 && version != ENVELOPE_VERSION {
+            bail!("unsupported encryption envelope version {}", version);
+        }
+        let id_len = take(&mut rest, 1)?[0] as usize;
+        let key_id = std::str::from_utf8(take(&mut rest, id_len)?).context("envelope key id")?;
+        let key_nonce = Nonce::from_slice(take(&mut rest, NONCE_BYTES)?);
+        let wrapped = take(&mut rest, WRAPPED_KEY_BYTES)?;
+
+        let master = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| anyhow!("master key '{}' not configured", key_id))?;
+        let data_key = master
+            .decrypt(key_nonce, wrapped)
+            .map_err(|_| anyhow!("unwrap data key with master key '{}'", key_id))?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key));
+
+        if version == 1 {
+            let data_nonce = Nonce::from_slice(take(&mut rest, NONCE_BYTES)?);
+            return cipher
+                .decrypt(data_nonce, rest)
+                .map_err(|_| anyhow!("decrypt chunk, data corrupt or tampered"));
+        }
+
+        let nonce_prefix = take(&mut rest, NONCE_PREFIX_BYTES)?;
+        if rest.is_empty() {
+            bail!("encryption envelope truncated");
+        }
+        let segments = rest.chunks(SEGMENT_BYTES + TAG_BYTES);
+        let count = segments.len();
+        let mut plaintext = Vec::with_capacity(rest.len());
+        for (index, segment) in segments.enumerate() {
+            let nonce = segment_nonce(nonce_prefix, index as u32, index + 1 == count);
+            let data = cipher
+                .decrypt(&nonce, segment)
+                .map_err(|_| anyhow!("decrypt chunk, data corrupt or tampered"))?;
+            plaintext.extend_from_slice(&data);
+        }
+        Ok(plaintext)
+    }
+}
+
+fn segment_nonce(
+    prefix: &[u8],
+    index: u32,
+    last: bool,
+) -> Nonce<<Aes256Gcm as AeadCore>::NonceSize> {
+    let mut nonce = [0; NONCE_BYTES];
+    nonce[..NONCE_PREFIX_BYTES].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_BYTES..NONCE_BYTES - 1].copy_from_slice(&index.to_be_bytes());
+    nonce[NONCE_BYTES - 1] = last as u8;
+    nonce.into()
+}
+
+// fill the buffer unless the reader ends first, returning the bytes read
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+fn take<'a>(data: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if data.len() < n {
+        bail!("encryption envelope truncated");
+    }
+    let (head, tail) = data.split_at(n);
+    *data = tail;
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    fn master_key(id: &str, byte: u8) -> config::MasterKey {
+        config::MasterKey {
+            id: id.to_string(),
+            key: Some(STANDARD.encode([byte; KEY_BYTES])),
+            key_file: None,
+        }
+    }
+
+    fn encrypt(keyring: &Keyring, plaintext: &[u8]) -> (Vec<u8>, String) {
+        let mut envelope = Vec::new();
+        let key_id = keyring.encrypt(plaintext, &mut envelope).unwrap();
+        (envelope, key_id)
+    }
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let keyring = Keyring::from_config(&config::Encryption {
+            active_key: "k1".to_string(),
+            keys: vec![master_key("k1", 1)],
+        })
+        .unwrap();
+
+        let (envelope, key_id) = encrypt(&keyring, b"chunk data");
+        assert_eq!(key_id, "k1");
+        assert_eq!(keyring.decrypt(&envelope).unwrap(), b"chunk data");
+
+        let mut tampered = envelope.clone();
+        *tampered.last_mut().unwrap() ^= 0x01;
+        assert!(keyring.decrypt(&tampered).is_err());
+        assert!(keyring.decrypt(&envelope[..envelope.len() - 20]).is_err());
+
+        let (envelope, _) = encrypt(&keyring, b"");
+        assert_eq!(keyring.decrypt(&envelope).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_encrypt_segments() {
+        let keyring = Keyring::from_config(&config::Encryption {
+            active_key: "k1".to_string(),
+            keys: vec![master_key("k1", 1)],
+        })
+        .unwrap();
+
+        for len in [SEGMENT_BYTES, 2 * SEGMENT_BYTES + 10] {
+            let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let (envelope, _) = encrypt(&keyring, &plaintext);
+            assert_eq!(keyring.decrypt(&envelope).unwrap(), plaintext);
+        }
+
+        // cut at a segment boundary, the new last segment was not sealed as one
+        let plaintext = vec![1; 2 * SEGMENT_BYTES + 10];
+        let (envelope, _) = encrypt(&keyring, &plaintext);
+        assert!(keyring.decrypt(&envelope[..envelope.len() - 26]).is_err());
+
+        // segments swapped
+        let header = envelope.len() - plaintext.len() - 3 * TAG_BYTES;
+        let segment = SEGMENT_BYTES + TAG_BYTES;
+        let mut swapped = envelope[..header].to_vec();
+        swapped.extend_from_slice(&envelope[header + segment..header + 2 * segment]);
+        swapped.extend_from_slice(&envelope[header..header + segment]);
+        swapped.extend_from_slice(&envelope[header + 2 * segment..]);
+        assert!(keyring.decrypt(&swapped).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_version_1() {
+        let keyring = Keyring::from_config(&config::Encryption {
+            active_key: "k1".to_string(),
+            keys: vec![master_key("k1", 1)],
+        })
+        .unwrap();
+
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let key_nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let wrapped = keyring.keys["k1"]
+            .encrypt(&key_nonce, data_key.as_slice())
+            .unwrap();
+        let data_nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(&data_key)
+            .encrypt(&data_nonce, b"old envelope".as_ref())
+            .unwrap();
+
+        let mut envelope = ENVELOPE_MAGIC.to_vec();
+        envelope.extend_from_slice(&[1, 2]);
+        envelope.extend_from_slice(b"k1");
+        envelope.extend_from_slice(&key_nonce);
+        envelope.extend_from_slice(&wrapped);
+        envelope.extend_from_slice(&data_nonce);
+        envelope.extend_from_slice(&ciphertext);
+        assert_eq!(keyring.decrypt(&envelope).unwrap(), b"old envelope");
+    }
+
+    #[test]
+    fn test_rotated_key_decrypts_old_chunks() {
+        let old = Keyring::from_config(&config::Encryption {
+            active_key: "k1".to_string(),
+            keys: vec![master_key("k1", 1)],
+        })
+        .unwrap();
+        let (envelope, _) = encrypt(&old, b"old chunk");
+
+        let rotated = Keyring::from_config(&config::Encryption {
+            active_key: "k2".to_string(),
+            keys: vec![master_key("k1", 1), master_key("k2", 2)],
+        })
+        .unwrap();
+        assert_eq!(rotated.decrypt(&envelope).unwrap(), b"old chunk");
+        let (_, key_id) = encrypt(&rotated, b"new chunk");
+        assert_eq!(key_id, "k2");
+
+        let retired = Keyring::from_config(&config::Encryption {
+            active_key: "k2".to_string(),
+            keys: vec![master_key("k2", 2)],
+        })
+        .unwrap();
+        assert!(retired.decrypt(&envelope).is_err());
+    }
+}
@@ -1,14 +1,18 @@
 use nats3_types::ValidationError;
 use thiserror::Error;
 
-use crate::{db, registry};
+use crate::{db, nats, registry};
 
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("job store error: {0}")]
     JobStore(#[from] db::JobStoreError),
+    #[error("chunk store error: {0}")]
+    ChunkStore(#[from] db::ChunkMetadataError),
     #[error("job registry error: {0}")]
     JobRegistry(#[from] registry::RegistryError),
+    #[error("consumer error: {0}")]
+    Consumer(#[from] nats::ConsumerError),
     #[error("config validation error: {0}")]
     Validation(#[from] ValidationError),
 }
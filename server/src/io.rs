@@ -1,38 +1,92 @@
-use anyhow::Result;
-use async_nats::jetstream;
+use anyhow::{anyhow, bail, Context, Result};
+use async_nats::jetstream::{self, message::Acker, object_store::GetErrorKind};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use futures::StreamExt;
-use nats3_types::Codec;
-use std::sync::Arc;
+use futures::{stream::FuturesOrdered, Future, StreamExt};
+use nats3_types::{
+    AckPolicy, ChunkVerification, Codec, Compression, Retry, ValidationError, VerificationStatus,
+};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 use tokio::{
     sync::{mpsc, RwLock},
     time,
 };
-use tokio_util::sync::CancellationToken;
+use tokio_util::{
+    io::{StreamReader, SyncIoBridge},
+    sync::CancellationToken,
+};
 use tracing::{debug, trace, warn};
 
-use nats3_types::{LoadJob, StoreJob};
+use nats3_types::{Deliver, KvJob, LoadJob, ObjectJob, StoreJob, DEFAULT_KEY_TEMPLATE};
+
+use crate::{
+    backup, body::Body, db, encoding, encryption, kv, metrics, nats, object, registry, retry, s3,
+    signing, spool,
+};
 
-use crate::{db, encoding, metrics, nats, registry, s3};
+// nats server ack wait of consumers that do not set one
+const DEFAULT_ACK_WAIT: time::Duration = time::Duration::from_secs(30);
+// progress acks sent at once by the keep alive
+const PROGRESS_ACKS_IN_FLIGHT: usize = 64;
+const STREAM_DECODE_BUFFER: usize = 64;
+// sealed batches of a store job uploading at once
+const UPLOADS_IN_FLIGHT: usize = 2;
 
-const KEEP_ALIVE_INTERVAL: time::Duration = time::Duration::from_secs(10);
-const DEFAULT_BATCH_WAIT: time::Duration = time::Duration::from_secs(10);
+// messages a store job may hold unacked, the open batch filling while every
+// sealed batch uploads
+pub fn max_ack_pending(messages_max: i64) -> i64 {
+    (UPLOADS_IN_FLIGHT as i64 + 1) * messages_max
+}
 
 #[derive(Debug, Clone)]
 pub struct ConsumeConfig {
     pub stream: String,
     pub consumer: Option<String>,
+    // consumer recorded with the chunks, the bound one or a backup's own so a
+    // restore reads only the chunks of that backup
+    pub chunk_consumer: Option<String>,
     pub subject: String,
+    // every filter subject of the job, subject first
+    pub subjects: Vec<String>,
     pub bucket: String,
     pub prefix: Option<String>,
     pub bytes_max: i64,
     pub messages_max: i64,
+    pub max_age: time::Duration,
     pub codec: Codec,
+    pub compression: Compression,
+    pub compression_level: Option<i32>,
+    pub encrypt: bool,
+    pub key_template: String,
+    pub deliver: Deliver,
+    pub split_by_subject: bool,
+    pub subject_depth: Option<u32>,
+    pub ack_policy: AckPolicy,
+    // record the stream config with the chunks
+    pub backup: bool,
+    // sealed chunks may go through the disk spool, when one is configured
+    pub spool: bool,
+    // the server retry policy is used when unset
+    pub retry: Option<Retry>,
 }
 
 impl From<StoreJob> for ConsumeConfig {
     fn from(job: StoreJob) -> Self {
+        let chunk_consumer = match &job.consumer {
+            Some(consumer) => Some(consumer.clone()),
+            None if job.backup => Some(nats::consumer_name(&job.id)),
+            None => None,
+        };
         Self {
+            chunk_consumer,
+            subjects: job.filter_subjects(),
             stream: job.stream,
             consumer: job.consumer,
             subject: job.subject,
@@ -40,7 +94,53 @@ impl From<StoreJob> for ConsumeConfig {
             prefix: job.prefix,
             bytes_max: job.batch.max_bytes,
             messages_max: job.batch.max_count,
+            max_age: job.batch.max_age,
             codec: job.encoding.codec,
+            compression: job.encoding.compression,
+            compression_level: job.encoding.compression_level,
+            encrypt: job.encoding.encrypt,
+            key_template: job
+                .key_template
+                .unwrap_or_else(|| DEFAULT_KEY_TEMPLATE.to_string()),
+            deliver: job.deliver,
+            split_by_subject: job.split_by_subject,
+            subject_depth: job.subject_depth,
+            ack_policy: job.ack_policy,
+            backup: job.backup,
+            spool: true,
+            retry: job.retry,
+        }
+    }
+}
+
+// an archive writes its bucket in batch sized chunks keyed under the job id.
+// chunks are uploaded before the job completes, not spooled.
+impl From<&KvJob> for ConsumeConfig {
+    fn from(job: &KvJob) -> Self {
+        let batch = nats3_types::Batch::default();
+        Self {
+            stream: kv::stream_name(&job.kv_bucket),
+            consumer: Some(kv::archive_consumer(&job.id)),
+            chunk_consumer: Some(kv::archive_consumer(&job.id)),
+            subject: kv::filter_subject(&job.kv_bucket),
+            subjects: vec![kv::filter_subject(&job.kv_bucket)],
+            bucket: job.bucket.clone(),
+            prefix: job.prefix.clone(),
+            bytes_max: batch.max_bytes,
+            messages_max: batch.max_count,
+            max_age: batch.max_age,
+            codec: job.encoding.codec.clone(),
+            compression: job.encoding.compression.clone(),
+            compression_level: job.encoding.compression_level,
+            encrypt: job.encoding.encrypt,
+            key_template: format!("{{stream}}/{}/{{timestamp}}-{{seq_start}}.{{ext}}", job.id),
+            deliver: Deliver::default(),
+            split_by_subject: false,
+            subject_depth: None,
+            ack_policy: AckPolicy::Explicit,
+            backup: false,
+            spool: false,
+            retry: job.retry.clone(),
         }
     }
 }
@@ -55,8 +155,13 @@ pub struct PublishConfig {
     pub prefix: Option<String>,
     pub poll_interval: Option<time::Duration>,
     pub delete_chunks: bool,
+    pub partial_recovery: bool,
     pub from_time: Option<DateTime<Utc>>,
     pub to_time: Option<DateTime<Utc>>,
+    // backup job to recreate the stream from, replaying to original subjects
+    pub backup_id: Option<String>,
+    pub restore_stream: Option<String>,
+    pub retry: Option<Retry>,
 }
 
 impl From<LoadJob> for PublishConfig {
@@ -70,8 +175,12 @@ impl From<LoadJob> for PublishConfig {
             prefix: job.prefix,
             poll_interval: job.poll_interval,
             delete_chunks: job.delete_chunks,
+            partial_recovery: job.partial_recovery,
             from_time: job.from_time,
             to_time: job.to_time,
+            backup_id: job.backup_id,
+            restore_stream: job.restore_stream,
+            retry: job.retry,
         }
     }
 }
@@ -83,14 +192,24 @@ pub struct IO {
     pub s3_client: s3::Client,
     pub nats_client: nats::Client,
     pub chunk_db: db::DynChunkStorer,
+    pub keyring: Option<Arc<encryption::Keyring>>,
+    pub signer: Option<Arc<signing::Signer>>,
+    pub spool: Option<spool::Spool>,
+    // retry policy of jobs that don't set their own
+    pub retry: Retry,
 }
 
 impl IO {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         metrics: metrics::Metrics,
         s3_client: s3::Client,
         nats_client: nats::Client,
         chunk_db: db::DynChunkStorer,
+        keyring: Option<Arc<encryption::Keyring>>,
+        signer: Option<Arc<signing::Signer>>,
+        spool: Option<spool::Spool>,
+        retry: Retry,
     ) -> IO {
         debug!("create new IO instance");
 
@@ -99,8 +218,21 @@ impl IO {
             s3_client,
             nats_client,
             chunk_db,
+            keyring,
+            signer,
+            spool,
+            retry,
         }
     }
+
+    pub fn encryption_enabled(&self) -> bool {
+        self.keyring.is_some()
+    }
+
+    fn retry_policy<'a>(&'a self, job_retry: Option<&'a Retry>) -> &'a Retry {
+        job_retry.unwrap_or(&self.retry)
+    }
+
     pub async fn consume_stream(
         &self,
         job_id: String,
@@ -116,62 +248,150 @@ impl IO {
             bucket = config.bucket,
             prefix = config.prefix,
             codec = config.codec.to_string(),
+            compression = config.compression.to_string(),
             "consume stream and upload to bucket"
         );
 
-        let buffer = MessageBuffer::new(cancel_token.clone(), pause_token.clone());
-        buffer.keep_alive(KEEP_ALIVE_INTERVAL);
+        if config.encrypt && !self.encryption_enabled() {
+            bail!(ValidationError::EncryptionNotConfigured);
+        }
+
+        let retry = self.retry_policy(config.retry.as_ref());
+
+        if config.backup {
+            self.record_stream_config(&config, retry).await?;
+        }
+
+        // messages at or below the checkpoint are already archived
+        let checkpoint = retry::retry(retry, metrics::SERVICE_POSTGRES, &self.metrics, || {
+            self.chunk_db.get_checkpoint(&job_id)
+        })
+        .await?;
+        if let Some(checkpoint) = checkpoint {
+            debug!(checkpoint = checkpoint, "resume store job from checkpoint");
+        }
+        let mut watermark = Watermark::new(checkpoint.map(|checkpoint| checkpoint as u64));
+
+        let buffer = MessageBuffer::new(
+            config.ack_policy,
+            self.metrics.clone(),
+            cancel_token.clone(),
+            pause_token.clone(),
+        );
+        let mut writer = ChunkRouter::default();
 
         let mut bytes_total = 0;
-        let mut messages = self
-            .nats_client
-            .consume(
-                config.stream.clone(),
-                config.subject.clone(),
-                config.messages_max,
-            )
-            .await?;
+        let consumer = nats::store_job_consumer(&job_id, config.consumer.as_ref());
+        let (mut messages, ack_wait) = match &config.consumer {
+            Some(consumer) => {
+                self.nats_client
+                    .bind(
+                        config.stream.clone(),
+                        consumer.clone(),
+                        &config.subjects,
+                        config.ack_policy,
+                        max_ack_pending(config.messages_max),
+                    )
+                    .await?
+            }
+            None => {
+                self.nats_client
+                    .consume(
+                        config.stream.clone(),
+                        consumer.clone(),
+                        &config.subjects,
+                        &config.deliver,
+                        config.ack_policy,
+                        max_ack_pending(config.messages_max),
+                    )
+                    .await?
+            }
+        };
+        buffer.keep_alive(ack_wait);
         let prefix = &config.prefix;
 
-        let mut interval = tokio::time::interval(DEFAULT_BATCH_WAIT);
+        // sealed batches upload while the next one fills, finishing in the
+        // order they were sealed so their messages are acked in order
+        let mut uploads = FuturesOrdered::new();
+
+        let mut interval = tokio::time::interval(config.max_age);
         interval.tick().await;
 
         loop {
             tokio::select! {
-                maybe_message = messages.next() => {
+                maybe_message = messages.next(), if uploads.len() < UPLOADS_IN_FLIGHT => {
                     match maybe_message {
                         Some(Ok(message)) => {
                             trace!(
                                 subject = message.subject.to_string(),
                                 "consumer got message"
                             );
+                            let data = encoding::Message::from(&message);
+                            if config.deliver.is_past_end(data.sequence, data.timestamp) {
+                                debug!(sequence = data.sequence, "message past end bound");
+                                break;
+                            }
+                            let sequence = data.sequence;
+                            if watermark.contains(sequence) {
+                                trace!(sequence = sequence, "drop message already archived");
+                                self.metrics.io.nats_duplicates_total.inc();
+                                if let Err(err) = message.ack().await {
+                                    warn!(err = err, "message ack");
+                                }
+                                count_acks(&self.metrics, metrics::ACK_KIND_ACK, 1);
+                                continue;
+                            }
+                            let pending = message.info().map(|info| info.pending).unwrap_or(1);
                             bytes_total += &message.payload.len();
-                            buffer.push(message).await;
+                            writer.write(&config, data)?;
+                            let (_, acker) = message.split();
+                            buffer.push(acker, sequence).await;
+
+                            if config.deliver.is_at_end(sequence, pending, Utc::now()) {
+                                debug!(sequence = sequence, "store job reached end bound");
+                                break;
+                            }
 
                             let messages_total = buffer.len().await;
                             if messages_total >= config.messages_max as usize
                                 || bytes_total >= config.bytes_max as usize
                             {
-                                self.upload_buffer(&buffer, &mut bytes_total, &config, prefix).await?;
+                                debug!(messages = messages_total, bytes = bytes_total, "buffer threshold reached");
+                                let messages_total = buffer.seal().await;
+                                uploads.push_back(self.upload_batch(writer.take(), messages_total, &config, prefix));
+                                bytes_total = 0;
                             }
                         }
                         Some(Err(e)) => return Err(e.into()),
                         None => break,
                     }
                 }
+                Some(uploaded) = uploads.next(), if !uploads.is_empty() => {
+                    uploaded?;
+                    self.complete_batch(&job_id, &consumer, &config, &buffer, &mut watermark).await;
+                }
                 _ = interval.tick() => {
                     let messages_total = buffer.len().await;
-                    if messages_total > 0 {
+                    if messages_total > 0 && uploads.len() < UPLOADS_IN_FLIGHT {
                         debug!(messages = messages_total, "timer triggered upload");
-                        self.upload_buffer(&buffer, &mut bytes_total, &config, prefix).await?;
+                        let messages_total = buffer.seal().await;
+                        uploads.push_back(self.upload_batch(writer.take(), messages_total, &config, prefix));
+                        bytes_total = 0;
+                    }
+                    if config.deliver.end_time.is_some_and(|end| end <= Utc::now())
+                        && self.nats_client.consumer_pending(&config.stream, &consumer).await? == 0
+                    {
+                        debug!("store job reached end time");
+                        break;
                     }
                 }
                 _ = pause_token.cancelled() => {
                     debug!("consume stream paused, flushing buffer");
-                    let messages_total = buffer.len().await;
-                    if messages_total > 0 {
-                        self.upload_buffer(&buffer, &mut bytes_total, &config, prefix).await?;
+                    if buffer.len().await > 0 {
+                        let messages_total = buffer.seal().await;
+                        uploads.push_back(self.upload_batch(writer.take(), messages_total, &config, prefix));
                     }
+                    self.finish_uploads(&job_id, &consumer, &config, &mut uploads, &buffer, &mut watermark).await?;
                     let _ = exit_tx.send(registry::TaskExitInfo {
                         reason: registry::TaskExitReason::Paused,
                         job_id: job_id.clone(),
@@ -181,10 +401,11 @@ impl IO {
             }
                 _ = cancel_token.cancelled() => {
                     debug!("consume stream cancelled, flushing buffer");
-                    let messages_total = buffer.len().await;
-                    if messages_total > 0 {
-                        self.upload_buffer(&buffer, &mut bytes_total, &config, prefix).await?;
+                    if buffer.len().await > 0 {
+                        let messages_total = buffer.seal().await;
+                        uploads.push_back(self.upload_batch(writer.take(), messages_total, &config, prefix));
                     }
+                    self.finish_uploads(&job_id, &consumer, &config, &mut uploads, &buffer, &mut watermark).await?;
                     let _ = exit_tx.send(registry::TaskExitInfo {
                         reason: registry::TaskExitReason::Cancelled,
                         job_id: job_id.clone(),
@@ -193,6 +414,19 @@ impl IO {
                     }
             }
         }
+        if buffer.len().await > 0 {
+            let messages_total = buffer.seal().await;
+            uploads.push_back(self.upload_batch(writer.take(), messages_total, &config, prefix));
+        }
+        self.finish_uploads(
+            &job_id,
+            &consumer,
+            &config,
+            &mut uploads,
+            &buffer,
+            &mut watermark,
+        )
+        .await?;
         let _ = exit_tx.send(registry::TaskExitInfo {
             reason: registry::TaskExitReason::Completed(Ok(())),
             job_id,
@@ -200,43 +434,104 @@ impl IO {
         Ok(())
     }
 
-    async fn upload_buffer(
+    // write the stream config next to the chunks of a backup, a restarted
+    // backup records any change to it
+    async fn record_stream_config(&self, config: &ConsumeConfig, retry: &Retry) -> Result<()> {
+        let stream_config = self.nats_client.stream_config(&config.stream).await?;
+        let path = object_path(config.prefix.as_ref(), &backup::config_key(&config.stream));
+        debug!(stream = config.stream, path = path, "record stream config");
+        self.s3_client
+            .upload_object(
+                Body::from(serde_json::to_vec(&stream_config)?),
+                &config.bucket,
+                &path,
+                vec![],
+                retry,
+            )
+            .await
+    }
+
+    // wait for in flight uploads, completing each batch in order
+    async fn finish_uploads<F>(
         &self,
+        job_id: &str,
+        consumer: &str,
+        config: &ConsumeConfig,
+        uploads: &mut FuturesOrdered<F>,
         buffer: &MessageBuffer,
-        bytes_total: &mut usize,
+        watermark: &mut Watermark,
+    ) -> Result<()>
+    where
+        F: Future<Output = Result<()>>,
+    {
+        while let Some(uploaded) = uploads.next().await {
+            uploaded?;
+            self.complete_batch(job_id, consumer, config, buffer, watermark)
+                .await;
+        }
+        Ok(())
+    }
+
+    // ack the messages of the oldest uploaded batch, then checkpoint it. a
+    // missed checkpoint only means redelivered messages may be archived twice
+    async fn complete_batch(
+        &self,
+        job_id: &str,
+        consumer: &str,
+        config: &ConsumeConfig,
+        buffer: &MessageBuffer,
+        watermark: &mut Watermark,
+    ) {
+        let Some(batch) = buffer.pop_oldest().await else {
+            return;
+        };
+        buffer.ack(&batch).await;
+
+        // a message delivered but never acked, and no longer buffered, comes
+        // back after ack wait. the checkpoint stays below it until it's acked
+        let acked = match self
+            .nats_client
+            .consumer_ack_floor(&config.stream, consumer)
+            .await
+        {
+            Ok(acked) => acked,
+            Err(err) => {
+                warn!(err = ?err, job_id = job_id, "get consumer ack floor");
+                0
+            }
+        };
+        let floor = buffer.sequence_floor().await;
+        if let Some(sequence) = watermark.complete(batch.sequence_max, floor, acked) {
+            let retry = self.retry_policy(config.retry.as_ref());
+            let checkpointed =
+                retry::retry(retry, metrics::SERVICE_POSTGRES, &self.metrics, || {
+                    self.chunk_db.set_checkpoint(job_id, sequence as i64)
+                })
+                .await;
+            if let Err(err) = checkpointed {
+                warn!(err = ?err, job_id = job_id, "set store job checkpoint");
+            }
+        }
+    }
+
+    // upload the chunks of a sealed batch, its messages are acked by the caller
+    async fn upload_batch(
+        &self,
+        writers: BTreeMap<String, encoding::ChunkWriter>,
+        messages_total: usize,
         config: &ConsumeConfig,
         prefix: &Option<String>,
     ) -> Result<()> {
-        let messages_total = buffer.len().await;
-
         debug!(
             messages = messages_total,
-            bytes = *bytes_total,
-            "buffer threshold reached"
+            chunks = writers.len(),
+            "upload sealed batch"
         );
 
-        let block = encoding::MessageBlock::from(buffer.to_vec().await);
-        let chunk = encoding::Chunk::from(block);
-        let key = chunk.key(config.codec.clone()).to_string();
-
-        let stream = config.stream.clone();
-        let subject = config.subject.clone();
-        let key = format!("{stream}/{subject}/{key}");
-
-        let path = if let Some(prefix) = prefix {
-            format!("{}/{}", prefix, key)
-        } else {
-            key
-        };
-
-        let serialized = chunk.serialize(config.codec.clone())?;
-        let byte_count = serialized.len();
-        self.s3_client
-            .upload_chunk(serialized, &config.bucket, &path, config.codec.clone())
-            .await?;
-
-        let chunk_md = chunk.to_chunk_metadata(config, &path, byte_count);
-        self.chunk_db.create_chunk(chunk_md).await?;
+        let mut byte_count = 0;
+        for (subject, writer) in writers {
+            byte_count += self.upload_chunk(writer, &subject, config, prefix).await?;
+        }
 
         self.metrics
             .io
@@ -253,13 +548,75 @@ impl IO {
             })
             .inc_by(byte_count as u64);
 
-        buffer.ack_all().await;
-        buffer.clear().await;
-        *bytes_total = 0;
-
         Ok(())
     }
 
+    // seal, upload and record one chunk, returning its size in bytes
+    async fn upload_chunk(
+        &self,
+        writer: encoding::ChunkWriter,
+        subject: &str,
+        config: &ConsumeConfig,
+        prefix: &Option<String>,
+    ) -> Result<usize> {
+        let mut chunk = writer.finish()?;
+        if config.encrypt {
+            let keyring = self
+                .keyring
+                .as_ref()
+                .ok_or(ValidationError::EncryptionNotConfigured)?;
+            chunk.encrypt(keyring)?;
+        }
+        if let Some(signer) = &self.signer {
+            chunk.sign(signer);
+        }
+        let key = chunk.key(config, subject).render(&config.key_template)?;
+        let path = object_path(prefix.as_ref(), &key);
+
+        let chunk_md = chunk.to_chunk_metadata(config, subject, &key);
+        let object_metadata = chunk.object_metadata();
+        let byte_count = chunk.data.len() as usize;
+        // a key without a ulid is rendered again when the same messages are
+        // consumed again, an object already there is kept and checked
+        let write_once = !nats3_types::key_template_is_unique(&config.key_template);
+
+        // spooled chunks are acked now and uploaded in the background
+        if let Some(spool) = self.spool.as_ref().filter(|_| config.spool) {
+            let entry = spool::SpoolEntry {
+                bucket: config.bucket.clone(),
+                path,
+                codec: config.codec.clone(),
+                object_metadata: object_metadata
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value))
+                    .collect(),
+                write_once,
+                chunk: chunk_md,
+            };
+            spool.push(entry, chunk.data).await?;
+            return Ok(byte_count);
+        }
+
+        let retry = self.retry_policy(config.retry.as_ref());
+        self.s3_client
+            .upload_chunk(
+                chunk.data,
+                &config.bucket,
+                &path,
+                config.codec.clone(),
+                object_metadata,
+                write_once,
+                retry,
+            )
+            .await?;
+
+        retry::retry(retry, metrics::SERVICE_POSTGRES, &self.metrics, || {
+            db::record_chunk(self.chunk_db.as_ref(), chunk_md.clone())
+        })
+        .await?;
+        Ok(byte_count)
+    }
+
     pub async fn publish_stream(
         &self,
         job_id: String,
@@ -277,13 +634,19 @@ impl IO {
             bucket = config.bucket,
             prefix = config.prefix,
             delete_chunks = config.delete_chunks,
+            partial_recovery = config.partial_recovery,
             "download from bucket and publish to stream"
         );
 
-        let write_subject = config.write_subject.clone();
+        let target = if let Some(backup_id) = &config.backup_id {
+            self.restore_target(&config, backup_id).await?
+        } else {
+            PublishTarget::Subject(config.write_subject.clone())
+        };
         let read_stream = config.read_stream.clone();
         let read_consumer = config.read_consumer.clone();
         let read_subject = config.read_subject.clone();
+        let retry = self.retry_policy(config.retry.as_ref());
 
         let query = db::ListChunksQuery {
             stream: read_stream.clone(),
@@ -298,7 +661,15 @@ impl IO {
         };
 
         loop {
-            let chunks = self.chunk_db.list_chunks(query.clone()).await?;
+            let mut chunks = retry::retry(retry, metrics::SERVICE_POSTGRES, &self.metrics, || {
+                self.chunk_db.list_chunks(query.clone())
+            })
+            .await?;
+            if config.backup_id.is_some() {
+                chunks.sort_by_key(|chunk_md| {
+                    (chunk_md.stream_sequence_start, chunk_md.sequence_number)
+                });
+            }
             for chunk_md in chunks {
                 if cancel_token.is_cancelled() {
                     debug!("publish stream cancelled during chunk list");
@@ -317,51 +688,32 @@ impl IO {
 
                     return Ok(());
                 }
-                let path = if chunk_md.prefix.clone().is_some_and(|p| !p.is_empty()) {
-                    format!(
-                        "{}/{}",
-                        chunk_md.prefix.expect("prefix already checked as not none"),
-                        chunk_md.key
-                    )
-                } else {
-                    chunk_md.key
-                };
+                let path = object_path(chunk_md.prefix.as_ref(), &chunk_md.key);
 
-                let chunk = match self
-                    .s3_client
-                    .download_chunk(&chunk_md.bucket, &path, chunk_md.codec)
-                    .await
-                {
-                    Ok(chunk) => chunk,
-                    Err(e) => {
-                        warn!(
-                            bucket = chunk_md.bucket,
-                            key = path,
-                            error = ?e,
-                            "metadata exists but s3 object is missing, skipping"
-                        );
-                        continue;
-                    }
-                };
-                // Recalculate block hash and compare it to the stored hash
-                if chunk.block.hash() != chunk_md.hash {
-                    warn!(
-                        key = path,
-                        bucket = config.bucket,
-                        sequence_number = chunk_md.sequence_number,
-                        "download chunk hash mismatch, skip publish"
-                    );
-                    continue;
-                }
+                // a chunk whose signature doesn't verify fails the job
+                self.verify_signature(&chunk_md)
+                    .with_context(|| format!("verify chunk {}", path))?;
 
-                for message in chunk.block.messages {
-                    self.nats_client
-                        .publish(write_subject.clone(), message.payload, message.headers)
-                        .await?;
+                let published = self
+                    .publish_chunk(
+                        &job_id,
+                        &chunk_md,
+                        &path,
+                        &target,
+                        config.partial_recovery,
+                        retry,
+                    )
+                    .await?;
+                if !published {
+                    continue;
                 }
 
                 if config.delete_chunks {
-                    if let Err(e) = self.s3_client.delete_chunk(&chunk_md.bucket, &path).await {
+                    if let Err(e) = self
+                        .s3_client
+                        .delete_chunk(&chunk_md.bucket, &path, retry)
+                        .await
+                    {
                         warn!(
                             bucket = chunk_md.bucket,
                             path = path,
@@ -371,11 +723,12 @@ impl IO {
                         continue;
                     }
 
-                    if let Err(e) = self
-                        .chunk_db
-                        .soft_delete_chunk(chunk_md.sequence_number)
-                        .await
-                    {
+                    let deleted =
+                        retry::retry(retry, metrics::SERVICE_POSTGRES, &self.metrics, || {
+                            self.chunk_db.soft_delete_chunk(chunk_md.sequence_number)
+                        })
+                        .await;
+                    if let Err(e) = deleted {
                         warn!(
                             sequence_number = chunk_md.sequence_number,
                             error = ?e,
@@ -420,7 +773,7 @@ impl IO {
 
         debug!(
             read_subject = read_subject,
-            write_subject = write_subject,
+            write_subject = config.write_subject,
             bucket = config.bucket,
             "finish download from s3 and publish to nats"
         );
@@ -431,47 +784,1061 @@ impl IO {
 
         Ok(())
     }
+
+    // create the stream a restore publishes to from its recorded config
+    async fn restore_target(
+        &self,
+        config: &PublishConfig,
+        backup_id: &str,
+    ) -> Result<PublishTarget> {
+        let retry = self.retry_policy(config.retry.as_ref());
+        let path = object_path(
+            config.prefix.as_ref(),
+            &backup::config_key(&config.read_stream),
+        );
+        let stream_config: jetstream::stream::Config = serde_json::from_slice(
+            &self
+                .s3_client
+                .download_object(&config.bucket, &path, retry)
+                .await
+                .with_context(|| format!("read stream config {}", path))?,
+        )
+        .with_context(|| format!("decode stream config {}", path))?;
+
+        let name = config
+            .restore_stream
+            .as_ref()
+            .unwrap_or(&config.read_stream);
+        let restored = self
+            .nats_client
+            .create_restore_stream(backup::restore_config(stream_config, name))
+            .await?;
+        debug!(
+            stream = name,
+            restored = restored,
+            "restore stream from backup"
+        );
+        Ok(PublishTarget::Stream {
+            backup_id: backup_id.to_string(),
+            last_sequence: AtomicU64::new(restored),
+        })
+    }
+
+    // publish the messages of a chunk, returns false if the chunk was skipped
+    async fn publish_chunk(
+        &self,
+        job_id: &str,
+        chunk_md: &db::ChunkMetadata,
+        path: &str,
+        target: &PublishTarget,
+        partial_recovery: bool,
+        retry: &Retry,
+    ) -> Result<bool> {
+        match chunk_md.codec {
+            Codec::Framed | Codec::Jsonl => {
+                self.publish_streamed_chunk(job_id, chunk_md, path, target, partial_recovery, retry)
+                    .await
+            }
+            Codec::Json | Codec::Binary | Codec::Parquet => {
+                self.publish_block_chunk(job_id, chunk_md, path, target, retry)
+                    .await
+            }
+        }
+    }
+
+    async fn publish_message(
+        &self,
+        job_id: &str,
+        target: &PublishTarget,
+        message: encoding::Message,
+        retry: &Retry,
+    ) -> Result<()> {
+        let (subject, headers) = match target {
+            PublishTarget::Subject(subject) => (subject.clone(), message.headers),
+            PublishTarget::KvBucket { source, target } => {
+                let key = kv::key(source, &message.subject).ok_or_else(|| {
+                    anyhow!(
+                        "subject {} is not a key of bucket {}",
+                        message.subject,
+                        source
+                    )
+                })?;
+                (
+                    kv::subject(target, key),
+                    kv::restore_headers(message.headers),
+                )
+            }
+            PublishTarget::Stream {
+                backup_id,
+                last_sequence,
+            } => {
+                // chunks overlap where a store job archived redelivered messages
+                if last_sequence.fetch_max(message.sequence, Ordering::Relaxed) >= message.sequence
+                {
+                    trace!(sequence = message.sequence, "skip message already restored");
+                    return Ok(());
+                }
+                (
+                    message.subject,
+                    backup::restore_headers(message.headers, backup_id, message.sequence),
+                )
+            }
+        };
+        let headers = nats::with_message_id(headers, job_id, &message.stream, message.sequence);
+        self.nats_client
+            .publish(subject, message.payload, headers, retry)
+            .await
+    }
+
+    // download a whole chunk, verify it and publish its messages.
+    // returns false if the chunk was skipped.
+    async fn publish_block_chunk(
+        &self,
+        job_id: &str,
+        chunk_md: &db::ChunkMetadata,
+        path: &str,
+        target: &PublishTarget,
+        retry: &Retry,
+    ) -> Result<bool> {
+        let data = match self
+            .s3_client
+            .download_object(&chunk_md.bucket, path, retry)
+            .await
+        {
+            Ok(data) => data,
+            Err(e) => {
+                warn!(
+                    bucket = chunk_md.bucket,
+                    key = path,
+                    error = ?e,
+                    "metadata exists but s3 object is missing, skipping"
+                );
+                return Ok(false);
+            }
+        };
+        let data = match self.decrypt_chunk(chunk_md, data) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!(
+                    bucket = chunk_md.bucket,
+                    key = path,
+                    error = ?e,
+                    "fail decrypt chunk, skip publish"
+                );
+                return Ok(false);
+            }
+        };
+        // unknown or corrupt chunks fail the job rather than being skipped
+        let chunk = encoding::Chunk::deserialize(
+            &data,
+            chunk_md.codec.clone(),
+            &chunk_md.compression,
+            &chunk_md.version,
+        )
+        .with_context(|| format!("decode chunk {}", path))?;
+        // Recalculate block hash and compare it to the stored hash
+        if chunk.content_hash() != chunk_md.hash {
+            if chunk_md.signature.is_some() {
+                bail!("chunk {} does not match its signed hash", path);
+            }
+            warn!(
+                key = path,
+                bucket = chunk_md.bucket,
+                sequence_number = chunk_md.sequence_number,
+                "download chunk hash mismatch, skip publish"
+            );
+            return Ok(false);
+        }
+
+        for message in chunk.block.messages {
+            self.publish_message(job_id, target, message, retry).await?;
+        }
+        Ok(true)
+    }
+
+    // stream a framed or jsonl chunk from s3, publishing each message as it is decoded.
+    // the chunk hash can only be verified once every record has been read, so
+    // a corrupt chunk may be partially published before the job fails. with
+    // partial recovery, records failing their checksum are skipped instead and
+    // the lost sequences recorded against the chunk. returns false if the chunk
+    // was skipped, only partially recovered or does not match its metadata.
+    async fn publish_streamed_chunk(
+        &self,
+        job_id: &str,
+        chunk_md: &db::ChunkMetadata,
+        path: &str,
+        target: &PublishTarget,
+        partial_recovery: bool,
+        retry: &Retry,
+    ) -> Result<bool> {
+        let reader = match self.open_streamed_chunk(chunk_md, path, retry).await {
+            Ok(reader) => reader,
+            Err(e) => {
+                warn!(
+                    bucket = chunk_md.bucket,
+                    key = path,
+                    error = ?e,
+                    "fail open chunk from s3, skipping"
+                );
+                return Ok(false);
+            }
+        };
+
+        // decode on a blocking thread, handing messages over a bounded channel
+        let (tx, mut rx) = mpsc::channel(STREAM_DECODE_BUFFER);
+        let codec = chunk_md.codec.clone();
+        let compression = chunk_md.compression.clone();
+        let version = chunk_md.version.clone();
+        let decoder = tokio::task::spawn_blocking(move || -> Result<DecodedChunk> {
+            let mut reader = encoding::MessageReader::new(reader, codec, &compression, &version)?;
+            let mut decoded = DecodedChunk::default();
+            loop {
+                let record = if partial_recovery {
+                    reader.next_record()
+                } else {
+                    reader
+                        .next_message()
+                        .map(|m| m.map(encoding::Decoded::Message))
+                };
+                match record {
+                    Ok(Some(encoding::Decoded::Message(message))) => {
+                        if tx.blocking_send(message).is_err() {
+                            bail!("chunk receiver dropped");
+                        }
+                    }
+                    Ok(Some(encoding::Decoded::Damaged { sequence })) => {
+                        decoded.damaged.push(sequence)
+                    }
+                    Ok(None) => break,
+                    // the rest of the chunk is unreadable, keep what was recovered
+                    Err(e) if partial_recovery => {
+                        decoded.unreadable = Some(e);
+                        return Ok(decoded);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            decoded.hash = Some(
+                reader
+                    .hash()?
+                    .ok_or_else(|| anyhow!("chunk hash missing after last message"))?,
+            );
+            Ok(decoded)
+        });
+
+        let mut published = 0;
+        while let Some(message) = rx.recv().await {
+            self.publish_message(job_id, target, message, retry).await?;
+            published += 1;
+        }
+
+        match decoder.await? {
+            Ok(decoded) if decoded.is_damaged() => {
+                let recovery = decoded.recovery(published);
+                warn!(
+                    key = path,
+                    bucket = chunk_md.bucket,
+                    sequence_number = chunk_md.sequence_number,
+                    recovered = recovery.recovered_count,
+                    lost_sequences = ?recovery.lost_sequences,
+                    lost_sequences_complete = recovery.lost_sequences_complete,
+                    error = ?decoded.unreadable,
+                    "chunk damaged, published intact messages only"
+                );
+                retry::retry(retry, metrics::SERVICE_POSTGRES, &self.metrics, || {
+                    self.chunk_db
+                        .record_chunk_recovery(chunk_md.sequence_number, recovery.clone())
+                })
+                .await?;
+                // keep the damaged chunk around, even with delete_chunks
+                Ok(false)
+            }
+            Ok(decoded) if decoded.hash.as_ref() == Some(&chunk_md.hash) => Ok(true),
+            Ok(_) if chunk_md.signature.is_some() => {
+                bail!("chunk {} does not match its signed hash", path)
+            }
+            Ok(_) => {
+                warn!(
+                    key = path,
+                    bucket = chunk_md.bucket,
+                    sequence_number = chunk_md.sequence_number,
+                    published = published,
+                    "chunk hash does not match metadata"
+                );
+                Ok(false)
+            }
+            Err(e) if e.is::<encoding::ChunkError>() => {
+                Err(e.context(format!("decode chunk {}", path)))
+            }
+            Err(e) => {
+                warn!(
+                    key = path,
+                    bucket = chunk_md.bucket,
+                    sequence_number = chunk_md.sequence_number,
+                    published = published,
+                    error = ?e,
+                    "fail decode chunk"
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    // snapshot a key-value bucket into chunks. the snapshot covers the
+    // messages pending when the archive started, and messages written while it
+    // runs until the consumer catches up
+    pub async fn archive_kv(
+        &self,
+        job: KvJob,
+        cancel_token: CancellationToken,
+        exit_tx: mpsc::UnboundedSender<registry::TaskExitInfo>,
+    ) -> Result<()> {
+        debug!(
+            job_id = job.id,
+            kv_bucket = job.kv_bucket,
+            bucket = job.bucket,
+            prefix = job.prefix,
+            history = job.history,
+            "archive key-value bucket to bucket"
+        );
+
+        if job.encoding.encrypt && !self.encryption_enabled() {
+            bail!(ValidationError::EncryptionNotConfigured);
+        }
+
+        let config = ConsumeConfig::from(&job);
+        let consumer = kv::archive_consumer(&job.id);
+        let (mut messages, mut pending) = self
+            .nats_client
+            .archive_kv(&job.kv_bucket, &consumer, job.history)
+            .await?;
+
+        let mut writer = ChunkRouter::default();
+        let mut messages_total = 0;
+        let mut bytes_total = 0;
+        while pending > 0 {
+            tokio::select! {
+                maybe_message = messages.next() => {
+                    let message = match maybe_message {
+                        Some(message) => message?,
+                        None => bail!("key-value consumer closed before the snapshot completed"),
+                    };
+                    pending = message.info().map_err(|e| anyhow!(e))?.pending;
+                    bytes_total += message.payload.len();
+                    writer.write(&config, encoding::Message::from(&message))?;
+                    messages_total += 1;
+
+                    if messages_total >= config.messages_max as usize
+                        || bytes_total >= config.bytes_max as usize
+                    {
+                        self.upload_batch(writer.take(), messages_total, &config, &config.prefix)
+                            .await?;
+                        messages_total = 0;
+                        bytes_total = 0;
+                    }
+                }
+                _ = cancel_token.cancelled() => {
+                    debug!("key-value archive cancelled");
+                    self.nats_client
+                        .delete_consumer(&config.stream, &consumer)
+                        .await?;
+                    let _ = exit_tx.send(registry::TaskExitInfo {
+                        reason: registry::TaskExitReason::Cancelled,
+                        job_id: job.id.clone(),
+                    });
+                    return Ok(());
+                }
+            }
+        }
+        if messages_total > 0 {
+            self.upload_batch(writer.take(), messages_total, &config, &config.prefix)
+                .await?;
+        }
+        self.nats_client
+            .delete_consumer(&config.stream, &consumer)
+            .await?;
+
+        debug!(job_id = job.id, "finish key-value archive");
+        let _ = exit_tx.send(registry::TaskExitInfo {
+            reason: registry::TaskExitReason::Completed(Ok(())),
+            job_id: job.id,
+        });
+        Ok(())
+    }
+
+    // write the messages of an archive back in stream order, creating the
+    // target bucket if it's missing. a chunk that can't be restored fails the
+    // job, since the keys it holds would be left at older revisions.
+    pub async fn restore_kv(
+        &self,
+        job: KvJob,
+        archive: KvJob,
+        cancel_token: CancellationToken,
+        exit_tx: mpsc::UnboundedSender<registry::TaskExitInfo>,
+    ) -> Result<()> {
+        debug!(
+            job_id = job.id,
+            archive_id = archive.id,
+            source = archive.kv_bucket,
+            kv_bucket = job.kv_bucket,
+            "restore key-value bucket from archive"
+        );
+        let retry = self.retry_policy(job.retry.as_ref());
+
+        let history = if archive.history { kv::MAX_HISTORY } else { 1 };
+        self.nats_client
+            .create_kv_bucket(&job.kv_bucket, history)
+            .await?;
+
+        let query = db::ListChunksQuery {
+            stream: kv::stream_name(&archive.kv_bucket),
+            consumer: Some(kv::archive_consumer(&archive.id)),
+            subject: kv::filter_subject(&archive.kv_bucket),
+            bucket: archive.bucket.clone(),
+            prefix: archive.prefix.clone(),
+            timestamp_start: None,
+            timestamp_end: None,
+            limit: None,
+            include_deleted: false,
+        };
+        let mut chunks = retry::retry(retry, metrics::SERVICE_POSTGRES, &self.metrics, || {
+            self.chunk_db.list_chunks(query.clone())
+        })
+        .await?;
+        chunks.sort_by_key(|chunk_md| (chunk_md.stream_sequence_start, chunk_md.sequence_number));
+
+        let target = PublishTarget::KvBucket {
+            source: archive.kv_bucket.clone(),
+            target: job.kv_bucket.clone(),
+        };
+        for chunk_md in chunks {
+            if cancel_token.is_cancelled() {
+                debug!("key-value restore cancelled");
+                let _ = exit_tx.send(registry::TaskExitInfo {
+                    reason: registry::TaskExitReason::Cancelled,
+                    job_id: job.id.clone(),
+                });
+                return Ok(());
+            }
+            let path = object_path(chunk_md.prefix.as_ref(), &chunk_md.key);
+            self.verify_signature(&chunk_md)
+                .with_context(|| format!("verify chunk {}", path))?;
+            if !self
+                .publish_chunk(&job.id, &chunk_md, &path, &target, false, retry)
+                .await?
+            {
+                bail!("chunk {} could not be restored", path);
+            }
+        }
+
+        debug!(job_id = job.id, "finish key-value restore");
+        let _ = exit_tx.send(registry::TaskExitInfo {
+            reason: registry::TaskExitReason::Completed(Ok(())),
+            job_id: job.id,
+        });
+        Ok(())
+    }
+
+    // copy the objects of an object store bucket to S3, then write the manifest
+    // listing them. objects put after the listing are left out, objects
+    // replaced meanwhile are archived at their newer version
+    pub async fn archive_objects(
+        &self,
+        job: ObjectJob,
+        cancel_token: CancellationToken,
+        exit_tx: mpsc::UnboundedSender<registry::TaskExitInfo>,
+    ) -> Result<()> {
+        debug!(
+            job_id = job.id,
+            object_bucket = job.object_bucket,
+            bucket = job.bucket,
+            prefix = job.prefix,
+            "archive object store bucket to bucket"
+        );
+        let retry = self.retry_policy(job.retry.as_ref());
+
+        let store = self.nats_client.object_store(&job.object_bucket).await?;
+        let mut list = store.list().await?;
+        let mut infos = vec![];
+        while let Some(info) = list.next().await {
+            infos.push(info?);
+        }
+
+        let mut entries = Vec::with_capacity(infos.len());
+        for info in infos {
+            if cancel_token.is_cancelled() {
+                debug!("object store archive cancelled");
+                let _ = exit_tx.send(registry::TaskExitInfo {
+                    reason: registry::TaskExitReason::Cancelled,
+                    job_id: job.id.clone(),
+                });
+                return Ok(());
+            }
+            if object::link(&info).is_some() {
+                entries.push(object::ManifestEntry { key: None, info });
+                continue;
+            }
+
+            let object = match store.get(&info.name).await {
+                Ok(object) => object,
+                Err(e) if e.kind() == GetErrorKind::NotFound => {
+                    debug!(name = info.name, "object deleted during archive, skip");
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let info = object.info().clone();
+
+            // the object is read part by part as it is uploaded
+            let key = object::object_key(&job.object_bucket, &job.id, &info.nuid);
+            let path = object_path(job.prefix.as_ref(), &key);
+            self.s3_client
+                .upload_reader(object, info.size as u64, &job.bucket, &path, vec![], retry)
+                .await
+                .with_context(|| format!("archive object {}", info.name))?;
+            trace!(name = info.name, path = path, "object archived");
+            entries.push(object::ManifestEntry {
+                key: Some(key),
+                info,
+            });
+        }
+
+        let manifest = object::Manifest {
+            bucket: job.object_bucket.clone(),
+            created: Utc::now(),
+            objects: entries,
+        };
+        let path = object_path(
+            job.prefix.as_ref(),
+            &object::manifest_key(&job.object_bucket, &job.id),
+        );
+        self.s3_client
+            .upload_object(
+                Body::from(serde_json::to_vec(&manifest)?),
+                &job.bucket,
+                &path,
+                vec![],
+                retry,
+            )
+            .await?;
+
+        debug!(
+            job_id = job.id,
+            objects = manifest.objects.len(),
+            "finish object store archive"
+        );
+        let _ = exit_tx.send(registry::TaskExitInfo {
+            reason: registry::TaskExitReason::Completed(Ok(())),
+            job_id: job.id,
+        });
+        Ok(())
+    }
+
+    // put the objects of an archive into the target bucket, creating it if
+    // it's missing, then add back the links between them. an object whose
+    // digest changed on the way fails the job.
+    pub async fn restore_objects(
+        &self,
+        job: ObjectJob,
+        archive: ObjectJob,
+        cancel_token: CancellationToken,
+        exit_tx: mpsc::UnboundedSender<registry::TaskExitInfo>,
+    ) -> Result<()> {
+        debug!(
+            job_id = job.id,
+            archive_id = archive.id,
+            source = archive.object_bucket,
+            object_bucket = job.object_bucket,
+            "restore object store bucket from archive"
+        );
+        let retry = self.retry_policy(job.retry.as_ref());
+
+        let path = object_path(
+            archive.prefix.as_ref(),
+            &object::manifest_key(&archive.object_bucket, &archive.id),
+        );
+        let manifest: object::Manifest = serde_json::from_slice(
+            &self
+                .s3_client
+                .download_object(&archive.bucket, &path, retry)
+                .await?,
+        )
+        .with_context(|| format!("decode manifest {}", path))?;
+
+        let store = self
+            .nats_client
+            .create_object_store(&job.object_bucket)
+            .await?;
+        for entry in manifest.restore_order() {
+            if cancel_token.is_cancelled() {
+                debug!("object store restore cancelled");
+                let _ = exit_tx.send(registry::TaskExitInfo {
+                    reason: registry::TaskExitReason::Cancelled,
+                    job_id: job.id.clone(),
+                });
+                return Ok(());
+            }
+            let name = &entry.info.name;
+
+            if let Some(link) = object::link(&entry.info) {
+                let bucket = object::link_bucket(link, &manifest.bucket, &job.object_bucket);
+                match &link.name {
+                    Some(linked) if bucket == job.object_bucket => {
+                        let linked = store.info(linked).await?;
+                        store.add_link(name, &linked).await?;
+                    }
+                    Some(linked) => {
+                        let linked = self
+                            .nats_client
+                            .object_store(bucket)
+                            .await?
+                            .info(linked)
+                            .await?;
+                        store.add_link(name, &linked).await?;
+                    }
+                    None => {
+                        store.add_bucket_link(name, bucket).await?;
+                    }
+                }
+                trace!(name = name, bucket = bucket, "link restored");
+                continue;
+            }
+
+            let key = entry
+                .key
+                .as_ref()
+                .ok_or_else(|| anyhow!("object {} has no archived data", name))?;
+            let path = object_path(archive.prefix.as_ref(), key);
+            let data = self
+                .s3_client
+                .download_object(&archive.bucket, &path, retry)
+                .await?;
+            let restored = store
+                .put(object::restore_metadata(&entry.info), &mut data.as_ref())
+                .await
+                .with_context(|| format!("put object {}", name))?;
+            if entry.info.digest.is_some() && restored.digest != entry.info.digest {
+                bail!(
+                    "object {} restored with digest {:?}, archived with {:?}",
+                    name,
+                    restored.digest,
+                    entry.info.digest
+                );
+            }
+            trace!(name = name, path = path, "object restored");
+        }
+
+        debug!(job_id = job.id, "finish object store restore");
+        let _ = exit_tx.send(registry::TaskExitInfo {
+            reason: registry::TaskExitReason::Completed(Ok(())),
+            job_id: job.id,
+        });
+        Ok(())
+    }
+
+    // check the chunk hash signature, unsigned chunks pass unless signing is required
+    fn verify_signature(&self, chunk_md: &db::ChunkMetadata) -> Result<()> {
+        let (signature, key_id) = match (&chunk_md.signature, &chunk_md.signature_key_id) {
+            (Some(signature), Some(key_id)) => (signature, key_id),
+            (None, None) => {
+                if self.signer.as_ref().is_some_and(|s| s.require_signed()) {
+                    bail!("chunk is not signed and signed chunks are required");
+                }
+                return Ok(());
+            }
+            _ => bail!("chunk signature metadata is incomplete"),
+        };
+        let signer = self
+            .signer
+            .as_ref()
+            .ok_or_else(|| anyhow!("chunk is signed but no signing key is configured"))?;
+        signer.verify(&chunk_md.hash, signature, key_id)
+    }
+
+    // check a stored chunk end to end: the hash signature, the signature kept
+    // on the s3 object and the hash of the content itself
+    pub async fn verify_chunk(&self, chunk_md: &db::ChunkMetadata) -> ChunkVerification {
+        let path = object_path(chunk_md.prefix.as_ref(), &chunk_md.key);
+        let (status, reason) = match self.check_chunk(chunk_md, &path).await {
+            Ok(()) if chunk_md.signature.is_some() => (VerificationStatus::Verified, None),
+            Ok(()) => (VerificationStatus::Unsigned, None),
+            Err(e) => (VerificationStatus::Invalid, Some(format!("{:#}", e))),
+        };
+        ChunkVerification {
+            sequence_number: chunk_md.sequence_number,
+            bucket: chunk_md.bucket.clone(),
+            key: path,
+            status,
+            reason,
+        }
+    }
+
+    async fn check_chunk(&self, chunk_md: &db::ChunkMetadata, path: &str) -> Result<()> {
+        self.verify_signature(chunk_md)?;
+
+        let object_metadata = self
+            .s3_client
+            .object_metadata(&chunk_md.bucket, path, &self.retry)
+            .await?;
+        let object_signature = object_metadata
+            .get(encoding::SIGNATURE_METADATA)
+            .map(|s| STANDARD.decode(s))
+            .transpose()
+            .context("decode object signature")?;
+        if object_signature.as_deref() != chunk_md.signature.as_deref()
+            || object_metadata.get(encoding::SIGNATURE_KEY_METADATA)
+                != chunk_md.signature_key_id.as_ref()
+        {
+            bail!("object signature does not match chunk metadata");
+        }
+
+        let data = self
+            .s3_client
+            .download_object(&chunk_md.bucket, path, &self.retry)
+            .await?;
+        let data = self.decrypt_chunk(chunk_md, data)?;
+        let codec = chunk_md.codec.clone();
+        let compression = chunk_md.compression.clone();
+        let version = chunk_md.version.clone();
+        let hash = tokio::task::spawn_blocking(move || -> Result<Bytes> {
+            match codec {
+                Codec::Framed | Codec::Jsonl => {
+                    let mut reader = encoding::MessageReader::new(
+                        std::io::Cursor::new(data),
+                        codec,
+                        &compression,
+                        &version,
+                    )?;
+                    while reader.next_message()?.is_some() {}
+                    reader
+                        .hash()?
+                        .ok_or_else(|| anyhow!("chunk hash missing after last message"))
+                }
+                Codec::Json | Codec::Binary | Codec::Parquet => {
+                    let chunk = encoding::Chunk::deserialize(&data, codec, &compression, &version)?;
+                    Ok(chunk.content_hash())
+                }
+            }
+        })
+        .await??;
+        if hash != chunk_md.hash {
+            bail!("chunk content does not match its hash");
+        }
+        Ok(())
+    }
+
+    // open a streamed chunk for reading. encrypted chunks can only be
+    // authenticated as a whole, so they are downloaded and decrypted up front.
+    async fn open_streamed_chunk(
+        &self,
+        chunk_md: &db::ChunkMetadata,
+        path: &str,
+        retry: &Retry,
+    ) -> Result<Box<dyn std::io::Read + Send>> {
+        if chunk_md.encryption_key_id.is_some() {
+            let data = self
+                .s3_client
+                .download_object(&chunk_md.bucket, path, retry)
+                .await?;
+            let data = self.decrypt_chunk(chunk_md, data)?;
+            return Ok(Box::new(std::io::Cursor::new(data)));
+        }
+        let stream = self
+            .s3_client
+            .download_chunk_stream(&chunk_md.bucket, path, retry)
+            .await?;
+        Ok(Box::new(std::io::BufReader::new(SyncIoBridge::new(
+            StreamReader::new(stream),
+        ))))
+    }
+
+    // decrypt an encrypted chunk, plaintext chunks are passed through
+    fn decrypt_chunk(&self, chunk_md: &db::ChunkMetadata, data: Bytes) -> Result<Bytes> {
+        if chunk_md.encryption_key_id.is_none() {
+            return Ok(data);
+        }
+        let keyring = self
+            .keyring
+            .as_ref()
+            .ok_or_else(|| anyhow!("chunk is encrypted but no master key is configured"))?;
+        Ok(Bytes::from(keyring.decrypt(&data)?))
+    }
+}
+
+// object path in the bucket, chunk metadata keeps the key and prefix apart
+fn object_path(prefix: Option<&String>, key: &str) -> String {
+    match prefix.filter(|p| !p.is_empty()) {
+        Some(prefix) => format!("{}/{}", prefix, key),
+        None => key.to_string(),
+    }
 }
 
-// MessageBuffer is a thread safe Vec<jetstream::Message>
+// where a load publishes the messages of a chunk
+enum PublishTarget {
+    // every message to one subject
+    Subject(String),
+    // each message to its key in a key-value bucket, from the archived bucket
+    KvBucket {
+        source: String,
+        target: String,
+    },
+    // each message to its own subject in a stream restored from a backup,
+    // skipping backed up sequences up to the last one restored
+    Stream {
+        backup_id: String,
+        last_sequence: AtomicU64,
+    },
+}
+
+// ChunkRouter writes consumed messages to one chunk per route subject. Jobs
+// that don't split by subject have a single route, the job subject.
+#[derive(Default)]
+struct ChunkRouter {
+    writers: BTreeMap<String, encoding::ChunkWriter>,
+}
+
+impl ChunkRouter {
+    fn write(&mut self, config: &ConsumeConfig, message: encoding::Message) -> Result<()> {
+        let subject = route_subject(config, &message.subject);
+        let writer = match self.writers.entry(subject) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(encoding::ChunkWriter::new(
+                config.codec.clone(),
+                &config.compression,
+                config.compression_level,
+            )?),
+        };
+        writer.write(message)
+    }
+
+    fn take(&mut self) -> BTreeMap<String, encoding::ChunkWriter> {
+        std::mem::take(&mut self.writers)
+    }
+}
+
+// subject a message's chunk is keyed and indexed with. subjects longer than
+// the subject depth are grouped under a wildcard of their first tokens.
+fn route_subject(config: &ConsumeConfig, subject: &str) -> String {
+    if !config.split_by_subject {
+        return config.subject.clone();
+    }
+    let Some(depth) = config.subject_depth else {
+        return subject.to_string();
+    };
+    let tokens: Vec<&str> = subject.split('.').collect();
+    if tokens.len() <= depth as usize {
+        return subject.to_string();
+    }
+    format!("{}.>", tokens[..depth as usize].join("."))
+}
+
+// outcome of decoding a streamed chunk
+#[derive(Default)]
+struct DecodedChunk {
+    // verified chunk hash, none if the chunk could not be read to the end
+    hash: Option<Bytes>,
+    // sequences of records that failed their checksum, where known
+    damaged: Vec<Option<u64>>,
+    // error that stopped a partial recovery before the end of the chunk
+    unreadable: Option<encoding::ChunkError>,
+}
+
+impl DecodedChunk {
+    fn is_damaged(&self) -> bool {
+        !self.damaged.is_empty() || self.unreadable.is_some()
+    }
+
+    fn recovery(&self, recovered_count: i64) -> db::ChunkRecovery {
+        db::ChunkRecovery {
+            recovered_count,
+            lost_sequences: self.damaged.iter().flatten().map(|s| *s as i64).collect(),
+            lost_sequences_complete: self.unreadable.is_none()
+                && self.damaged.iter().all(Option::is_some),
+        }
+    }
+}
+
+// stream sequences a store job has archived. a batch can hold redelivered
+// messages below the ones of a batch sealed before it, and a message can be
+// delivered and not acked without being buffered at all, so a completed batch
+// only moves the watermark up to the lowest sequence still buffered and no
+// further than the consumer's ack floor
+#[derive(Debug)]
+struct Watermark {
+    // every message delivered at or below it is archived
+    archived: Option<u64>,
+    // highest sequence of any completed batch
+    completed: Option<u64>,
+}
+
+impl Watermark {
+    fn new(checkpoint: Option<u64>) -> Self {
+        Self {
+            archived: checkpoint,
+            completed: checkpoint,
+        }
+    }
+
+    fn contains(&self, sequence: u64) -> bool {
+        self.archived.is_some_and(|archived| sequence <= archived)
+    }
+
+    // record a completed batch given the lowest sequence still buffered and
+    // the consumer's ack floor, returning the new watermark if it moved
+    fn complete(
+        &mut self,
+        sequence_max: Option<u64>,
+        floor: Option<u64>,
+        acked: u64,
+    ) -> Option<u64> {
+        self.completed = self.completed.max(sequence_max);
+        let mut archived = self.completed?.min(acked);
+        if let Some(floor) = floor {
+            archived = archived.min(floor.saturating_sub(1));
+        }
+        if archived == 0 || Some(archived) <= self.archived {
+            return None;
+        }
+        self.archived = Some(archived);
+        Some(archived)
+    }
+}
+
+// messages written to the same chunks, acked together once they are uploaded
+#[derive(Default)]
+struct Batch {
+    // message ackers with their stream sequence
+    ackers: Vec<(u64, Arc<Acker>)>,
+    // highest stream sequence in the batch
+    sequence_max: Option<u64>,
+    // when the first message was pushed or the batch was last kept alive,
+    // updated by the keep alive under a read lock
+    touched: std::sync::Mutex<Option<time::Instant>>,
+}
+
+impl Batch {
+    fn sequence_min(&self) -> Option<u64> {
+        self.ackers.iter().map(|(sequence, _)| *sequence).min()
+    }
+
+    // whether the batch was last touched longer than due ago, touching it if so
+    fn is_due(&self, due: time::Duration) -> bool {
+        let mut touched = self.touched.lock().unwrap();
+        if touched.is_none_or(|touched| touched.elapsed() < due) {
+            return false;
+        }
+        *touched = Some(time::Instant::now());
+        true
+    }
+}
+
+// messages to send progress acks for. with AckAll one progress ack on the
+// newest message is enough
+fn progress_ackers<'a>(
+    batches: impl Iterator<Item = &'a Batch>,
+    due: time::Duration,
+    ack_policy: AckPolicy,
+) -> Vec<Arc<Acker>> {
+    let due_ackers = batches
+        .filter(|batch| batch.is_due(due))
+        .flat_map(|batch| batch.ackers.iter());
+    match ack_policy {
+        AckPolicy::Explicit => due_ackers.map(|(_, acker)| acker.clone()).collect(),
+        AckPolicy::All => due_ackers
+            .max_by_key(|(sequence, _)| *sequence)
+            .map(|(_, acker)| acker.clone())
+            .into_iter()
+            .collect(),
+    }
+}
+
+// with AckAll, the sequence whose ack completes a batch. an ack covers every
+// message delivered before it, so it has to stay below the lowest sequence
+// still buffered. messages of the batch above it are acked by a later batch
+// or redelivered and dropped at the checkpoint
+fn ack_all_target(sequences: impl Iterator<Item = u64>, floor: Option<u64>) -> Option<u64> {
+    sequences
+        .filter(|sequence| floor.is_none_or(|floor| *sequence < floor))
+        .max()
+}
+
+fn count_acks(metrics: &metrics::Metrics, kind: &str, count: u64) {
+    metrics
+        .io
+        .nats_acks_total
+        .get_or_create(&metrics::AckKindLabel {
+            kind: kind.to_string(),
+        })
+        .inc_by(count);
+}
+
+// MessageBuffer is a thread safe Vec<Acker>, holding on to the ability to
+// ack messages whose contents have already been written to a chunk. Sealed
+// batches wait in order for their chunks to upload.
 struct MessageBuffer {
-    messages: Arc<RwLock<Vec<jetstream::Message>>>,
+    messages: Arc<RwLock<Batch>>,
+    sealed: Arc<RwLock<VecDeque<Batch>>>,
+    ack_policy: AckPolicy,
+    metrics: metrics::Metrics,
     cancel_token: CancellationToken,
     pause_token: CancellationToken,
 }
 
 impl MessageBuffer {
-    fn new(cancel_token: CancellationToken, pause_token: CancellationToken) -> Self {
+    fn new(
+        ack_policy: AckPolicy,
+        metrics: metrics::Metrics,
+        cancel_token: CancellationToken,
+        pause_token: CancellationToken,
+    ) -> Self {
         Self {
-            messages: Arc::new(RwLock::new(Vec::new())),
+            messages: Arc::new(RwLock::new(Batch::default())),
+            sealed: Arc::new(RwLock::new(VecDeque::new())),
+            ack_policy,
+            metrics,
             cancel_token,
             pause_token,
         }
     }
 
-    // starts a thread periodically keeping nats messages alive
-    fn keep_alive(&self, interval: time::Duration) {
+    // starts a thread keeping buffered messages from being redelivered. a
+    // batch is only kept alive once half the ack wait has passed since it was
+    // last touched, so batches acked sooner send no progress acks at all
+    fn keep_alive(&self, ack_wait: time::Duration) {
+        let ack_wait = if ack_wait.is_zero() {
+            DEFAULT_ACK_WAIT
+        } else {
+            ack_wait
+        };
         let messages = self.messages.clone();
+        let sealed = self.sealed.clone();
+        let ack_policy = self.ack_policy;
+        let metrics = self.metrics.clone();
         let cancel_token = self.cancel_token.clone();
         let pause_token = self.pause_token.clone();
 
         tokio::spawn(async move {
-            let mut interval = time::interval(interval);
+            let mut interval = time::interval(ack_wait / 4);
             loop {
                 tokio::select! {
                     _ = interval.tick() => {
-                        let messages = messages.read().await;
-                        for i in 0..messages.len() {
-                            let message = &messages[i];
-                            match message
-                                .ack_with(jetstream::message::AckKind::Progress)
-                                .await
-                            {
-                                Ok(()) => {}
-                                Err(err) => warn!(err = ?err, "message ack::progress"),
-                            }
-                        }
-                        drop(messages);
+                        // acks are sent after the locks are released, so
+                        // messages keep coming in meanwhile
+                        let ackers = {
+                            let messages = messages.read().await;
+                            let sealed = sealed.read().await;
+                            let batches = sealed.iter().chain(std::iter::once(&*messages));
+                            progress_ackers(batches, ack_wait / 2, ack_policy)
+                        };
+                        let count = ackers.len() as u64;
+                        futures::stream::iter(ackers)
+                            .for_each_concurrent(PROGRESS_ACKS_IN_FLIGHT, |acker| async move {
+                                if let Err(err) = acker.ack_with(jetstream::message::AckKind::Progress).await {
+                                    warn!(err = ?err, "message ack::progress");
+                                }
+                            })
+                            .await;
+                        count_acks(&metrics, metrics::ACK_KIND_PROGRESS, count);
                     }
                     _ = cancel_token.cancelled() => {
                         debug!("keepalive thread cancelled (cancel)");
@@ -486,32 +1853,338 @@ impl MessageBuffer {
         });
     }
 
-    // push message onto vec
-    async fn push(&self, message: jetstream::Message) -> () {
-        self.messages.clone().write_owned().await.push(message);
+    // push message acker onto the open batch
+    async fn push(&self, acker: Acker, sequence: u64) -> () {
+        let mut batch = self.messages.write().await;
+        batch.ackers.push((sequence, Arc::new(acker)));
+        batch.sequence_max = batch.sequence_max.max(Some(sequence));
+        batch
+            .touched
+            .get_mut()
+            .unwrap()
+            .get_or_insert_with(time::Instant::now);
+    }
+
+    // messages in the open batch
+    async fn len(&self) -> usize {
+        self.messages.read().await.ackers.len()
     }
 
-    // clear vec
-    async fn clear(&self) -> () {
-        self.messages.clone().write_owned().await.clear();
+    // close the open batch behind any batches still uploading, returning its length
+    async fn seal(&self) -> usize {
+        let batch = std::mem::take(&mut *self.messages.write().await);
+        let len = batch.ackers.len();
+        self.sealed.write().await.push_back(batch);
+        len
     }
 
-    async fn len(&self) -> usize {
-        self.messages.clone().write_owned().await.len()
+    async fn pop_oldest(&self) -> Option<Batch> {
+        self.sealed.write().await.pop_front()
     }
-    async fn to_vec(&self) -> Vec<jetstream::Message> {
-        self.messages.clone().read().await.to_vec()
+
+    // lowest stream sequence still buffered
+    async fn sequence_floor(&self) -> Option<u64> {
+        let sealed = self
+            .sealed
+            .read()
+            .await
+            .iter()
+            .filter_map(Batch::sequence_min)
+            .min();
+        let open = self.messages.read().await.sequence_min();
+        sealed.into_iter().chain(open).min()
     }
 
-    // ack all messages with nats
-    async fn ack_all(&self) -> () {
-        let messages = self.messages.read().await;
-        for i in 0..messages.len() {
-            let message = &messages[i];
-            match message.ack().await {
-                Ok(()) => {}
-                Err(err) => warn!(err = err, "message ack"),
+    // ack the messages of a popped batch, one ack per message or with
+    // AckAll a single ack for the batch
+    async fn ack(&self, batch: &Batch) {
+        let ackers: Vec<&Arc<Acker>> = match self.ack_policy {
+            AckPolicy::Explicit => batch.ackers.iter().map(|(_, acker)| acker).collect(),
+            AckPolicy::All => {
+                let floor = self.sequence_floor().await;
+                let target =
+                    ack_all_target(batch.ackers.iter().map(|(sequence, _)| *sequence), floor);
+                batch
+                    .ackers
+                    .iter()
+                    .find(|(sequence, _)| Some(*sequence) == target)
+                    .map(|(_, acker)| acker)
+                    .into_iter()
+                    .collect()
+            }
+        };
+        for acker in &ackers {
+            if let Err(err) = acker.ack().await {
+                warn!(err = err, "message ack");
             }
         }
+        count_acks(&self.metrics, metrics::ACK_KIND_ACK, ackers.len() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn consume_config(split_by_subject: bool, subject_depth: Option<u32>) -> ConsumeConfig {
+        ConsumeConfig {
+            stream: "orders".to_string(),
+            consumer: None,
+            chunk_consumer: None,
+            subject: "orders.>".to_string(),
+            subjects: vec!["orders.>".to_string()],
+            bucket: "archive".to_string(),
+            prefix: None,
+            bytes_max: 1_000_000,
+            messages_max: 1000,
+            max_age: time::Duration::from_secs(10),
+            codec: Codec::Binary,
+            compression: Compression::None,
+            compression_level: None,
+            encrypt: false,
+            key_template: DEFAULT_KEY_TEMPLATE.to_string(),
+            deliver: Deliver::default(),
+            split_by_subject,
+            subject_depth,
+            ack_policy: AckPolicy::Explicit,
+            backup: false,
+            spool: true,
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn test_route_subject() {
+        let config = consume_config(false, None);
+        assert_eq!(route_subject(&config, "orders.eu.created"), "orders.>");
+
+        let config = consume_config(true, None);
+        assert_eq!(
+            route_subject(&config, "orders.eu.created"),
+            "orders.eu.created"
+        );
+
+        let config = consume_config(true, Some(2));
+        assert_eq!(route_subject(&config, "orders.eu.created"), "orders.eu.>");
+        assert_eq!(route_subject(&config, "orders.eu"), "orders.eu");
+        assert_eq!(route_subject(&config, "orders"), "orders");
+    }
+
+    #[test]
+    fn test_kv_archive_keys() {
+        let job = KvJob {
+            id: "job-1".to_string(),
+            name: "archive".to_string(),
+            kind: nats3_types::KvJobKind::Archive,
+            status: nats3_types::KvJobStatus::Running,
+            kv_bucket: "config".to_string(),
+            bucket: "archive".to_string(),
+            prefix: None,
+            history: false,
+            encoding: nats3_types::Encoding {
+                codec: Codec::Binary,
+                ..Default::default()
+            },
+            archive_id: None,
+            retry: None,
+            error: None,
+            created: Utc::now(),
+            updated: Utc::now(),
+        };
+        let config = ConsumeConfig::from(&job);
+
+        // keys written within one second fill several chunks with the same
+        // timestamp, each chunk still gets its own object
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let total = config.messages_max as u64 * 2 + 1;
+        let mut keys = std::collections::HashSet::new();
+        let mut restored = 0;
+        for batch in (1..=total)
+            .collect::<Vec<_>>()
+            .chunks(config.messages_max as usize)
+        {
+            let mut writer =
+                encoding::ChunkWriter::new(config.codec.clone(), &config.compression, None)
+                    .unwrap();
+            for &sequence in batch {
+                writer
+                    .write(encoding::Message {
+                        subject: format!("$KV.config.key-{}", sequence),
+                        payload: Bytes::from_static(b"value"),
+                        headers: None,
+                        length: 5,
+                        timestamp,
+                        sequence,
+                        stream: config.stream.clone(),
+                        consumer_sequence: sequence,
+                        delivered: 1,
+                    })
+                    .unwrap();
+            }
+            let chunk = writer.finish().unwrap();
+            let key = chunk
+                .key(&config, &config.subject)
+                .render(&config.key_template)
+                .unwrap();
+            assert!(keys.insert(key.clone()), "{key}");
+
+            let mut data = Vec::new();
+            chunk.data.reader().unwrap().read_to_end(&mut data).unwrap();
+            let data = Bytes::from(data);
+            let decoded = encoding::Chunk::deserialize(
+                &data,
+                config.codec.clone(),
+                &config.compression,
+                &chunk.version,
+            )
+            .unwrap();
+            restored += decoded.block.messages.len() as u64;
+        }
+        assert_eq!(keys.len(), 3);
+        assert_eq!(restored, total);
+    }
+
+    async fn acker() -> Acker {
+        let client = async_nats::ConnectOptions::new()
+            .retry_on_initial_connect()
+            .connect("nats://127.0.0.1:1")
+            .await
+            .unwrap();
+        Acker::new(jetstream::new(client), None)
+    }
+
+    #[tokio::test]
+    async fn test_batch_fills_while_uploading() {
+        let messages_max = 10;
+        let buffer = MessageBuffer::new(
+            AckPolicy::Explicit,
+            metrics::Metrics::default(),
+            CancellationToken::new(),
+            CancellationToken::new(),
+        );
+
+        // the consumer delivers while fewer than max ack pending messages are
+        // unacked, and no upload completes
+        let mut sequence = 0;
+        let mut uploading = 0;
+        loop {
+            let sealed: usize = buffer
+                .sealed
+                .read()
+                .await
+                .iter()
+                .map(|b| b.ackers.len())
+                .sum();
+            let unacked = sealed + buffer.len().await;
+            if unacked as i64 >= max_ack_pending(messages_max) {
+                break;
+            }
+            sequence += 1;
+            buffer.push(acker().await, sequence).await;
+            if buffer.len().await == messages_max as usize && uploading < UPLOADS_IN_FLIGHT {
+                buffer.seal().await;
+                uploading += 1;
+            }
+        }
+
+        // every upload slot is taken and the next batch is full behind them
+        assert_eq!(uploading, UPLOADS_IN_FLIGHT);
+        assert_eq!(buffer.len().await, messages_max as usize);
+        assert_eq!(buffer.sequence_floor().await, Some(1));
+    }
+
+    // a batch of messages last touched idle ago
+    async fn idle_batch(sequences: &[u64], idle: time::Duration) -> Batch {
+        let mut batch = Batch::default();
+        for sequence in sequences {
+            batch.ackers.push((*sequence, Arc::new(acker().await)));
+        }
+        *batch.touched.get_mut().unwrap() = Some(time::Instant::now() - idle);
+        batch
+    }
+
+    #[tokio::test]
+    async fn test_progress_ackers() {
+        let due = time::Duration::from_secs(10);
+        let batches = [
+            idle_batch(&[1, 2, 3], due * 2).await,
+            idle_batch(&[4, 5], due * 2).await,
+            idle_batch(&[6], due / 10).await,
+        ];
+
+        // every message of the due batches, once per due interval
+        let ackers = progress_ackers(batches.iter(), due, AckPolicy::Explicit);
+        assert_eq!(ackers.len(), 5);
+        assert!(progress_ackers(batches.iter(), due, AckPolicy::Explicit).is_empty());
+
+        // a single ack on the newest message of the due batches
+        for batch in &batches[..2] {
+            *batch.touched.lock().unwrap() = Some(time::Instant::now() - due * 2);
+        }
+        let ackers = progress_ackers(batches.iter(), due, AckPolicy::All);
+        assert_eq!(ackers.len(), 1);
+        assert!(Arc::ptr_eq(&ackers[0], &batches[1].ackers[1].1));
+    }
+
+    #[test]
+    fn test_watermark() {
+        let mut watermark = Watermark::new(None);
+        assert!(!watermark.contains(1));
+
+        // 4 was redelivered into the open batch after the batch holding 8
+        // was sealed, so completing that batch only covers up to 3
+        assert_eq!(watermark.complete(Some(8), Some(4), 8), Some(3));
+        assert!(watermark.contains(3));
+        assert!(!watermark.contains(4));
+        assert!(!watermark.contains(6));
+
+        // nothing below the open batch completed
+        assert_eq!(watermark.complete(None, Some(4), 8), None);
+
+        // the batch holding 4 completes and everything up to 9 is archived
+        assert_eq!(watermark.complete(Some(9), None, 9), Some(9));
+        assert!(watermark.contains(8));
+
+        // a batch of only old redeliveries doesn't move it back
+        assert_eq!(watermark.complete(Some(5), None, 9), None);
+        assert!(watermark.contains(9));
+
+        let watermark = Watermark::new(Some(42));
+        assert!(watermark.contains(42));
+        assert!(!watermark.contains(43));
+    }
+
+    #[test]
+    fn test_watermark_ack_floor() {
+        let mut watermark = Watermark::new(None);
+
+        // 4 was delivered but never acked or buffered, so the consumer's ack
+        // floor stays at 3 while the batch holding 5 to 8 completes
+        assert_eq!(watermark.complete(Some(8), None, 3), Some(3));
+        assert!(!watermark.contains(4));
+        assert!(!watermark.contains(8));
+
+        // nothing acked yet, nothing archived
+        assert_eq!(watermark.complete(Some(12), None, 0), None);
+
+        // 4 comes back after ack wait and is archived, not dropped as already
+        // archived. its batch completes and the ack floor moves past it
+        assert!(!watermark.contains(4));
+        assert_eq!(watermark.complete(Some(4), None, 12), Some(12));
+        assert!(watermark.contains(4));
+        assert!(watermark.contains(12));
+    }
+
+    #[test]
+    fn test_ack_all_target() {
+        // nothing else buffered, the highest message acks the batch
+        assert_eq!(ack_all_target([3, 1, 2].into_iter(), None), Some(3));
+        // later batches start above the batch
+        assert_eq!(ack_all_target([1, 2, 3].into_iter(), Some(4)), Some(3));
+        // a redelivered message buffered after the batch caps the ack below it
+        assert_eq!(ack_all_target([4, 5, 7].into_iter(), Some(6)), Some(5));
+        assert_eq!(ack_all_target([4, 5].into_iter(), Some(2)), None);
+        assert_eq!(ack_all_target(std::iter::empty(), None), None);
     }
 }
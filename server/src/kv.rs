@@ -0,0 +1,93 @@
+// Naming of the JetStream resources behind a key-value bucket.
+//
+// A bucket is the stream KV_<bucket>, each key the subject $KV.<bucket>.<key>.
+// Deletes and purges are messages on the key's subject with a KV-Operation
+// header, so archiving the stream keeps delete markers and restoring it is
+// publishing each message, in stream order, to the key in the target bucket.
+
+use std::collections::BTreeMap;
+
+// most revisions a bucket can keep per key
+pub const MAX_HISTORY: i64 = 64;
+
+// headers asserting the stream state at publish time. they hold for the
+// bucket the message was first written to, not the one it is restored into.
+const EXPECTED_HEADER_PREFIX: &str = "Nats-Expected-";
+
+pub fn stream_name(bucket: &str) -> String {
+    format!("KV_{}", bucket)
+}
+
+// subject filter matching every key in a bucket
+pub fn filter_subject(bucket: &str) -> String {
+    format!("$KV.{}.>", bucket)
+}
+
+pub fn subject(bucket: &str, key: &str) -> String {
+    format!("$KV.{}.{}", bucket, key)
+}
+
+// key a bucket subject is written to, none if the subject isn't in the bucket
+pub fn key<'a>(bucket: &str, subject: &'a str) -> Option<&'a str> {
+    subject
+        .strip_prefix("$KV.")?
+        .strip_prefix(bucket)?
+        .strip_prefix('.')
+        .filter(|key| !key.is_empty())
+}
+
+// consumer an archive job reads its bucket with, also recorded on its chunks
+pub fn archive_consumer(job_id: &str) -> String {
+    format!("nats3-kv-{}", job_id)
+}
+
+// headers of an archived message to restore it with
+pub fn restore_headers(
+    headers: Option<BTreeMap<String, Vec<String>>>,
+) -> Option<BTreeMap<String, Vec<String>>> {
+    let headers: BTreeMap<_, _> = headers?
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with(EXPECTED_HEADER_PREFIX))
+        .collect();
+    Some(headers).filter(|headers| !headers.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        assert_eq!(key("config", "$KV.config.app.timeout"), Some("app.timeout"));
+        assert_eq!(key("config", "$KV.config2.app"), None);
+        assert_eq!(key("config", "$KV.other.app"), None);
+        assert_eq!(key("config", "$KV.config."), None);
+        assert_eq!(key("config", "config.app"), None);
+        assert_eq!(key("config", &subject("config", "a.b")), Some("a.b"));
+    }
+
+    #[test]
+    fn test_restore_headers() {
+        let headers = BTreeMap::from([
+            ("KV-Operation".to_string(), vec!["DEL".to_string()]),
+            (
+                "Nats-Expected-Last-Subject-Sequence".to_string(),
+                vec!["7".to_string()],
+            ),
+        ]);
+        assert_eq!(
+            restore_headers(Some(headers)),
+            Some(BTreeMap::from([(
+                "KV-Operation".to_string(),
+                vec!["DEL".to_string()]
+            )]))
+        );
+
+        let headers = BTreeMap::from([(
+            "Nats-Expected-Last-Subject-Sequence".to_string(),
+            vec!["0".to_string()],
+        )]);
+        assert_eq!(restore_headers(Some(headers)), None);
+        assert_eq!(restore_headers(None), None);
+    }
+}
@@ -6,19 +6,27 @@ use tracing::{debug, info, warn};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
 mod app;
+mod backup;
+mod body;
 mod completer;
 mod config;
 mod coordinator;
 mod db;
 mod encoding;
+mod encryption;
 mod error;
 mod io;
+mod kv;
 mod metrics;
 mod nats;
+mod object;
 mod registry;
+mod retry;
 mod s3;
 mod server;
 mod shutdown;
+mod signing;
+mod spool;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
@@ -49,11 +57,16 @@ async fn main() -> Result<(), Error> {
     // Restart existing jobs
     app.start_store_jobs().await?;
     app.start_load_jobs().await?;
+    app.fail_interrupted_kv_jobs().await?;
+    app.fail_interrupted_object_jobs().await?;
 
     // Thread periodically cleaning up async threads
     let cleanup_token = shutdown.subscribe();
     app.start_task_completer(cleanup_token);
 
+    // Thread draining spooled chunks to S3
+    app.start_spool_uploader(shutdown.subscribe());
+
     // start server
     let server_token = shutdown.subscribe();
     let server_handle = {
@@ -21,6 +21,16 @@ pub struct DirectionLabel {
     pub direction: String,
 }
 
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct AckKindLabel {
+    pub kind: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ServiceLabel {
+    pub service: String,
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct JobMetrics {
     pub jobs_total: Family<JobLabels, Counter>,
@@ -33,6 +43,12 @@ pub struct IoMetrics {
     pub nats_bytes_total: Family<DirectionLabel, Counter>,
     pub s3_objects_total: Family<DirectionLabel, Counter>,
     pub s3_bytes_total: Family<DirectionLabel, Counter>,
+    pub nats_duplicates_total: Counter,
+    pub nats_acks_total: Family<AckKindLabel, Counter>,
+    pub retries_total: Family<ServiceLabel, Counter>,
+    pub spool_chunks: Gauge,
+    pub spool_bytes: Gauge,
+    pub spool_failed_total: Counter,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -78,6 +94,36 @@ impl Metrics {
             "Total S3 bytes processed",
             io.s3_bytes_total.clone(),
         );
+        registry.register(
+            "nats3_nats_duplicates_total",
+            "Redelivered NATS messages dropped as already archived",
+            io.nats_duplicates_total.clone(),
+        );
+        registry.register(
+            "nats3_nats_acks_total",
+            "Acks sent to NATS for stored messages",
+            io.nats_acks_total.clone(),
+        );
+        registry.register(
+            "nats3_retries_total",
+            "Retried S3, NATS and Postgres operations",
+            io.retries_total.clone(),
+        );
+        registry.register(
+            "nats3_spool_chunks",
+            "Chunks waiting in the spool for upload",
+            io.spool_chunks.clone(),
+        );
+        registry.register(
+            "nats3_spool_bytes",
+            "Bytes waiting in the spool for upload",
+            io.spool_bytes.clone(),
+        );
+        registry.register(
+            "nats3_spool_failed_total",
+            "Spooled chunks moved to failed/ after a permanent upload error",
+            io.spool_failed_total.clone(),
+        );
 
         Metrics {
             registry: Arc::new(registry),
@@ -90,7 +136,14 @@ impl Metrics {
 // Usage constants
 pub const JOB_TYPE_STORE: &str = "store";
 pub const JOB_TYPE_LOAD: &str = "load";
+pub const JOB_TYPE_KV: &str = "kv";
+pub const JOB_TYPE_OBJECT: &str = "object";
 pub const STATUS_COMPLETED: &str = "completed";
 pub const STATUS_FAILED: &str = "failed";
 pub const DIRECTION_IN: &str = "in";
 pub const DIRECTION_OUT: &str = "out";
+pub const ACK_KIND_ACK: &str = "ack";
+pub const ACK_KIND_PROGRESS: &str = "progress";
+pub const SERVICE_S3: &str = "s3";
+pub const SERVICE_NATS: &str = "nats";
+pub const SERVICE_POSTGRES: &str = "postgres";
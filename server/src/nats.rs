@@ -1,16 +1,164 @@
-use anyhow::{Context, Error, Result};
+use anyhow::{bail, Context, Error, Result};
 use async_nats::{
-    header::HeaderMap,
+    header::{HeaderMap, NATS_MESSAGE_ID},
     jetstream::{
         self,
-        consumer::{pull::Stream, PullConsumer},
+        consumer::{self, pull::Stream, AckPolicy, PullConsumer},
+        context::{ConsumerInfoError, ConsumerInfoErrorKind, GetStreamError, GetStreamErrorKind},
+        object_store::ObjectStore,
+        stream::{ConsumerError as StreamConsumerError, ConsumerErrorKind},
+        ErrorCode,
     },
 };
 use bytes::Bytes;
-use std::collections::BTreeMap;
-use tracing::{debug, trace};
+use futures::TryStreamExt;
+use nats3_types::{Deliver, DeliverPolicy, Retry};
+use std::{collections::BTreeMap, time::Duration};
+use thiserror::Error;
+use tracing::{debug, trace, warn};
 
-use crate::metrics;
+use crate::{backup, kv, metrics, object, retry};
+
+// how long an archive consumer outlives an interrupted archive job
+const ARCHIVE_INACTIVE_THRESHOLD: Duration = Duration::from_secs(300);
+
+#[derive(Error, Debug)]
+pub enum ConsumerError {
+    #[error("stream {stream} not found")]
+    StreamNotFound { stream: String },
+    #[error("consumer {consumer} not found on stream {stream}")]
+    NotFound { stream: String, consumer: String },
+    #[error("consumer {consumer} filters {filters:?}, store job subjects are {subjects:?}")]
+    FilterMismatch {
+        consumer: String,
+        filters: Vec<String>,
+        subjects: Vec<String>,
+    },
+    #[error("consumer {consumer} has ack policy {policy:?}, store job acks with {expected:?}")]
+    AckPolicy {
+        consumer: String,
+        policy: AckPolicy,
+        expected: AckPolicy,
+    },
+    #[error("consumer {consumer} is a push consumer, store jobs need a pull consumer")]
+    PushConsumer { consumer: String },
+    #[error(
+        "consumer {consumer} allows {max_ack_pending} unacked messages, store job needs {required}"
+    )]
+    MaxAckPending {
+        consumer: String,
+        max_ack_pending: i64,
+        required: i64,
+    },
+    #[error("key-value bucket {bucket} not found")]
+    KvBucketNotFound { bucket: String },
+    #[error("object store bucket {bucket} not found")]
+    ObjectBucketNotFound { bucket: String },
+    #[error("consumer {consumer} is already used by store job {job_id}")]
+    InUse { consumer: String, job_id: String },
+    #[error("get stream: {0}")]
+    Stream(#[from] GetStreamError),
+    #[error("consumer info: {0}")]
+    Info(#[from] ConsumerInfoError),
+    #[error("delete consumer: {0}")]
+    Delete(#[from] StreamConsumerError),
+}
+
+// durable consumer created for a store job that is not bound to a consumer
+pub fn consumer_name(job_id: &str) -> String {
+    format!("nats3-store-{}", job_id)
+}
+
+// durable consumer a store job reads from, bound or created
+pub fn store_job_consumer(job_id: &str, consumer: Option<&String>) -> String {
+    consumer.cloned().unwrap_or_else(|| consumer_name(job_id))
+}
+
+// consumer deliver policy for a store job's deliver settings
+fn deliver_policy(deliver: &Deliver) -> Result<consumer::DeliverPolicy, Error> {
+    let policy = match deliver.policy {
+        DeliverPolicy::All => consumer::DeliverPolicy::All,
+        DeliverPolicy::New => consumer::DeliverPolicy::New,
+        DeliverPolicy::Last => consumer::DeliverPolicy::Last,
+        DeliverPolicy::ByStartSequence => consumer::DeliverPolicy::ByStartSequence {
+            start_sequence: deliver.start_sequence.context("deliver start sequence")?,
+        },
+        DeliverPolicy::ByStartTime => {
+            let start_time = deliver.start_time.context("deliver start time")?;
+            consumer::DeliverPolicy::ByStartTime {
+                start_time: time::OffsetDateTime::from_unix_timestamp_nanos(
+                    start_time
+                        .timestamp_nanos_opt()
+                        .context("deliver start time out of range")?
+                        .into(),
+                )?,
+            }
+        }
+    };
+    Ok(policy)
+}
+
+// consumer ack policy for a store job's ack policy
+fn ack_policy(policy: nats3_types::AckPolicy) -> AckPolicy {
+    match policy {
+        nats3_types::AckPolicy::Explicit => AckPolicy::Explicit,
+        nats3_types::AckPolicy::All => AckPolicy::All,
+    }
+}
+
+// check an existing consumer delivers what the store job expects
+fn check_consumer_config(
+    name: &str,
+    config: &consumer::Config,
+    subjects: &[String],
+    policy: nats3_types::AckPolicy,
+) -> Result<(), ConsumerError> {
+    if config.deliver_subject.is_some() {
+        return Err(ConsumerError::PushConsumer {
+            consumer: name.to_string(),
+        });
+    }
+    if config.ack_policy != ack_policy(policy) {
+        return Err(ConsumerError::AckPolicy {
+            consumer: name.to_string(),
+            policy: config.ack_policy,
+            expected: ack_policy(policy),
+        });
+    }
+    let mut filters = config.filter_subjects.clone();
+    if !config.filter_subject.is_empty() {
+        filters.push(config.filter_subject.clone());
+    }
+    let mut expected = subjects.to_vec();
+    filters.sort();
+    expected.sort();
+    if filters != expected {
+        return Err(ConsumerError::FilterMismatch {
+            consumer: name.to_string(),
+            filters,
+            subjects: subjects.to_vec(),
+        });
+    }
+    Ok(())
+}
+
+// check an existing consumer lets the store job hold every batch it may have
+// unacked, otherwise the next batch can't fill while one is uploading.
+// negative is unlimited
+fn check_max_ack_pending(
+    name: &str,
+    config: &consumer::Config,
+    required: i64,
+) -> Result<(), ConsumerError> {
+    if config.max_ack_pending >= 0 && config.max_ack_pending < required {
+        return Err(ConsumerError::MaxAckPending {
+            consumer: name.to_string(),
+            max_ack_pending: config.max_ack_pending,
+            required,
+        });
+    }
+    Ok(())
+}
 
 #[derive(Clone, Debug)]
 pub struct Client {
@@ -28,75 +176,453 @@ impl Client {
         Ok(client)
     }
 
-    pub async fn consume(
+    // validate an existing consumer can be bound to a store job
+    pub async fn check_consumer(
+        &self,
+        stream_name: &str,
+        consumer_name: &str,
+        subjects: &[String],
+        policy: nats3_types::AckPolicy,
+        max_ack_pending: i64,
+    ) -> Result<(), ConsumerError> {
+        let jetstream = jetstream::new(self.client.clone());
+        let stream = jetstream
+            .get_stream(stream_name)
+            .await
+            .map_err(|e| match e.kind() {
+                GetStreamErrorKind::JetStream(err)
+                    if err.error_code() == ErrorCode::STREAM_NOT_FOUND =>
+                {
+                    ConsumerError::StreamNotFound {
+                        stream: stream_name.to_string(),
+                    }
+                }
+                _ => e.into(),
+            })?;
+        let info = stream
+            .consumer_info(consumer_name)
+            .await
+            .map_err(|e| match e.kind() {
+                ConsumerInfoErrorKind::NotFound => ConsumerError::NotFound {
+                    stream: stream_name.to_string(),
+                    consumer: consumer_name.to_string(),
+                },
+                _ => e.into(),
+            })?;
+        check_consumer_config(consumer_name, &info.config, subjects, policy)?;
+        check_max_ack_pending(consumer_name, &info.config, max_ack_pending)
+    }
+
+    // consume from an existing consumer, after checking it matches the job.
+    // returns the messages and the consumer's ack wait
+    pub async fn bind(
         &self,
         stream_name: String,
-        subject: String,
+        consumer_name: String,
+        subjects: &[String],
+        policy: nats3_types::AckPolicy,
         max_ack_pending: i64,
-    ) -> Result<Stream, Error> {
-        debug!(stream = stream_name, subject = subject, "consume stream");
+    ) -> Result<(Stream, Duration), Error> {
+        debug!(
+            stream = stream_name,
+            consumer = consumer_name,
+            subjects = ?subjects,
+            "bind consumer"
+        );
+        // jobs bound before the check was added keep running, only slower
+        match self
+            .check_consumer(
+                &stream_name,
+                &consumer_name,
+                subjects,
+                policy,
+                max_ack_pending,
+            )
+            .await
+        {
+            Err(e @ ConsumerError::MaxAckPending { .. }) => {
+                warn!(error = %e, "batches won't upload while the next one fills")
+            }
+            result => result?,
+        }
+
         let jetstream = jetstream::new(self.client.clone());
+        let stream = jetstream.get_stream(stream_name).await?;
+        let consumer: PullConsumer = stream
+            .get_consumer(&consumer_name)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
 
-        let stream = jetstream.get_stream(stream_name.clone()).await?;
+        let ack_wait = consumer.cached_info().config.ack_wait;
+        let messages = consumer.messages().await?;
+        Ok((messages, ack_wait))
+    }
 
-        // TODO: option to subscribe to existing consumer
-        // let consumer: PullConsumer = stream.get_consumer(&consumer_name).await?;
+    // consume from a durable consumer, created if missing. returns the
+    // messages and the consumer's ack wait
+    pub async fn consume(
+        &self,
+        stream_name: String,
+        consumer_name: String,
+        subjects: &[String],
+        deliver: &Deliver,
+        policy: nats3_types::AckPolicy,
+        max_ack_pending: i64,
+    ) -> Result<(Stream, Duration), Error> {
+        debug!(stream = stream_name, subjects = ?subjects, "consume stream");
+        let jetstream = jetstream::new(self.client.clone());
 
-        // need to replace special chars for consumer names
-        let name = subject
-            .replace(".", "_")
-            .replace(">", "_")
-            .replace("*", "_");
+        let stream = jetstream.get_stream(stream_name.clone()).await?;
 
-        let filter_subject = subject.clone();
+        // a single subject uses filter_subject, which older servers support
+        let (filter_subject, filter_subjects) = match subjects {
+            [subject] => (subject.clone(), Vec::new()),
+            subjects => (String::new(), subjects.to_vec()),
+        };
 
         debug!(
-            name = name,
-            filter_subject = subject.clone(),
+            name = consumer_name,
+            filter_subjects = ?subjects,
             "create consumer"
         );
 
         let consumer: PullConsumer = stream
             .get_or_create_consumer(
-                name.as_str(),
+                consumer_name.as_str(),
                 jetstream::consumer::pull::Config {
                     filter_subject,
-                    durable_name: Some(name.clone()),
+                    filter_subjects,
+                    durable_name: Some(consumer_name.clone()),
+                    deliver_policy: deliver_policy(deliver)?,
+                    ack_policy: ack_policy(policy),
                     max_ack_pending,
                     ..Default::default()
                 },
             )
             .await?;
+        // a consumer created by an older version isn't resized
+        if let Err(e) = check_max_ack_pending(
+            &consumer_name,
+            &consumer.cached_info().config,
+            max_ack_pending,
+        ) {
+            warn!(error = %e, "batches won't upload while the next one fills");
+        }
 
+        let ack_wait = consumer.cached_info().config.ack_wait;
         let messages = consumer.messages().await?;
-        Ok(messages)
+        Ok((messages, ack_wait))
+    }
+
+    // messages left for a consumer to deliver
+    pub async fn consumer_pending(
+        &self,
+        stream_name: &str,
+        consumer_name: &str,
+    ) -> Result<u64, Error> {
+        let jetstream = jetstream::new(self.client.clone());
+        let stream = jetstream.get_stream(stream_name).await?;
+        let info = stream.consumer_info(consumer_name).await?;
+        Ok(info.num_pending)
     }
 
+    // highest stream sequence at or below which a consumer has every message acked
+    pub async fn consumer_ack_floor(
+        &self,
+        stream_name: &str,
+        consumer_name: &str,
+    ) -> Result<u64, Error> {
+        let jetstream = jetstream::new(self.client.clone());
+        let stream = jetstream.get_stream(stream_name).await?;
+        let info = stream.consumer_info(consumer_name).await?;
+        Ok(info.ack_floor.stream_sequence)
+    }
+
+    // delete a durable consumer, a missing stream or consumer is not an error
+    pub async fn delete_consumer(
+        &self,
+        stream_name: &str,
+        consumer_name: &str,
+    ) -> Result<(), ConsumerError> {
+        debug!(
+            stream = stream_name,
+            consumer = consumer_name,
+            "delete consumer"
+        );
+        let jetstream = jetstream::new(self.client.clone());
+        let stream = match jetstream.get_stream(stream_name).await {
+            Ok(stream) => stream,
+            Err(e) => match e.kind() {
+                GetStreamErrorKind::JetStream(err)
+                    if err.error_code() == ErrorCode::STREAM_NOT_FOUND =>
+                {
+                    return Ok(())
+                }
+                _ => return Err(e.into()),
+            },
+        };
+        match stream.delete_consumer(consumer_name).await {
+            Ok(_) => Ok(()),
+            Err(e) => match e.kind() {
+                ConsumerErrorKind::JetStream(err)
+                    if err.error_code() == ErrorCode::CONSUMER_NOT_FOUND =>
+                {
+                    Ok(())
+                }
+                _ => Err(e.into()),
+            },
+        }
+    }
+
+    pub async fn stream_config(
+        &self,
+        stream_name: &str,
+    ) -> Result<jetstream::stream::Config, ConsumerError> {
+        let jetstream = jetstream::new(self.client.clone());
+        let stream = jetstream
+            .get_stream(stream_name)
+            .await
+            .map_err(|e| match e.kind() {
+                GetStreamErrorKind::JetStream(err)
+                    if err.error_code() == ErrorCode::STREAM_NOT_FOUND =>
+                {
+                    ConsumerError::StreamNotFound {
+                        stream: stream_name.to_string(),
+                    }
+                }
+                _ => e.into(),
+            })?;
+        Ok(stream.cached_info().config.clone())
+    }
+
+    // create the stream a backup is restored into. a stream left by an
+    // earlier run of the restore is kept, returns the last backed up sequence
+    // it holds
+    pub async fn create_restore_stream(
+        &self,
+        config: jetstream::stream::Config,
+    ) -> Result<u64, Error> {
+        let jetstream = jetstream::new(self.client.clone());
+        match jetstream.get_stream(&config.name).await {
+            Ok(stream) => {
+                let info = stream.cached_info();
+                if info.config.subjects != config.subjects {
+                    bail!(
+                        "stream {} exists with subjects {:?}, the backup has {:?}",
+                        config.name,
+                        info.config.subjects,
+                        config.subjects
+                    );
+                }
+                if info.state.messages == 0 {
+                    return Ok(0);
+                }
+                // resume after the backed up sequence of the last restored message
+                let message = stream.get_raw_message(info.state.last_sequence).await?;
+                let Some(restored) = backup::backup_sequence(&message.headers) else {
+                    bail!(
+                        "stream {} holds messages that are not from the backup",
+                        config.name
+                    );
+                };
+                debug!(
+                    stream = config.name,
+                    restored = restored,
+                    "continue restore into existing stream"
+                );
+                return Ok(restored);
+            }
+            Err(e) => match e.kind() {
+                GetStreamErrorKind::JetStream(err)
+                    if err.error_code() == ErrorCode::STREAM_NOT_FOUND => {}
+                _ => return Err(e.into()),
+            },
+        }
+        // a restore under another name keeps the backed up subjects, which
+        // can't be bound while the backed up stream still has them
+        let mut streams = jetstream.streams();
+        while let Some(info) = streams.try_next().await? {
+            let overlap = info.config.subjects.iter().find(|subject| {
+                config
+                    .subjects
+                    .iter()
+                    .any(|restored| backup::subjects_overlap(subject, restored))
+            });
+            if let Some(subject) = overlap {
+                bail!(
+                    "stream {} has subject {} overlapping the backup's subjects {:?}, \
+                     it can't be restored into stream {} while that stream exists",
+                    info.config.name,
+                    subject,
+                    config.subjects,
+                    config.name
+                );
+            }
+        }
+        debug!(stream = config.name, "create stream to restore");
+        jetstream.create_stream(config).await?;
+        Ok(0)
+    }
+
+    // validate a key-value bucket exists
+    pub async fn check_kv_bucket(&self, bucket: &str) -> Result<(), ConsumerError> {
+        let jetstream = jetstream::new(self.client.clone());
+        jetstream
+            .get_stream(kv::stream_name(bucket))
+            .await
+            .map_err(|e| match e.kind() {
+                GetStreamErrorKind::JetStream(err)
+                    if err.error_code() == ErrorCode::STREAM_NOT_FOUND =>
+                {
+                    ConsumerError::KvBucketNotFound {
+                        bucket: bucket.to_string(),
+                    }
+                }
+                _ => e.into(),
+            })?;
+        Ok(())
+    }
+
+    // read a key-value bucket with an ephemeral consumer, every revision of
+    // each key with history or else its latest. returns the messages and the
+    // count the consumer has to deliver
+    pub async fn archive_kv(
+        &self,
+        bucket: &str,
+        consumer_name: &str,
+        history: bool,
+    ) -> Result<(Stream, u64), Error> {
+        debug!(
+            bucket = bucket,
+            consumer = consumer_name,
+            history = history,
+            "archive key-value bucket"
+        );
+        self.check_kv_bucket(bucket).await?;
+
+        let jetstream = jetstream::new(self.client.clone());
+        let stream = jetstream.get_stream(kv::stream_name(bucket)).await?;
+        let deliver_policy = if history {
+            consumer::DeliverPolicy::All
+        } else {
+            consumer::DeliverPolicy::LastPerSubject
+        };
+        let consumer: PullConsumer = stream
+            .create_consumer(jetstream::consumer::pull::Config {
+                name: Some(consumer_name.to_string()),
+                filter_subject: kv::filter_subject(bucket),
+                deliver_policy,
+                ack_policy: AckPolicy::None,
+                inactive_threshold: ARCHIVE_INACTIVE_THRESHOLD,
+                ..Default::default()
+            })
+            .await?;
+
+        let pending = consumer.cached_info().num_pending;
+        let messages = consumer.messages().await?;
+        Ok((messages, pending))
+    }
+
+    // create a key-value bucket to restore into unless it exists
+    pub async fn create_kv_bucket(&self, bucket: &str, history: i64) -> Result<(), Error> {
+        match self.check_kv_bucket(bucket).await {
+            Ok(()) => return Ok(()),
+            Err(ConsumerError::KvBucketNotFound { .. }) => {}
+            Err(e) => return Err(e.into()),
+        }
+        debug!(
+            bucket = bucket,
+            history = history,
+            "create key-value bucket"
+        );
+        let jetstream = jetstream::new(self.client.clone());
+        jetstream
+            .create_key_value(jetstream::kv::Config {
+                bucket: bucket.to_string(),
+                history,
+                ..Default::default()
+            })
+            .await?;
+        Ok(())
+    }
+
+    // validate an object store bucket exists
+    pub async fn check_object_bucket(&self, bucket: &str) -> Result<(), ConsumerError> {
+        let jetstream = jetstream::new(self.client.clone());
+        jetstream
+            .get_stream(object::stream_name(bucket))
+            .await
+            .map_err(|e| match e.kind() {
+                GetStreamErrorKind::JetStream(err)
+                    if err.error_code() == ErrorCode::STREAM_NOT_FOUND =>
+                {
+                    ConsumerError::ObjectBucketNotFound {
+                        bucket: bucket.to_string(),
+                    }
+                }
+                _ => e.into(),
+            })?;
+        Ok(())
+    }
+
+    pub async fn object_store(&self, bucket: &str) -> Result<ObjectStore, Error> {
+        self.check_object_bucket(bucket).await?;
+        let jetstream = jetstream::new(self.client.clone());
+        Ok(jetstream.get_object_store(bucket).await?)
+    }
+
+    // an object store bucket to restore into, created unless it exists
+    pub async fn create_object_store(&self, bucket: &str) -> Result<ObjectStore, Error> {
+        match self.check_object_bucket(bucket).await {
+            Ok(()) => return self.object_store(bucket).await,
+            Err(ConsumerError::ObjectBucketNotFound { .. }) => {}
+            Err(e) => return Err(e.into()),
+        }
+        debug!(bucket = bucket, "create object store bucket");
+        let jetstream = jetstream::new(self.client.clone());
+        Ok(jetstream
+            .create_object_store(jetstream::object_store::Config {
+                bucket: bucket.to_string(),
+                ..Default::default()
+            })
+            .await?)
+    }
+
+    // a message whose ack timed out may still have been stored, a retried
+    // publish is only dropped as a duplicate when it carries a Nats-Msg-Id
     pub async fn publish(
         &self,
         subject: String,
         payload: Bytes,
         headers: Option<BTreeMap<String, Vec<String>>>,
+        retry: &Retry,
     ) -> Result<(), Error> {
         let byte_count = payload.len();
         trace!(bytes = byte_count, subject = subject, "publish message");
         let jetstream = jetstream::new(self.client.clone());
 
-        let ack = if let Some(headers_map) = headers {
+        let headers = headers.map(|headers_map| {
             let mut nats_headers = HeaderMap::new();
             for (key, values) in headers_map {
                 for value in values {
                     nats_headers.append(key.as_str(), value.as_str());
                 }
             }
-            jetstream
-                .publish_with_headers(subject, nats_headers, payload)
-                .await?
-        } else {
-            jetstream.publish(subject, payload).await?
-        };
+            nats_headers
+        });
 
-        ack.await?;
+        retry::retry(retry, metrics::SERVICE_NATS, &self.metrics, || async {
+            let ack = match &headers {
+                Some(headers) => {
+                    jetstream
+                        .publish_with_headers(subject.clone(), headers.clone(), payload.clone())
+                        .await?
+                }
+                None => jetstream.publish(subject.clone(), payload.clone()).await?,
+            };
+            ack.await
+        })
+        .await?;
 
         self.metrics
             .io
@@ -115,3 +641,201 @@ impl Client {
         Ok(())
     }
 }
+
+// headers to replay a message with, carrying a dedupe id derived from the
+// replaying job and the stream and sequence it was archived from, so retries
+// of one job are dropped and another job replaying the same messages is not.
+// an id it was published with is kept
+pub fn with_message_id(
+    headers: Option<BTreeMap<String, Vec<String>>>,
+    job_id: &str,
+    stream: &str,
+    sequence: u64,
+) -> Option<BTreeMap<String, Vec<String>>> {
+    if stream.is_empty() || sequence == 0 {
+        return headers;
+    }
+    let mut headers = headers.unwrap_or_default();
+    headers
+        .entry(NATS_MESSAGE_ID.to_string())
+        .or_insert_with(|| vec![format!("{}:{}:{}", job_id, stream, sequence)]);
+    Some(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nats3_types::AckPolicy as JobAckPolicy;
+
+    fn subjects(subjects: &[&str]) -> Vec<String> {
+        subjects.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn pull_config(filter_subject: &str) -> consumer::Config {
+        consumer::Config {
+            durable_name: Some("archiver".to_string()),
+            filter_subject: filter_subject.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_consumer_config() {
+        let config = pull_config("orders.created");
+        assert!(check_consumer_config(
+            "archiver",
+            &config,
+            &subjects(&["orders.created"]),
+            JobAckPolicy::Explicit
+        )
+        .is_ok());
+
+        let config = consumer::Config {
+            filter_subjects: vec!["orders.created".to_string()],
+            ..pull_config("")
+        };
+        assert!(check_consumer_config(
+            "archiver",
+            &config,
+            &subjects(&["orders.created"]),
+            JobAckPolicy::Explicit
+        )
+        .is_ok());
+
+        assert!(matches!(
+            check_consumer_config(
+                "archiver",
+                &pull_config("orders.*"),
+                &subjects(&["orders.created"]),
+                JobAckPolicy::Explicit
+            ),
+            Err(ConsumerError::FilterMismatch { .. })
+        ));
+        assert!(matches!(
+            check_consumer_config(
+                "archiver",
+                &pull_config(""),
+                &subjects(&["orders.created"]),
+                JobAckPolicy::Explicit
+            ),
+            Err(ConsumerError::FilterMismatch { .. })
+        ));
+
+        let config = consumer::Config {
+            ack_policy: AckPolicy::None,
+            ..pull_config("orders.created")
+        };
+        assert!(matches!(
+            check_consumer_config(
+                "archiver",
+                &config,
+                &subjects(&["orders.created"]),
+                JobAckPolicy::Explicit
+            ),
+            Err(ConsumerError::AckPolicy { .. })
+        ));
+
+        // the consumer acks the way the job does
+        let config = consumer::Config {
+            ack_policy: AckPolicy::All,
+            ..pull_config("orders.created")
+        };
+        let orders = subjects(&["orders.created"]);
+        assert!(check_consumer_config("archiver", &config, &orders, JobAckPolicy::All).is_ok());
+        assert!(matches!(
+            check_consumer_config("archiver", &config, &orders, JobAckPolicy::Explicit),
+            Err(ConsumerError::AckPolicy { .. })
+        ));
+        assert!(matches!(
+            check_consumer_config(
+                "archiver",
+                &pull_config("orders.created"),
+                &orders,
+                JobAckPolicy::All
+            ),
+            Err(ConsumerError::AckPolicy { .. })
+        ));
+
+        let config = consumer::Config {
+            deliver_subject: Some("deliver.orders".to_string()),
+            ..pull_config("orders.created")
+        };
+        assert!(matches!(
+            check_consumer_config(
+                "archiver",
+                &config,
+                &subjects(&["orders.created"]),
+                JobAckPolicy::Explicit
+            ),
+            Err(ConsumerError::PushConsumer { .. })
+        ));
+
+        let config = consumer::Config {
+            filter_subjects: subjects(&["payments.>", "orders.created"]),
+            ..pull_config("")
+        };
+        assert!(check_consumer_config(
+            "archiver",
+            &config,
+            &subjects(&["orders.created", "payments.>"]),
+            JobAckPolicy::Explicit
+        )
+        .is_ok());
+        assert!(matches!(
+            check_consumer_config(
+                "archiver",
+                &config,
+                &subjects(&["orders.created"]),
+                JobAckPolicy::Explicit
+            ),
+            Err(ConsumerError::FilterMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_max_ack_pending() {
+        let config = consumer::Config {
+            max_ack_pending: 3000,
+            ..pull_config("orders.created")
+        };
+        assert!(check_max_ack_pending("archiver", &config, 3000).is_ok());
+        assert!(matches!(
+            check_max_ack_pending("archiver", &config, 3001),
+            Err(ConsumerError::MaxAckPending {
+                max_ack_pending: 3000,
+                required: 3001,
+                ..
+            })
+        ));
+
+        let unlimited = consumer::Config {
+            max_ack_pending: -1,
+            ..pull_config("orders.created")
+        };
+        assert!(check_max_ack_pending("archiver", &unlimited, 3000).is_ok());
+    }
+
+    #[test]
+    fn test_with_message_id() {
+        let headers = with_message_id(None, "load-1", "ORDERS", 42).unwrap();
+        assert_eq!(headers["Nats-Msg-Id"], ["load-1:ORDERS:42"]);
+
+        // a retry of the same job is a duplicate, a second load of the same
+        // range is not and still delivers its messages
+        let retried = with_message_id(None, "load-1", "ORDERS", 42).unwrap();
+        assert_eq!(retried["Nats-Msg-Id"], headers["Nats-Msg-Id"]);
+        let reloaded = with_message_id(None, "load-2", "ORDERS", 42).unwrap();
+        assert_ne!(reloaded["Nats-Msg-Id"], headers["Nats-Msg-Id"]);
+
+        let headers = BTreeMap::from([
+            ("Nats-Msg-Id".to_string(), vec!["order-1".to_string()]),
+            ("region".to_string(), vec!["eu".to_string()]),
+        ]);
+        let restored = with_message_id(Some(headers.clone()), "load-1", "ORDERS", 42).unwrap();
+        assert_eq!(restored, headers);
+
+        // messages archived without their origin can't be told apart
+        assert_eq!(with_message_id(None, "load-1", "", 42), None);
+        assert_eq!(with_message_id(None, "load-1", "ORDERS", 0), None);
+    }
+}
@@ -0,0 +1,148 @@
+// Layout of object store archives in S3.
+//
+// A bucket is the stream OBJ_<bucket>. An archive job writes the reassembled
+// bytes of every object to <bucket>/<job id>/objects/<nuid> and, once they are
+// all uploaded, a manifest holding the info of each object next to them. The
+// manifest is what a restore reads, an archive without one is incomplete.
+
+use async_nats::jetstream::object_store::{ObjectInfo, ObjectLink, ObjectMetadata};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Manifest {
+    // bucket the objects were archived from
+    pub bucket: String,
+    pub created: DateTime<Utc>,
+    pub objects: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestEntry {
+    // key of the object bytes, none for links which have no data
+    #[serde(default)]
+    pub key: Option<String>,
+    pub info: ObjectInfo,
+}
+
+impl Manifest {
+    // objects before the links pointing at them
+    pub fn restore_order(&self) -> impl Iterator<Item = &ManifestEntry> {
+        let (links, objects): (Vec<_>, Vec<_>) = self
+            .objects
+            .iter()
+            .partition(|entry| link(&entry.info).is_some());
+        objects.into_iter().chain(links)
+    }
+}
+
+pub fn stream_name(bucket: &str) -> String {
+    format!("OBJ_{}", bucket)
+}
+
+pub fn manifest_key(bucket: &str, job_id: &str) -> String {
+    format!("{}/{}/{}", bucket, job_id, MANIFEST)
+}
+
+pub fn object_key(bucket: &str, job_id: &str, nuid: &str) -> String {
+    format!("{}/{}/objects/{}", bucket, job_id, nuid)
+}
+
+pub fn link(info: &ObjectInfo) -> Option<&ObjectLink> {
+    info.options.as_ref()?.link.as_ref()
+}
+
+// bucket a restored link points to, links within the archived bucket follow
+// it to the target bucket
+pub fn link_bucket<'a>(link: &'a ObjectLink, source: &str, target: &'a str) -> &'a str {
+    if link.bucket == source {
+        target
+    } else {
+        &link.bucket
+    }
+}
+
+// metadata to put an archived object with
+pub fn restore_metadata(info: &ObjectInfo) -> ObjectMetadata {
+    ObjectMetadata {
+        name: info.name.clone(),
+        description: info.description.clone(),
+        chunk_size: info.options.as_ref().and_then(|o| o.max_chunk_size),
+        metadata: info.metadata.clone(),
+        headers: info.headers.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_nats::jetstream::object_store::ObjectOptions;
+
+    fn info(name: &str, link: Option<ObjectLink>) -> ObjectInfo {
+        serde_json::from_value::<ObjectInfo>(serde_json::json!({
+            "name": name,
+            "bucket": "artifacts",
+            "nuid": format!("nuid-{}", name),
+            "size": 3,
+            "chunks": 1,
+            "digest": "SHA-256=abc",
+        }))
+        .map(|info| ObjectInfo {
+            options: link.map(|link| ObjectOptions {
+                link: Some(link),
+                max_chunk_size: None,
+            }),
+            ..info
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_restore_order() {
+        let link = ObjectLink {
+            name: Some("b".to_string()),
+            bucket: "artifacts".to_string(),
+        };
+        let manifest = Manifest {
+            bucket: "artifacts".to_string(),
+            created: Utc::now(),
+            objects: vec![
+                ManifestEntry {
+                    key: None,
+                    info: info("latest", Some(link)),
+                },
+                ManifestEntry {
+                    key: Some(object_key("artifacts", "job", "nuid-b")),
+                    info: info("b", None),
+                },
+            ],
+        };
+
+        let names: Vec<_> = manifest
+            .restore_order()
+            .map(|entry| entry.info.name.as_str())
+            .collect();
+        assert_eq!(names, ["b", "latest"]);
+
+        // the manifest survives a round trip through json
+        let decoded: Manifest =
+            serde_json::from_slice(&serde_json::to_vec(&manifest).unwrap()).unwrap();
+        assert_eq!(decoded.objects[0].info, manifest.objects[0].info);
+        assert_eq!(
+            decoded.objects[1].key.as_deref(),
+            Some("artifacts/job/objects/nuid-b")
+        );
+    }
+
+    #[test]
+    fn test_link_bucket() {
+        let link = ObjectLink {
+            name: Some("b".to_string()),
+            bucket: "artifacts".to_string(),
+        };
+        assert_eq!(link_bucket(&link, "artifacts", "copy"), "copy");
+        assert_eq!(link_bucket(&link, "other", "copy"), "artifacts");
+    }
+}
@@ -41,10 +41,20 @@ struct LoadJobHandle {
     pause_token: CancellationToken,
 }
 
+// key-value and object store jobs run to completion, they can be cancelled
+// but not paused
+#[derive(Debug)]
+struct KvJobHandle {
+    handle: JoinHandle<Result<()>>,
+    cancel_token: CancellationToken,
+}
+
 #[derive(Clone, Debug)]
 pub struct Registry {
     store_handles: Arc<RwLock<HashMap<String, StoreJobHandle>>>,
     load_handles: Arc<RwLock<HashMap<String, LoadJobHandle>>>,
+    kv_handles: Arc<RwLock<HashMap<String, KvJobHandle>>>,
+    object_handles: Arc<RwLock<HashMap<String, KvJobHandle>>>,
     shutdown_token: CancellationToken,
     exit_tx: mpsc::UnboundedSender<TaskExitInfo>,
     exit_rx: Arc<Mutex<mpsc::UnboundedReceiver<TaskExitInfo>>>,
@@ -56,6 +66,8 @@ impl Registry {
         Self {
             store_handles: Arc::new(RwLock::new(HashMap::new())),
             load_handles: Arc::new(RwLock::new(HashMap::new())),
+            kv_handles: Arc::new(RwLock::new(HashMap::new())),
+            object_handles: Arc::new(RwLock::new(HashMap::new())),
             shutdown_token,
             exit_tx,
             exit_rx: Arc::new(Mutex::new(exit_rx)),
@@ -129,6 +141,54 @@ impl Registry {
         true
     }
 
+    pub async fn try_register_kv_job(
+        &self,
+        job_id: String,
+        handle: JoinHandle<Result<()>>,
+        cancel_token: CancellationToken,
+    ) -> bool {
+        debug!(job_id = job_id, "register kv job handle");
+
+        let mut handles = self.kv_handles.write().await;
+        if handles.contains_key(&job_id) {
+            handle.abort();
+            return false;
+        }
+
+        handles.insert(
+            job_id,
+            KvJobHandle {
+                handle,
+                cancel_token,
+            },
+        );
+        true
+    }
+
+    pub async fn try_register_object_job(
+        &self,
+        job_id: String,
+        handle: JoinHandle<Result<()>>,
+        cancel_token: CancellationToken,
+    ) -> bool {
+        debug!(job_id = job_id, "register object job handle");
+
+        let mut handles = self.object_handles.write().await;
+        if handles.contains_key(&job_id) {
+            handle.abort();
+            return false;
+        }
+
+        handles.insert(
+            job_id,
+            KvJobHandle {
+                handle,
+                cancel_token,
+            },
+        );
+        true
+    }
+
     pub async fn is_store_job_running(&self, job_id: &str) -> bool {
         let handles = self.store_handles.read().await;
         handles.contains_key(job_id)
@@ -139,9 +199,21 @@ impl Registry {
         handles.contains_key(job_id)
     }
 
+    pub async fn is_kv_job_running(&self, job_id: &str) -> bool {
+        let handles = self.kv_handles.read().await;
+        handles.contains_key(job_id)
+    }
+
+    pub async fn is_object_job_running(&self, job_id: &str) -> bool {
+        let handles = self.object_handles.read().await;
+        handles.contains_key(job_id)
+    }
+
     pub async fn remove_job(&self, job_id: &str) {
         self.store_handles.write().await.remove(job_id);
         self.load_handles.write().await.remove(job_id);
+        self.kv_handles.write().await.remove(job_id);
+        self.object_handles.write().await.remove(job_id);
     }
 
     pub async fn cancel_store_job(&self, job_id: &str) {
@@ -158,6 +230,20 @@ impl Registry {
         }
     }
 
+    pub async fn cancel_kv_job(&self, job_id: &str) {
+        let handles = self.kv_handles.read().await;
+        if let Some(job_handle) = handles.get(job_id) {
+            job_handle.cancel_token.cancel();
+        }
+    }
+
+    pub async fn cancel_object_job(&self, job_id: &str) {
+        let handles = self.object_handles.read().await;
+        if let Some(job_handle) = handles.get(job_id) {
+            job_handle.cancel_token.cancel();
+        }
+    }
+
     pub async fn pause_store_job(&self, job_id: &str) {
         let handles = self.store_handles.read().await;
         if let Some(job_handle) = handles.get(job_id) {
@@ -185,6 +271,16 @@ impl Registry {
             handles.drain().collect()
         };
 
+        let kv_handles: Vec<_> = {
+            let mut handles = self.kv_handles.write().await;
+            handles.drain().collect()
+        };
+
+        let object_handles: Vec<_> = {
+            let mut handles = self.object_handles.write().await;
+            handles.drain().collect()
+        };
+
         for (job_id, job_handle) in store_handles {
             trace!(job_id = job_id, "wait for store job to complete");
             let _ = job_handle.handle.await;
@@ -195,6 +291,16 @@ impl Registry {
             let _ = job_handle.handle.await;
         }
 
+        for (job_id, job_handle) in kv_handles {
+            trace!(job_id = job_id, "wait for kv job to complete");
+            let _ = job_handle.handle.await;
+        }
+
+        for (job_id, job_handle) in object_handles {
+            trace!(job_id = job_id, "wait for object job to complete");
+            let _ = job_handle.handle.await;
+        }
+
         debug!("all tasks completed");
         Ok(())
     }
@@ -0,0 +1,252 @@
+// Retry of transient S3, NATS and Postgres errors.
+//
+// An operation is tried up to the policy's max attempts, waiting an
+// exponential backoff between tries. Only errors classified as transient are
+// retried, anything else fails the operation on the first try.
+
+use async_nats::jetstream::context::{PublishError, PublishErrorKind};
+use nats3_types::Retry;
+use s3::error::S3Error;
+use std::{error::Error, fmt::Debug, future::Future, time::Duration};
+use tokio_postgres::error::SqlState;
+use tracing::warn;
+
+use crate::{
+    db::{postgres::PostgresError, ChunkMetadataError},
+    metrics,
+};
+
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+}
+
+// the first error in the chain that can be classified decides
+impl Transient for anyhow::Error {
+    fn is_transient(&self) -> bool {
+        self.chain().find_map(classify).unwrap_or(false)
+    }
+}
+
+impl Transient for PublishError {
+    fn is_transient(&self) -> bool {
+        matches!(
+            self.kind(),
+            PublishErrorKind::TimedOut
+                | PublishErrorKind::BrokenPipe
+                | PublishErrorKind::MaxAckPending
+        )
+    }
+}
+
+impl Transient for ChunkMetadataError {
+    fn is_transient(&self) -> bool {
+        match self {
+            // no connection could be taken from the pool in time
+            ChunkMetadataError::Postgres(PostgresError::Pool(_)) => true,
+            ChunkMetadataError::Postgres(PostgresError::Database(e))
+            | ChunkMetadataError::Database(e) => postgres_transient(e),
+            _ => false,
+        }
+    }
+}
+
+fn classify(error: &(dyn Error + 'static)) -> Option<bool> {
+    if let Some(e) = error.downcast_ref::<S3Error>() {
+        return Some(s3_transient(e));
+    }
+    if let Some(e) = error.downcast_ref::<PublishError>() {
+        return Some(e.is_transient());
+    }
+    if let Some(e) = error.downcast_ref::<ChunkMetadataError>() {
+        return Some(e.is_transient());
+    }
+    if let Some(e) = error.downcast_ref::<tokio_postgres::Error>() {
+        return Some(postgres_transient(e));
+    }
+    if error.is::<std::io::Error>() {
+        return Some(true);
+    }
+    None
+}
+
+// throttling, timeouts, server errors and failed requests
+fn s3_transient(error: &S3Error) -> bool {
+    match error {
+        S3Error::HttpFailWithBody(code, _) => *code == 408 || *code == 429 || *code >= 500,
+        S3Error::Reqwest(e) => !e.is_builder(),
+        S3Error::Io(_) => true,
+        _ => false,
+    }
+}
+
+// lost connections, serialization failures and deadlocks, server restarts
+fn postgres_transient(error: &tokio_postgres::Error) -> bool {
+    match error.code() {
+        Some(code) => {
+            code.code().starts_with("08")
+                || [
+                    SqlState::T_R_SERIALIZATION_FAILURE,
+                    SqlState::T_R_DEADLOCK_DETECTED,
+                    SqlState::ADMIN_SHUTDOWN,
+                    SqlState::CANNOT_CONNECT_NOW,
+                    SqlState::TOO_MANY_CONNECTIONS,
+                ]
+                .contains(code)
+        }
+        None => error.is_closed() || error.source().is_some_and(|e| e.is::<std::io::Error>()),
+    }
+}
+
+// run an operation, retrying transient errors as the policy allows
+pub async fn retry<T, E, F, Fut>(
+    policy: &Retry,
+    service: &str,
+    metrics: &metrics::Metrics,
+    mut op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Transient + Debug,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_attempts && e.is_transient() => {
+                let wait = backoff(policy, attempt);
+                warn!(
+                    error = ?e,
+                    service = service,
+                    attempt = attempt,
+                    wait = ?wait,
+                    "transient error, retry"
+                );
+                metrics
+                    .io
+                    .retries_total
+                    .get_or_create(&metrics::ServiceLabel {
+                        service: service.to_string(),
+                    })
+                    .inc();
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// wait before the retry following an attempt, starting at 1
+fn backoff(policy: &Retry, attempt: u32) -> Duration {
+    let wait = policy
+        .backoff
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(policy.max_backoff);
+    if policy.jitter {
+        wait.mul_f64(rand::random::<f64>())
+    } else {
+        wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Debug)]
+    struct TestError {
+        transient: bool,
+    }
+
+    impl Transient for TestError {
+        fn is_transient(&self) -> bool {
+            self.transient
+        }
+    }
+
+    fn policy(max_attempts: u32) -> Retry {
+        Retry {
+            max_attempts,
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            jitter: false,
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = policy(10);
+        let waits: Vec<_> = (1..=5).map(|attempt| backoff(&policy, attempt)).collect();
+        assert_eq!(waits, [1, 2, 4, 4, 4].map(Duration::from_millis).to_vec());
+
+        let policy = Retry {
+            jitter: true,
+            ..policy
+        };
+        for attempt in 1..=5 {
+            assert!(backoff(&policy, attempt) <= Duration::from_millis(4));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient() {
+        let metrics = metrics::Metrics::new();
+        let calls = AtomicU32::new(0);
+        let result = retry(&policy(3), "test", &metrics, || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(TestError { transient: true }),
+                _ => Ok(()),
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let retries = metrics
+            .io
+            .retries_total
+            .get_or_create(&metrics::ServiceLabel {
+                service: "test".to_string(),
+            })
+            .get();
+        assert_eq!(retries, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let metrics = metrics::Metrics::new();
+
+        // attempts run out
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = retry(&policy(3), "test", &metrics, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(TestError { transient: true })
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // permanent errors are not retried
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = retry(&policy(3), "test", &metrics, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(TestError { transient: false })
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_classify_s3() {
+        let error = anyhow::Error::from(S3Error::HttpFailWithBody(503, String::new()));
+        assert!(error.context("put object").is_transient());
+
+        let error = anyhow::Error::from(S3Error::HttpFailWithBody(403, String::new()));
+        assert!(!error.context("put object").is_transient());
+
+        assert!(!anyhow::anyhow!("unexpected status code").is_transient());
+    }
+}
@@ -1,9 +1,20 @@
-use anyhow::{Context, Result};
-use nats3_types::Codec;
-use s3::{creds::Credentials, Bucket, BucketConfiguration, Region};
+use anyhow::{anyhow, bail, Context, Result};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use bytes::Bytes;
+use futures::{Stream, StreamExt, TryStreamExt};
+use nats3_types::{Codec, Retry};
+use s3::{
+    creds::Credentials, error::S3Error, serde_types::Part, Bucket, BucketConfiguration, Region,
+};
+use std::{collections::HashMap, ops::Range};
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tracing::{debug, info, warn};
 
-use crate::{encoding, metrics};
+use crate::{body::Body, config, encoding::HASH_METADATA, metrics, retry};
+
+const CONTENT_TYPE: &str = "application/octet-stream";
+// smallest part s3 accepts, other than the last
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
 
 #[derive(Clone, Debug)]
 pub struct Client {
@@ -11,6 +22,7 @@ pub struct Client {
     endpoint: String,
     access_key: String,
     secret_key: String,
+    multipart: config::Multipart,
     metrics: metrics::Metrics,
 }
 
@@ -20,6 +32,7 @@ impl Client {
         endpoint: String,
         access_key: String,
         secret_key: String,
+        multipart: config::Multipart,
         metrics: metrics::Metrics,
     ) -> Self {
         debug!(endpoint = endpoint, region = region, "create new s3 client");
@@ -28,37 +41,122 @@ impl Client {
             endpoint,
             access_key,
             secret_key,
+            multipart,
             metrics,
         }
     }
 
+    // upload a chunk. a write once key is looked up first: a chunk already
+    // there with the same hash is an earlier attempt of this upload and is
+    // kept, any other object fails the upload rather than being overwritten
+    #[allow(clippy::too_many_arguments)]
     pub async fn upload_chunk(
         &self,
-        chunk: Vec<u8>,
+        chunk: Body,
         bucket_name: &str,
         path: &str,
         codec: Codec,
+        metadata: Vec<(&str, String)>,
+        write_once: bool,
+        retry: &Retry,
     ) -> Result<()> {
-        let bucket = self.bucket(bucket_name, true).await?;
-        let resp = bucket
-            .put_object(&path, &chunk)
-            .await
-            .context("put object")?;
-        let code = resp.status_code();
-        if code != 200 {
-            warn!(
-                code = code,
-                bucket = bucket_name,
-                path = path,
-                "upload chunk, unexpected status code"
-            )
+        if write_once {
+            if let Some(existing) = self.find_object_metadata(bucket_name, path, retry).await? {
+                let hash = metadata
+                    .iter()
+                    .find(|(key, _)| *key == HASH_METADATA)
+                    .map(|(_, hash)| hash);
+                if existing.get(HASH_METADATA) != hash {
+                    bail!(
+                        "object {} in bucket {} holds a different chunk",
+                        path,
+                        bucket_name
+                    );
+                }
+                debug!(
+                    bucket = bucket_name,
+                    path = path,
+                    "chunk already uploaded, skip"
+                );
+                return Ok(());
+            }
         }
+        self.upload_object(chunk, bucket_name, path, metadata, retry)
+            .await?;
         debug!(
             bucket = bucket_name,
             path = path,
             codec = codec.to_string(),
             "finish upload block to s3"
         );
+        Ok(())
+    }
+
+    // upload any object, in parts once it passes the multipart threshold
+    pub async fn upload_object(
+        &self,
+        body: Body,
+        bucket_name: &str,
+        path: &str,
+        metadata: Vec<(&str, String)>,
+        retry: &Retry,
+    ) -> Result<()> {
+        let byte_count = body.len();
+        if byte_count > self.multipart.threshold as u64 {
+            // each part is read from the body when it is sent
+            let parts = part_ranges(byte_count, self.part_size())
+                .map(move |range| Ok((body.clone(), range)));
+            self.upload_multipart(
+                bucket_name,
+                futures::stream::iter(parts),
+                byte_count,
+                path,
+                metadata,
+                retry,
+            )
+            .await?;
+        } else {
+            let chunk = body.into_bytes().await.context("read object body")?;
+            retry::retry(retry, metrics::SERVICE_S3, &self.metrics, || {
+                self.put_chunk(chunk.clone(), bucket_name, path, metadata.clone())
+            })
+            .await?;
+        }
+        self.count_upload(byte_count);
+        Ok(())
+    }
+
+    // upload an object read from a reader of known length. past the multipart
+    // threshold the reader is read one part at a time as the parts are sent,
+    // so only the parts in flight are held
+    pub async fn upload_reader<R: AsyncRead + Unpin + Send>(
+        &self,
+        mut reader: R,
+        len: u64,
+        bucket_name: &str,
+        path: &str,
+        metadata: Vec<(&str, String)>,
+        retry: &Retry,
+    ) -> Result<()> {
+        if len <= self.multipart.threshold as u64 {
+            let mut data = Vec::with_capacity(len as usize);
+            reader
+                .read_to_end(&mut data)
+                .await
+                .context("read object body")?;
+            return self
+                .upload_object(Body::from(data), bucket_name, path, metadata, retry)
+                .await;
+        }
+
+        let parts = reader_parts(reader, self.part_size());
+        self.upload_multipart(bucket_name, parts, len, path, metadata, retry)
+            .await?;
+        self.count_upload(len);
+        Ok(())
+    }
+
+    fn count_upload(&self, byte_count: u64) {
         self.metrics
             .io
             .s3_objects_total
@@ -72,18 +170,166 @@ impl Client {
             .get_or_create(&metrics::DirectionLabel {
                 direction: metrics::DIRECTION_IN.to_string(),
             })
-            .inc_by(chunk.len() as u64);
+            .inc_by(byte_count);
+    }
+
+    fn part_size(&self) -> u64 {
+        self.multipart.part_size.max(MIN_PART_SIZE) as u64
+    }
+
+    async fn put_chunk(
+        &self,
+        chunk: Bytes,
+        bucket_name: &str,
+        path: &str,
+        metadata: Vec<(&str, String)>,
+    ) -> Result<()> {
+        let bucket = self.bucket(bucket_name, true).await?;
+        let mut put = bucket.put_object_builder(path, &chunk);
+        for (key, value) in metadata {
+            put = put.with_metadata(key, value).context("object metadata")?;
+        }
+        let resp = put.execute().await.context("put object")?;
+        let code = resp.status_code();
+        if code != 200 {
+            warn!(
+                code = code,
+                bucket = bucket_name,
+                path = path,
+                "upload chunk, unexpected status code"
+            )
+        }
         Ok(())
     }
 
-    pub async fn download_chunk(
+    // upload in parts, aborting the upload if any part or the completion fails.
+    // each part is a range of a body
+    async fn upload_multipart<S>(
         &self,
         bucket_name: &str,
+        parts: S,
+        len: u64,
         path: &str,
-        codec: Codec,
-    ) -> Result<encoding::Chunk> {
-        let bucket = self.bucket(bucket_name, false).await?;
-        let resp = bucket.get_object(path).await?;
+        metadata: Vec<(&str, String)>,
+        retry: &Retry,
+    ) -> Result<()>
+    where
+        S: Stream<Item = Result<(Body, Range<u64>)>>,
+    {
+        // parts are retried one by one, retrying the whole upload as well
+        // would multiply the attempts
+        let bucket = &retry::retry(retry, metrics::SERVICE_S3, &self.metrics, || {
+            self.bucket(bucket_name, true)
+        })
+        .await?;
+
+        // object metadata is set when the upload is initiated
+        let mut headers = HeaderMap::new();
+        for (key, value) in metadata {
+            headers.insert(
+                HeaderName::try_from(format!("x-amz-meta-{}", key)).context("object metadata")?,
+                HeaderValue::try_from(value).context("object metadata")?,
+            );
+        }
+        let initiate = bucket.with_extra_headers(headers)?;
+        let upload = retry::retry(retry, metrics::SERVICE_S3, &self.metrics, || async {
+            Ok::<_, anyhow::Error>(
+                initiate
+                    .initiate_multipart_upload(path, CONTENT_TYPE)
+                    .await?,
+            )
+        })
+        .await
+        .context("initiate multipart upload")?;
+        debug!(
+            path = path,
+            upload_id = upload.upload_id,
+            bytes = len,
+            "start multipart upload"
+        );
+
+        let completed = async {
+            let parts = self
+                .upload_parts(bucket, parts, path, &upload.upload_id, retry)
+                .await?;
+            // not retried, a completion that went through leaves no upload to
+            // complete again
+            let resp = bucket
+                .complete_multipart_upload(path, &upload.upload_id, parts)
+                .await
+                .context("complete multipart upload")?;
+            if resp.status_code() >= 300 {
+                bail!(
+                    "complete multipart upload, unexpected status code {}",
+                    resp.status_code()
+                );
+            }
+            Ok(())
+        }
+        .await;
+
+        if completed.is_err() {
+            if let Err(e) = bucket.abort_upload(path, &upload.upload_id).await {
+                warn!(
+                    error = ?e,
+                    path = path,
+                    upload_id = upload.upload_id,
+                    "abort multipart upload"
+                );
+            }
+        }
+        completed
+    }
+
+    async fn upload_parts<S>(
+        &self,
+        bucket: &Bucket,
+        parts: S,
+        path: &str,
+        upload_id: &str,
+        retry: &Retry,
+    ) -> Result<Vec<Part>>
+    where
+        S: Stream<Item = Result<(Body, Range<u64>)>>,
+    {
+        let parts = parts.enumerate().map(|(i, part)| {
+            let part_number = i as u32 + 1;
+            async move {
+                let (body, range) = part?;
+                // each attempt copies the part again from its body, read
+                // from the file a body spilled to when it has one
+                retry::retry(retry, metrics::SERVICE_S3, &self.metrics, || async {
+                    let data = body.read_range(range.clone()).await?;
+                    Ok::<_, anyhow::Error>(
+                        bucket
+                            .put_multipart_chunk(data, path, part_number, upload_id, CONTENT_TYPE)
+                            .await?,
+                    )
+                })
+                .await
+                .with_context(|| format!("upload part {}", part_number))
+            }
+        });
+
+        // buffered keeps the parts in order for completion, and only pulls the
+        // next part once one of those in flight is done
+        parts
+            .buffered(self.multipart.concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    pub async fn download_object(
+        &self,
+        bucket_name: &str,
+        path: &str,
+        retry: &Retry,
+    ) -> Result<Bytes> {
+        let resp = retry::retry(retry, metrics::SERVICE_S3, &self.metrics, || async {
+            let bucket = self.bucket(bucket_name, false).await?;
+            Ok::<_, anyhow::Error>(bucket.get_object(path).await?)
+        })
+        .await?;
         let code = resp.status_code();
         if code != 200 {
             warn!(
@@ -93,14 +339,11 @@ impl Client {
                 "download chunk, unexpected status code"
             )
         }
-        let b = resp.bytes();
-        let bytes_download = b.len();
-        let chunk = encoding::Chunk::deserialize(b, codec.clone())?;
+        let data = resp.into_bytes();
 
         debug!(
             bucket = bucket_name,
             path = path,
-            codec = codec.to_string(),
             "finish download block from s3"
         );
 
@@ -117,14 +360,102 @@ impl Client {
             .get_or_create(&metrics::DirectionLabel {
                 direction: metrics::DIRECTION_OUT.to_string(),
             })
-            .inc_by(bytes_download as u64);
+            .inc_by(data.len() as u64);
+
+        Ok(data)
+    }
+
+    // user metadata stored on an object, keys without the x-amz-meta- prefix
+    pub async fn object_metadata(
+        &self,
+        bucket_name: &str,
+        path: &str,
+        retry: &Retry,
+    ) -> Result<HashMap<String, String>> {
+        self.find_object_metadata(bucket_name, path, retry)
+            .await?
+            .ok_or_else(|| anyhow!("head object, object not found"))
+    }
 
-        Ok(chunk)
+    // user metadata stored on an object, none if there is no object at path
+    pub async fn find_object_metadata(
+        &self,
+        bucket_name: &str,
+        path: &str,
+        retry: &Retry,
+    ) -> Result<Option<HashMap<String, String>>> {
+        let head = retry::retry(retry, metrics::SERVICE_S3, &self.metrics, || async {
+            let bucket = self.bucket(bucket_name, false).await?;
+            match bucket.head_object(path).await {
+                Ok((_, 404)) | Err(S3Error::HttpFailWithBody(404, _)) => Ok(None),
+                Ok((head, 200)) => Ok(Some(head)),
+                Ok((_, code)) => bail!("head object, unexpected status code {}", code),
+                Err(e) => Err(anyhow::Error::new(e).context("head object")),
+            }
+        })
+        .await?;
+        Ok(head.map(|head| head.metadata.unwrap_or_default()))
     }
 
-    pub async fn delete_chunk(&self, bucket_name: &str, path: &str) -> Result<()> {
-        let bucket = self.bucket(bucket_name, false).await?;
-        let response_data = bucket.delete_object(path).await?;
+    // stream the raw object bytes, for chunks decoded record by record
+    pub async fn download_chunk_stream(
+        &self,
+        bucket_name: &str,
+        path: &str,
+        retry: &Retry,
+    ) -> Result<impl Stream<Item = std::io::Result<Bytes>> + Send + Unpin + 'static> {
+        // only opening the stream is retried, a failure while reading fails the load
+        let resp = retry::retry(retry, metrics::SERVICE_S3, &self.metrics, || async {
+            let bucket = self.bucket(bucket_name, false).await?;
+            Ok::<_, anyhow::Error>(bucket.get_object_stream(path).await?)
+        })
+        .await?;
+        let code = resp.status_code;
+        if code != 200 {
+            warn!(
+                code = code,
+                bucket = bucket_name,
+                path = path,
+                "download chunk stream, unexpected status code"
+            )
+        }
+        debug!(
+            bucket = bucket_name,
+            path = path,
+            "start streaming block from s3"
+        );
+
+        self.metrics
+            .io
+            .s3_objects_total
+            .get_or_create(&metrics::DirectionLabel {
+                direction: metrics::DIRECTION_OUT.to_string(),
+            })
+            .inc();
+        let bytes_total = self
+            .metrics
+            .io
+            .s3_bytes_total
+            .get_or_create(&metrics::DirectionLabel {
+                direction: metrics::DIRECTION_OUT.to_string(),
+            })
+            .clone();
+
+        let stream = resp.bytes.map(move |item| {
+            item.inspect(|b| {
+                bytes_total.inc_by(b.len() as u64);
+            })
+            .map_err(std::io::Error::other)
+        });
+        Ok(stream)
+    }
+
+    pub async fn delete_chunk(&self, bucket_name: &str, path: &str, retry: &Retry) -> Result<()> {
+        let response_data = retry::retry(retry, metrics::SERVICE_S3, &self.metrics, || async {
+            let bucket = self.bucket(bucket_name, false).await?;
+            Ok::<_, anyhow::Error>(bucket.delete_object(path).await?)
+        })
+        .await?;
         let code = response_data.status_code();
         if code >= 300 {
             warn!(
@@ -177,3 +508,61 @@ impl Client {
         Ok(*bucket)
     }
 }
+
+fn part_ranges(len: u64, part_size: u64) -> impl Iterator<Item = Range<u64>> {
+    (0..len)
+        .step_by(part_size as usize)
+        .map(move |start| start..len.min(start + part_size))
+}
+
+// parts read from a reader in turn, each read when the upload pulls it
+fn reader_parts<R: AsyncRead + Unpin>(
+    reader: R,
+    part_size: u64,
+) -> impl Stream<Item = Result<(Body, Range<u64>)>> {
+    futures::stream::try_unfold(reader, move |mut reader| async move {
+        let mut data = Vec::with_capacity(part_size as usize);
+        (&mut reader)
+            .take(part_size)
+            .read_to_end(&mut data)
+            .await
+            .context("read object body")?;
+        if data.is_empty() {
+            return Ok(None);
+        }
+        let range = 0..data.len() as u64;
+        Ok(Some(((Body::from(data), range), reader)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_ranges() {
+        assert_eq!(
+            part_ranges(10, 4).collect::<Vec<_>>(),
+            vec![0..4, 4..8, 8..10]
+        );
+        assert_eq!(part_ranges(8, 4).collect::<Vec<_>>(), vec![0..4, 4..8]);
+        assert_eq!(part_ranges(3, 4).collect::<Vec<_>>(), vec![0..3]);
+        assert_eq!(part_ranges(0, 4).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reader_parts() {
+        let parts: Vec<_> = reader_parts(&b"0123456789"[..], 4)
+            .try_collect()
+            .await
+            .unwrap();
+        let mut read = vec![];
+        for (body, range) in parts {
+            read.push(body.read_range(range).await.unwrap());
+        }
+        assert_eq!(read, [&b"0123"[..], b"4567", b"89"]);
+
+        let parts: Vec<_> = reader_parts(&b""[..], 4).try_collect().await.unwrap();
+        assert!(parts.is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+use anyhow::Result;
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use nats3_types::{ChunkOverlap, ChunkVerification, DuplicateChunksQuery, VerifyChunksQuery};
+use serde::Deserialize;
+
+use crate::{error::AppError, server::Dependencies};
+
+pub fn create_router(deps: Dependencies) -> Router {
+    let router: Router = Router::new()
+        .route("/chunk/verify", get(verify_chunk))
+        .route("/chunks/verify", get(verify_chunks))
+        .route("/chunks/duplicates", get(duplicate_chunks))
+        .with_state(deps);
+    router
+}
+
+#[derive(Deserialize)]
+struct VerifyChunkParams {
+    sequence_number: i64,
+}
+
+#[debug_handler]
+async fn verify_chunk(
+    State(state): State<Dependencies>,
+    Query(params): Query<VerifyChunkParams>,
+) -> Result<Json<ChunkVerification>, AppError> {
+    let verification = state
+        .coordinator
+        .verify_chunk(params.sequence_number)
+        .await?;
+    Ok(Json(verification))
+}
+
+#[debug_handler]
+async fn verify_chunks(
+    State(state): State<Dependencies>,
+    Query(query): Query<VerifyChunksQuery>,
+) -> Result<Json<Vec<ChunkVerification>>, AppError> {
+    let verifications = state.coordinator.verify_chunks(query).await?;
+    Ok(Json(verifications))
+}
+
+#[debug_handler]
+async fn duplicate_chunks(
+    State(state): State<Dependencies>,
+    Query(query): Query<DuplicateChunksQuery>,
+) -> Result<Json<Vec<ChunkOverlap>>, AppError> {
+    let overlaps = state.coordinator.duplicate_chunks(query).await?;
+    Ok(Json(overlaps))
+}
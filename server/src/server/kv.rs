@@ -0,0 +1,71 @@
+use anyhow::Result;
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use nats3_types::{KvArchiveCreate, KvJob, KvRestoreCreate};
+use serde::Deserialize;
+
+use crate::{error::AppError, server::Dependencies};
+
+pub fn create_router(deps: Dependencies) -> Router {
+    let router: Router = Router::new()
+        .route("/kv/job", get(get_kv_job))
+        .route("/kv/job", delete(delete_kv_job))
+        .route("/kv/archive", post(start_kv_archive))
+        .route("/kv/restore", post(start_kv_restore))
+        .route("/kv/jobs", get(get_kv_jobs))
+        .with_state(deps);
+    router
+}
+
+#[derive(Deserialize)]
+struct GetJobParams {
+    job_id: String,
+}
+
+#[debug_handler]
+async fn get_kv_job(
+    State(state): State<Dependencies>,
+    Query(params): Query<GetJobParams>,
+) -> Result<Json<KvJob>, AppError> {
+    let job = state.db.get_kv_job(params.job_id).await?;
+    Ok(Json(job))
+}
+
+#[debug_handler]
+async fn get_kv_jobs(State(state): State<Dependencies>) -> Result<Json<Vec<KvJob>>, AppError> {
+    let jobs = state.db.get_kv_jobs(None).await?;
+    Ok(Json(jobs))
+}
+
+#[debug_handler]
+async fn delete_kv_job(
+    State(state): State<Dependencies>,
+    Query(params): Query<GetJobParams>,
+) -> Result<(), AppError> {
+    state.coordinator.delete_kv_job(params.job_id).await?;
+    Ok(())
+}
+
+#[debug_handler]
+async fn start_kv_archive(
+    State(state): State<Dependencies>,
+    Json(payload): Json<KvArchiveCreate>,
+) -> Result<Json<KvJob>, AppError> {
+    payload.validate()?;
+    let out = state.coordinator.start_new_kv_archive(payload).await?;
+    Ok(Json(out))
+}
+
+#[debug_handler]
+async fn start_kv_restore(
+    State(state): State<Dependencies>,
+    Json(payload): Json<KvRestoreCreate>,
+) -> Result<Json<KvJob>, AppError> {
+    payload.validate()?;
+    let out = state.coordinator.start_new_kv_restore(payload).await?;
+    Ok(Json(out))
+}
@@ -21,10 +21,13 @@ use tower_http::{
 };
 use tracing::{debug, info, info_span, warn, Span};
 
-use crate::{coordinator, db, error, metrics as counter, registry};
+use crate::{coordinator, db, error, metrics as counter, nats, registry};
 
+pub mod chunks;
+pub mod kv;
 pub mod load;
 pub mod metrics;
+pub mod object;
 pub mod status;
 pub mod store;
 
@@ -126,7 +129,11 @@ fn unwrap_infallible<T>(result: Result<T, Infallible>) -> T {
 }
 
 fn create_router(deps: Dependencies) -> Router {
-    let api_v1_router = load::create_router(deps.clone()).merge(store::create_router(deps.clone()));
+    let api_v1_router = load::create_router(deps.clone())
+        .merge(store::create_router(deps.clone()))
+        .merge(chunks::create_router(deps.clone()))
+        .merge(kv::create_router(deps.clone()))
+        .merge(object::create_router(deps.clone()));
     let api_router = status::create_router()
         .merge(metrics::create_router(deps.clone()))
         .nest("/api/v1", api_v1_router);
@@ -172,6 +179,33 @@ impl IntoResponse for error::AppError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "internal server error".to_string(),
             ),
+            error::AppError::ChunkStore(db::ChunkMetadataError::NotFound { sequence_number }) => (
+                StatusCode::NOT_FOUND,
+                format!("chunk {} not found", sequence_number),
+            ),
+            error::AppError::ChunkStore(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal server error".to_string(),
+            ),
+            error::AppError::Consumer(
+                e @ nats::ConsumerError::StreamNotFound { .. }
+                | e @ nats::ConsumerError::NotFound { .. }
+                | e @ nats::ConsumerError::KvBucketNotFound { .. }
+                | e @ nats::ConsumerError::ObjectBucketNotFound { .. },
+            ) => (StatusCode::NOT_FOUND, e.to_string()),
+            error::AppError::Consumer(
+                e @ nats::ConsumerError::FilterMismatch { .. }
+                | e @ nats::ConsumerError::AckPolicy { .. }
+                | e @ nats::ConsumerError::PushConsumer { .. }
+                | e @ nats::ConsumerError::MaxAckPending { .. },
+            ) => (StatusCode::BAD_REQUEST, e.to_string()),
+            error::AppError::Consumer(e @ nats::ConsumerError::InUse { .. }) => {
+                (StatusCode::CONFLICT, e.to_string())
+            }
+            error::AppError::Consumer(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal server error".to_string(),
+            ),
             error::AppError::JobRegistry(registry::RegistryError::JobAlreadyRunning { job_id }) => {
                 (
                     StatusCode::CONFLICT,
@@ -182,6 +216,23 @@ impl IntoResponse for error::AppError {
                 StatusCode::BAD_REQUEST,
                 "invalid load job config".to_string(),
             ),
+            error::AppError::Validation(
+                e @ nats3_types::ValidationError::InvalidCompressionLevel { .. }
+                | e @ nats3_types::ValidationError::EncryptionNotConfigured
+                | e @ nats3_types::ValidationError::InvalidKeyTemplate { .. }
+                | e @ nats3_types::ValidationError::InvalidDeliver { .. }
+                | e @ nats3_types::ValidationError::BoundConsumerDeliverPolicy
+                | e @ nats3_types::ValidationError::InvalidSubjects { .. }
+                | e @ nats3_types::ValidationError::InvalidSubjectDepth
+                | e @ nats3_types::ValidationError::InvalidBatchMaxAge
+                | e @ nats3_types::ValidationError::InvalidRetry { .. }
+                | e @ nats3_types::ValidationError::InvalidKvBucket { .. }
+                | e @ nats3_types::ValidationError::InvalidKvArchive { .. }
+                | e @ nats3_types::ValidationError::InvalidObjectBucket { .. }
+                | e @ nats3_types::ValidationError::InvalidObjectArchive { .. }
+                | e @ nats3_types::ValidationError::InvalidStreamBackup { .. }
+                | e @ nats3_types::ValidationError::InvalidStreamRestore { .. },
+            ) => (StatusCode::BAD_REQUEST, e.to_string()),
         };
         let body = Json(json!({
             "error": error_message,
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use nats3_types::{ObjectArchiveCreate, ObjectJob, ObjectRestoreCreate};
+use serde::Deserialize;
+
+use crate::{error::AppError, server::Dependencies};
+
+pub fn create_router(deps: Dependencies) -> Router {
+    let router: Router = Router::new()
+        .route("/object/job", get(get_object_job))
+        .route("/object/job", delete(delete_object_job))
+        .route("/object/archive", post(start_object_archive))
+        .route("/object/restore", post(start_object_restore))
+        .route("/object/jobs", get(get_object_jobs))
+        .with_state(deps);
+    router
+}
+
+#[derive(Deserialize)]
+struct GetJobParams {
+    job_id: String,
+}
+
+#[debug_handler]
+async fn get_object_job(
+    State(state): State<Dependencies>,
+    Query(params): Query<GetJobParams>,
+) -> Result<Json<ObjectJob>, AppError> {
+    let job = state.db.get_object_job(params.job_id).await?;
+    Ok(Json(job))
+}
+
+#[debug_handler]
+async fn get_object_jobs(
+    State(state): State<Dependencies>,
+) -> Result<Json<Vec<ObjectJob>>, AppError> {
+    let jobs = state.db.get_object_jobs(None).await?;
+    Ok(Json(jobs))
+}
+
+#[debug_handler]
+async fn delete_object_job(
+    State(state): State<Dependencies>,
+    Query(params): Query<GetJobParams>,
+) -> Result<(), AppError> {
+    state.coordinator.delete_object_job(params.job_id).await?;
+    Ok(())
+}
+
+#[debug_handler]
+async fn start_object_archive(
+    State(state): State<Dependencies>,
+    Json(payload): Json<ObjectArchiveCreate>,
+) -> Result<Json<ObjectJob>, AppError> {
+    payload.validate()?;
+    let out = state.coordinator.start_new_object_archive(payload).await?;
+    Ok(Json(out))
+}
+
+#[debug_handler]
+async fn start_object_restore(
+    State(state): State<Dependencies>,
+    Json(payload): Json<ObjectRestoreCreate>,
+) -> Result<Json<ObjectJob>, AppError> {
+    payload.validate()?;
+    let out = state.coordinator.start_new_object_restore(payload).await?;
+    Ok(Json(out))
+}
@@ -47,16 +47,22 @@ async fn get_store_jobs(
     Ok(Json(jobs))
 }
 
+#[derive(Deserialize)]
+struct DeleteJobParams {
+    job_id: String,
+    #[serde(default)]
+    keep_consumer: bool,
+}
+
 #[debug_handler]
 async fn delete_store_job(
     State(state): State<Dependencies>,
-    Query(params): Query<GetJobParams>,
+    Query(params): Query<DeleteJobParams>,
 ) -> Result<(), AppError> {
     state
         .coordinator
-        .stop_store_job(params.job_id.clone())
-        .await;
-    state.db.delete_store_job(params.job_id).await?;
+        .delete_store_job(params.job_id, params.keep_consumer)
+        .await?;
     Ok(())
 }
 
@@ -65,6 +71,7 @@ async fn start_store_job(
     State(state): State<Dependencies>,
     Json(payload): Json<StoreJobCreate>,
 ) -> Result<Json<StoreJob>, AppError> {
+    payload.validate()?;
     let out = state.coordinator.start_new_store_job(payload).await?;
 
     // return a 201 resp
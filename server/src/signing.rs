@@ -0,0 +1,197 @@
+// Signing of chunk hashes, so archived chunks can be shown to be unaltered.
+//
+// Store jobs sign the hash of each chunk with the active key, either an
+// HMAC-SHA256 secret or an Ed25519 private key. The signature is kept in the
+// chunk metadata and on the s3 object. Retired keys stay configured to verify
+// chunks signed before a rotation.
+
+use anyhow::{anyhow, bail, Result};
+use ed25519_dalek::{Signer as _, Verifier as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{collections::HashMap, fmt};
+
+use crate::config::{self, SigningAlgorithm};
+
+// domain separation, a chunk signature can't be replayed as anything else
+const SIGNATURE_CONTEXT: &[u8] = b"nats3-chunk-signature-v1";
+const ED25519_KEY_BYTES: usize = 32;
+const MIN_HMAC_KEY_BYTES: usize = 32;
+
+enum Key {
+    Hmac(Vec<u8>),
+    Ed25519(ed25519_dalek::SigningKey),
+}
+
+pub struct Signer {
+    active: String,
+    require_signed: bool,
+    keys: HashMap<String, Key>,
+}
+
+impl fmt::Debug for Signer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Signer")
+            .field("active", &self.active)
+            .field("require_signed", &self.require_signed)
+            .field("keys", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Signer {
+    pub fn from_config(config: &config::Signing) -> Result<Self> {
+        let mut keys = HashMap::new();
+        for key in &config.keys {
+            let material = config::read_key(&key.id, key.key.as_ref(), key.key_file.as_ref())?;
+            let signing_key = match key.algorithm {
+                SigningAlgorithm::HmacSha256 => {
+                    if material.len() < MIN_HMAC_KEY_BYTES {
+                        bail!(
+                            "hmac key '{}' must be at least {} bytes",
+                            key.id,
+                            MIN_HMAC_KEY_BYTES
+                        );
+                    }
+                    Key::Hmac(material)
+                }
+                SigningAlgorithm::Ed25519 => {
+                    let seed: [u8; ED25519_KEY_BYTES] = material.try_into().map_err(|_| {
+                        anyhow!(
+                            "ed25519 key '{}' must be {} bytes",
+                            key.id,
+                            ED25519_KEY_BYTES
+                        )
+                    })?;
+                    Key::Ed25519(ed25519_dalek::SigningKey::from_bytes(&seed))
+                }
+            };
+            if keys.insert(key.id.clone(), signing_key).is_some() {
+                bail!("duplicate signing key id '{}'", key.id);
+            }
+        }
+        if !keys.contains_key(&config.active_key) {
+            bail!("active signing key '{}' not configured", config.active_key);
+        }
+        Ok(Self {
+            active: config.active_key.clone(),
+            require_signed: config.require_signed,
+            keys,
+        })
+    }
+
+    pub fn require_signed(&self) -> bool {
+        self.require_signed
+    }
+
+    // sign a chunk hash with the active key, returning the signature and key id
+    pub fn sign(&self, hash: &[u8]) -> (Vec<u8>, String) {
+        let message = signed_message(hash);
+        let signature = match &self.keys[&self.active] {
+            Key::Hmac(secret) => {
+                let mut mac = hmac_sha256(secret);
+                mac.update(&message);
+                mac.finalize().into_bytes().to_vec()
+            }
+            Key::Ed25519(key) => key.sign(&message).to_bytes().to_vec(),
+        };
+        (signature, self.active.clone())
+    }
+
+    // verify a chunk hash against a signature made with the given key
+    pub fn verify(&self, hash: &[u8], signature: &[u8], key_id: &str) -> Result<()> {
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| anyhow!("signing key '{}' not configured", key_id))?;
+        let message = signed_message(hash);
+        match key {
+            Key::Hmac(secret) => {
+                let mut mac = hmac_sha256(secret);
+                mac.update(&message);
+                mac.verify_slice(signature)
+                    .map_err(|_| anyhow!("signature does not verify with key '{}'", key_id))
+            }
+            Key::Ed25519(key) => {
+                let signature = ed25519_dalek::Signature::from_slice(signature)
+                    .map_err(|_| anyhow!("malformed ed25519 signature"))?;
+                key.verifying_key()
+                    .verify(&message, &signature)
+                    .map_err(|_| anyhow!("signature does not verify with key '{}'", key_id))
+            }
+        }
+    }
+}
+
+fn signed_message(hash: &[u8]) -> Vec<u8> {
+    [SIGNATURE_CONTEXT, hash].concat()
+}
+
+fn hmac_sha256(secret: &[u8]) -> Hmac<Sha256> {
+    // hmac accepts keys of any length
+    Hmac::<Sha256>::new_from_slice(secret).expect("hmac key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    fn signing_key(id: &str, algorithm: SigningAlgorithm, byte: u8) -> config::SigningKey {
+        config::SigningKey {
+            id: id.to_string(),
+            algorithm,
+            key: Some(STANDARD.encode([byte; 32])),
+            key_file: None,
+        }
+    }
+
+    fn signer(active: &str, keys: Vec<config::SigningKey>) -> Signer {
+        Signer::from_config(&config::Signing {
+            active_key: active.to_string(),
+            require_signed: false,
+            keys,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        for algorithm in [SigningAlgorithm::HmacSha256, SigningAlgorithm::Ed25519] {
+            let signer = signer("k1", vec![signing_key("k1", algorithm, 1)]);
+            let hash = [7u8; 32];
+            let (signature, key_id) = signer.sign(&hash);
+            assert_eq!(key_id, "k1");
+            signer.verify(&hash, &signature, &key_id).unwrap();
+
+            let mut tampered = hash;
+            tampered[0] ^= 0x01;
+            assert!(signer.verify(&tampered, &signature, &key_id).is_err());
+
+            let mut forged = signature.clone();
+            forged[0] ^= 0x01;
+            assert!(signer.verify(&hash, &forged, &key_id).is_err());
+            assert!(signer.verify(&hash, &signature, "k2").is_err());
+        }
+    }
+
+    #[test]
+    fn test_rotated_key_verifies_old_chunks() {
+        let old = signer(
+            "k1",
+            vec![signing_key("k1", SigningAlgorithm::HmacSha256, 1)],
+        );
+        let (signature, key_id) = old.sign(b"hash");
+
+        let rotated = signer(
+            "k2",
+            vec![
+                signing_key("k1", SigningAlgorithm::HmacSha256, 1),
+                signing_key("k2", SigningAlgorithm::Ed25519, 2),
+            ],
+        );
+        rotated.verify(b"hash", &signature, &key_id).unwrap();
+        let (_, key_id) = rotated.sign(b"hash");
+        assert_eq!(key_id, "k2");
+    }
+}
@@ -0,0 +1,425 @@
+// Local disk spool of sealed chunks.
+//
+// With a spool configured, store jobs write each sealed chunk to disk and ack
+// its messages, and a background uploader drains the spool to S3 and the chunk
+// metadata store, retrying until both succeed. Jobs keep consuming through S3
+// or Postgres outages until the spool is full, then wait for it to drain.
+//
+// entry: <ulid>.chunk holds the chunk data and <ulid>.json the upload details.
+// The json file is written last, a chunk file without one is an interrupted
+// write and is removed when the spool is opened. Entries failing with a
+// permanent error are moved to failed/ so they don't block the ones behind.
+
+use anyhow::{bail, Context, Result};
+use nats3_types::{Codec, Retry};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{fs, io::AsyncWriteExt, sync::Notify};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+use ulid::Ulid;
+
+use crate::{body::Body, config, db, metrics, retry::Transient, s3};
+
+const CHUNK_EXT: &str = "chunk";
+const ENTRY_EXT: &str = "json";
+const TMP_EXT: &str = "tmp";
+const FAILED_DIR: &str = "failed";
+
+// upload details of a spooled chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpoolEntry {
+    pub bucket: String,
+    pub path: String,
+    pub codec: Codec,
+    pub object_metadata: Vec<(String, String)>,
+    // the key may be rendered again for other data, never overwrite it
+    #[serde(default)]
+    pub write_once: bool,
+    pub chunk: db::CreateChunkMetadata,
+}
+
+#[derive(Debug, Clone)]
+pub struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+    retry_interval: Duration,
+    // chunk count and bytes currently spooled
+    depth: Arc<Mutex<(u64, u64)>>,
+    // last entry id handed out, ids increase so the uploader keeps write order
+    last_id: Arc<Mutex<Ulid>>,
+    queued: Arc<Notify>,
+    drained: Arc<Notify>,
+    retry: Retry,
+    s3_client: s3::Client,
+    chunk_db: db::DynChunkStorer,
+    metrics: metrics::Metrics,
+}
+
+impl Spool {
+    pub async fn open(
+        config: &config::Spool,
+        retry: Retry,
+        s3_client: s3::Client,
+        chunk_db: db::DynChunkStorer,
+        metrics: metrics::Metrics,
+    ) -> Result<Self> {
+        fs::create_dir_all(&config.dir)
+            .await
+            .with_context(|| format!("create spool dir {}", config.dir.display()))?;
+
+        let (chunks, bytes) = scan(&config.dir).await?;
+        if chunks > 0 {
+            info!(chunks = chunks, bytes = bytes, "found spooled chunks");
+        }
+
+        let spool = Spool {
+            dir: config.dir.clone(),
+            max_bytes: config.max_bytes,
+            retry_interval: Duration::from_secs(config.retry_secs),
+            depth: Arc::new(Mutex::new((chunks, bytes))),
+            last_id: Arc::new(Mutex::new(Ulid::nil())),
+            queued: Arc::new(Notify::new()),
+            drained: Arc::new(Notify::new()),
+            retry,
+            s3_client,
+            chunk_db,
+            metrics,
+        };
+        spool.resize(chunks as i64, bytes as i64);
+        Ok(spool)
+    }
+
+    // durably write a chunk to the spool, waiting while the spool is full
+    pub async fn push(&self, entry: SpoolEntry, data: Body) -> Result<()> {
+        let size = data.len();
+        loop {
+            let drained = self.drained.notified();
+            if self.reserve(size) {
+                break;
+            }
+            debug!(bytes = size, "spool full, wait for uploads");
+            drained.await;
+        }
+
+        let id = {
+            let mut last_id = self.last_id.lock().unwrap();
+            let Some(id) = next_id(*last_id) else {
+                drop(last_id);
+                self.release(size);
+                bail!("spool entry ids exhausted");
+            };
+            *last_id = id;
+            id.to_string()
+        };
+        let written = async {
+            write_durable(&self.entry_path(&id, CHUNK_EXT), &data).await?;
+            write_durable(
+                &self.entry_path(&id, ENTRY_EXT),
+                &Body::from(serde_json::to_vec(&entry)?),
+            )
+            .await?;
+            fs::File::open(&self.dir).await?.sync_all().await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        if let Err(e) = written {
+            self.release(size);
+            let _ = fs::remove_file(self.entry_path(&id, CHUNK_EXT)).await;
+            return Err(e.context("write chunk to spool"));
+        }
+
+        debug!(id = id, path = entry.path, "chunk spooled");
+        self.queued.notify_one();
+        Ok(())
+    }
+
+    // drain the spool in write order until shutdown
+    pub fn start(self, shutdown_token: CancellationToken) {
+        tokio::spawn(async move {
+            loop {
+                let queued = self.queued.notified();
+                let result = match oldest_entry(&self.dir).await {
+                    Ok(Some(id)) => match self.upload(&id).await {
+                        // a retry can't succeed, set the entry aside and go on
+                        Err(e) if !e.is_transient() => {
+                            warn!(id = id, error = ?e, "upload spooled chunk failed, move to failed");
+                            quarantine(&self.dir, &id).await.map(|size| {
+                                self.metrics.io.spool_failed_total.inc();
+                                self.release(size);
+                            })
+                        }
+                        result => result,
+                    },
+                    Ok(None) => {
+                        tokio::select! {
+                            _ = queued => continue,
+                            _ = shutdown_token.cancelled() => break,
+                        }
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    warn!(error = ?e, "upload spooled chunk, retry");
+                    tokio::select! {
+                        _ = tokio::time::sleep(self.retry_interval) => {}
+                        _ = shutdown_token.cancelled() => break,
+                    }
+                }
+            }
+            debug!("spool uploader shutting down");
+        });
+    }
+
+    async fn upload(&self, id: &str) -> Result<()> {
+        let entry: SpoolEntry =
+            serde_json::from_slice(&fs::read(self.entry_path(id, ENTRY_EXT)).await?)
+                .context("decode spool entry")?;
+        let data = Body::open(self.entry_path(id, CHUNK_EXT)).await?;
+        let size = data.len();
+
+        let object_metadata = entry
+            .object_metadata
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.clone()))
+            .collect();
+        self.s3_client
+            .upload_chunk(
+                data,
+                &entry.bucket,
+                &entry.path,
+                entry.codec,
+                object_metadata,
+                entry.write_once,
+                &self.retry,
+            )
+            .await?;
+
+        // a retry after the metadata was written finds the chunk already there
+        db::record_chunk(self.chunk_db.as_ref(), entry.chunk).await?;
+
+        self.remove(id, size).await?;
+        debug!(id = id, path = entry.path, "spooled chunk uploaded");
+        Ok(())
+    }
+
+    async fn remove(&self, id: &str, size: u64) -> Result<()> {
+        fs::remove_file(self.entry_path(id, ENTRY_EXT)).await?;
+        fs::remove_file(self.entry_path(id, CHUNK_EXT)).await?;
+        self.release(size);
+        Ok(())
+    }
+
+    fn entry_path(&self, id: &str, ext: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, ext))
+    }
+
+    // an empty spool takes any chunk, so one larger than max bytes still goes through
+    fn reserve(&self, size: u64) -> bool {
+        let mut depth = self.depth.lock().unwrap();
+        if depth.0 > 0 && depth.1 + size > self.max_bytes {
+            return false;
+        }
+        depth.0 += 1;
+        depth.1 += size;
+        drop(depth);
+        self.resize(1, size as i64);
+        true
+    }
+
+    fn release(&self, size: u64) {
+        let mut depth = self.depth.lock().unwrap();
+        depth.0 = depth.0.saturating_sub(1);
+        depth.1 = depth.1.saturating_sub(size);
+        drop(depth);
+        self.resize(-1, -(size as i64));
+        self.drained.notify_waiters();
+    }
+
+    fn resize(&self, chunks: i64, bytes: i64) {
+        self.metrics.io.spool_chunks.inc_by(chunks);
+        self.metrics.io.spool_bytes.inc_by(bytes);
+    }
+}
+
+// an id above the last one. ulids only sort by time to the millisecond, so
+// entries spooled within one take the next id up instead
+fn next_id(last_id: Ulid) -> Option<Ulid> {
+    let id = Ulid::new();
+    if id > last_id {
+        Some(id)
+    } else {
+        last_id.increment()
+    }
+}
+
+// write to a temporary file, sync and rename into place
+async fn write_durable(path: &Path, data: &Body) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".");
+    tmp.push(TMP_EXT);
+    let mut file = fs::File::create(&tmp).await?;
+    data.copy_to(&mut file).await?;
+    file.flush().await?;
+    file.sync_all().await?;
+    fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+// drop interrupted writes, returning the count and bytes of complete chunks
+async fn scan(dir: &Path) -> Result<(u64, u64)> {
+    let mut entries = fs::read_dir(dir).await?;
+    let mut chunks = 0;
+    let mut bytes = 0;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(TMP_EXT) => fs::remove_file(&path).await?,
+            Some(CHUNK_EXT) if !path.with_extension(ENTRY_EXT).exists() => {
+                warn!(path = ?path, "remove incomplete spooled chunk");
+                fs::remove_file(&path).await?;
+            }
+            Some(CHUNK_EXT) => {
+                chunks += 1;
+                bytes += entry.metadata().await?.len();
+            }
+            _ => {}
+        }
+    }
+    Ok((chunks, bytes))
+}
+
+// move an entry out of the upload queue into failed/, returning its chunk size
+async fn quarantine(dir: &Path, id: &str) -> Result<u64> {
+    let failed = dir.join(FAILED_DIR);
+    fs::create_dir_all(&failed).await?;
+    let size = fs::metadata(dir.join(format!("{}.{}", id, CHUNK_EXT)))
+        .await?
+        .len();
+    // the entry file goes first, a chunk left behind is dropped as incomplete
+    for ext in [ENTRY_EXT, CHUNK_EXT] {
+        let name = format!("{}.{}", id, ext);
+        fs::rename(dir.join(&name), failed.join(&name)).await?;
+    }
+    Ok(size)
+}
+
+// id of the oldest complete entry, ulids sort in write order
+async fn oldest_entry(dir: &Path) -> Result<Option<String>> {
+    let mut entries = fs::read_dir(dir).await?;
+    let mut oldest: Option<String> = None;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(ENTRY_EXT) {
+            continue;
+        }
+        if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+            if oldest.as_deref().is_none_or(|oldest| id < oldest) {
+                oldest = Some(id.to_string());
+            }
+        }
+    }
+    Ok(oldest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scan_drops_incomplete_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+
+        write_durable(&path.join("01A.chunk"), &Body::from(b"complete".to_vec()))
+            .await
+            .unwrap();
+        write_durable(&path.join("01A.json"), &Body::from(b"{}".to_vec()))
+            .await
+            .unwrap();
+        write_durable(&path.join("01B.chunk"), &Body::from(b"no entry".to_vec()))
+            .await
+            .unwrap();
+        fs::write(path.join("01C.json.tmp"), b"{").await.unwrap();
+
+        assert_eq!(scan(path).await.unwrap(), (1, 8));
+        assert!(path.join("01A.chunk").exists());
+        assert!(!path.join("01B.chunk").exists());
+        assert!(!path.join("01C.json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_oldest_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        assert_eq!(oldest_entry(path).await.unwrap(), None);
+
+        for id in ["01B", "01A"] {
+            write_durable(
+                &path.join(format!("{}.chunk", id)),
+                &Body::from(b"data".to_vec()),
+            )
+            .await
+            .unwrap();
+            write_durable(
+                &path.join(format!("{}.json", id)),
+                &Body::from(b"{}".to_vec()),
+            )
+            .await
+            .unwrap();
+        }
+        // a chunk still being written is not picked up
+        write_durable(&path.join("000.chunk"), &Body::from(b"data".to_vec()))
+            .await
+            .unwrap();
+
+        assert_eq!(oldest_entry(path).await.unwrap(), Some("01A".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_quarantine() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+
+        for id in ["01A", "01B"] {
+            write_durable(
+                &path.join(format!("{}.chunk", id)),
+                &Body::from(b"data".to_vec()),
+            )
+            .await
+            .unwrap();
+            write_durable(
+                &path.join(format!("{}.json", id)),
+                &Body::from(b"{}".to_vec()),
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(quarantine(path, "01A").await.unwrap(), 4);
+        assert!(path.join("failed/01A.chunk").exists());
+        assert!(path.join("failed/01A.json").exists());
+
+        // the next entry is up and the failed one is not counted on open
+        assert_eq!(oldest_entry(path).await.unwrap(), Some("01B".to_string()));
+        assert_eq!(scan(path).await.unwrap(), (1, 4));
+    }
+
+    #[test]
+    fn test_next_id() {
+        let mut last_id = Ulid::nil();
+        for _ in 0..1000 {
+            let id = next_id(last_id).unwrap();
+            assert!(id > last_id);
+            last_id = id;
+        }
+
+        // ids keep increasing when the last one is ahead of the clock
+        let ahead = Ulid::from_parts(u64::MAX >> 16, 0);
+        assert_eq!(next_id(ahead), ahead.increment());
+    }
+}
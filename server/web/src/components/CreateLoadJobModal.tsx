@@ -24,6 +24,7 @@ export function CreateLoadJobModal({
     write_subject: "",
     poll_interval: "",
     delete_chunks: false,
+    partial_recovery: false,
     from_time: "",
     to_time: "",
   });
@@ -96,6 +97,7 @@ export function CreateLoadJobModal({
         ? parseDuration(formData.poll_interval) || undefined
         : undefined,
       delete_chunks: formData.delete_chunks,
+      partial_recovery: formData.partial_recovery,
       from_time: formData.from_time || undefined,
       to_time: formData.to_time || undefined,
     };
@@ -112,6 +114,7 @@ export function CreateLoadJobModal({
       write_subject: "",
       poll_interval: "",
       delete_chunks: false,
+      partial_recovery: false,
       from_time: "",
       to_time: "",
     });
@@ -312,6 +315,21 @@ export function CreateLoadJobModal({
           </label>
         </div>
 
+        <div className="flex items-center">
+          <input
+            type="checkbox"
+            id="partial_recovery"
+            checked={formData.partial_recovery}
+            onChange={(e) =>
+              setFormData({ ...formData, partial_recovery: e.target.checked })
+            }
+            className="mr-2"
+          />
+          <label htmlFor="partial_recovery" className="text-md">
+            Recover intact messages from damaged chunks
+          </label>
+        </div>
+
         <div className="flex justify-end gap-2 pt-4">
           <Button type="button" variant="secondary" onClick={handleClose}>
             Cancel
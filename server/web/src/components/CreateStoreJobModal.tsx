@@ -3,7 +3,7 @@ import { Modal } from "./Modal";
 import { Button } from "./Button";
 import { useCreateStoreJob } from "../hooks/useCreateStoreJob";
 import type { CreateStoreJob } from "../types/store";
-import type { Codec } from "../types/common";
+import type { Codec, Compression } from "../types/common";
 
 interface CreateStoreJobModalProps {
   isOpen: boolean;
@@ -25,9 +25,12 @@ export function CreateStoreJobModal({
     subject: "",
     bucket: "",
     prefix: "",
+    key_template: "",
     max_bytes: DEFAULT_MAX_BYTES.toString(),
     max_count: DEFAULT_MAX_COUNT.toString(),
     codec: "binary" as Codec,
+    compression: "none" as Compression,
+    encrypt: false,
   });
 
   const [errors, setErrors] = useState<Record<string, string>>({});
@@ -65,12 +68,15 @@ export function CreateStoreJobModal({
       subject: formData.subject,
       bucket: formData.bucket,
       prefix: formData.prefix || undefined,
+      key_template: formData.key_template || undefined,
       batch: {
         max_bytes: parseInt(formData.max_bytes),
         max_count: parseInt(formData.max_count),
       },
       encoding: {
         codec: formData.codec,
+        compression: formData.compression,
+        encrypt: formData.encrypt,
       },
     };
 
@@ -83,9 +89,12 @@ export function CreateStoreJobModal({
       subject: "",
       bucket: "",
       prefix: "",
+      key_template: "",
       max_bytes: DEFAULT_MAX_BYTES.toString(),
       max_count: DEFAULT_MAX_COUNT.toString(),
       codec: "binary",
+      compression: "none",
+      encrypt: false,
     });
   };
 
@@ -189,6 +198,19 @@ export function CreateStoreJobModal({
           />
         </div>
 
+        <div>
+          <label className="block text-sm font-medium mb-1">Key Template</label>
+          <input
+            type="text"
+            value={formData.key_template}
+            onChange={(e) =>
+              setFormData({ ...formData, key_template: e.target.value })
+            }
+            placeholder="{stream}/{subject}/{timestamp}-{count}.{ext}"
+            className="w-full px-3 py-2 bg-bg-main border border-border-subtle rounded focus:outline-none focus:border-accent"
+          />
+        </div>
+
         <div className="border-t border-border-subtle pt-4">
           <h3 className="text-md font-medium mb-3">Batch Settings</h3>
 
@@ -242,8 +264,44 @@ export function CreateStoreJobModal({
             >
               <option value="binary">binary</option>
               <option value="json">json</option>
+              <option value="framed">framed</option>
+              <option value="parquet">parquet</option>
+              <option value="jsonl">jsonl</option>
             </select>
           </div>
+
+          <div className="mt-3">
+            <select
+              value={formData.compression}
+              onChange={(e) =>
+                setFormData({
+                  ...formData,
+                  compression: e.target.value as Compression,
+                })
+              }
+              className="w-full px-3 py-2 bg-bg-main border border-border-subtle rounded focus:outline-none focus:border-accent"
+            >
+              <option value="none">none</option>
+              <option value="zstd">zstd</option>
+              <option value="gzip">gzip</option>
+              <option value="lz4">lz4</option>
+            </select>
+          </div>
+
+          <div className="flex items-center mt-3">
+            <input
+              type="checkbox"
+              id="encrypt"
+              checked={formData.encrypt}
+              onChange={(e) =>
+                setFormData({ ...formData, encrypt: e.target.checked })
+              }
+              className="mr-2"
+            />
+            <label htmlFor="encrypt" className="text-md">
+              Encrypt chunks
+            </label>
+          </div>
         </div>
 
         <div className="flex justify-end gap-2 pt-4">
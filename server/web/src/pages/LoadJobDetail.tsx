@@ -118,6 +118,14 @@ export function LoadJobDetail() {
           </div>
 
           <div className="space-y-6">
+            {job.error && (
+              <div>
+                <h2 className="text-lg font-medium mb-2">Error</h2>
+                <p className="font-mono text-sm text-error break-all">
+                  {job.error}
+                </p>
+              </div>
+            )}
             <div>
               <h2 className="text-lg font-medium mb-4">Configuration</h2>
               <dl className="grid grid-cols-2 gap-4">
@@ -153,6 +161,12 @@ export function LoadJobDetail() {
                   <dt className="text-sm text-text-muted">Delete Chunks</dt>
                   <dd className="mt-1">{job.delete_chunks ? "Yes" : "No"}</dd>
                 </div>
+                <div>
+                  <dt className="text-sm text-text-muted">Partial Recovery</dt>
+                  <dd className="mt-1">
+                    {job.partial_recovery ? "Yes" : "No"}
+                  </dd>
+                </div>
                 <div>
                   <dt className="text-sm text-text-muted">From Time</dt>
                   <dd className="mt-1 text-sm">
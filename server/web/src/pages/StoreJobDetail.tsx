@@ -169,6 +169,16 @@ export function StoreJobDetail() {
                   <dt className="text-sm text-text-muted">Codec</dt>
                   <dd className="mt-1">{job.encoding.codec}</dd>
                 </div>
+                <div>
+                  <dt className="text-sm text-text-muted">Compression</dt>
+                  <dd className="mt-1">
+                    {job.encoding.compression ?? "none"}
+                  </dd>
+                </div>
+                <div>
+                  <dt className="text-sm text-text-muted">Encrypted</dt>
+                  <dd className="mt-1">{job.encoding.encrypt ? "yes" : "no"}</dd>
+                </div>
               </dl>
             </div>
           </div>
@@ -1,10 +1,23 @@
-export type Codec = "json" | "binary";
+export type Codec = "json" | "binary" | "framed" | "parquet" | "jsonl";
+
+export type Compression = "none" | "zstd" | "gzip" | "lz4";
 
 export interface Batch {
   max_bytes: number;
   max_count: number;
+  max_age?: { secs: number; nanos: number };
 }
 
 export interface Encoding {
   codec: Codec;
+  compression?: Compression;
+  compression_level?: number;
+  encrypt?: boolean;
+}
+
+export interface Retry {
+  max_attempts?: number;
+  backoff?: { secs: number; nanos: number };
+  max_backoff?: { secs: number; nanos: number };
+  jitter?: boolean;
 }
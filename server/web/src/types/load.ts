@@ -1,3 +1,5 @@
+import type { Retry } from "./common";
+
 export type LoadJobStatus =
   | "Created"
   | "Running"
@@ -17,8 +19,11 @@ export interface LoadJob {
   write_subject: string;
   poll_interval?: { secs: number; nanos: number };
   delete_chunks: boolean;
+  partial_recovery?: boolean;
   from_time?: string;
   to_time?: string;
+  retry?: Retry;
+  error?: string;
   created: string;
   updated: string;
 }
@@ -33,6 +38,8 @@ export interface CreateLoadJob {
   write_subject: string;
   poll_interval?: { secs: number; nanos: number };
   delete_chunks: boolean;
+  partial_recovery?: boolean;
   from_time?: string;
   to_time?: string;
+  retry?: Retry;
 }
@@ -1,4 +1,21 @@
-import type { Batch, Encoding } from "./common";
+import type { Batch, Encoding, Retry } from "./common";
+
+export type DeliverPolicy =
+  | "all"
+  | "new"
+  | "last"
+  | "by_start_sequence"
+  | "by_start_time";
+
+export interface Deliver {
+  policy?: DeliverPolicy;
+  start_sequence?: number;
+  start_time?: string;
+  end_sequence?: number;
+  end_time?: string;
+}
+
+export type AckPolicy = "explicit" | "all";
 
 export type StoreJobStatus =
   | "Created"
@@ -14,10 +31,17 @@ export interface StoreJob {
   stream: string;
   consumer?: string;
   subject: string;
+  subjects?: string[];
   bucket: string;
   prefix?: string;
   batch: Batch;
   encoding: Encoding;
+  key_template?: string;
+  deliver: Deliver;
+  split_by_subject: boolean;
+  subject_depth?: number;
+  ack_policy?: AckPolicy;
+  retry?: Retry;
   created: string;
   updated: string;
 }
@@ -27,8 +51,15 @@ export interface CreateStoreJob {
   stream: string;
   consumer?: string;
   subject: string;
+  subjects?: string[];
   bucket: string;
   prefix?: string;
   batch?: Batch;
   encoding?: Encoding;
+  key_template?: string;
+  deliver?: Deliver;
+  split_by_subject?: boolean;
+  subject_depth?: number;
+  ack_policy?: AckPolicy;
+  retry?: Retry;
 }
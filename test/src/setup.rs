@@ -1,7 +1,7 @@
 use crate::config::Config;
 use async_nats::jetstream::stream::{Config as StreamConfig, RetentionPolicy, StorageType};
 use nats3_client::Client;
-use nats3_types::{Batch, Encoding, LoadJobCreate, StoreJobCreate};
+use nats3_types::{AckPolicy, Batch, Deliver, Encoding, LoadJobCreate, StoreJobCreate};
 use s3::{creds::Credentials, Bucket, BucketConfiguration, Region};
 use tracing::{debug, info};
 
@@ -122,10 +122,18 @@ async fn create_store_job(config: &Config) -> anyhow::Result<()> {
         stream: config.input_stream.clone(),
         consumer: None,
         subject: config.input_subject.clone(),
+        subjects: Vec::new(),
         bucket: config.bucket_name(),
         prefix: None,
         batch: Batch::default(),
         encoding: Encoding::default(),
+        key_template: None,
+        deliver: Deliver::default(),
+        split_by_subject: false,
+        subject_depth: None,
+        ack_policy: AckPolicy::default(),
+        backup: false,
+        retry: None,
     };
 
     match client.create_store_job(create_job).await {
@@ -158,8 +166,12 @@ async fn create_load_job(config: &Config) -> anyhow::Result<()> {
         write_subject: config.output_subject.clone(),
         poll_interval: config.poll_interval_sec.map(std::time::Duration::from_secs),
         delete_chunks: true,
+        partial_recovery: false,
         from_time: None,
         to_time: None,
+        backup_id: None,
+        restore_stream: None,
+        retry: None,
     };
 
     match client.create_load_job(create_job).await {
@@ -16,6 +16,7 @@ pub struct Verifier {
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Mismatch {
     HeaderMismatch { seq: usize, key: String },
     PayloadMismatch { seq: usize },
@@ -40,11 +41,7 @@ pub struct TestResults {
 
 impl Verifier {
     pub fn new(messages: Vec<TestMessage>) -> Self {
-        let expected: HashMap<_, _> = messages
-            .into_iter()
-            .enumerate()
-            .map(|(i, msg)| (i, msg))
-            .collect();
+        let expected: HashMap<_, _> = messages.into_iter().enumerate().collect();
 
         let missing = expected.keys().copied().collect();
 
@@ -184,7 +181,7 @@ pub async fn consume_and_verify(
                     break;
                 }
 
-                if (verifier.received.len() % 10) == 0 {
+                if verifier.received.len().is_multiple_of(10) {
                     info!(
                         "Progress: {}/{} messages verified",
                         verifier.received.len(),
@@ -6,7 +6,12 @@ use thiserror::Error;
 
 const DEFAULT_MAX_BYTES: i64 = 1_000_000;
 const DEFAULT_MAX_COUNT: i64 = 1000;
+const DEFAULT_MAX_AGE: time::Duration = time::Duration::from_secs(10);
 const DEFAULT_CODEC: Codec = Codec::Binary;
+const DEFAULT_COMPRESSION: Compression = Compression::None;
+const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 3;
+const DEFAULT_RETRY_BACKOFF: time::Duration = time::Duration::from_millis(200);
+const DEFAULT_RETRY_MAX_BACKOFF: time::Duration = time::Duration::from_secs(10);
 
 #[derive(Serialize, Deserialize, Clone, Debug, Display, Eq, PartialEq)]
 pub enum Codec {
@@ -14,6 +19,12 @@ pub enum Codec {
     Json,
     #[serde(alias = "binary", alias = "bin")]
     Binary,
+    #[serde(alias = "framed")]
+    Framed,
+    #[serde(alias = "parquet")]
+    Parquet,
+    #[serde(alias = "jsonl")]
+    Jsonl,
 }
 
 impl Codec {
@@ -21,6 +32,9 @@ impl Codec {
         match self {
             Codec::Json => "json",
             Codec::Binary => "bin",
+            Codec::Framed => "framed",
+            Codec::Parquet => "parquet",
+            Codec::Jsonl => "jsonl",
         }
     }
 }
@@ -44,8 +58,65 @@ impl FromStr for Codec {
             "json" => Ok(Self::Json),
             "bin" => Ok(Self::Binary),
             "binary" => Ok(Self::Binary),
+            "framed" => Ok(Self::Framed),
+            "parquet" => Ok(Self::Parquet),
+            "jsonl" => Ok(Self::Jsonl),
             _ => Err(CodecParseError(format!(
-                "Invalid codec '{}'. Valid options: json, bin",
+                "Invalid codec '{}'. Valid options: json, bin, framed, parquet, jsonl",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Display, Eq, PartialEq, Default)]
+pub enum Compression {
+    #[default]
+    #[serde(alias = "none")]
+    None,
+    #[serde(alias = "zstd")]
+    Zstd,
+    #[serde(alias = "gzip", alias = "gz")]
+    Gzip,
+    #[serde(alias = "lz4")]
+    Lz4,
+}
+
+impl Compression {
+    // file extension appended after the codec extension, if any
+    pub fn to_extension(&self) -> Option<&str> {
+        match self {
+            Compression::None => None,
+            Compression::Zstd => Some("zst"),
+            Compression::Gzip => Some("gz"),
+            Compression::Lz4 => Some("lz4"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CompressionParseError(String);
+
+impl std::fmt::Display for CompressionParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CompressionParseError {}
+
+impl FromStr for Compression {
+    type Err = CompressionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "zstd" => Ok(Self::Zstd),
+            "gzip" => Ok(Self::Gzip),
+            "gz" => Ok(Self::Gzip),
+            "lz4" => Ok(Self::Lz4),
+            _ => Err(CompressionParseError(format!(
+                "Invalid compression '{}'. Valid options: none, zstd, gzip, lz4",
                 s
             ))),
         }
@@ -59,9 +130,36 @@ pub struct StoreJobCreate {
     pub prefix: Option<String>,
     pub stream: String,
     pub consumer: Option<String>,
+    // left empty for a backup, which takes its subjects from the stream
+    #[serde(default)]
     pub subject: String,
+    // more filter subjects consumed alongside subject
+    #[serde(default)]
+    pub subjects: Vec<String>,
     pub batch: Batch,
     pub encoding: Encoding,
+    // object key layout, defaults to DEFAULT_KEY_TEMPLATE
+    #[serde(default)]
+    pub key_template: Option<String>,
+    // where a created consumer starts and where the job ends
+    #[serde(default)]
+    pub deliver: Deliver,
+    // upload one chunk per subject of each batch instead of one per batch
+    #[serde(default)]
+    pub split_by_subject: bool,
+    // with split_by_subject, group subjects by their first tokens
+    #[serde(default)]
+    pub subject_depth: Option<u32>,
+    // how the job's consumer acks, all acks each batch with one message
+    #[serde(default)]
+    pub ack_policy: AckPolicy,
+    // archive every subject of the stream and record its config with the
+    // chunks, so a load job can restore the whole stream
+    #[serde(default)]
+    pub backup: bool,
+    // overrides the server retry policy
+    #[serde(default)]
+    pub retry: Option<Retry>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -99,10 +197,26 @@ pub struct StoreJob {
     pub stream: String,
     pub consumer: Option<String>,
     pub subject: String,
+    #[serde(default)]
+    pub subjects: Vec<String>,
     pub bucket: String,
     pub prefix: Option<String>,
     pub batch: Batch,
     pub encoding: Encoding,
+    #[serde(default)]
+    pub key_template: Option<String>,
+    #[serde(default)]
+    pub deliver: Deliver,
+    #[serde(default)]
+    pub split_by_subject: bool,
+    #[serde(default)]
+    pub subject_depth: Option<u32>,
+    #[serde(default)]
+    pub ack_policy: AckPolicy,
+    #[serde(default)]
+    pub backup: bool,
+    #[serde(default)]
+    pub retry: Option<Retry>,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
 }
@@ -122,6 +236,9 @@ pub struct Batch {
     pub max_bytes: i64,
     #[serde(default = "max_count_default")]
     pub max_count: i64,
+    // longest a message waits in the buffer before the batch is flushed
+    #[serde(default = "max_age_default")]
+    pub max_age: time::Duration,
 }
 
 impl Default for Batch {
@@ -129,10 +246,20 @@ impl Default for Batch {
         Self {
             max_bytes: max_bytes_default(),
             max_count: max_count_default(),
+            max_age: max_age_default(),
         }
     }
 }
 
+impl Batch {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.max_age.is_zero() {
+            return Err(ValidationError::InvalidBatchMaxAge);
+        }
+        Ok(())
+    }
+}
+
 fn max_bytes_default() -> i64 {
     DEFAULT_MAX_BYTES
 }
@@ -141,16 +268,90 @@ fn max_count_default() -> i64 {
     DEFAULT_MAX_COUNT
 }
 
+fn max_age_default() -> time::Duration {
+    DEFAULT_MAX_AGE
+}
+
+// retry policy for transient S3, NATS and Postgres errors
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Retry {
+    // tries per operation, 1 disables retries
+    #[serde(default = "retry_max_attempts_default")]
+    pub max_attempts: u32,
+    // wait before the first retry, doubled for each retry after it
+    #[serde(default = "retry_backoff_default")]
+    pub backoff: time::Duration,
+    #[serde(default = "retry_max_backoff_default")]
+    pub max_backoff: time::Duration,
+    // wait a random part of each backoff so failed jobs don't retry in step
+    #[serde(default = "retry_jitter_default")]
+    pub jitter: bool,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            max_attempts: retry_max_attempts_default(),
+            backoff: retry_backoff_default(),
+            max_backoff: retry_max_backoff_default(),
+            jitter: retry_jitter_default(),
+        }
+    }
+}
+
+impl Retry {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.max_attempts == 0 {
+            return Err(ValidationError::InvalidRetry {
+                reason: "max attempts must be at least 1".to_string(),
+            });
+        }
+        if self.backoff > self.max_backoff {
+            return Err(ValidationError::InvalidRetry {
+                reason: "backoff is longer than max backoff".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn retry_max_attempts_default() -> u32 {
+    DEFAULT_RETRY_MAX_ATTEMPTS
+}
+
+fn retry_backoff_default() -> time::Duration {
+    DEFAULT_RETRY_BACKOFF
+}
+
+fn retry_max_backoff_default() -> time::Duration {
+    DEFAULT_RETRY_MAX_BACKOFF
+}
+
+fn retry_jitter_default() -> bool {
+    true
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Encoding {
     #[serde(default = "codec_default")]
     pub codec: Codec,
+    #[serde(default = "compression_default")]
+    pub compression: Compression,
+    // algorithm specific level, uses the algorithm default when unset
+    #[serde(default)]
+    pub compression_level: Option<i32>,
+    // envelope encrypt chunks with the server's active master key
+    #[serde(default)]
+    pub encrypt: bool,
 }
 
 impl Default for Encoding {
     fn default() -> Self {
         Self {
             codec: codec_default(),
+            compression: compression_default(),
+            compression_level: None,
+            encrypt: false,
         }
     }
 }
@@ -159,6 +360,163 @@ fn codec_default() -> Codec {
     DEFAULT_CODEC
 }
 
+fn compression_default() -> Compression {
+    DEFAULT_COMPRESSION
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Display, Eq, PartialEq, Default)]
+pub enum DeliverPolicy {
+    #[default]
+    #[serde(alias = "all")]
+    All,
+    #[serde(alias = "new")]
+    New,
+    #[serde(alias = "last")]
+    Last,
+    #[serde(alias = "by_start_sequence")]
+    ByStartSequence,
+    #[serde(alias = "by_start_time")]
+    ByStartTime,
+}
+
+#[derive(Debug)]
+pub struct DeliverPolicyParseError(String);
+
+impl std::fmt::Display for DeliverPolicyParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for DeliverPolicyParseError {}
+
+impl FromStr for DeliverPolicy {
+    type Err = DeliverPolicyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "new" => Ok(Self::New),
+            "last" => Ok(Self::Last),
+            "by_start_sequence" => Ok(Self::ByStartSequence),
+            "by_start_time" => Ok(Self::ByStartTime),
+            _ => Err(DeliverPolicyParseError(format!(
+                "Invalid deliver policy '{}'. Valid options: all, new, last, by_start_sequence, by_start_time",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Display, Eq, PartialEq, Default)]
+pub enum AckPolicy {
+    // every message is acked on its own
+    #[default]
+    #[serde(alias = "explicit")]
+    Explicit,
+    // acking a message acks every message delivered before it
+    #[serde(alias = "all")]
+    All,
+}
+
+#[derive(Debug)]
+pub struct AckPolicyParseError(String);
+
+impl std::fmt::Display for AckPolicyParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for AckPolicyParseError {}
+
+impl FromStr for AckPolicy {
+    type Err = AckPolicyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "explicit" => Ok(Self::Explicit),
+            "all" => Ok(Self::All),
+            _ => Err(AckPolicyParseError(format!(
+                "Invalid ack policy '{}'. Valid options: explicit, all",
+                s
+            ))),
+        }
+    }
+}
+
+// where a store job's consumer starts reading and, optionally, the stream
+// sequence or message time after which the job completes
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Deliver {
+    #[serde(default)]
+    pub policy: DeliverPolicy,
+    // first stream sequence for ByStartSequence
+    #[serde(default)]
+    pub start_sequence: Option<u64>,
+    // first message time for ByStartTime
+    #[serde(default)]
+    pub start_time: Option<DateTime<Utc>>,
+    // last stream sequence to store, inclusive
+    #[serde(default)]
+    pub end_sequence: Option<u64>,
+    // last message time to store, inclusive
+    #[serde(default)]
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+impl Deliver {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let invalid = |reason: &str| {
+            Err(ValidationError::InvalidDeliver {
+                reason: reason.to_string(),
+            })
+        };
+        match self.policy {
+            DeliverPolicy::ByStartSequence if self.start_sequence.is_none_or(|s| s == 0) => {
+                return invalid("by_start_sequence needs a start sequence of at least 1");
+            }
+            DeliverPolicy::ByStartTime if self.start_time.is_none() => {
+                return invalid("by_start_time needs a start time");
+            }
+            _ => {}
+        }
+        if self.start_sequence.is_some() && self.policy != DeliverPolicy::ByStartSequence {
+            return invalid("start sequence needs the by_start_sequence policy");
+        }
+        if self.start_time.is_some() && self.policy != DeliverPolicy::ByStartTime {
+            return invalid("start time needs the by_start_time policy");
+        }
+        if let (Some(start), Some(end)) = (self.start_sequence, self.end_sequence) {
+            if end < start {
+                return invalid("end sequence is before start sequence");
+            }
+        }
+        if let (Some(start), Some(end)) = (self.start_time, self.end_time) {
+            if end < start {
+                return invalid("end time is before start time");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn has_end(&self) -> bool {
+        self.end_sequence.is_some() || self.end_time.is_some()
+    }
+
+    // whether a message falls after the end bound
+    pub fn is_past_end(&self, sequence: u64, timestamp: DateTime<Utc>) -> bool {
+        self.end_sequence.is_some_and(|end| sequence > end)
+            || self.end_time.is_some_and(|end| timestamp > end)
+    }
+
+    // whether no later message can fall within the end bound
+    pub fn is_at_end(&self, sequence: u64, pending: u64, now: DateTime<Utc>) -> bool {
+        self.end_sequence.is_some_and(|end| sequence >= end)
+            || (pending == 0 && self.end_time.is_some_and(|end| end <= now))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LoadJobCreate {
     pub name: String,
@@ -166,18 +524,257 @@ pub struct LoadJobCreate {
     pub prefix: Option<String>,
     pub read_stream: String,
     pub read_consumer: Option<String>,
+    // both subjects are left empty for a restore
+    #[serde(default)]
     pub read_subject: String,
+    #[serde(default)]
     pub write_subject: String,
     pub poll_interval: Option<time::Duration>,
     pub delete_chunks: bool,
+    // publish the intact messages of a damaged chunk instead of failing
+    #[serde(default)]
+    pub partial_recovery: bool,
     pub from_time: Option<DateTime<Utc>>,
     pub to_time: Option<DateTime<Utc>>,
+    // backup store job to recreate read_stream from, with its recorded config.
+    // only that job's chunks are read, each message is replayed to its
+    // original subject
+    #[serde(default)]
+    pub backup_id: Option<String>,
+    // name of the recreated stream, defaults to read_stream
+    #[serde(default)]
+    pub restore_stream: Option<String>,
+    // overrides the server retry policy
+    #[serde(default)]
+    pub retry: Option<Retry>,
 }
 
 #[derive(Error, Debug)]
 pub enum ValidationError {
     #[error("load job with polling must delete chunks")]
     PollMustDelete,
+    #[error("compression level {level} out of range for {compression}")]
+    InvalidCompressionLevel {
+        compression: Compression,
+        level: i32,
+    },
+    #[error("chunk encryption requested but no master key is configured")]
+    EncryptionNotConfigured,
+    #[error("invalid key template '{template}': {reason}")]
+    InvalidKeyTemplate { template: String, reason: String },
+    #[error("invalid deliver policy: {reason}")]
+    InvalidDeliver { reason: String },
+    #[error("deliver policy of a bound consumer is set on the consumer")]
+    BoundConsumerDeliverPolicy,
+    #[error("invalid subjects: {reason}")]
+    InvalidSubjects { reason: String },
+    #[error("subject depth must be at least 1 and needs split by subject")]
+    InvalidSubjectDepth,
+    #[error("batch max age must be greater than zero")]
+    InvalidBatchMaxAge,
+    #[error("invalid retry policy: {reason}")]
+    InvalidRetry { reason: String },
+    #[error("invalid key-value bucket name '{bucket}'")]
+    InvalidKvBucket { bucket: String },
+    #[error("invalid key-value archive: {reason}")]
+    InvalidKvArchive { reason: String },
+    #[error("invalid object store bucket name '{bucket}'")]
+    InvalidObjectBucket { bucket: String },
+    #[error("invalid object store archive: {reason}")]
+    InvalidObjectArchive { reason: String },
+    #[error("invalid stream backup: {reason}")]
+    InvalidStreamBackup { reason: String },
+    #[error("invalid stream restore: {reason}")]
+    InvalidStreamRestore { reason: String },
+}
+
+impl StoreJobCreate {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.batch.validate()?;
+        self.encoding.validate()?;
+        if self.backup {
+            self.validate_backup()?;
+        } else {
+            validate_subjects(&self.filter_subjects())?;
+        }
+        if let Some(template) = &self.key_template {
+            validate_key_template(template)?;
+        }
+        self.deliver.validate()?;
+        if self.consumer.is_some() && self.deliver.policy != DeliverPolicy::All {
+            return Err(ValidationError::BoundConsumerDeliverPolicy);
+        }
+        if let Some(depth) = self.subject_depth {
+            if depth == 0 || !self.split_by_subject {
+                return Err(ValidationError::InvalidSubjectDepth);
+            }
+        }
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl StoreJobCreate {
+    // a backup consumes the stream's own subjects in sequence order
+    fn validate_backup(&self) -> Result<(), ValidationError> {
+        let invalid = |reason: &str| {
+            Err(ValidationError::InvalidStreamBackup {
+                reason: reason.to_string(),
+            })
+        };
+        if !self.subject.is_empty() || !self.subjects.is_empty() {
+            return invalid("subjects are taken from the stream");
+        }
+        if self.consumer.is_some() {
+            return invalid("a backup creates its own consumer");
+        }
+        if self.split_by_subject {
+            return invalid("chunks split by subject are not in sequence order");
+        }
+        Ok(())
+    }
+
+    // every subject the job consumes, subject first
+    pub fn filter_subjects(&self) -> Vec<String> {
+        filter_subjects(&self.subject, &self.subjects)
+    }
+}
+
+impl StoreJob {
+    // every subject the job consumes, subject first
+    pub fn filter_subjects(&self) -> Vec<String> {
+        filter_subjects(&self.subject, &self.subjects)
+    }
+}
+
+fn filter_subjects(subject: &str, subjects: &[String]) -> Vec<String> {
+    std::iter::once(subject.to_string())
+        .chain(subjects.iter().cloned())
+        .collect()
+}
+
+fn validate_subjects(subjects: &[String]) -> Result<(), ValidationError> {
+    for (i, subject) in subjects.iter().enumerate() {
+        if subject.is_empty() || subject.split('.').any(|token| token.is_empty()) {
+            return Err(ValidationError::InvalidSubjects {
+                reason: format!("'{}' is not a valid subject", subject),
+            });
+        }
+        if subjects[..i].contains(subject) {
+            return Err(ValidationError::InvalidSubjects {
+                reason: format!("'{}' is listed more than once", subject),
+            });
+        }
+    }
+    Ok(())
+}
+
+// object key layout used when a store job doesn't set one
+pub const DEFAULT_KEY_TEMPLATE: &str = "{stream}/{subject}/{timestamp}-{ulid}.{ext}";
+
+// placeholders a key template may use. time fields are the UTC time of the
+// first message in the chunk, seq_start and seq_end its stream sequence range.
+pub const KEY_PLACEHOLDERS: &[&str] = &[
+    "stream",
+    "subject",
+    "timestamp",
+    "count",
+    "seq_start",
+    "seq_end",
+    "ulid",
+    "ext",
+    "YYYY",
+    "MM",
+    "DD",
+    "HH",
+    "mm",
+];
+
+// split a key template into literal text and placeholders
+pub fn parse_key_template(template: &str) -> Result<Vec<KeyPart<'_>>, ValidationError> {
+    let invalid = |reason: &str| ValidationError::InvalidKeyTemplate {
+        template: template.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        match rest.find(['{', '}']) {
+            Some(i) if rest.as_bytes()[i] == b'}' => return Err(invalid("unmatched '}'")),
+            Some(i) => {
+                if i > 0 {
+                    parts.push(KeyPart::Literal(&rest[..i]));
+                }
+                let end = rest[i..]
+                    .find('}')
+                    .ok_or_else(|| invalid("unmatched '{'"))?;
+                let name = &rest[i + 1..i + end];
+                if !KEY_PLACEHOLDERS.contains(&name) {
+                    return Err(invalid(&format!("unknown placeholder '{{{name}}}'")));
+                }
+                parts.push(KeyPart::Placeholder(name));
+                rest = &rest[i + end + 1..];
+            }
+            None => {
+                parts.push(KeyPart::Literal(rest));
+                rest = "";
+            }
+        }
+    }
+    Ok(parts)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeyPart<'a> {
+    Literal(&'a str),
+    Placeholder(&'a str),
+}
+
+pub fn validate_key_template(template: &str) -> Result<(), ValidationError> {
+    let invalid = |reason: &str| ValidationError::InvalidKeyTemplate {
+        template: template.to_string(),
+        reason: reason.to_string(),
+    };
+    let parts = parse_key_template(template)?;
+    if template.starts_with('/') || template.ends_with('/') || template.contains("//") {
+        return Err(invalid("path segments must not be empty"));
+    }
+    // chunks written with the same key would overwrite each other, and many
+    // chunks can share a timestamp. stream sequences only tell chunks of one
+    // stream apart
+    let has = |name: &str| parts.contains(&KeyPart::Placeholder(name));
+    if !(has("ulid") || has("seq_start") && has("stream")) {
+        return Err(invalid("must contain {ulid}, or {stream} and {seq_start}"));
+    }
+    Ok(())
+}
+
+// whether every chunk gets a key of its own. other templates render the same
+// key when the same messages are consumed again
+pub fn key_template_is_unique(template: &str) -> bool {
+    template.contains("{ulid}")
+}
+
+impl Encoding {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let Some(level) = self.compression_level else {
+            return Ok(());
+        };
+        let valid = match self.compression {
+            Compression::None | Compression::Lz4 => false,
+            Compression::Zstd => (1..=22).contains(&level),
+            Compression::Gzip => (0..=9).contains(&level),
+        };
+        if !valid {
+            return Err(ValidationError::InvalidCompressionLevel {
+                compression: self.compression.clone(),
+                level,
+            });
+        }
+        Ok(())
+    }
 }
 
 impl LoadJobCreate {
@@ -185,8 +782,48 @@ impl LoadJobCreate {
         if self.poll_interval.is_some() && !self.delete_chunks {
             return Err(ValidationError::PollMustDelete);
         }
+        self.validate_restore()?;
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
         Ok(())
     }
+
+    fn validate_restore(&self) -> Result<(), ValidationError> {
+        let invalid = |reason: &str| {
+            Err(ValidationError::InvalidStreamRestore {
+                reason: reason.to_string(),
+            })
+        };
+        if self.backup_id.is_none() {
+            if self.restore_stream.is_some() {
+                return invalid("restore stream needs a backup id");
+            }
+            return Ok(());
+        }
+        if !self.read_subject.is_empty() || !self.write_subject.is_empty() {
+            return invalid("messages are replayed to their original subjects");
+        }
+        if self.read_consumer.is_some() {
+            return invalid("the chunks of the backup's own consumer are read");
+        }
+        if self.delete_chunks || self.poll_interval.is_some() {
+            return invalid("a restore reads every chunk once and keeps them");
+        }
+        if let Some(stream) = &self.restore_stream {
+            if !valid_stream_name(stream) {
+                return invalid(&format!("'{}' is not a valid stream name", stream));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn valid_stream_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '.' | '*' | '>' | '/' | '\\'))
 }
 
 #[derive(Clone, Debug, Default)]
@@ -230,8 +867,19 @@ pub struct LoadJob {
     pub poll_interval: Option<time::Duration>,
     pub write_subject: String,
     pub delete_chunks: bool,
+    #[serde(default)]
+    pub partial_recovery: bool,
     pub from_time: Option<DateTime<Utc>>,
     pub to_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub backup_id: Option<String>,
+    #[serde(default)]
+    pub restore_stream: Option<String>,
+    #[serde(default)]
+    pub retry: Option<Retry>,
+    // reason the job failed, set when status is failure
+    #[serde(default)]
+    pub error: Option<String>,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
 }
@@ -244,3 +892,350 @@ pub enum LoadJobStatus {
     Success,
     Failure,
 }
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Display, Eq, PartialEq)]
+pub enum KvJobKind {
+    // snapshot a key-value bucket to S3
+    Archive,
+    // write an archived snapshot back into a key-value bucket
+    Restore,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Display, Eq, PartialEq)]
+pub enum KvJobStatus {
+    Created,
+    Running,
+    Success,
+    Failure,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KvArchiveCreate {
+    pub name: String,
+    pub kv_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    // every revision of each key instead of only its latest value
+    #[serde(default)]
+    pub history: bool,
+    #[serde(default)]
+    pub encoding: Encoding,
+    // overrides the server retry policy
+    #[serde(default)]
+    pub retry: Option<Retry>,
+}
+
+impl KvArchiveCreate {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_kv_bucket(&self.kv_bucket)?;
+        self.encoding.validate()?;
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KvRestoreCreate {
+    pub name: String,
+    // id of the successful archive job to restore
+    pub archive_id: String,
+    // bucket to restore into, defaults to the archived bucket
+    #[serde(default)]
+    pub kv_bucket: Option<String>,
+    // overrides the server retry policy
+    #[serde(default)]
+    pub retry: Option<Retry>,
+}
+
+impl KvRestoreCreate {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(bucket) = &self.kv_bucket {
+            validate_kv_bucket(bucket)?;
+        }
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+        Ok(())
+    }
+}
+
+// key-value job as stored, restores copy the S3 location and encoding of
+// their archive
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KvJobCreate {
+    pub name: String,
+    pub kind: KvJobKind,
+    pub kv_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub history: bool,
+    pub encoding: Encoding,
+    pub archive_id: Option<String>,
+    pub retry: Option<Retry>,
+}
+
+impl From<KvArchiveCreate> for KvJobCreate {
+    fn from(job: KvArchiveCreate) -> Self {
+        Self {
+            name: job.name,
+            kind: KvJobKind::Archive,
+            kv_bucket: job.kv_bucket,
+            bucket: job.bucket,
+            prefix: job.prefix,
+            history: job.history,
+            encoding: job.encoding,
+            archive_id: None,
+            retry: job.retry,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ListKvJobsQuery {
+    pub statuses: Option<Vec<KvJobStatus>>,
+    pub kind: Option<KvJobKind>,
+    pub kv_bucket: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl ListKvJobsQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_statuses(mut self, statuses: Vec<KvJobStatus>) -> Self {
+        self.statuses = Some(statuses);
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KvJob {
+    pub id: String,
+    pub name: String,
+    pub kind: KvJobKind,
+    pub status: KvJobStatus,
+    // bucket archived, or restored into
+    pub kv_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub history: bool,
+    pub encoding: Encoding,
+    // archive job a restore reads from, none for archives
+    #[serde(default)]
+    pub archive_id: Option<String>,
+    #[serde(default)]
+    pub retry: Option<Retry>,
+    // reason the job failed, set when status is failure
+    #[serde(default)]
+    pub error: Option<String>,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+fn validate_kv_bucket(bucket: &str) -> Result<(), ValidationError> {
+    if !valid_bucket_name(bucket) {
+        return Err(ValidationError::InvalidKvBucket {
+            bucket: bucket.to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Display, Eq, PartialEq)]
+pub enum ObjectJobKind {
+    // copy the objects of an object store bucket to S3
+    Archive,
+    // write archived objects back into an object store bucket
+    Restore,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Display, Eq, PartialEq)]
+pub enum ObjectJobStatus {
+    Created,
+    Running,
+    Success,
+    Failure,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectArchiveCreate {
+    pub name: String,
+    pub object_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    // overrides the server retry policy
+    #[serde(default)]
+    pub retry: Option<Retry>,
+}
+
+impl ObjectArchiveCreate {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_object_bucket(&self.object_bucket)?;
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectRestoreCreate {
+    pub name: String,
+    // id of the successful archive job to restore
+    pub archive_id: String,
+    // bucket to restore into, defaults to the archived bucket
+    #[serde(default)]
+    pub object_bucket: Option<String>,
+    // overrides the server retry policy
+    #[serde(default)]
+    pub retry: Option<Retry>,
+}
+
+impl ObjectRestoreCreate {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(bucket) = &self.object_bucket {
+            validate_object_bucket(bucket)?;
+        }
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+        Ok(())
+    }
+}
+
+// object store job as stored, restores copy the S3 location of their archive
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectJobCreate {
+    pub name: String,
+    pub kind: ObjectJobKind,
+    pub object_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub archive_id: Option<String>,
+    pub retry: Option<Retry>,
+}
+
+impl From<ObjectArchiveCreate> for ObjectJobCreate {
+    fn from(job: ObjectArchiveCreate) -> Self {
+        Self {
+            name: job.name,
+            kind: ObjectJobKind::Archive,
+            object_bucket: job.object_bucket,
+            bucket: job.bucket,
+            prefix: job.prefix,
+            archive_id: None,
+            retry: job.retry,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ListObjectJobsQuery {
+    pub statuses: Option<Vec<ObjectJobStatus>>,
+    pub kind: Option<ObjectJobKind>,
+    pub object_bucket: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl ListObjectJobsQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_statuses(mut self, statuses: Vec<ObjectJobStatus>) -> Self {
+        self.statuses = Some(statuses);
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectJob {
+    pub id: String,
+    pub name: String,
+    pub kind: ObjectJobKind,
+    pub status: ObjectJobStatus,
+    // bucket archived, or restored into
+    pub object_bucket: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    // archive job a restore reads from, none for archives
+    #[serde(default)]
+    pub archive_id: Option<String>,
+    #[serde(default)]
+    pub retry: Option<Retry>,
+    // reason the job failed, set when status is failure
+    #[serde(default)]
+    pub error: Option<String>,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+fn validate_object_bucket(bucket: &str) -> Result<(), ValidationError> {
+    if !valid_bucket_name(bucket) {
+        return Err(ValidationError::InvalidObjectBucket {
+            bucket: bucket.to_string(),
+        });
+    }
+    Ok(())
+}
+
+// key-value and object store bucket names follow the same nats rules
+fn valid_bucket_name(bucket: &str) -> bool {
+    !bucket.is_empty()
+        && bucket
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// result of checking a stored chunk against its hash and signature
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChunkVerification {
+    pub sequence_number: i64,
+    pub bucket: String,
+    pub key: String,
+    pub status: VerificationStatus,
+    // why the chunk failed verification, set when status is invalid
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Display, Eq, PartialEq)]
+pub enum VerificationStatus {
+    // content matches its hash and the hash signature verifies
+    Verified,
+    // content matches its hash but the chunk was never signed
+    Unsigned,
+    Invalid,
+}
+
+// two chunks of the same stream and subject holding some of the same messages
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChunkOverlap {
+    pub stream: String,
+    pub subject: String,
+    pub first_sequence_number: i64,
+    pub first_key: String,
+    pub second_sequence_number: i64,
+    pub second_key: String,
+    // stream sequences found in both chunks
+    pub stream_sequence_start: i64,
+    pub stream_sequence_end: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DuplicateChunksQuery {
+    pub stream: String,
+    pub subject: Option<String>,
+    pub bucket: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct VerifyChunksQuery {
+    pub stream: String,
+    pub consumer: Option<String>,
+    pub subject: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+}